@@ -2,7 +2,9 @@
 // Displays the initial Othello board.
 // A sample program to display an 8x8 board.
 // Implements the following features:
-// - Support for array literals
+// - Support for array literals, including repeat literals like `[Cell::Empty; CELLS]`
+// - `const` board sizes and a `static` table of row labels shared by every call
+// - An enum for the state of a cell, displayed with a match
 // - Support for string literals
 // - For loops with initialization, condition, and increment expressions
 // - Arithmetic operations and index access
@@ -10,41 +12,42 @@
 // - Writing to standard output (write function)
 //
 
+enum Cell {
+    Empty,
+    White,
+    Black,
+}
+
+const SIZE: i32 = 8;
+const CELLS: i32 = SIZE * SIZE;
+
+static INDEXLIST: [&str; SIZE] = [
+    "1", "2", "3", "4", "5", "6", "7", "8"
+];
+
 fn displayboard() -> i32 {
-    let board = [
-        0,0,0,0,0,0,0,0,
-        0,0,0,0,0,0,0,0,
-        0,0,0,0,0,0,0,0,
-        0,0,0,1,2,0,0,0,
-        0,0,0,2,1,0,0,0,
-        0,0,0,0,0,0,0,0,
-        0,0,0,0,0,0,0,0,
-        0,0,0,0,0,0,0,0
-    ];
-    let indexlist = [
-        "1", "2", "3", "4", "5", "6", "7", "8"
-    ];
+    // an empty board with the four starting discs in the centre
+    let mut board: [Cell; CELLS] = [Cell::Empty; CELLS];
+    board[27] = Cell::White;
+    board[28] = Cell::Black;
+    board[35] = Cell::Black;
+    board[36] = Cell::White;
 
     write("  A  B  C  D  E  F  G  H\n");
-    let idx = 0;
-    let cell = 0;
-    for ( i=0; i<8; i=i+1 ) {
-        write(indexlist[i]);
+    let mut idx = 0;
+    let mut i = 0;
+    let mut j = 0;
+    for ( i=0; i<SIZE; i=i+1 ) {
+        write(INDEXLIST[i]);
         write(" ");
-        for ( j=0; j<8; j=j+1 ) {
-            idx = i*8+j;
-            cell = board[idx];
-
-            if ( cell==0 ) {
-                write("・");
-            }
-            if ( cell==1 ) {
-                write("○ ");
-            }
-            if ( cell==2 ) {
-                write("● ");
-            }
-            if ( j<7 ) {
+        for ( j=0; j<SIZE; j=j+1 ) {
+            idx = i*SIZE+j;
+            match board[idx] {
+                Cell::Empty => write("・"),
+                Cell::White => write("○ "),
+                Cell::Black => write("● "),
+            };
+            if ( j<SIZE-1 ) {
                 write(" ");
             }
         }
@@ -56,6 +59,4 @@ fn displayboard() -> i32 {
 fn main() {
 
     displayboard();
-
-    return 0;
 }
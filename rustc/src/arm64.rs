@@ -0,0 +1,766 @@
+use crate::codegen::{
+    LABEL_COUNTER, Target, emit_statics, emit_string, jump_table, panic_label, panic_strings,
+    static_label, target,
+};
+use crate::ir::{BinOp, BlockId, CmpOp, Function, Inst, IntTy, Module, Panic, Terminator, VReg};
+use crate::regalloc::{self, Allocation, Location, RegisterSet};
+use std::sync::atomic::Ordering;
+
+// AAPCS64 integer argument registers
+const ARG_REGS: usize = 8;
+
+// helper to mangle a function name into an assembly symbol
+fn sym(name: &str) -> String {
+    match target() {
+        Target::Aarch64Macos => format!("_{}", name),
+        _ => name.to_string(),
+    }
+}
+
+// helper to switch to the code section
+fn text_section() {
+    match target() {
+        Target::Aarch64Macos => println!(".section __TEXT,__text"),
+        _ => println!(".text"),
+    }
+}
+
+// helper to switch to the section holding string literals
+fn data_section() {
+    match target() {
+        Target::Aarch64Macos => println!(".section __DATA,__data"),
+        _ => println!(".section .rodata"),
+    }
+}
+
+// helper to make the system call numbered `linux` on Linux and `macos` on macOS
+fn svc(linux: i64, macos: i64) {
+    match target() {
+        Target::Aarch64Macos => {
+            mov_imm("x16", 0x2000000 | macos);
+            println!("    svc #0x80");
+        }
+        _ => {
+            mov_imm("x8", linux);
+            println!("    svc #0");
+        }
+    }
+}
+
+// helper to load the address of a local label into a register (PC-relative)
+fn load_label(reg: &str, label: &str) {
+    match target() {
+        Target::Aarch64Macos => {
+            println!("    adrp {}, {}@PAGE", reg, label);
+            println!("    add {}, {}, {}@PAGEOFF", reg, reg, label);
+        }
+        _ => {
+            println!("    adrp {}, {}", reg, label);
+            println!("    add {}, {}, :lo12:{}", reg, reg, label);
+        }
+    }
+}
+
+// helper to load an arbitrary 64-bit immediate with movz/movk
+fn mov_imm(reg: &str, value: i64) {
+    let bits = value as u64;
+    if bits <= 0xffff {
+        println!("    mov {}, #{}", reg, bits);
+        return;
+    }
+    if (-0x10000..0).contains(&value) {
+        // movn covers small negative numbers in one instruction
+        println!("    mov {}, #{}", reg, value);
+        return;
+    }
+    println!("    movz {}, #{}", reg, bits & 0xffff);
+    for shift in [16, 32, 48] {
+        let part = (bits >> shift) & 0xffff;
+        if part != 0 {
+            println!("    movk {}, #{}, lsl #{}", reg, part, shift);
+        }
+    }
+}
+
+// helper to compute `base - offset` into `dst`, handling offsets beyond the 12-bit immediate
+fn sub_offset(dst: &str, base: &str, offset: u64) {
+    if offset < 4096 {
+        println!("    sub {}, {}, #{}", dst, base, offset);
+    } else {
+        mov_imm("x16", offset as i64);
+        println!("    sub {}, {}, x16", dst, base);
+    }
+}
+
+// label of a basic block, unique across the module
+fn block_label(func: &Function, block: BlockId) -> String {
+    format!(".L{}_{}", func.name, block)
+}
+
+// label of a string literal
+fn string_label(id: usize) -> String {
+    format!(".L.str.{}", id)
+}
+
+// registers handed to the allocator; x0-x8 carry arguments and syscall
+// operands, x16/x17 are scratch and x18 is reserved by the platform
+const REGISTERS: RegisterSet = RegisterSet {
+    caller_saved: &["x9", "x10", "x11", "x12", "x13", "x14", "x15"],
+    callee_saved: &[
+        "x19", "x20", "x21", "x22", "x23", "x24", "x25", "x26", "x27", "x28",
+    ],
+};
+
+// Emits one IR function. Virtual registers live in the machine registers
+// chosen by the allocator; spilled ones get a frame slot below the saved
+// callee-saved registers, which in turn sit below the local variables.
+struct FunctionEmitter<'a> {
+    func: &'a Function,
+    alloc: Allocation,
+    frame_size: u64,
+}
+
+impl<'a> FunctionEmitter<'a> {
+    fn new(func: &'a Function) -> Self {
+        let alloc = regalloc::allocate(func, &REGISTERS);
+        let slots = (alloc.callee_saved.len() + alloc.spill_slots) as u64;
+        let frame_size = (func.locals_size + slots * 8).div_ceil(16) * 16;
+        Self {
+            func,
+            alloc,
+            frame_size,
+        }
+    }
+
+    // frame offset (below x29) of the i-th save or spill slot
+    fn slot(&self, i: usize) -> u64 {
+        self.func.locals_size + (i as u64 + 1) * 8
+    }
+
+    // load a frame slot into a machine register
+    fn load_slot(&self, reg: &str, off: u64) {
+        if off <= 256 {
+            println!("    ldur {}, [x29, #-{}]", reg, off);
+        } else {
+            sub_offset("x16", "x29", off);
+            println!("    ldr {}, [x16]", reg);
+        }
+    }
+
+    // store a machine register into a frame slot
+    fn store_slot(&self, reg: &str, off: u64) {
+        if off <= 256 {
+            println!("    stur {}, [x29, #-{}]", reg, off);
+        } else {
+            sub_offset("x16", "x29", off);
+            println!("    str {}, [x16]", reg);
+        }
+    }
+
+    // register holding `v`, loading a spilled value into `scratch` first
+    fn operand<'r>(&self, v: VReg, scratch: &'r str) -> &'r str {
+        match self.alloc.location(v) {
+            Location::Reg(r) => REGISTERS.name(r),
+            Location::Spill(i) => {
+                self.load_slot(scratch, self.slot(self.alloc.callee_saved.len() + i));
+                scratch
+            }
+        }
+    }
+
+    // register an instruction should write `v` into; `scratch` if `v` is spilled
+    fn dest<'r>(&self, v: VReg, scratch: &'r str) -> &'r str {
+        match self.alloc.location(v) {
+            Location::Reg(r) => REGISTERS.name(r),
+            Location::Spill(_) => scratch,
+        }
+    }
+
+    // write back a value computed into `reg` by `dest` when `v` is spilled
+    fn commit(&self, v: VReg, reg: &str) {
+        if let Location::Spill(i) = self.alloc.location(v) {
+            self.store_slot(reg, self.slot(self.alloc.callee_saved.len() + i));
+        }
+    }
+
+    // copy `src` into the location of `v`
+    fn move_into(&self, v: VReg, src: &str) {
+        let dst = self.dest(v, src);
+        if dst != src {
+            println!("    mov {}, {}", dst, src);
+        }
+        self.commit(v, dst);
+    }
+
+    // load `v` into a specific register
+    fn move_from(&self, reg: &str, v: VReg) {
+        match self.alloc.location(v) {
+            Location::Reg(r) => println!("    mov {}, {}", reg, REGISTERS.name(r)),
+            Location::Spill(i) => self.load_slot(reg, self.slot(self.alloc.callee_saved.len() + i)),
+        }
+    }
+
+    fn prologue(&self) {
+        let name = sym(&self.func.name);
+        println!(".globl {}", name);
+        if target() != Target::Aarch64Macos {
+            println!(".type {}, %function", name);
+        }
+        println!("{}:", name);
+        // save old frame pointer and set up new
+        println!("    stp x29, x30, [sp, #-16]!");
+        println!("    mov x29, sp");
+        // reserve space for local variables, saved registers and spills
+        if self.frame_size < 4096 {
+            println!("    sub sp, sp, #{}", self.frame_size);
+        } else {
+            mov_imm("x16", self.frame_size as i64);
+            println!("    sub sp, sp, x16");
+        }
+        // preserve the callee-saved registers the function uses
+        for (i, reg) in self.alloc.callee_saved.iter().enumerate() {
+            self.store_slot(REGISTERS.name(*reg), self.slot(i));
+        }
+        // move incoming arguments into their virtual registers
+        for (i, param) in self.func.params.iter().enumerate() {
+            if i < ARG_REGS {
+                self.move_into(*param, &format!("x{}", i));
+            } else {
+                // stack arguments sit above the saved frame pointer and link register
+                let reg = self.dest(*param, "x17");
+                println!("    ldr {}, [x29, #{}]", reg, 16 + (i - ARG_REGS) * 8);
+                self.commit(*param, reg);
+            }
+        }
+    }
+
+    fn epilogue(&self) {
+        // restore callee-saved registers, stack pointer, frame pointer and link register, then return
+        for (i, reg) in self.alloc.callee_saved.iter().enumerate() {
+            self.load_slot(REGISTERS.name(*reg), self.slot(i));
+        }
+        println!("    mov sp, x29");
+        println!("    ldp x29, x30, [sp], #16");
+        println!("    ret");
+    }
+
+    fn emit_call(&self, dst: VReg, func: &str, args: &[VReg]) {
+        // arguments past the eighth are passed on the stack, keeping sp 16-byte aligned
+        let stack_bytes = (args.len().saturating_sub(ARG_REGS) as u64 * 8).div_ceil(16) * 16;
+        if stack_bytes > 0 {
+            println!("    sub sp, sp, #{}", stack_bytes);
+            for (i, arg) in args.iter().enumerate().skip(ARG_REGS) {
+                let reg = self.operand(*arg, "x17");
+                println!("    str {}, [sp, #{}]", reg, (i - ARG_REGS) * 8);
+            }
+        }
+        // allocated registers never overlap x0-x7, so the moves cannot clobber each other
+        for (i, arg) in args.iter().enumerate().take(ARG_REGS) {
+            self.move_from(&format!("x{}", i), *arg);
+        }
+        println!("    bl {}", sym(func));
+        if stack_bytes > 0 {
+            println!("    add sp, sp, #{}", stack_bytes);
+        }
+        self.move_into(dst, "x0");
+    }
+
+    fn emit_syscall(&self, dst: VReg, name: &str, args: &[VReg]) {
+        match name {
+            "write" => {
+                // For write syscall:
+                // x0 = file descriptor (1 for stdout)
+                // x1 = buffer address
+                // x2 = buffer length
+                self.move_from("x1", args[0]); // string address
+                println!("    mov x0, #1"); // stdout file descriptor
+                // Calculate string length
+                println!("    mov x2, #0"); // Initialize length counter
+                println!("    mov x3, x1"); // Copy string address to x3
+                let id = LABEL_COUNTER.fetch_add(1, Ordering::SeqCst);
+                let loop_label = format!(".Lstrlen_loop{}", id);
+                let end_label = format!(".Lstrlen_end{}", id);
+                println!("{}:", loop_label); // Label for string length calculation loop
+                println!("    ldrb w4, [x3], #1"); // Load byte and increment pointer
+                println!("    cbz w4, {}", end_label); // If zero (null terminator), exit loop
+                println!("    add x2, x2, #1"); // Increment length counter
+                println!("    b {}", loop_label); // Branch back to loop start
+                println!("{}:", end_label); // Label for loop end
+                svc(64, 4); // write
+            }
+            _ => panic!("unsupported system call: {}", name),
+        }
+        self.move_into(dst, "x0");
+    }
+
+    fn emit_inst(&self, inst: &Inst) {
+        match inst {
+            Inst::Const { dst, value } => {
+                let reg = self.dest(*dst, "x0");
+                mov_imm(reg, *value);
+                self.commit(*dst, reg);
+            }
+            Inst::Str { dst, id } => {
+                let reg = self.dest(*dst, "x0");
+                load_label(reg, &string_label(*id));
+                self.commit(*dst, reg);
+            }
+            Inst::FrameAddr { dst, offset } => {
+                let reg = self.dest(*dst, "x0");
+                sub_offset(reg, "x29", *offset);
+                self.commit(*dst, reg);
+            }
+            Inst::StaticAddr { dst, name } => {
+                let reg = self.dest(*dst, "x0");
+                load_label(reg, &static_label(name));
+                self.commit(*dst, reg);
+            }
+            Inst::Load { dst, addr, ty } => {
+                let a = self.operand(*addr, "x0");
+                let reg = self.dest(*dst, "x0");
+                // loads into a w register clear the upper half
+                match ty {
+                    IntTy::I8 => println!("    ldrsb {}, [{}]", reg, a),
+                    IntTy::U8 => println!("    ldrb {}, [{}]", w(reg), a),
+                    IntTy::I16 => println!("    ldrsh {}, [{}]", reg, a),
+                    IntTy::U16 => println!("    ldrh {}, [{}]", w(reg), a),
+                    IntTy::I32 => println!("    ldrsw {}, [{}]", reg, a),
+                    IntTy::U32 => println!("    ldr {}, [{}]", w(reg), a),
+                    IntTy::I64 | IntTy::U64 => println!("    ldr {}, [{}]", reg, a),
+                }
+                self.commit(*dst, reg);
+            }
+            Inst::Store { addr, src, ty } => {
+                let a = self.operand(*addr, "x0");
+                let v = self.operand(*src, "x1");
+                match ty {
+                    IntTy::I8 | IntTy::U8 => println!("    strb {}, [{}]", w(v), a),
+                    IntTy::I16 | IntTy::U16 => println!("    strh {}, [{}]", w(v), a),
+                    IntTy::I32 | IntTy::U32 => println!("    str {}, [{}]", w(v), a),
+                    IntTy::I64 | IntTy::U64 => println!("    str {}, [{}]", v, a),
+                }
+            }
+            Inst::Ext { dst, src, ty } => {
+                let v = self.operand(*src, "x0");
+                let reg = self.dest(*dst, "x0");
+                match ty {
+                    IntTy::I8 => println!("    sxtb {}, {}", reg, w(v)),
+                    IntTy::I16 => println!("    sxth {}, {}", reg, w(v)),
+                    IntTy::I32 => println!("    sxtw {}, {}", reg, w(v)),
+                    IntTy::U8 => println!("    uxtb {}, {}", w(reg), w(v)),
+                    IntTy::U16 => println!("    uxth {}, {}", w(reg), w(v)),
+                    IntTy::U32 => println!("    mov {}, {}", w(reg), w(v)),
+                    IntTy::I64 | IntTy::U64 => println!("    mov {}, {}", reg, v),
+                }
+                self.commit(*dst, reg);
+            }
+            Inst::Bin { op, dst, lhs, rhs } => {
+                let l = self.operand(*lhs, "x0");
+                let r = self.operand(*rhs, "x1");
+                let reg = self.dest(*dst, "x0");
+                let mnemonic = match op {
+                    BinOp::Add => "add",
+                    BinOp::Sub => "sub",
+                    BinOp::Mul => "mul",
+                    BinOp::Div => "sdiv",
+                    BinOp::UDiv => "udiv",
+                    BinOp::Rem | BinOp::URem => {
+                        // l - (l / r) * r
+                        let div = if *op == BinOp::Rem { "sdiv" } else { "udiv" };
+                        println!("    {} x17, {}, {}", div, l, r);
+                        println!("    msub {}, x17, {}, {}", reg, r, l);
+                        self.commit(*dst, reg);
+                        return;
+                    }
+                    BinOp::And => "and",
+                    BinOp::Or => "orr",
+                    BinOp::Xor => "eor",
+                    BinOp::Shl => "lsl",
+                    BinOp::Shr => "asr",
+                    BinOp::LShr => "lsr",
+                };
+                println!("    {} {}, {}, {}", mnemonic, reg, l, r);
+                self.commit(*dst, reg);
+            }
+            Inst::CheckedBin {
+                op,
+                dst,
+                lhs,
+                rhs,
+                ty,
+                loc,
+            } => {
+                let l = self.operand(*lhs, "x0");
+                let r = self.operand(*rhs, "x1");
+                let reg = self.dest(*dst, "x0");
+                let id = LABEL_COUNTER.fetch_add(1, Ordering::SeqCst);
+                let ok = format!(".Loverflow_ok{}", id);
+                match (op, ty) {
+                    // a 64-bit product overflows when its high half is not
+                    // the extension of its low half
+                    (BinOp::Mul, IntTy::I64) => {
+                        println!("    smulh x16, {}, {}", l, r);
+                        println!("    mul {}, {}, {}", reg, l, r);
+                        println!("    cmp x16, {}, asr #63", reg);
+                        println!("    b.eq {}", ok);
+                    }
+                    (BinOp::Mul, IntTy::U64) => {
+                        println!("    umulh x16, {}, {}", l, r);
+                        println!("    mul {}, {}, {}", reg, l, r);
+                        println!("    cbz x16, {}", ok);
+                    }
+                    // signed overflow sets V; an unsigned addition carries
+                    // out and an unsigned subtraction borrows
+                    (_, IntTy::I64 | IntTy::U64) => {
+                        let (mnemonic, cond) = match (op, ty) {
+                            (BinOp::Add, IntTy::I64) => ("adds", "vc"),
+                            (_, IntTy::I64) => ("subs", "vc"),
+                            (BinOp::Add, _) => ("adds", "lo"),
+                            _ => ("subs", "hs"),
+                        };
+                        println!("    {} {}, {}, {}", mnemonic, reg, l, r);
+                        println!("    b.{} {}", cond, ok);
+                    }
+                    // narrower operands cannot overflow 64 bits, so the
+                    // result only has to survive extension from its width
+                    _ => {
+                        let mnemonic = match op {
+                            BinOp::Add => "add",
+                            BinOp::Sub => "sub",
+                            _ => "mul",
+                        };
+                        let extend = match ty {
+                            IntTy::I8 => "sxtb",
+                            IntTy::I16 => "sxth",
+                            IntTy::I32 => "sxtw",
+                            IntTy::U8 => "uxtb",
+                            IntTy::U16 => "uxth",
+                            _ => "uxtw",
+                        };
+                        println!("    {} {}, {}, {}", mnemonic, reg, l, r);
+                        println!("    cmp {}, {}, {}", reg, w(reg), extend);
+                        println!("    b.eq {}", ok);
+                    }
+                }
+                load_label("x21", &string_label(*loc));
+                println!("    b {}", panic_label(Panic::Overflow(*op)));
+                println!("{}:", ok);
+                self.commit(*dst, reg);
+            }
+            Inst::CheckIndex { index, len, loc } => {
+                let i = self.operand(*index, "x0");
+                mov_imm("x16", *len as i64);
+                println!("    cmp {}, x16", i);
+                let id = LABEL_COUNTER.fetch_add(1, Ordering::SeqCst);
+                let ok = format!(".Lindex_ok{}", id);
+                println!("    b.lo {}", ok);
+                // the panic does not return, so any register may carry its operands
+                println!("    mov x20, {}", i);
+                println!("    mov x19, x16");
+                load_label("x21", &string_label(*loc));
+                println!("    b {}", panic_label(Panic::Bounds));
+                println!("{}:", ok);
+            }
+            Inst::CheckDivisor { op, divisor, loc } => {
+                let d = self.operand(*divisor, "x0");
+                let id = LABEL_COUNTER.fetch_add(1, Ordering::SeqCst);
+                let ok = format!(".Ldivisor_ok{}", id);
+                println!("    cbnz {}, {}", d, ok);
+                load_label("x21", &string_label(*loc));
+                println!("    b {}", panic_label(Panic::ZeroDivisor(*op)));
+                println!("{}:", ok);
+            }
+            Inst::CheckDivOverflow {
+                op,
+                dividend,
+                divisor,
+                ty,
+                loc,
+            } => {
+                let l = self.operand(*dividend, "x0");
+                let r = self.operand(*divisor, "x1");
+                let id = LABEL_COUNTER.fetch_add(1, Ordering::SeqCst);
+                let ok = format!(".Ldivide_ok{}", id);
+                println!("    cmn {}, #1", r);
+                println!("    b.ne {}", ok);
+                mov_imm("x16", ty.min());
+                println!("    cmp {}, x16", l);
+                println!("    b.ne {}", ok);
+                load_label("x21", &string_label(*loc));
+                println!("    b {}", panic_label(Panic::Overflow(*op)));
+                println!("{}:", ok);
+            }
+            Inst::CheckShift {
+                op,
+                amount,
+                bits,
+                loc,
+            } => {
+                let a = self.operand(*amount, "x0");
+                let id = LABEL_COUNTER.fetch_add(1, Ordering::SeqCst);
+                let ok = format!(".Lshift_ok{}", id);
+                println!("    cmp {}, #{}", a, bits);
+                println!("    b.lo {}", ok);
+                load_label("x21", &string_label(*loc));
+                println!("    b {}", panic_label(Panic::Overflow(*op)));
+                println!("{}:", ok);
+            }
+            Inst::CheckNeg { src, ty, loc } => {
+                let v = self.operand(*src, "x0");
+                let id = LABEL_COUNTER.fetch_add(1, Ordering::SeqCst);
+                let ok = format!(".Lneg_ok{}", id);
+                mov_imm("x16", ty.min());
+                println!("    cmp {}, x16", v);
+                println!("    b.ne {}", ok);
+                load_label("x21", &string_label(*loc));
+                println!("    b {}", panic_label(Panic::NegOverflow));
+                println!("{}:", ok);
+            }
+            Inst::Cmp { op, dst, lhs, rhs } => {
+                let l = self.operand(*lhs, "x0");
+                let r = self.operand(*rhs, "x1");
+                let reg = self.dest(*dst, "x0");
+                println!("    cmp {}, {}", l, r);
+                println!("    cset {}, {}", reg, cond(*op));
+                self.commit(*dst, reg);
+            }
+            Inst::Copy { dst, src } => {
+                let v = self.operand(*src, "x0");
+                self.move_into(*dst, v);
+            }
+            Inst::Call { dst, func, args } => self.emit_call(*dst, func, args),
+            Inst::Syscall { dst, name, args } => self.emit_syscall(*dst, name, args),
+        }
+    }
+
+    fn emit_terminator(&self, term: &Terminator, next: Option<BlockId>) {
+        match term {
+            Terminator::Jump(target) => {
+                // fall through to the next block when possible
+                if Some(*target) != next {
+                    println!("    b {}", block_label(self.func, *target));
+                }
+            }
+            Terminator::Branch {
+                cond,
+                then_bb,
+                else_bb,
+            } => {
+                let c = self.operand(*cond, "x0");
+                println!("    cbz {}, {}", c, block_label(self.func, *else_bb));
+                if Some(*then_bb) != next {
+                    println!("    b {}", block_label(self.func, *then_bb));
+                }
+            }
+            Terminator::CmpBranch {
+                op,
+                lhs,
+                rhs,
+                then_bb,
+                else_bb,
+            } => {
+                let l = self.operand(*lhs, "x0");
+                let r = self.operand(*rhs, "x1");
+                println!("    cmp {}, {}", l, r);
+                // branch away on the negated condition to fall into `then_bb`
+                if Some(*then_bb) == next {
+                    let else_label = block_label(self.func, *else_bb);
+                    println!("    b.{} {}", cond(op.negate()), else_label);
+                    return;
+                }
+                println!("    b.{} {}", cond(*op), block_label(self.func, *then_bb));
+                if Some(*else_bb) != next {
+                    println!("    b {}", block_label(self.func, *else_bb));
+                }
+            }
+            Terminator::Switch {
+                value,
+                cases,
+                default,
+            } => {
+                let v = self.operand(*value, "x0");
+                let default_label = block_label(self.func, *default);
+                let Some((lo, table)) = jump_table(cases, *default) else {
+                    // few or sparse cases: compare against each in turn
+                    for (case, target) in cases {
+                        mov_imm("x17", *case);
+                        println!("    cmp {}, x17", v);
+                        println!("    b.eq {}", block_label(self.func, *target));
+                    }
+                    if Some(*default) != next {
+                        println!("    b {}", default_label);
+                    }
+                    return;
+                };
+                // index the table by the value's distance from its lowest
+                // case; below it the unsigned comparison sees a huge index
+                let id = LABEL_COUNTER.fetch_add(1, Ordering::SeqCst);
+                let table_label = format!(".Lswitch{}", id);
+                mov_imm("x17", lo);
+                println!("    sub x16, {}, x17", v);
+                mov_imm("x17", table.len() as i64);
+                println!("    cmp x16, x17");
+                println!("    b.hs {}", default_label);
+                // each entry is a branch instruction to the target
+                println!("    adr x17, {}", table_label);
+                println!("    add x17, x17, x16, lsl #2");
+                println!("    br x17");
+                println!("{}:", table_label);
+                for target in table {
+                    println!("    b {}", block_label(self.func, target));
+                }
+            }
+            Terminator::Return(v) => {
+                self.move_from("x0", *v);
+                self.epilogue();
+            }
+        }
+    }
+
+    fn emit(&self) {
+        self.prologue();
+        for (i, block) in self.func.blocks.iter().enumerate() {
+            println!("{}:", block_label(self.func, BlockId(i)));
+            for inst in &block.insts {
+                self.emit_inst(inst);
+            }
+            let next = (i + 1 < self.func.blocks.len()).then_some(BlockId(i + 1));
+            self.emit_terminator(&block.term, next);
+        }
+    }
+}
+
+// condition code for a comparison
+fn cond(op: CmpOp) -> &'static str {
+    match op {
+        CmpOp::Eq => "eq",
+        CmpOp::Ne => "ne",
+        CmpOp::Lt => "lt",
+        CmpOp::Gt => "gt",
+        CmpOp::Le => "le",
+        CmpOp::Ge => "ge",
+        CmpOp::ULt => "lo",
+        CmpOp::UGt => "hi",
+        CmpOp::ULe => "ls",
+        CmpOp::UGe => "hs",
+    }
+}
+
+// the 32-bit view of an x register
+fn w(reg: &str) -> String {
+    format!("w{}", &reg[1..])
+}
+
+// the routines failed checks branch to: like a Rust panic, each writes where
+// it happened and its message to stderr and exits with status 101. Checks
+// pass their source location in x21, and a failed index check also passes
+// the length of the array in x19 and the index in x20.
+fn emit_panics(panics: &[Panic]) {
+    if panics.is_empty() {
+        return;
+    }
+    for panic in panics {
+        let label = panic_label(*panic);
+        println!("{}:", label);
+        println!("    bl .Lpanic.header");
+        match panic {
+            Panic::Overflow(_) | Panic::ZeroDivisor(_) | Panic::NegOverflow => {}
+            Panic::Bounds => {
+                load_label("x1", ".Lpanic.bounds.len");
+                println!("    bl .Lpanic.write_str");
+                println!("    mov x0, x19");
+                println!("    bl .Lpanic.write_num");
+                load_label("x1", ".Lpanic.bounds.index");
+                println!("    bl .Lpanic.write_str");
+                println!("    mov x0, x20");
+                println!("    bl .Lpanic.write_num");
+                load_label("x1", ".Lpanic.bounds.end");
+                println!("    b .Lpanic");
+                continue;
+            }
+        }
+        load_label("x1", &format!("{}.msg", label));
+        println!("    b .Lpanic");
+    }
+    // write the rest of the message, at x1, and exit
+    println!(".Lpanic:");
+    println!("    bl .Lpanic.write_str");
+    println!("    mov x0, #101");
+    svc(93, 1); // exit
+    // write the `thread 'main' panicked at <location>:` line
+    println!(".Lpanic.header:");
+    println!("    mov x22, x30");
+    load_label("x1", ".Lpanic.thread");
+    println!("    bl .Lpanic.write_str");
+    println!("    mov x1, x21");
+    println!("    bl .Lpanic.write_str");
+    load_label("x1", ".Lpanic.colon");
+    println!("    bl .Lpanic.write_str");
+    println!("    ret x22");
+    // write the null-terminated string at x1 to stderr
+    println!(".Lpanic.write_str:");
+    println!("    mov x2, #0");
+    println!(".Lpanic.strlen:");
+    println!("    ldrb w3, [x1, x2]");
+    println!("    cbz w3, .Lpanic.write");
+    println!("    add x2, x2, #1");
+    println!("    b .Lpanic.strlen");
+    println!(".Lpanic.write:");
+    println!("    mov x0, #2"); // stderr
+    svc(64, 4); // write
+    println!("    ret");
+    // write x0 as an unsigned decimal, whose digits are stored from the end
+    // of a buffer on the stack
+    println!(".Lpanic.write_num:");
+    println!("    sub sp, sp, #32");
+    println!("    add x1, sp, #32");
+    println!("    mov x3, #10");
+    println!(".Lpanic.digit:");
+    println!("    udiv x4, x0, x3");
+    println!("    msub x5, x4, x3, x0");
+    println!("    add w5, w5, #48"); // '0'
+    println!("    strb w5, [x1, #-1]!");
+    println!("    mov x0, x4");
+    println!("    cbnz x0, .Lpanic.digit");
+    println!("    add x2, sp, #32");
+    println!("    sub x2, x2, x1");
+    println!("    mov x0, #2"); // stderr
+    svc(64, 4); // write
+    println!("    add sp, sp, #32");
+    println!("    ret");
+    data_section();
+    for (label, s) in panic_strings(panics) {
+        emit_string(&label, &s);
+    }
+}
+
+/// Generate ARM64 assembly for the IR module in the flavour of the current target.
+pub(crate) fn generate(module: &Module) {
+    text_section();
+    for func in &module.functions {
+        FunctionEmitter::new(func).emit();
+    }
+    emit_panics(&module.panics());
+    // Emit the string literals in the data section
+    if !module.strings.is_empty() {
+        data_section();
+        for (id, s) in module.strings.iter().enumerate() {
+            emit_string(&string_label(id), s);
+        }
+    }
+    match target() {
+        // a Mach-O zero-fill section only takes `.zerofill`, which cannot put
+        // the label after the first slots, so zeroed statics stay in data
+        Target::Aarch64Macos => emit_statics(
+            module,
+            ".section __DATA,__data",
+            ".section __DATA,__data",
+            string_label,
+        ),
+        _ => emit_statics(module, ".data", ".bss", string_label),
+    }
+    if target() == Target::Aarch64Linux {
+        // mark the stack as non-executable for the GNU linker
+        println!(".section .note.GNU-stack,\"\",%progbits");
+    }
+}
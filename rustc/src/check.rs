@@ -1,5 +1,4 @@
 use crate::token::*;
-use std::cell::RefCell;
 use std::fmt;
 
 /// Represents a parsing error with message and position in input.
@@ -22,18 +21,48 @@ impl ParseError {
     }
 }
 
-thread_local! {
-    static CURRENT_EXP: RefCell<String> = RefCell::new(String::new());
+impl ParseError {
+    /// Render the error as the offending source line with a caret under `pos`.
+    pub fn render(&self, exp: &str) -> String {
+        render_at(exp, Span::new(self.pos, self.pos), &self.msg)
+    }
 }
 
-/// Set the current input expression for error reporting.
-pub fn set_current_exp(exp: &str) {
-    CURRENT_EXP.with(|c| *c.borrow_mut() = exp.to_string());
+/// Byte range `start..end` of the source text a syntax node was parsed from.
+///
+/// Spans never take part in comparisons, so two trees parsed from differently
+/// laid out source are equal; compare `start` and `end` to check positions.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
 }
 
-/// Reports an error at a specific position in the input and returns a ParseError.
-pub fn error_at(exp: &str, pos: usize, msg: &str) -> ParseError {
-    // Calculate line number and starting byte index of the line
+impl PartialEq for Span {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Span {}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// The smallest span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+// line number, counted from 1, and starting byte index of the line holding
+// byte `pos` of `exp`
+fn line_of(exp: &str, pos: usize) -> (usize, usize) {
     let mut line_num = 1;
     let mut line_start = 0;
     for (idx, ch) in exp.char_indices() {
@@ -45,6 +74,33 @@ pub fn error_at(exp: &str, pos: usize, msg: &str) -> ParseError {
             line_start = idx + ch.len_utf8();
         }
     }
+    (line_num, line_start)
+}
+
+/// A source file and the path it was read from, which runtime panics point into.
+#[derive(Debug, Clone, Copy)]
+pub struct SourceFile<'a> {
+    pub path: &'a str,
+    pub text: &'a str,
+}
+
+impl SourceFile<'_> {
+    /// The `path:line:column` of the start of `span`, counted from 1 as in
+    /// the location of a Rust panic.
+    pub fn location(&self, span: Span) -> String {
+        let pos = span.start.min(self.text.len());
+        let (line_num, line_start) = line_of(self.text, pos);
+        let col = self.text[line_start..pos].chars().count() + 1;
+        format!("{}:{}:{}", self.path, line_num, col)
+    }
+}
+
+/// Render `msg` under the source line holding `span`, underlining the span
+/// with carets up to the end of that line (at least one caret).
+pub fn render_at(exp: &str, span: Span, msg: &str) -> String {
+    // Calculate line number and starting byte index of the line
+    let pos = span.start.min(exp.len());
+    let (line_num, line_start) = line_of(exp, pos);
     // Determine the end of the current line
     let line_end = exp[line_start..]
         .find('\n')
@@ -53,12 +109,27 @@ pub fn error_at(exp: &str, pos: usize, msg: &str) -> ParseError {
     let line = &exp[line_start..line_end];
     // Calculate the column (character offset) within the line
     let col = exp[line_start..pos].chars().count();
-    // Print the line with its number
-    println!("{} | {}", line_num, line);
-    // Build and print the caret line with message
+    // underline the span, stopping at the end of the line
+    let width = exp[pos..span.end.clamp(pos, line_end)]
+        .chars()
+        .count()
+        .max(1);
+    // The line with its number, then the caret line with the message
     let line_num_str = line_num.to_string();
     let prefix_spaces = " ".repeat(line_num_str.len()) + " | ";
-    println!("{}{}^ {}", prefix_spaces, " ".repeat(col), msg);
+    format!(
+        "{} | {}\n{}{}{} {}",
+        line_num,
+        line,
+        prefix_spaces,
+        " ".repeat(col),
+        "^".repeat(width),
+        msg
+    )
+}
+
+/// Creates an error at a specific position in the input.
+pub fn error_at(pos: usize, msg: &str) -> ParseError {
     ParseError {
         msg: msg.to_string(),
         pos,
@@ -67,22 +138,16 @@ pub fn error_at(exp: &str, pos: usize, msg: &str) -> ParseError {
 
 /// Checks whether the current token matches expected kind; returns Ok or ParseError
 pub fn expect_token(cur: &Token, expected_kind: &TokenKind) -> Result<(), ParseError> {
-    let exp_str = CURRENT_EXP.with(|c| c.borrow().clone());
     if cur.kind == *expected_kind {
         Ok(())
     } else {
-        Err(error_at(
-            &exp_str,
-            cur.pos,
-            &format!("expected {:?}", expected_kind),
-        ))
+        Err(error_at(cur.pos, &format!("expected {:?}", expected_kind)))
     }
 }
 
 /// Reports a parsing error at the given token and returns a ParseError.
 pub fn error_tok(cur: &Token, msg: &str) -> ParseError {
-    let exp_str = CURRENT_EXP.with(|c| c.borrow().clone());
-    error_at(&exp_str, cur.pos, msg)
+    error_at(cur.pos, msg)
 }
 
 #[cfg(test)]
@@ -93,7 +158,7 @@ mod tests {
     fn test_expect_token_ok() {
         let head = tokenize(";").unwrap();
         let tok = head.next.as_ref().unwrap();
-        expect_token(&tok, &TokenKind::Semicolon).unwrap();
+        expect_token(tok, &TokenKind::Semicolon).unwrap();
     }
 
     #[test]
@@ -101,7 +166,7 @@ mod tests {
     fn test_expect_token_panic() {
         let head = tokenize("x").unwrap();
         let tok = head.next.as_ref().unwrap().next.as_ref().unwrap();
-        expect_token(&tok, &TokenKind::Semicolon).unwrap();
+        expect_token(tok, &TokenKind::Semicolon).unwrap();
     }
 
     #[test]
@@ -109,23 +174,52 @@ mod tests {
     fn test_error_tok() {
         let head = tokenize("x").unwrap();
         let tok = head.next.as_ref().unwrap();
-        error_tok(&tok, "test error").unwrap();
+        error_tok(tok, "test error").unwrap();
     }
 
     #[test]
-    #[should_panic(expected = "multiline start")]
-    fn test_error_at_multiline_line1() {
+    fn test_render_multiline_line1() {
         let exp = "first line\nsecond line\nthird line";
         // position in first line (pos 0)
-        error_at(exp, 0, "multiline start").unwrap();
+        let err = error_at(0, "multiline start");
+        assert_eq!(err.render(exp), "1 | first line\n  | ^ multiline start");
     }
 
     #[test]
-    #[should_panic(expected = "multiline mid")]
-    fn test_error_at_multiline_line2() {
+    fn test_render_multiline_line2() {
         let exp = "first line\nsecond line foo\nthird line";
         // position of 'foo' in second line
         let pos = exp.find("foo").unwrap();
-        error_at(exp, pos, "multiline mid").unwrap();
+        let err = error_at(pos, "multiline mid");
+        assert_eq!(
+            err.render(exp),
+            "2 | second line foo\n  |             ^ multiline mid"
+        );
+    }
+
+    #[test]
+    fn test_render_span_underlines_range() {
+        let exp = "let x = a + b;";
+        let span = Span::new(8, 13);
+        assert_eq!(
+            render_at(exp, span, "bad sum"),
+            "1 | let x = a + b;\n  |         ^^^^^ bad sum"
+        );
+    }
+
+    #[test]
+    fn test_source_file_location() {
+        let file = SourceFile {
+            path: "src/main.rs",
+            text: "fn main() {\n    a[9];\n}",
+        };
+        let pos = file.text.find("a[").unwrap();
+        assert_eq!(file.location(Span::new(pos, pos + 4)), "src/main.rs:2:5");
+    }
+
+    #[test]
+    fn test_span_to_covers_both() {
+        let span = Span::new(4, 6).to(Span::new(1, 3));
+        assert_eq!((span.start, span.end), (1, 6));
     }
 }
@@ -1,234 +1,71 @@
-use crate::node::{Node, OpKind};
-use std::sync::atomic::{AtomicUsize, Ordering};
-static LABEL_COUNTER: AtomicUsize = AtomicUsize::new(0);
+use crate::check::SourceFile;
+use crate::node::{Node, OpKind, Pattern};
+use crate::{arm64, ir, x86_64};
+use std::cell::Cell;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::AtomicUsize;
+pub(crate) static LABEL_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
-// helper to push an immediate onto the stack
-fn push_imm(n: u64) {
-    println!("    mov x0, #{}", n);
-    println!("    str x0, [sp, #-16]!");
+/// Object format and OS conventions of the generated assembly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Target {
+    /// AArch64 Mach-O for macOS (`_`-prefixed symbols, BSD syscalls).
+    #[default]
+    Aarch64Macos,
+    /// AArch64 ELF for Linux, accepted by GNU as.
+    Aarch64Linux,
+    /// x86-64 ELF for Linux, System V calling convention.
+    X86_64Linux,
 }
 
-// helper to emit code for binary operations
-fn emit_binop(op: &str, lhs: &Node, rhs: &Node) {
-    gen_node(lhs);
-    gen_node(rhs);
-    println!("    ldr x1, [sp], #16");
-    println!("    ldr x0, [sp], #16");
-    println!("    {} x0, x0, x1", op);
-    println!("    str x0, [sp, #-16]!");
-}
-
-// helper to emit code for comparisons, using cmp + cset
-fn emit_cmp(cond: &str, lhs: &Node, rhs: &Node) {
-    gen_node(lhs);
-    gen_node(rhs);
-    println!("    ldr x1, [sp], #16");
-    println!("    ldr x0, [sp], #16");
-    println!("    cmp x0, x1");
-    println!("    cset x0, {}", cond);
-    println!("    str x0, [sp, #-16]!");
-}
-
-// helper to emit code for assignments
-fn emit_assign(lhs: &Node, rhs: &Node) {
-    // evaluate RHS and pop into x1
-    gen_node(rhs);
-    println!("    ldr x1, [sp], #16");
-    // determine variable offset or error
-    let off = match lhs {
-        Node::Var { offset } => *offset,
-        other => panic!("assignment to non-variable: {:?}", other),
-    };
-    // store into variable slot via register-based addressing (handles large offsets)
-    println!("    mov x2, x29");
-    println!("    sub x2, x2, #{}", off);
-    println!("    str x1, [x2]");
-    // push assigned value back onto stack
-    println!("    str x1, [sp, #-16]!");
-}
-
-// helper to emit code for variable load
-fn emit_var(off: u64) {
-    // load variable via register-based addressing (handles large offsets)
-    println!("    mov x2, x29");
-    println!("    sub x2, x2, #{}", off);
-    println!("    ldr x0, [x2]");
-    // push loaded value onto stack
-    println!("    str x0, [sp, #-16]!");
-}
-
-// helper to emit code for sequence of two nodes
-fn emit_seq(lhs: &Node, rhs: &Node) {
-    gen_node(lhs);
-    // discard lhs result
-    println!("    ldr x0, [sp], #16");
-    gen_node(rhs);
-}
-
-// helper to emit code for return statement
-fn emit_return(node: &Node) {
-    gen_node(node);
-    // pop return value into x0
-    println!("    ldr x0, [sp], #16");
-    // restore stack pointer to frame pointer
-    println!("    mov sp, x29");
-    // restore frame pointer and link register
-    println!("    ldp x29, x30, [sp], #16");
-    // return
-    println!("    ret");
-}
+impl FromStr for Target {
+    type Err = String;
 
-// helper to emit code for if-else statements
-fn emit_if(cond: &Node, then_stmt: &Node, else_stmt: Option<&Node>) {
-    // Evaluate condition and pop into x0
-    gen_node(cond);
-    println!("    ldr x0, [sp], #16");
-    // Compare with zero
-    println!("    cmp x0, #0");
-    // Generate unique labels
-    let id = LABEL_COUNTER.fetch_add(1, Ordering::SeqCst);
-    let else_label = format!(".Lelse{}", id);
-    let end_label = format!(".Lend{}", id);
-    // If zero, jump to else
-    println!("    beq {}", else_label);
-    // then branch
-    gen_node(then_stmt);
-    // Jump to end
-    println!("    b {}", end_label);
-    // else label
-    println!("{}:", else_label);
-    if let Some(es) = else_stmt {
-        gen_node(es);
-    } else {
-        // push default zero for no else branch to balance stack
-        println!("    mov x0, #0");
-        println!("    str x0, [sp, #-16]!");
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "aarch64-macos" | "aarch64-apple-darwin" | "arm64-apple-darwin" => {
+                Ok(Target::Aarch64Macos)
+            }
+            "aarch64-linux" | "aarch64-unknown-linux-gnu" => Ok(Target::Aarch64Linux),
+            "x86_64-linux" | "x86_64-unknown-linux-gnu" => Ok(Target::X86_64Linux),
+            _ => Err(format!("unknown target: {}", s)),
+        }
     }
-    // end label
-    println!("{}:", end_label);
-}
-
-fn emit_while(cond: &Node, body: &Node) {
-    // while loop: .LloopX: if !(cond) break; body; b .LloopX; .LendX:
-    let id = LABEL_COUNTER.fetch_add(1, Ordering::SeqCst);
-    let loop_label = format!(".Lloop{}", id);
-    let end_label = format!(".Lend{}", id);
-    // loop start label
-    println!("{}:", loop_label);
-    // evaluate condition and pop into x0
-    gen_node(cond);
-    println!("    ldr x0, [sp], #16");
-    println!("    cmp x0, #0");
-    // if zero, jump to end
-    println!("    beq {}", end_label);
-    // loop body
-    gen_node(body);
-    // jump back to loop start
-    println!("    b {}", loop_label);
-    // end label
-    println!("{}:", end_label);
-}
-
-// helper to emit code for for-loop statements
-fn emit_for(init: &Node, cond: &Node, update: &Node, body: &Node) {
-    // for(init; cond; update) body
-    let id = LABEL_COUNTER.fetch_add(1, Ordering::SeqCst);
-    let loop_label = format!(".Lfor{}", id);
-    let cond_label = format!(".Lcond{}", id);
-    let end_label = format!(".Lend{}", id);
-    // init
-    gen_node(init);
-    println!("    ldr x0, [sp], #16");
-    // jump to cond check
-    println!("    b {}", cond_label);
-    // loop body
-    println!("{}:", loop_label);
-    gen_node(body);
-    // update
-    gen_node(update);
-    println!("    ldr x0, [sp], #16");
-    // condition check
-    println!("{}:", cond_label);
-    gen_node(cond);
-    println!("    ldr x0, [sp], #16");
-    println!("    cmp x0, #0");
-    println!("    bne {}", loop_label);
-    // end label
-    println!("{}:", end_label);
 }
 
-// helper to emit code for system calls
-fn emit_syscall(name: &String, args: &[Node]) {
-    // evaluate arguments and push onto stack
-    for arg in args {
-        gen_node(arg);
-    }
-    // pop arguments into x registers (reverse order)
-    for i in (0..args.len()).rev() {
-        println!("    ldr x{}, [sp], #16", i);
-    }
-    // Set up system call number in x16
-    match name.as_str() {
-        "write" => {
-            // For write syscall:
-            // x0 = file descriptor (1 for stdout)
-            // x1 = buffer address
-            // x2 = buffer length
-            println!("    mov x1, x0"); // Move string address to x1
-            println!("    mov x0, #1"); // stdout file descriptor
-            // Calculate string length
-            println!("    mov x2, #0"); // Initialize length counter
-            println!("    mov x3, x1"); // Copy string address to x3
-            let id = LABEL_COUNTER.fetch_add(1, Ordering::SeqCst);
-            let loop_label = format!(".Lstrlen_loop{}", id);
-            let end_label = format!(".Lstrlen_end{}", id);
-            println!("{}:", loop_label); // Label for string length calculation loop
-            println!("    ldrb w4, [x3], #1"); // Load byte and increment pointer
-            println!("    cbz w4, {}", end_label); // If zero (null terminator), exit loop
-            println!("    add x2, x2, #1"); // Increment length counter
-            println!("    b {}", loop_label); // Branch back to loop start
-            println!("{}:", end_label); // Label for loop end
-            println!("    movz x16, #0x0004, lsl #0"); // Set lower 16 bits
-            println!("    movk x16, #0x2000, lsl #16"); // Set upper 16 bits
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Aarch64Macos => write!(f, "aarch64-macos"),
+            Target::Aarch64Linux => write!(f, "aarch64-linux"),
+            Target::X86_64Linux => write!(f, "x86_64-linux"),
         }
-        _ => panic!("unsupported system call: {}", name),
     }
-    // Make the system call
-    println!("    svc #0x80");
-    // Push return value onto stack
-    println!("    str x0, [sp, #-16]!");
 }
 
-// helper to emit code for function call statements with arguments
-fn emit_call(name: &String, args: &[Node]) {
-    // evaluate arguments and push onto stack
-    for arg in args {
-        gen_node(arg);
-    }
-    // pop arguments into x registers (reverse order)
-    for i in (0..args.len()).rev() {
-        println!("    ldr x{}, [sp], #16", i);
-    }
-    // Save caller-saved registers
-    println!("    stp x29, x30, [sp, #-16]!");
-    println!("    bl _{}", name);
-    // Restore caller-saved registers
-    println!("    ldp x29, x30, [sp], #16");
-    // Push return value onto stack
-    println!("    str x0, [sp, #-16]!");
+thread_local! {
+    static CURRENT_TARGET: Cell<Target> = const { Cell::new(Target::Aarch64Macos) };
+}
+
+pub(crate) fn target() -> Target {
+    CURRENT_TARGET.with(|t| t.get())
 }
 
 // Compute maximum stack offset needed for local variables and arrays
 fn compute_max_offset(node: &Node) -> u64 {
     match node {
-        Node::Seq { first, second } => {
+        Node::Seq { first, second, .. } => {
             let m1 = compute_max_offset(first);
             let m2 = compute_max_offset(second);
             if m1 > m2 { m1 } else { m2 }
         }
         Node::Function { body, .. } => compute_max_offset(body),
-        Node::Num { .. } | Node::StringLiteral { .. } => 0,
-        Node::Var { offset } => *offset,
+        Node::Struct { .. } | Node::Enum { .. } | Node::Const { .. } | Node::Static { .. } => 0,
+        Node::Num { .. } | Node::StringLiteral { .. } | Node::Variant { .. } => 0,
+        Node::Bool { .. } | Node::Unit { .. } => 0,
+        Node::Global { .. } => 0,
+        Node::Var { offset, .. } => *offset,
         Node::Call { args, .. } | Node::Syscall { args, .. } => {
             let mut m = 0;
             for arg in args {
@@ -239,15 +76,24 @@ fn compute_max_offset(node: &Node) -> u64 {
             }
             m
         }
-        Node::Assign { lhs, rhs } => compute_max_offset(lhs).max(compute_max_offset(rhs)),
-        Node::BinaryOp { op: _, lhs, rhs } => compute_max_offset(lhs).max(compute_max_offset(rhs)),
-        Node::Return { expr } | Node::Deref { expr } | Node::Addr { expr } => {
-            compute_max_offset(expr)
+        Node::Assign { lhs, rhs, .. } | Node::CompoundAssign { lhs, rhs, .. } => {
+            compute_max_offset(lhs).max(compute_max_offset(rhs))
         }
+        Node::BinaryOp { lhs, rhs, .. } => compute_max_offset(lhs).max(compute_max_offset(rhs)),
+        Node::Index { expr, index, .. } => compute_max_offset(expr).max(compute_max_offset(index)),
+        Node::Semi { expr, .. }
+        | Node::Let { init: expr, .. }
+        | Node::Not { expr, .. }
+        | Node::Neg { expr, .. }
+        | Node::Cast { expr, .. }
+        | Node::Deref { expr, .. }
+        | Node::Addr { expr, .. }
+        | Node::Field { expr, .. } => compute_max_offset(expr),
         Node::If {
             cond,
             then_stmt,
             else_stmt,
+            ..
         } => {
             let mut m = compute_max_offset(cond).max(compute_max_offset(then_stmt));
             if let Some(e) = else_stmt {
@@ -255,12 +101,13 @@ fn compute_max_offset(node: &Node) -> u64 {
             }
             m
         }
-        Node::While { cond, body } => compute_max_offset(cond).max(compute_max_offset(body)),
+        Node::While { cond, body, .. } => compute_max_offset(cond).max(compute_max_offset(body)),
         Node::For {
             init,
             cond,
             update,
             body,
+            ..
         } => {
             let mut m = compute_max_offset(init);
             m = m.max(compute_max_offset(cond));
@@ -268,7 +115,27 @@ fn compute_max_offset(node: &Node) -> u64 {
             m = m.max(compute_max_offset(body));
             m
         }
-        Node::ArrayAssign { offset, elements } => {
+        Node::ForRange {
+            offset,
+            start,
+            end,
+            body,
+            ..
+        } => {
+            let mut m = *offset;
+            m = m.max(compute_max_offset(start));
+            m = m.max(compute_max_offset(end));
+            m = m.max(compute_max_offset(body));
+            m
+        }
+        Node::Loop { body, .. } => compute_max_offset(body),
+        Node::Break { value, .. } | Node::Return { expr: value, .. } => {
+            value.as_deref().map_or(0, compute_max_offset)
+        }
+        Node::Continue { .. } => 0,
+        Node::ArrayAssign {
+            offset, elements, ..
+        } => {
             let mut m = *offset;
             if !elements.is_empty() {
                 let eo = *offset + (elements.len() as u64 - 1) * 8;
@@ -284,39 +151,50 @@ fn compute_max_offset(node: &Node) -> u64 {
             }
             m
         }
+        Node::ArrayRepeat {
+            offset, value, len, ..
+        } => {
+            let last = *offset + len.saturating_sub(1) * 8;
+            last.max(compute_max_offset(value))
+        }
+        // the field initializers cover every slot of the struct
+        Node::StructLit { offset, fields, .. } => fields
+            .iter()
+            .map(|(_, init)| compute_max_offset(init))
+            .fold(*offset, u64::max),
+        Node::VariantLit { offset, fields, .. } => fields
+            .iter()
+            .map(compute_max_offset)
+            .fold(*offset, u64::max),
+        Node::Match { expr, arms, .. } => {
+            let mut m = compute_max_offset(expr);
+            for arm in arms {
+                m = m.max(compute_max_offset(&arm.body));
+                // slots of the names bound by the patterns
+                for pat in &arm.pats {
+                    match pat {
+                        Pattern::Bind { offset } => m = m.max(*offset),
+                        Pattern::Variant { fields, .. } => {
+                            m = fields.iter().flatten().copied().fold(m, u64::max);
+                        }
+                        Pattern::Wild | Pattern::Range { .. } | Pattern::Bool { .. } => {}
+                    }
+                }
+            }
+            m
+        }
     }
 }
 
-fn gen_prologue(name: &String, frame_size: u64) {
-    println!(".globl _{}", name);
-    println!("_{}:", name);
-    // save old frame pointer and set up new
-    println!("    stp x29, x30, [sp, #-16]!");
-    println!("    mov x29, sp");
-    // reserve space for local variables
-    println!("    sub sp, sp, #{}", frame_size);
-    println!("    str x0, [x29, #-8]");
-}
-
-fn gen_epilogue(frame_size: u64) {
-    // pop return value into x0
-    println!("    ldr x0, [sp], #16");
-    // deallocate local variable region
-    println!("    add sp, sp, #{}", frame_size);
-    // restore frame pointer and return
-    println!("    ldp x29, x30, [sp], #16");
-    println!("    ret");
-}
-
-// helper to emit code for function definitions
-fn emit_function(name: &String, args: &Vec<Node>, body: &Box<Node>) {
-    // compute required frame size based on arguments and body
+/// Compute the 16-byte aligned frame size (at least 48 bytes) needed by a function's
+/// parameters, local variables and arrays.
+pub(crate) fn frame_size(args: &[Node], body: &Node) -> u64 {
     let mut max_offset = 0u64;
     for arg in args.iter() {
-        if let Node::Var { offset } = arg {
-            if *offset > max_offset {
-                max_offset = *offset;
-            }
+        if let Node::Var { offset, .. } = arg
+            && *offset > max_offset
+        {
+            max_offset = *offset;
         }
     }
     let body_max = compute_max_offset(body);
@@ -324,153 +202,154 @@ fn emit_function(name: &String, args: &Vec<Node>, body: &Box<Node>) {
         max_offset = body_max;
     }
     // align frame size to 16 bytes, at least 48
-    let frame_size = if max_offset > 48 {
-        ((max_offset + 15) / 16) * 16
+    if max_offset > 48 {
+        max_offset.div_ceil(16) * 16
     } else {
         48
-    };
-    gen_prologue(name, frame_size);
-    // Save arguments to local variables
-    for (i, arg) in args.iter().enumerate() {
-        if let Node::Var { offset } = arg {
-            println!("    str x{}, [x29, #-{}]", i, offset);
-        }
     }
-    gen_node(body);
-    gen_epilogue(frame_size);
 }
 
-// helper to emit code for dereference
-fn emit_deref(node: &Node) {
-    gen_node(node);
-    println!("    ldr x0, [sp], #16");
-    println!("    ldr x0, [x0]");
-    println!("    str x0, [sp, #-16]!");
+/// Lowest value and per-value targets of a jump table for a `Switch` with
+/// these cases, if they are dense enough to be worth one: at least four cases
+/// filling at least half of the table. Values without a case go to `default`.
+pub(crate) fn jump_table(
+    cases: &[(i64, ir::BlockId)],
+    default: ir::BlockId,
+) -> Option<(i64, Vec<ir::BlockId>)> {
+    if cases.len() < 4 {
+        return None;
+    }
+    let lo = cases.iter().map(|(v, _)| *v).min()?;
+    let hi = cases.iter().map(|(v, _)| *v).max()?;
+    let len = usize::try_from(hi.checked_sub(lo)?).ok()? + 1;
+    if len > cases.len() * 2 {
+        return None;
+    }
+    let mut table = vec![default; len];
+    for (v, target) in cases {
+        table[(v - lo) as usize] = *target;
+    }
+    Some((lo, table))
 }
 
-// helper to emit code for address-of
-fn emit_addr(node: &Node) {
-    match node {
-        Node::Var { offset } => {
-            println!("    mov x0, x29");
-            println!("    sub x0, x0, #{}", offset);
-            println!("    str x0, [sp, #-16]!");
-        }
-        Node::Deref { expr } => {
-            gen_node(expr);
-        }
-        _ => panic!("address-of not supported for {:?}", node),
+/// Label of the routine reporting `panic`.
+pub(crate) fn panic_label(panic: ir::Panic) -> String {
+    match panic {
+        ir::Panic::Overflow(op) => format!(".Lpanic.{}_overflow", op),
+        ir::Panic::Bounds => ".Lpanic.bounds".to_string(),
+        ir::Panic::ZeroDivisor(op) => format!(".Lpanic.{}_by_zero", op),
+        ir::Panic::NegOverflow => ".Lpanic.neg_overflow".to_string(),
     }
 }
 
-// helper to emit code for string literals
-fn emit_string(s: &str) {
-    // Generate a unique label for this string
-    let id = LABEL_COUNTER.fetch_add(1, Ordering::SeqCst);
-    let label = format!(".L.str.{}", id);
+/// Labels and contents of the strings the routines for `panics`
+/// write: the pieces of the `thread 'main' panicked at <location>:` line,
+/// of the index out of bounds message and one message per other routine.
+pub(crate) fn panic_strings(panics: &[ir::Panic]) -> Vec<(String, String)> {
+    let mut strings = vec![
+        (
+            ".Lpanic.thread".to_string(),
+            "thread 'main' panicked at ".to_string(),
+        ),
+        (".Lpanic.colon".to_string(), ":\n".to_string()),
+    ];
+    for panic in panics {
+        let msg = match panic {
+            ir::Panic::Overflow(op) => op.overflow_message(),
+            ir::Panic::NegOverflow => OpKind::NEG_OVERFLOW,
+            ir::Panic::ZeroDivisor(op) => op.zero_divisor_message(),
+            // the length and the index are written between the pieces
+            ir::Panic::Bounds => {
+                strings.push((
+                    ".Lpanic.bounds.len".to_string(),
+                    "index out of bounds: the len is ".to_string(),
+                ));
+                strings.push((
+                    ".Lpanic.bounds.index".to_string(),
+                    " but the index is ".to_string(),
+                ));
+                strings.push((".Lpanic.bounds.end".to_string(), "\n".to_string()));
+                continue;
+            }
+        };
+        strings.push((format!("{}.msg", panic_label(*panic)), format!("{}\n", msg)));
+    }
+    strings
+}
 
-    // Emit the string data in the data section
-    println!(".section __DATA,__data");
+/// Emit `label` followed by the UTF-8 bytes of `s` and a terminating NUL as
+/// `.byte` directives, so that no character is reinterpreted by the assembler.
+pub(crate) fn emit_string(label: &str, s: &str) {
     println!("{}:", label);
-    println!("    .asciz \"{}\"", s);
-
-    // Switch back to text section
-    println!(".section __TEXT,__text");
-
-    // Load the address of the string into x0 (this represents the &str)
-    println!("    adrp x0, {}@PAGE", label);
-    println!("    add x0, x0, {}@PAGEOFF", label);
-
-    // Push the string slice address onto the stack
-    println!("    str x0, [sp, #-16]!");
+    let bytes: Vec<u8> = s.bytes().chain(std::iter::once(0)).collect();
+    for chunk in bytes.chunks(16) {
+        let list: Vec<String> = chunk.iter().map(|b| b.to_string()).collect();
+        println!("    .byte {}", list.join(", "));
+    }
 }
 
-// helper to emit code for array literal assignment
-fn emit_array_assign(offset: u64, elements: &[Node]) {
-    for (i, elem) in elements.iter().enumerate() {
-        // evaluate element value
-        gen_node(elem);
-        // pop into x1
-        println!("    ldr x1, [sp], #16");
-        // compute element offset and store using register addressing
-        let off_i = offset + (i as u64) * 8;
-        println!("    mov x2, x29");
-        println!("    sub x2, x2, #{}", off_i);
-        println!("    str x1, [x2]");
-    }
-    // push dummy to maintain stack balance
-    println!("    mov x0, #0");
-    println!("    str x0, [sp, #-16]!");
+/// Label of the first slot of `static` `name`.
+pub(crate) fn static_label(name: &str) -> String {
+    format!(".L.static.{}", name)
 }
 
-// helper to recursively generate code for each node
-fn gen_node(node: &Node) {
-    match node {
-        Node::Seq { first, second } => emit_seq(first, second),
-        Node::Function { name, args, body } => emit_function(name, args, body),
-        Node::Num { value } => push_imm(*value),
-        Node::StringLiteral { value } => emit_string(value),
-        Node::Var { offset } => emit_var(*offset),
-        Node::Call { name, args } => emit_call(name, args),
-        Node::Syscall { name, args } => emit_syscall(name, args),
-        Node::Return { expr } => emit_return(expr),
-        Node::If {
-            cond,
-            then_stmt,
-            else_stmt,
-        } => emit_if(cond, then_stmt, else_stmt.as_deref()),
-        Node::While { cond, body } => emit_while(cond, body),
-        Node::For {
-            init,
-            cond,
-            update,
-            body,
-        } => emit_for(init, cond, update, body),
-        Node::ArrayAssign { offset, elements } => emit_array_assign(*offset, elements),
-        Node::Assign { lhs, rhs } => emit_assign(lhs, rhs),
-        Node::BinaryOp { op, lhs, rhs } => {
-            // Detect array indexing: addr - idx * 8, then dereference
-            if *op == OpKind::Sub {
-                if let Node::Addr { .. } = lhs.as_ref() {
-                    if let Node::BinaryOp {
-                        op: OpKind::Mul,
-                        lhs: _,
-                        rhs: mul_rhs_box,
-                    } = rhs.as_ref()
-                    {
-                        if let Node::Num { value: 8 } = *mul_rhs_box.as_ref() {
-                            // compute address
-                            emit_binop("sub", lhs, rhs);
-                            // pop pointer, load from memory, push value
-                            println!("    ldr x0, [sp], #16");
-                            println!("    ldr x0, [x0]");
-                            println!("    str x0, [sp, #-16]!");
-                            return;
-                        }
-                    }
+/// Emit the `static` items of the module: those with a non-zero slot into
+/// the `data` section and the others into `bss`. The slots are written from
+/// the last one down to the label of slot 0, so that slot `k` lies `8 * k`
+/// bytes below it like in a local variable.
+pub(crate) fn emit_statics(
+    module: &ir::Module,
+    data: &str,
+    bss: &str,
+    string_label: fn(usize) -> String,
+) {
+    let (zeroed, initialized): (Vec<&ir::Static>, Vec<&ir::Static>) = module
+        .statics
+        .iter()
+        .partition(|s| s.data.iter().all(|d| *d == ir::Data::Int(0)));
+    if !initialized.is_empty() {
+        println!("{}", data);
+        for s in initialized {
+            println!(".p2align 3");
+            for (k, value) in s.data.iter().enumerate().rev() {
+                if k == 0 {
+                    println!("{}:", static_label(&s.name));
+                }
+                match value {
+                    ir::Data::Int(v) => println!("    .quad {}", v),
+                    ir::Data::Str(id) => println!("    .quad {}", string_label(*id)),
                 }
             }
-            // Normal binary operations
-            match op {
-                OpKind::Add => emit_binop("add", lhs, rhs),
-                OpKind::Sub => emit_binop("sub", lhs, rhs),
-                OpKind::Mul => emit_binop("mul", lhs, rhs),
-                OpKind::Div => emit_binop("sdiv", lhs, rhs),
-                OpKind::Eq => emit_cmp("eq", lhs, rhs),
-                OpKind::Ne => emit_cmp("ne", lhs, rhs),
-                OpKind::Lt => emit_cmp("lt", lhs, rhs),
-                OpKind::Gt => emit_cmp("gt", lhs, rhs),
-                OpKind::Le => emit_cmp("le", lhs, rhs),
-                OpKind::Ge => emit_cmp("ge", lhs, rhs),
+        }
+    }
+    if !zeroed.is_empty() {
+        println!("{}", bss);
+        for s in zeroed {
+            println!(".p2align 3");
+            if s.data.len() > 1 {
+                println!("    .zero {}", (s.data.len() - 1) * 8);
             }
+            println!("{}:", static_label(&s.name));
+            println!("    .zero 8");
         }
-        Node::Deref { expr } => emit_deref(expr),
-        Node::Addr { expr } => emit_addr(expr),
     }
 }
 
-/// Generate full ARM64 assembly for the AST, including prologue and epilogue.
-pub fn generate(node: &Node) {
-    println!(".section __TEXT,__text");
-    gen_node(node);
+/// Generate full assembly for the AST on the given target, including prologue and epilogue.
+/// The program is lowered to the IR first, which the target's emitter then consumes.
+/// `file` and `overflow_checks` are passed on to `ir::lower`: the first for the
+/// locations failed checks report, the second to select between panicking and
+/// wrapping integer arithmetic. Variables whose address is never taken are
+/// kept in virtual registers before the emitter allocates them.
+pub fn generate(node: &Node, file: &SourceFile, target: Target, overflow_checks: bool) {
+    CURRENT_TARGET.with(|t| t.set(target));
+    let mut module = ir::lower(node, file, overflow_checks);
+    for func in &mut module.functions {
+        ir::promote_locals(func);
+        ir::remove_dead_code(func);
+    }
+    match target {
+        Target::Aarch64Macos | Target::Aarch64Linux => arm64::generate(&module),
+        Target::X86_64Linux => x86_64::generate(&module),
+    }
 }
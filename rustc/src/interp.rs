@@ -0,0 +1,1167 @@
+use crate::check::{SourceFile, Span};
+use crate::codegen::frame_size;
+use crate::node::{ConstValue, MatchArm, Node, OpKind, Pattern, Type};
+use std::collections::HashMap;
+use std::fmt;
+use std::thread;
+
+/// Size of the simulated stack in bytes, that of the main thread on Linux.
+const STACK_SIZE: usize = 1 << 23;
+/// Size of the host thread stack the interpreter recurses on.
+const HOST_STACK_SIZE: usize = 1 << 30;
+/// Host stack a call leaves free for evaluating the body of the function.
+const HOST_STACK_RESERVE: usize = 1 << 24;
+/// Type of the slots accessed as whole words, like addresses and enum tags.
+const WORD: &Type = &Type::I64;
+
+/// Represents an error raised while interpreting a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeError {
+    pub msg: String,
+    /// The expression that failed, for the errors Rust reports as a panic.
+    pub span: Option<Span>,
+}
+
+impl RuntimeError {
+    /// Render the error as the program itself would report it: a panic as
+    /// `thread 'main' panicked at <location>:` followed by the message, like
+    /// the native targets do.
+    pub fn render(&self, file: &SourceFile) -> String {
+        match self.span {
+            Some(span) => format!(
+                "thread 'main' panicked at {}:\n{}",
+                file.location(span),
+                self.msg
+            ),
+            None => self.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Runtime error: {}", self.msg)
+    }
+}
+
+fn error<T>(msg: String) -> Result<T, RuntimeError> {
+    Err(RuntimeError { msg, span: None })
+}
+
+// a failed runtime check of the expression at `span`
+fn panic<T>(msg: String, span: Span) -> Result<T, RuntimeError> {
+    Err(RuntimeError {
+        msg,
+        span: Some(span),
+    })
+}
+
+/// Result of running a program: `main`'s return value and everything written to stdout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub exit_code: i64,
+    pub stdout: Vec<u8>,
+}
+
+// Control flow out of a node: a plain value, an early `return`, or a jump
+// to the loop with the given label (the innermost one when `None`)
+enum Flow {
+    Value(i64),
+    Return(i64),
+    Break(Option<String>, i64),
+    Continue(Option<String>),
+}
+
+// propagate a `return`, `break` or `continue` out of the enclosing
+// evaluation, otherwise yield the value
+macro_rules! value {
+    ($flow:expr) => {
+        match $flow? {
+            Flow::Value(v) => v,
+            other => return Ok(other),
+        }
+    };
+}
+
+// What a loop does after running its body once
+enum Step {
+    Next,
+    Exit(i64),
+    Escape(Flow),
+}
+
+// whether a `break` or `continue` aimed at `target` stops at the loop labelled `label`
+fn targets(target: &Option<String>, label: &Option<String>) -> bool {
+    target.is_none() || target == label
+}
+
+/// A tree-walking interpreter over the `Node` AST.
+///
+/// Memory is a flat byte array laid out like the native targets: `static` items and
+/// string literals live at the bottom, and each call allocates a frame below the
+/// caller's, addressing `Var { offset }` slots as `fp - offset` so that
+/// `Addr`/`Deref` and array indexing behave exactly as in the generated assembly.
+struct Interpreter<'a> {
+    // the definition of every function
+    functions: HashMap<&'a str, &'a Node>,
+    memory: Vec<u8>,
+    strings: HashMap<&'a str, i64>,
+    // address of the first slot of every `static` item
+    statics: HashMap<&'a str, i64>,
+    // variable types of the running function, by slot offset
+    locals: Option<&'a HashMap<u64, Type>>,
+    // whether `+`, `-`, `*`, negation and shifts panic on overflow rather
+    // than wrap
+    overflow_checks: bool,
+    fp: i64,
+    sp: i64,
+    stack_limit: i64,
+    // host stack address below which calls fail rather than recurse further
+    host_stack_limit: usize,
+    stdout: Vec<u8>,
+}
+
+impl<'a> Interpreter<'a> {
+    fn new(program: &'a Node, overflow_checks: bool, host_stack_limit: usize) -> Self {
+        let mut interp = Self {
+            functions: HashMap::new(),
+            // address 0 stays unused so that it can act as a null pointer
+            memory: vec![0; 8],
+            strings: HashMap::new(),
+            statics: HashMap::new(),
+            locals: None,
+            overflow_checks,
+            fp: 0,
+            sp: 0,
+            stack_limit: 0,
+            host_stack_limit,
+            stdout: Vec::new(),
+        };
+        interp.collect(program);
+        interp
+    }
+
+    // register every function definition in the program and lay out the
+    // `static` items
+    fn collect(&mut self, node: &'a Node) {
+        match node {
+            Node::Seq { first, second, .. } => {
+                self.collect(first);
+                self.collect(second);
+            }
+            Node::Function { name, .. } => {
+                self.functions.insert(name, node);
+            }
+            Node::Static { name, data, .. } => {
+                let addr = self.allocate_static(data);
+                self.statics.insert(name, addr);
+            }
+            _ => {}
+        }
+    }
+
+    // place the slots of a `static` below the stack, slot `k` at `addr - 8k`
+    // like those of a local, and return `addr`
+    fn allocate_static(&mut self, data: &'a [ConstValue]) -> i64 {
+        let values: Vec<i64> = data
+            .iter()
+            .map(|value| match value {
+                ConstValue::Int(v) => *v,
+                ConstValue::Str(s) => self.intern(s),
+            })
+            .collect();
+        let start = self.memory.len();
+        for v in values.iter().rev() {
+            self.memory.extend_from_slice(&v.to_le_bytes());
+        }
+        (start + 8 * values.len().saturating_sub(1)) as i64
+    }
+
+    // place string literals below the stack, null-terminated like in the data section
+    fn intern(&mut self, s: &'a str) -> i64 {
+        if let Some(&addr) = self.strings.get(s) {
+            return addr;
+        }
+        let addr = self.memory.len() as i64;
+        self.memory.extend_from_slice(s.as_bytes());
+        self.memory.push(0);
+        self.strings.insert(s, addr);
+        addr
+    }
+
+    fn check_addr(&self, addr: i64, len: usize) -> Result<usize, RuntimeError> {
+        if addr <= 0 || addr as usize + len > self.memory.len() {
+            return error(format!("invalid memory access at address {}", addr));
+        }
+        Ok(addr as usize)
+    }
+
+    fn load(&self, addr: i64) -> Result<i64, RuntimeError> {
+        let a = self.check_addr(addr, 8)?;
+        let bytes: [u8; 8] = self.memory[a..a + 8].try_into().unwrap();
+        Ok(i64::from_le_bytes(bytes))
+    }
+
+    fn store(&mut self, addr: i64, value: i64) -> Result<(), RuntimeError> {
+        let a = self.check_addr(addr, 8)?;
+        self.memory[a..a + 8].copy_from_slice(&value.to_le_bytes());
+        Ok(())
+    }
+
+    // read an integer of type `ty`, extended to 64 bits like the native loads
+    fn load_as(&self, addr: i64, ty: &Type) -> Result<i64, RuntimeError> {
+        let len = width(ty);
+        let a = self.check_addr(addr, len)?;
+        let mut bytes = [0; 8];
+        bytes[..len].copy_from_slice(&self.memory[a..a + len]);
+        Ok(ty.wrap(i64::from_le_bytes(bytes)))
+    }
+
+    // write the low bytes of `value` that fit `ty`
+    fn store_as(&mut self, addr: i64, value: i64, ty: &Type) -> Result<(), RuntimeError> {
+        let len = width(ty);
+        let a = self.check_addr(addr, len)?;
+        self.memory[a..a + len].copy_from_slice(&value.to_le_bytes()[..len]);
+        Ok(())
+    }
+
+    fn load_byte(&self, addr: i64) -> Result<u8, RuntimeError> {
+        let a = self.check_addr(addr, 1)?;
+        Ok(self.memory[a])
+    }
+
+    // address of a variable slot in the current frame
+    fn slot(&self, offset: u64) -> i64 {
+        self.fp - offset as i64
+    }
+
+    // type of the variable in slot `offset`; slots without a type of their
+    // own, like the fields of an enum value, are whole words
+    fn local_ty(&self, offset: u64) -> &'a Type {
+        self.locals.and_then(|l| l.get(&offset)).unwrap_or(WORD)
+    }
+
+    // type of the value an lvalue designates
+    fn place_ty(&self, node: &'a Node) -> &'a Type {
+        match node {
+            Node::Var { offset, .. } => self.local_ty(*offset),
+            Node::Global { ty, .. }
+            | Node::Deref { ty, .. }
+            | Node::Field { ty, .. }
+            | Node::Index { ty, .. } => ty,
+            _ => WORD,
+        }
+    }
+
+    // evaluate an arithmetic or bitwise operator at `span` on operands of
+    // type `ty`, checking `+`, `-`, `*` and shifts for overflow when asked to
+    fn arith(
+        &self,
+        op: OpKind,
+        l: i64,
+        r: i64,
+        ty: &Type,
+        span: Span,
+    ) -> Result<i64, RuntimeError> {
+        if self.overflow_checks
+            && matches!(op, OpKind::Add | OpKind::Sub | OpKind::Mul)
+            && let Some((min, max)) = ty.int_range()
+        {
+            // a `u64` above `i64::MAX` is held in an `i64` with the same bits
+            let widen = |v: i64| match ty.int_bits() {
+                Some((64, false)) => v as u64 as i128,
+                _ => v as i128,
+            };
+            let (l, r) = (widen(l), widen(r));
+            let exact = match op {
+                OpKind::Add => Some(l + r),
+                OpKind::Sub => Some(l - r),
+                _ => l.checked_mul(r),
+            };
+            if !exact.is_some_and(|v| (min..=max).contains(&v)) {
+                return panic(op.overflow_message().to_string(), span);
+            }
+        }
+        // a shift by the width of the type or more
+        if self.overflow_checks
+            && matches!(op, OpKind::Shl | OpKind::Shr)
+            && r as u64 >= ty.int_bits().map_or(64, |(bits, _)| bits as u64)
+        {
+            return panic(op.overflow_message().to_string(), span);
+        }
+        // a zero divisor or the lowest value divided by -1
+        binary(op, l, r, ty).or_else(|e| panic(e.msg, span))
+    }
+
+    // negate `v` of type `ty` at `span`, checking for overflow when asked to
+    fn neg(&self, v: i64, ty: &Type, span: Span) -> Result<i64, RuntimeError> {
+        if self.overflow_checks && ty.int_range().is_some_and(|(min, _)| v as i128 == min) {
+            return panic(OpKind::NEG_OVERFLOW.to_string(), span);
+        }
+        Ok(ty.wrap(v.wrapping_neg()))
+    }
+
+    fn call(&mut self, name: &str, args: Vec<i64>) -> Result<i64, RuntimeError> {
+        let Some(&Node::Function {
+            args: params,
+            body,
+            locals,
+            ..
+        }) = self.functions.get(name)
+        else {
+            return error(format!("call to undefined function: {}", name));
+        };
+        if params.len() != args.len() {
+            return error(format!(
+                "function {} takes {} arguments but {} were supplied",
+                name,
+                params.len(),
+                args.len()
+            ));
+        }
+        // allocate the stack on the first call; later strings are placed above it
+        if self.sp == 0 {
+            self.stack_limit = self.memory.len() as i64;
+            self.memory.resize(self.memory.len() + STACK_SIZE, 0);
+            self.sp = self.memory.len() as i64;
+        }
+        // push a new frame: [saved fp/lr][locals...] like the native prologue
+        let saved = (self.fp, self.sp, self.locals);
+        let fp = self.sp - 16;
+        let sp = fp - frame_size(params, body) as i64;
+        // interpreted calls recurse on the host stack as well, which may run
+        // out before the simulated one
+        let here = 0u8;
+        if sp < self.stack_limit || (&raw const here as usize) < self.host_stack_limit {
+            return error(format!("stack overflow in call to {}", name));
+        }
+        self.fp = fp;
+        self.sp = sp;
+        self.locals = Some(locals);
+        // bind arguments to their parameter slots
+        for (param, value) in params.iter().zip(args) {
+            if let Node::Var { offset, .. } = param {
+                self.store_as(self.slot(*offset), value, self.local_ty(*offset))?;
+            }
+        }
+        let result = self.eval(body);
+        (self.fp, self.sp, self.locals) = saved;
+        match result? {
+            Flow::Value(v) | Flow::Return(v) => Ok(v),
+            Flow::Break(..) | Flow::Continue(_) => error(format!(
+                "`break` or `continue` outside of a loop in {}",
+                name
+            )),
+        }
+    }
+
+    // run a loop body once, catching the `break` and `continue` meant for this loop
+    fn iterate(&mut self, body: &'a Node, label: &Option<String>) -> Result<Step, RuntimeError> {
+        Ok(match self.eval(body)? {
+            Flow::Value(_) => Step::Next,
+            Flow::Continue(target) if targets(&target, label) => Step::Next,
+            Flow::Break(target, v) if targets(&target, label) => Step::Exit(v),
+            other => Step::Escape(other),
+        })
+    }
+
+    // evaluate the address designated by an lvalue expression
+    fn addr_of(&mut self, node: &'a Node) -> Result<Flow, RuntimeError> {
+        match node {
+            Node::Var { offset, .. } => Ok(Flow::Value(self.slot(*offset))),
+            Node::Global { name, .. } => Ok(Flow::Value(self.statics[name.as_str()])),
+            Node::Deref { expr, .. } => self.eval(expr),
+            // a field lies `offset` bytes below the start of its struct
+            Node::Field { expr, offset, .. } => {
+                let base = value!(self.addr_of(expr));
+                Ok(Flow::Value(base - *offset as i64))
+            }
+            // an array element lies `8 * index` bytes below the start of the
+            // array, once the index is known to be within it
+            Node::Index {
+                expr: array,
+                index,
+                span,
+                ..
+            } => {
+                let base = value!(self.addr_of(array));
+                let index = value!(self.eval(index));
+                if let Type::Array(_, len) = self.place_ty(array)
+                    && index as u64 >= *len
+                {
+                    return panic(
+                        format!(
+                            "index out of bounds: the len is {} but the index is {}",
+                            len, index as u64
+                        ),
+                        *span,
+                    );
+                }
+                let scaled = binary(OpKind::Mul, index, 8, WORD)?;
+                Ok(Flow::Value(binary(OpKind::Sub, base, scaled, WORD)?))
+            }
+            // a struct literal is built in its own slots
+            Node::StructLit { offset, .. } => {
+                value!(self.eval_aggregate(node));
+                Ok(Flow::Value(self.slot(*offset)))
+            }
+            _ => error(format!("address-of not supported for {:?}", node)),
+        }
+    }
+
+    fn syscall(&mut self, name: &str, args: &[i64]) -> Result<i64, RuntimeError> {
+        match name {
+            "write" => {
+                let Some(&addr) = args.first() else {
+                    return error("write expects a string argument".to_string());
+                };
+                // write up to the null terminator, like the generated strlen loop
+                let mut len = 0;
+                while self.load_byte(addr + len)? != 0 {
+                    len += 1;
+                }
+                let start = self.check_addr(addr, len as usize)?;
+                let bytes = self.memory[start..start + len as usize].to_vec();
+                self.stdout.extend_from_slice(&bytes);
+                Ok(len)
+            }
+            _ => error(format!("unsupported system call: {}", name)),
+        }
+    }
+
+    // evaluate a loop; kept out of `eval` so that its locals do not grow the
+    // host stack frame of every interpreted call
+    fn eval_loop(&mut self, node: &'a Node) -> Result<Flow, RuntimeError> {
+        let v = match node {
+            Node::While {
+                cond, body, label, ..
+            } => {
+                while value!(self.eval(cond)) != 0 {
+                    match self.iterate(body, label)? {
+                        Step::Next => {}
+                        Step::Exit(_) => break,
+                        Step::Escape(flow) => return Ok(flow),
+                    }
+                }
+                0
+            }
+            Node::For {
+                init,
+                cond,
+                update,
+                body,
+                label,
+                ..
+            } => {
+                value!(self.eval(init));
+                while value!(self.eval(cond)) != 0 {
+                    match self.iterate(body, label)? {
+                        Step::Next => {}
+                        Step::Exit(_) => break,
+                        Step::Escape(flow) => return Ok(flow),
+                    }
+                    value!(self.eval(update));
+                }
+                0
+            }
+            Node::ForRange {
+                offset,
+                start,
+                end,
+                inclusive,
+                body,
+                label,
+                ..
+            } => {
+                // the bounds are evaluated once; assigning to the loop variable
+                // does not change the iteration
+                let mut i = value!(self.eval(start));
+                let end = value!(self.eval(end));
+                let ty = self.local_ty(*offset);
+                let op = if *inclusive { OpKind::Le } else { OpKind::Lt };
+                while binary(op, i, end, ty)? != 0 {
+                    self.store_as(self.slot(*offset), i, ty)?;
+                    match self.iterate(body, label)? {
+                        Step::Next => {}
+                        Step::Exit(_) => break,
+                        Step::Escape(flow) => return Ok(flow),
+                    }
+                    i += 1;
+                }
+                0
+            }
+            Node::Loop { body, label, .. } => loop {
+                match self.iterate(body, label)? {
+                    Step::Next => {}
+                    Step::Exit(v) => break v,
+                    Step::Escape(flow) => return Ok(flow),
+                }
+            },
+            _ => unreachable!("not a loop: {:?}", node),
+        };
+        Ok(Flow::Value(v))
+    }
+
+    fn eval(&mut self, node: &'a Node) -> Result<Flow, RuntimeError> {
+        let v = match node {
+            Node::Seq { first, second, .. } => {
+                value!(self.eval(first));
+                value!(self.eval(second))
+            }
+            // statements run for their effect and yield `()`
+            Node::Semi { expr, .. } | Node::Let { init: expr, .. } => {
+                value!(self.eval(expr));
+                0
+            }
+            Node::Function { .. }
+            | Node::Struct { .. }
+            | Node::Enum { .. }
+            | Node::Const { .. }
+            | Node::Static { .. } => 0,
+            Node::Num { value, .. } => *value as i64,
+            Node::Bool { value, .. } => *value as i64,
+            Node::Unit { .. } => 0,
+            Node::Variant { tag, .. } => *tag as i64,
+            Node::StringLiteral { value, .. } => self.intern(value),
+            Node::Call { name, args, .. } | Node::Syscall { name, args, .. } => {
+                let mut values = Vec::new();
+                for arg in args {
+                    values.push(value!(self.eval(arg)));
+                }
+                if let Node::Call { .. } = node {
+                    self.call(name, values)?
+                } else {
+                    self.syscall(name, &values)?
+                }
+            }
+            Node::Assign { rhs, .. } if matches!(**rhs, Node::StructLit { .. }) => {
+                return self.eval_aggregate(node);
+            }
+            Node::Assign { lhs, rhs, .. } => {
+                let value = value!(self.eval(rhs));
+                let addr = value!(self.addr_of(lhs));
+                self.store_as(addr, value, self.place_ty(lhs))?;
+                value
+            }
+            Node::CompoundAssign {
+                op,
+                lhs,
+                rhs,
+                ty,
+                span,
+            } => {
+                let r = value!(self.eval(rhs));
+                let addr = value!(self.addr_of(lhs));
+                let value = self.arith(*op, self.load_as(addr, ty)?, r, ty, *span)?;
+                self.store_as(addr, value, ty)?;
+                value
+            }
+            Node::BinaryOp {
+                op: op @ (OpKind::And | OpKind::Or),
+                lhs,
+                rhs,
+                ..
+            } => {
+                // the right operand only runs when the left one does not decide
+                let l = value!(self.eval(lhs));
+                if (l != 0) == (*op == OpKind::Or) {
+                    l
+                } else {
+                    value!(self.eval(rhs))
+                }
+            }
+            Node::Not {
+                expr, bitwise, ty, ..
+            } => {
+                let v = value!(self.eval(expr));
+                if *bitwise {
+                    ty.wrap(!v)
+                } else {
+                    (v == 0) as i64
+                }
+            }
+            // a negative literal needs no check, which would reject `i64::MIN`
+            Node::Neg { expr, ty, .. } if matches!(**expr, Node::Num { .. }) => {
+                ty.wrap(value!(self.eval(expr)).wrapping_neg())
+            }
+            Node::Neg { expr, ty, span } => {
+                let v = value!(self.eval(expr));
+                self.neg(v, ty, *span)?
+            }
+            Node::BinaryOp {
+                op,
+                lhs,
+                rhs,
+                ty,
+                span,
+            } => {
+                let l = value!(self.eval(lhs));
+                let r = value!(self.eval(rhs));
+                self.arith(*op, l, r, ty, *span)?
+            }
+            Node::Cast { expr, ty, .. } => ty.wrap(value!(self.eval(expr))),
+            Node::Return { expr, .. } => {
+                let v = match expr {
+                    Some(expr) => value!(self.eval(expr)),
+                    None => 0,
+                };
+                return Ok(Flow::Return(v));
+            }
+            Node::If {
+                cond,
+                then_stmt,
+                else_stmt,
+                ..
+            } => {
+                if value!(self.eval(cond)) != 0 {
+                    value!(self.eval(then_stmt))
+                } else if let Some(es) = else_stmt {
+                    value!(self.eval(es))
+                } else {
+                    0
+                }
+            }
+            Node::While { .. } | Node::For { .. } | Node::ForRange { .. } | Node::Loop { .. } => {
+                return self.eval_loop(node);
+            }
+            Node::Break { label, value, .. } => {
+                let v = match value {
+                    Some(v) => value!(self.eval(v)),
+                    None => 0,
+                };
+                return Ok(Flow::Break(label.clone(), v));
+            }
+            Node::Continue { label, .. } => return Ok(Flow::Continue(label.clone())),
+            Node::Var { .. }
+            | Node::Deref { .. }
+            | Node::Field { .. }
+            | Node::Index { .. }
+            | Node::Global { .. } => {
+                let addr = value!(self.addr_of(node));
+                self.load_as(addr, self.place_ty(node))?
+            }
+            Node::Addr { expr, .. } => value!(self.addr_of(expr)),
+            Node::ArrayAssign { .. }
+            | Node::ArrayRepeat { .. }
+            | Node::StructLit { .. }
+            | Node::VariantLit { .. } => {
+                return self.eval_aggregate(node);
+            }
+            Node::Match { .. } => return self.eval_match(node),
+        };
+        Ok(Flow::Value(v))
+    }
+
+    // store an array or struct literal into its slots, or assign a struct
+    // literal to a place; kept out of `eval` for the same reason as
+    // `eval_loop`
+    fn eval_aggregate(&mut self, node: &'a Node) -> Result<Flow, RuntimeError> {
+        match node {
+            // the struct literal is built in its own slots and copied into
+            // the place slot by slot
+            Node::Assign { lhs, rhs, .. } => {
+                let Node::StructLit { slots, .. } = rhs.as_ref() else {
+                    unreachable!("not a struct literal: {:?}", rhs);
+                };
+                let src = value!(self.addr_of(rhs));
+                let dst = value!(self.addr_of(lhs));
+                for k in 0..*slots as i64 {
+                    let value = self.load(src - k * 8)?;
+                    self.store(dst - k * 8, value)?;
+                }
+            }
+            Node::ArrayAssign {
+                offset, elements, ..
+            } => {
+                for (i, elem) in elements.iter().enumerate() {
+                    let value = value!(self.eval(elem));
+                    self.store(self.slot(*offset + i as u64 * 8), value)?;
+                }
+            }
+            Node::ArrayRepeat {
+                offset, value, len, ..
+            } => {
+                let value = value!(self.eval(value));
+                for i in 0..*len {
+                    self.store(self.slot(*offset + i * 8), value)?;
+                }
+            }
+            Node::StructLit { fields, .. } => {
+                for (_, init) in fields {
+                    value!(self.eval(init));
+                }
+            }
+            Node::VariantLit {
+                tag,
+                offset,
+                fields,
+                ..
+            } => {
+                self.store(self.slot(*offset), *tag as i64)?;
+                for init in fields {
+                    value!(self.eval(init));
+                }
+            }
+            _ => unreachable!("not an array, struct or enum literal: {:?}", node),
+        }
+        Ok(Flow::Value(0))
+    }
+
+    // run the first arm of a `match` whose pattern matches; kept out of
+    // `eval` for the same reason as `eval_loop`
+    fn eval_match(&mut self, node: &'a Node) -> Result<Flow, RuntimeError> {
+        let Node::Match {
+            expr, arms, by_ref, ..
+        } = node
+        else {
+            unreachable!("not a match: {:?}", node);
+        };
+        // an enum whose fields are bound is matched in place, with its tag
+        // in the first slot
+        let (value, addr) = if *by_ref || arms.iter().any(MatchArm::binds_fields) {
+            let addr = value!(self.addr_of(expr));
+            (self.load(addr)?, addr)
+        } else {
+            (value!(self.eval(expr)), 0)
+        };
+        for arm in arms {
+            for pat in &arm.pats {
+                if self.match_pattern(pat, value, addr, *by_ref)? {
+                    return self.eval(&arm.body);
+                }
+            }
+        }
+        error("no `match` arm matched".to_string())
+    }
+
+    // whether `pat` matches `value`, which is the tag of the enum at `addr`
+    // when fields are bound; the names the pattern binds are stored, as
+    // addresses when matching `by_ref`
+    fn match_pattern(
+        &mut self,
+        pat: &Pattern,
+        value: i64,
+        addr: i64,
+        by_ref: bool,
+    ) -> Result<bool, RuntimeError> {
+        Ok(match pat {
+            Pattern::Wild => true,
+            Pattern::Bind { offset } => {
+                let v = if by_ref { addr } else { value };
+                self.store_as(self.slot(*offset), v, self.local_ty(*offset))?;
+                true
+            }
+            Pattern::Range { lo, hi } => *lo <= value && value <= *hi,
+            Pattern::Bool { value: b } => value == *b as i64,
+            Pattern::Variant { tag, fields, .. } => {
+                if value != *tag as i64 {
+                    return Ok(false);
+                }
+                // the fields follow the tag
+                for (i, field) in fields.iter().enumerate() {
+                    if let Some(offset) = field {
+                        let ty = self.local_ty(*offset);
+                        let field_addr = addr - 8 * (i as i64 + 1);
+                        let v = if by_ref {
+                            field_addr
+                        } else {
+                            self.load_as(field_addr, ty)?
+                        };
+                        self.store_as(self.slot(*offset), v, ty)?;
+                    }
+                }
+                true
+            }
+        })
+    }
+}
+
+// number of bytes a value of type `ty` occupies in its slot
+fn width(ty: &Type) -> usize {
+    ty.int_bits().map_or(8, |(bits, _)| bits as usize / 8)
+}
+
+// evaluate a binary operator on operands of type `ty`, held sign- or
+// zero-extended to 64 bits, matching the native instructions
+fn binary(op: OpKind, l: i64, r: i64, ty: &Type) -> Result<i64, RuntimeError> {
+    let (bits, unsigned) = match ty.int_bits() {
+        Some((bits, signed)) => (bits, !signed),
+        None => (64, false),
+    };
+    let (ul, ur) = (l as u64, r as u64);
+    let v = match op {
+        OpKind::Add => l.wrapping_add(r),
+        OpKind::Sub => l.wrapping_sub(r),
+        OpKind::Mul => l.wrapping_mul(r),
+        OpKind::Div | OpKind::Rem if r == 0 => {
+            return error(op.zero_divisor_message().to_string());
+        }
+        // the lowest value divided by -1 does not fit
+        OpKind::Div | OpKind::Rem if !unsigned && r == -1 && l == i64::MIN >> (64 - bits) => {
+            return error(op.overflow_message().to_string());
+        }
+        OpKind::Div => {
+            if unsigned {
+                (ul / ur) as i64
+            } else {
+                l.wrapping_div(r)
+            }
+        }
+        OpKind::Rem => {
+            if unsigned {
+                (ul % ur) as i64
+            } else {
+                l.wrapping_rem(r)
+            }
+        }
+        OpKind::BitAnd => l & r,
+        OpKind::BitOr => l | r,
+        OpKind::BitXor => l ^ r,
+        // the shift amount wraps at the operand width
+        OpKind::Shl => l.wrapping_shl(r as u32 % bits),
+        OpKind::Shr if unsigned => ul.wrapping_shr(r as u32 % bits) as i64,
+        OpKind::Shr => l.wrapping_shr(r as u32 % bits),
+        // evaluated with short-circuiting in `eval`
+        OpKind::And | OpKind::Or => unreachable!("short-circuit operator {:?}", op),
+        OpKind::Eq => return Ok((l == r) as i64),
+        OpKind::Ne => return Ok((l != r) as i64),
+        OpKind::Lt if unsigned => return Ok((ul < ur) as i64),
+        OpKind::Gt if unsigned => return Ok((ul > ur) as i64),
+        OpKind::Le if unsigned => return Ok((ul <= ur) as i64),
+        OpKind::Ge if unsigned => return Ok((ul >= ur) as i64),
+        OpKind::Lt => return Ok((l < r) as i64),
+        OpKind::Gt => return Ok((l > r) as i64),
+        OpKind::Le => return Ok((l <= r) as i64),
+        OpKind::Ge => return Ok((l >= r) as i64),
+    };
+    Ok(ty.wrap(v))
+}
+
+/// Run a program produced by `node::program`, starting at `main`. With
+/// `overflow_checks`, integer `+`, `-`, `*` and negation fail with rustc's
+/// panic message when the result does not fit their type, as do shifts by at
+/// least the width of the type; otherwise they wrap. Failed runtime checks
+/// carry the span of the expression, so that `RuntimeError::render` can
+/// report them like the native targets do.
+pub fn run(program: &Node, overflow_checks: bool) -> Result<Outcome, RuntimeError> {
+    // evaluation recurses on the host stack, so give it room for deep interpreted recursion
+    thread::scope(|scope| {
+        thread::Builder::new()
+            .stack_size(HOST_STACK_SIZE)
+            .spawn_scoped(scope, || {
+                let base = 0u8;
+                let limit = (&raw const base as usize) - (HOST_STACK_SIZE - HOST_STACK_RESERVE);
+                let mut interp = Interpreter::new(program, overflow_checks, limit);
+                let exit_code = interp.call("main", Vec::new())?;
+                Ok(Outcome {
+                    exit_code,
+                    stdout: interp.stdout,
+                })
+            })
+            .expect("failed to spawn interpreter thread")
+            .join()
+            .expect("interpreter thread panicked")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::program;
+    use crate::symbol::SymbolTable;
+    use crate::token::tokenize;
+    use crate::typeck;
+
+    fn run_src(src: &str) -> Result<Outcome, RuntimeError> {
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let mut node = program(&mut iter, &mut vars).unwrap();
+        typeck::check(&mut node).unwrap();
+        run(&node, true)
+    }
+
+    #[test]
+    fn test_run_last_value_is_exit_code() {
+        let outcome = run_src("fn main() -> i32 { 1 + 2 * 3 }").unwrap();
+        assert_eq!(outcome.exit_code, 7);
+    }
+
+    #[test]
+    fn test_run_min_literals() {
+        let src = "fn main() -> i32 { let b: i64 = -9223372036854775808; let a: i32 = -2147483648;
+                   let c: i8 = -128; if b == -9223372036854775807 - 1 && a < -2147483647 { c as i32 } else { 0 } }";
+        assert_eq!(run_src(src).unwrap().exit_code, -128);
+    }
+
+    #[test]
+    fn test_run_samples() {
+        // the programs the README shows off must keep compiling and running
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("sample");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let src = std::fs::read_to_string(&path).unwrap();
+            let mut iter = tokenize(&src).unwrap().into_iter().peekable();
+            let mut node = program(&mut iter, &mut SymbolTable::new())
+                .unwrap_or_else(|e| panic!("{}: {}", path.display(), e[0].msg));
+            if let Err(errors) = typeck::check(&mut node) {
+                panic!("{}: {}", path.display(), errors[0].msg);
+            }
+            if let Err(e) = run(&node, true) {
+                panic!("{}: {}", path.display(), e);
+            }
+        }
+    }
+
+    #[test]
+    fn test_run_recursion() {
+        let src =
+            "fn fib(n: i32) -> i32 { if (n <= 1) { return n; } return fib(n - 1) + fib(n - 2); }
+                   fn main() -> i32 { return fib(10); }";
+        assert_eq!(run_src(src).unwrap().exit_code, 55);
+    }
+
+    #[test]
+    fn test_run_reference_and_dereference() {
+        let outcome = run_src("fn main() -> i32 { let a = 3; let b = &a; return *b; }").unwrap();
+        assert_eq!(outcome.exit_code, 3);
+    }
+
+    #[test]
+    fn test_run_reference_to_reference() {
+        let src = "fn get(r: &&i32) -> i32 { **r }
+                   fn main() -> i32 { let mut x = 5; let r = &&x; let n = get(&&x) + **r;
+                   { let m = &&mut x; **m += 1; } n + x }";
+        assert_eq!(run_src(src).unwrap().exit_code, 16);
+    }
+
+    #[test]
+    fn test_run_array_sum() {
+        let src = "fn main() -> i32 { let arr = [1, 2, 3, 4, 5]; let mut sum = 0;
+                   let mut i = 0; for ( i=0; i<5; i=i+1 ) { sum = sum + arr[i]; } return sum; }";
+        assert_eq!(run_src(src).unwrap().exit_code, 15);
+    }
+
+    #[test]
+    fn test_run_array_repeat() {
+        let src = "fn main() -> i32 { let mut n = 0; let mut a: [i32; 5] = [{ n += 1; n * 3 }; 5];
+                   a[4] = 1; a[0] + a[3] + a[4] + n }";
+        // the value is computed once: 3 + 3 + 1 + 1
+        assert_eq!(run_src(src).unwrap().exit_code, 8);
+    }
+
+    #[test]
+    fn test_run_struct_fields_through_reference() {
+        let src = "struct Pos { x: i32, y: i32 } struct Piece { pos: Pos, moves: [i32; 2] }
+                   fn step(p: &mut Piece) { p.pos.x += p.moves[1]; }
+                   fn main() -> i32 { let mut a = Piece { moves: [5, 7], pos: Pos { x: 1, y: 2 } };
+                   step(&mut a); a.moves[0] = 10; a.pos.x * 10 + a.pos.y + a.moves[0] }";
+        assert_eq!(run_src(src).unwrap().exit_code, 92);
+    }
+
+    #[test]
+    fn test_run_arrays_through_reference() {
+        let src = "fn set(a: &mut [i32; 3]) { a[0] = 4; a[1] += a[2]; }
+                   fn main() -> i32 { let mut arr = [1, 2, 3]; set(&mut arr); let q = &arr; q[0] * 10 + q[1] }";
+        assert_eq!(run_src(src).unwrap().exit_code, 45);
+    }
+
+    #[test]
+    fn test_run_logical_and_bitwise_operators() {
+        let src = "fn main() -> i32 { return 17 % 5 + (6 & 3) + (6 | 1) + (6 ^ 3) + (1 << 4) + (-32 >> 2) + !0 + 1; }";
+        // 2 + 2 + 7 + 5 + 16 - 8 - 1 + 1
+        assert_eq!(run_src(src).unwrap().exit_code, 24);
+    }
+
+    #[test]
+    fn test_run_short_circuit_skips_right_operand() {
+        let src =
+            "fn main() -> i32 { let n = 0; let a = 0 == 1 && 1 / n == 1; let b = 0 == 0 || 1 / n == 1;
+                   if !a && b { return 1; } return 0; }";
+        assert_eq!(run_src(src).unwrap().exit_code, 1);
+    }
+
+    #[test]
+    fn test_run_assign_through_element_and_pointer() {
+        let src = "fn main() -> i32 { let mut arr = [1, 2, 3]; let mut x = 4; let p = &mut x;
+                   arr[1] = 10; arr[2] *= 3; *p += arr[1]; x -= 1; return arr[1] + arr[2] + x; }";
+        // 10 + 9 + 13
+        assert_eq!(run_src(src).unwrap().exit_code, 32);
+    }
+
+    #[test]
+    fn test_run_labelled_break_and_continue() {
+        let src = "fn main() -> i32 { let mut n = 0;
+                   'outer: for i in 0..5 { for j in 0..5 { if j > i { continue 'outer; }
+                   if i == 3 { break 'outer; } n = n + 1; } } return n; }";
+        assert_eq!(run_src(src).unwrap().exit_code, 6);
+    }
+
+    #[test]
+    fn test_run_loop_break_value() {
+        let src = "fn main() -> i32 { let mut i = 0; let x = loop { i = i + 1; if i == 4 { break i * 10; } }; return x; }";
+        assert_eq!(run_src(src).unwrap().exit_code, 40);
+    }
+
+    #[test]
+    fn test_run_block_and_if_expressions() {
+        let src = "fn main() -> i32 { let n = 5; let x = if n > 3 { n * 2 } else { 0 }; { let y = x + 1; y * 2 } }";
+        assert_eq!(run_src(src).unwrap().exit_code, 22);
+    }
+
+    #[test]
+    fn test_run_match_enum() {
+        let src = "enum Shape { Dot, Rect(i32, i32) }
+                   fn area(s: &Shape) -> i32 { match s { Shape::Rect(w, h) => *w * *h, Shape::Dot => 1 } }
+                   fn main() -> i32 { let a = Shape::Rect(3, 4); let b = Shape::Dot;
+                   let n = area(&a) + area(&b); match n { 0..=9 => 0, 13 | 14 => n * 2, _ => 1 } }";
+        assert_eq!(run_src(src).unwrap().exit_code, 26);
+    }
+
+    #[test]
+    fn test_run_match_through_reference() {
+        let src = "enum Cell { Empty, Disc(i32) }
+                   fn value(c: &Cell) -> i32 { match c { Cell::Disc(v) => *v, Cell::Empty => 0 } }
+                   fn flip(c: &mut Cell) { match c { Cell::Disc(v) => { *v = 3 - *v; } Cell::Empty => {} } }
+                   fn main() -> i32 { let mut a = Cell::Disc(1); flip(&mut a); let r = &a;
+                   let n = match r { c => value(c) }; value(&a) * 10 + n }";
+        assert_eq!(run_src(src).unwrap().exit_code, 22);
+    }
+
+    #[test]
+    fn test_run_items_declared_after_use() {
+        let src = "fn main() -> i32 { let p = Point { x: 1, y: LIMIT }; unsafe { COUNT += p.x; }
+                   unsafe { COUNT + p.y + TABLE[1] } }
+                   struct Point { x: i32, y: i32 } static mut COUNT: i32 = 10;
+                   static TABLE: [i32; SIZE] = [5, 6, 7]; const SIZE: usize = 3; const LIMIT: i32 = 20;";
+        assert_eq!(run_src(src).unwrap().exit_code, 37);
+    }
+
+    #[test]
+    fn test_run_return_in_expression() {
+        let src = "static mut N: i32 = 0;
+                   fn bump(n: i32) { if n > 2 { return; } N += n; }
+                   fn pick(n: i32) -> i32 { let v = match n { 0 => 3, _ => return 9 }; v + 1 }
+                   fn main() -> i32 { bump(1); bump(5); pick(0) * 10 + pick(1) + N }";
+        assert_eq!(run_src(src).unwrap().exit_code, 50);
+    }
+
+    #[test]
+    fn test_run_bool() {
+        let src = "const OFF: bool = !true; static mut CALLS: i32 = 0;
+                   fn touch(b: bool) -> bool { CALLS += 1; b }
+                   fn main() -> i32 { let mut n = 0; if OFF || false && touch(true) { n += 100; }
+                   if !touch(false) && (true ^ OFF) { n += match n > 0 { true => 5, false => 7 }; }
+                   n + CALLS * 10 }";
+        assert_eq!(run_src(src).unwrap().exit_code, 17);
+    }
+
+    #[test]
+    fn test_run_static_items() {
+        let src =
+            "const N: i32 = 4; static mut COUNT: i32 = 0; static SQUARES: [i32; N] = [0, 1, 4, 9];
+                   fn bump() -> i32 { COUNT += 1; COUNT }
+                   fn main() -> i32 { bump(); bump(); COUNT + SQUARES[3] * N }";
+        assert_eq!(run_src(src).unwrap().exit_code, 38);
+    }
+
+    #[test]
+    fn test_run_integer_types() {
+        // narrow values wrap at their width and unsigned ones compare and
+        // divide as unsigned
+        let src = "static mut BYTES: [u8; 2] = [250, 3];
+                   fn main() -> i32 { BYTES[0] += 5; let b = BYTES[0]; let n: i8 = -2;
+                   let wide = n as u32; let mut r = 0;
+                   if wide > 10 && wide / 2 == 2147483647 { r += 1; }
+                   if !b == 0 && (b as i8) == -1 && (300 as u8) == 44 { r += 2; }
+                   if (n >> 1) == -1 && ((n as u8) >> 1) == 127 { r += 4; }
+                   r }";
+        assert_eq!(run_src(src).unwrap().exit_code, 7);
+    }
+
+    #[test]
+    fn test_run_overflow_panics() {
+        let err = run_src("fn main() -> i32 { let x: u8 = 200; (x + 56) as i32 }").unwrap_err();
+        assert_eq!(err.msg, "attempt to add with overflow");
+        let err = run_src("fn main() -> i32 { let mut n: u32 = 0; n -= 1; n as i32 }").unwrap_err();
+        assert_eq!(err.msg, "attempt to subtract with overflow");
+        let err =
+            run_src("fn main() -> i32 { let n: i64 = 4294967296; (n * n) as i32 }").unwrap_err();
+        assert_eq!(err.msg, "attempt to multiply with overflow");
+    }
+
+    #[test]
+    fn test_run_runtime_checks() {
+        let src =
+            "fn main() -> i32 { let a = [1, 2, 3]; let mut i = 0; while i < 4 { i += 1; } a[i] }";
+        let err = run_src(src).unwrap_err();
+        assert_eq!(
+            err.msg,
+            "index out of bounds: the len is 3 but the index is 4"
+        );
+        let err = run_src("fn main() -> i32 { let n = 0; 5 / n }").unwrap_err();
+        assert_eq!(err.msg, "attempt to divide by zero");
+    }
+
+    #[test]
+    fn test_run_overflow_wraps_without_checks() {
+        let src = "fn main() -> i32 { let x: u8 = 200; let y: i32 = 2147483647;
+                   if y + 1 == -2147483648 { (x + 56) as i32 } else { 1 } }";
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let mut node = program(&mut iter, &mut vars).unwrap();
+        typeck::check(&mut node).unwrap();
+        assert_eq!(run(&node, false).unwrap().exit_code, 0);
+    }
+
+    #[test]
+    fn test_run_range_ignores_assignment_to_variable() {
+        let src = "fn main() -> i32 { let mut n = 0; for mut i in 0..3 { i = 10; n = n + 1; } return n; }";
+        assert_eq!(run_src(src).unwrap().exit_code, 3);
+    }
+
+    #[test]
+    fn test_run_write_captures_stdout() {
+        let outcome = run_src(r#"fn main() { write("Hello, "); write("world!\n"); 0; }"#).unwrap();
+        assert_eq!(outcome.stdout, b"Hello, world!\n");
+        assert_eq!(outcome.exit_code, 0);
+    }
+
+    #[test]
+    fn test_run_undefined_function() {
+        let err = run_src("fn main() { foo(); }").unwrap_err();
+        assert_eq!(err.msg, "call to undefined function: foo");
+    }
+
+    #[test]
+    fn test_run_panic_location() {
+        let src = "fn main() -> i32 {\n    let n = 0;\n    5 / n\n}";
+        let err = run_src(src).unwrap_err();
+        let file = SourceFile {
+            path: "src/main.rs",
+            text: src,
+        };
+        assert_eq!(
+            err.render(&file),
+            "thread 'main' panicked at src/main.rs:3:5:\nattempt to divide by zero"
+        );
+        let err = run_src("fn main() { foo(); }").unwrap_err();
+        assert_eq!(
+            err.render(&file),
+            "Runtime error: call to undefined function: foo"
+        );
+    }
+
+    #[test]
+    fn test_run_deep_recursion() {
+        let src = "fn f(n: i32) -> i32 { if n == 0 { 0 } else { 1 + f(n - 1) } }
+                   fn main() -> i32 { f(15000) }";
+        assert_eq!(run_src(src).unwrap().exit_code, 15000);
+    }
+
+    #[test]
+    fn test_run_stack_overflow() {
+        let err =
+            run_src("fn f(n: i32) -> i32 { return f(n + 1); } fn main() { f(0); }").unwrap_err();
+        assert_eq!(err.msg, "stack overflow in call to f");
+    }
+}
@@ -0,0 +1,2654 @@
+use crate::check::{SourceFile, Span};
+use crate::codegen::frame_size;
+use crate::node::{ConstValue, MatchArm, Node, OpKind, Pattern, Type, TypeDefs};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// A virtual register. Every value computed by the IR lives in one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VReg(pub usize);
+
+/// Index of a basic block within its function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub usize);
+
+/// Type of a virtual register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ty {
+    /// 64-bit integer
+    I64,
+    /// address of a frame slot, `static`, string literal or array element
+    Ptr,
+}
+
+/// Width and signedness of an integer, as read from memory, extended to 64
+/// bits or checked for overflow. Registers hold narrow values sign- or
+/// zero-extended; `I64` also stands for anything else that fills a whole
+/// slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntTy {
+    I8,
+    I16,
+    I32,
+    U8,
+    U16,
+    U32,
+    I64,
+    U64,
+}
+
+impl IntTy {
+    /// The width a value of type `ty` is stored with.
+    pub fn of(ty: &Type) -> IntTy {
+        match ty.int_bits() {
+            Some((8, true)) => IntTy::I8,
+            Some((16, true)) => IntTy::I16,
+            Some((32, true)) => IntTy::I32,
+            Some((8, false)) => IntTy::U8,
+            Some((16, false)) => IntTy::U16,
+            Some((32, false)) => IntTy::U32,
+            Some((64, false)) => IntTy::U64,
+            _ => IntTy::I64,
+        }
+    }
+
+    /// The lowest value of this type, as a register holds it.
+    pub fn min(self) -> i64 {
+        match self {
+            IntTy::I8 => i8::MIN as i64,
+            IntTy::I16 => i16::MIN as i64,
+            IntTy::I32 => i32::MIN as i64,
+            IntTy::I64 => i64::MIN,
+            IntTy::U8 | IntTy::U16 | IntTy::U32 | IntTy::U64 => 0,
+        }
+    }
+
+    /// Whether values of this type fill a whole register.
+    pub fn is_wide(self) -> bool {
+        matches!(self, IntTy::I64 | IntTy::U64)
+    }
+}
+
+/// Arithmetic and bitwise operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    /// unsigned division
+    UDiv,
+    /// unsigned remainder
+    URem,
+    And,
+    Or,
+    Xor,
+    Shl,
+    /// arithmetic shift right
+    Shr,
+    /// logical shift right
+    LShr,
+}
+
+/// Comparison operators, producing 0 or 1. The `U` variants compare as
+/// unsigned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    ULt,
+    UGt,
+    ULe,
+    UGe,
+}
+
+impl BinOp {
+    /// What a panic reports when a `CheckedBin`, `CheckDivOverflow` or
+    /// `CheckShift` with this operator overflows.
+    pub fn overflow_message(self) -> &'static str {
+        match self {
+            BinOp::Add => OpKind::Add,
+            BinOp::Sub => OpKind::Sub,
+            BinOp::Mul => OpKind::Mul,
+            BinOp::Div => OpKind::Div,
+            BinOp::Rem => OpKind::Rem,
+            BinOp::Shl => OpKind::Shl,
+            BinOp::Shr | BinOp::LShr => OpKind::Shr,
+            _ => unreachable!("`{}` is not checked for overflow", self),
+        }
+        .overflow_message()
+    }
+
+    /// What a panic reports when a `CheckDivisor` with this operator finds a
+    /// zero divisor.
+    pub fn zero_divisor_message(self) -> &'static str {
+        match self {
+            BinOp::Div => OpKind::Div,
+            BinOp::Rem => OpKind::Rem,
+            _ => unreachable!("`{}` has no divisor", self),
+        }
+        .zero_divisor_message()
+    }
+}
+
+/// A routine the code jumps to when a runtime check fails. Like a Rust panic,
+/// it reports the failure on stderr and exits with status 101.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Panic {
+    /// a `CheckedBin`, `CheckDivOverflow` or `CheckShift` with this operator
+    /// overflowed
+    Overflow(BinOp),
+    /// a `CheckIndex` found the index past the end of the array
+    Bounds,
+    /// a `CheckDivisor` with this operator found a zero divisor
+    ZeroDivisor(BinOp),
+    /// a `CheckNeg` found the lowest value of its type
+    NegOverflow,
+}
+
+impl CmpOp {
+    /// The comparison that holds exactly when this one does not.
+    pub fn negate(self) -> CmpOp {
+        match self {
+            CmpOp::Eq => CmpOp::Ne,
+            CmpOp::Ne => CmpOp::Eq,
+            CmpOp::Lt => CmpOp::Ge,
+            CmpOp::Gt => CmpOp::Le,
+            CmpOp::Le => CmpOp::Gt,
+            CmpOp::Ge => CmpOp::Lt,
+            CmpOp::ULt => CmpOp::UGe,
+            CmpOp::UGt => CmpOp::ULe,
+            CmpOp::ULe => CmpOp::UGt,
+            CmpOp::UGe => CmpOp::ULt,
+        }
+    }
+}
+
+/// A three-address instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inst {
+    /// dst = value
+    Const { dst: VReg, value: i64 },
+    /// dst = address of string literal `id`
+    Str { dst: VReg, id: usize },
+    /// dst = frame pointer - offset
+    FrameAddr { dst: VReg, offset: u64 },
+    /// dst = address of the first slot of `static` `name`
+    StaticAddr { dst: VReg, name: String },
+    /// dst = *addr, reading an integer of type `ty` and extending it
+    Load { dst: VReg, addr: VReg, ty: IntTy },
+    /// *addr = src, writing the low bytes of `src` that fit `ty`
+    Store { addr: VReg, src: VReg, ty: IntTy },
+    /// dst = src truncated to `ty` and sign- or zero-extended back
+    Ext { dst: VReg, src: VReg, ty: IntTy },
+    /// dst = lhs op rhs
+    Bin {
+        op: BinOp,
+        dst: VReg,
+        lhs: VReg,
+        rhs: VReg,
+    },
+    /// dst = lhs op rhs for `add`, `sub` or `mul` on integers of type `ty`,
+    /// panicking when the result does not fit the type and reporting the
+    /// source location in string literal `loc`
+    CheckedBin {
+        op: BinOp,
+        dst: VReg,
+        lhs: VReg,
+        rhs: VReg,
+        ty: IntTy,
+        loc: usize,
+    },
+    /// panic unless `index` is below `len` as an unsigned number, reporting
+    /// the source location in string literal `loc`
+    CheckIndex { index: VReg, len: u64, loc: usize },
+    /// panic if `divisor` is zero for a `div` or `rem`, reporting the source
+    /// location in string literal `loc`
+    CheckDivisor {
+        op: BinOp,
+        divisor: VReg,
+        loc: usize,
+    },
+    /// panic if `dividend` is the lowest value of the signed `ty` and
+    /// `divisor` is -1 for a `div` or `rem`, whose result does not fit `ty`,
+    /// reporting the source location in string literal `loc`
+    CheckDivOverflow {
+        op: BinOp,
+        dividend: VReg,
+        divisor: VReg,
+        ty: IntTy,
+        loc: usize,
+    },
+    /// panic unless the `amount` of a `shl` or `shr` is below `bits`, the
+    /// width of the shifted type, as an unsigned number, reporting the
+    /// source location in string literal `loc`
+    CheckShift {
+        op: BinOp,
+        amount: VReg,
+        bits: u32,
+        loc: usize,
+    },
+    /// panic if `src` is the lowest value of the signed `ty`, whose negation
+    /// does not fit `ty`, reporting the source location in string literal
+    /// `loc`
+    CheckNeg { src: VReg, ty: IntTy, loc: usize },
+    /// dst = (lhs op rhs) ? 1 : 0
+    Cmp {
+        op: CmpOp,
+        dst: VReg,
+        lhs: VReg,
+        rhs: VReg,
+    },
+    /// dst = src
+    Copy { dst: VReg, src: VReg },
+    /// dst = func(args...)
+    Call {
+        dst: VReg,
+        func: String,
+        args: Vec<VReg>,
+    },
+    /// dst = syscall name(args...)
+    Syscall {
+        dst: VReg,
+        name: String,
+        args: Vec<VReg>,
+    },
+}
+
+/// The instruction that ends a basic block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Terminator {
+    Jump(BlockId),
+    /// jump to `then_bb` if `cond` is non-zero, otherwise to `else_bb`
+    Branch {
+        cond: VReg,
+        then_bb: BlockId,
+        else_bb: BlockId,
+    },
+    /// jump to `then_bb` if `lhs op rhs` holds, otherwise to `else_bb`,
+    /// testing the flags of the comparison without materialising 0 or 1
+    CmpBranch {
+        op: CmpOp,
+        lhs: VReg,
+        rhs: VReg,
+        then_bb: BlockId,
+        else_bb: BlockId,
+    },
+    /// jump to the block of the case equal to `value`, or to `default` if none is
+    Switch {
+        value: VReg,
+        cases: Vec<(i64, BlockId)>,
+        default: BlockId,
+    },
+    Return(VReg),
+}
+
+/// A straight-line sequence of instructions ending in a terminator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub insts: Vec<Inst>,
+    pub term: Terminator,
+}
+
+/// A function lowered to basic blocks. `blocks[0]` is the entry block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    pub name: String,
+    /// virtual registers holding the incoming arguments, in order
+    pub params: Vec<VReg>,
+    /// bytes of frame needed by local variable slots (`FrameAddr` offsets)
+    pub locals_size: u64,
+    /// offsets of the slots that hold a whole variable by themselves, which
+    /// `promote_locals` may keep in a virtual register instead
+    pub scalar_slots: Vec<u64>,
+    pub blocks: Vec<Block>,
+    /// type of every virtual register, indexed by `VReg.0`
+    pub vreg_types: Vec<Ty>,
+}
+
+/// Initial value of one 8-byte slot of a `static`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Data {
+    Int(i64),
+    /// address of string literal `id`
+    Str(usize),
+}
+
+/// A `static` item. Slot `k` lies `8 * k` bytes below the address of slot 0,
+/// as for a local variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Static {
+    pub name: String,
+    pub data: Vec<Data>,
+}
+
+/// A whole program: its functions, its `static` items and the string literals
+/// they reference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Module {
+    pub functions: Vec<Function>,
+    pub statics: Vec<Static>,
+    pub strings: Vec<String>,
+}
+
+impl Module {
+    /// The panic routines the module's checks jump to, each once in order of
+    /// first use.
+    pub fn panics(&self) -> Vec<Panic> {
+        let mut panics = Vec::new();
+        let insts = self
+            .functions
+            .iter()
+            .flat_map(|f| &f.blocks)
+            .flat_map(|b| &b.insts);
+        for inst in insts {
+            let panic = match inst {
+                Inst::CheckedBin { op, .. } => Panic::Overflow(*op),
+                Inst::CheckIndex { .. } => Panic::Bounds,
+                Inst::CheckDivisor { op, .. } => Panic::ZeroDivisor(*op),
+                Inst::CheckDivOverflow { op, .. } | Inst::CheckShift { op, .. } => {
+                    Panic::Overflow(*op)
+                }
+                Inst::CheckNeg { .. } => Panic::NegOverflow,
+                _ => continue,
+            };
+            if !panics.contains(&panic) {
+                panics.push(panic);
+            }
+        }
+        panics
+    }
+}
+
+impl Inst {
+    /// The register defined by this instruction, if any.
+    pub fn def(&self) -> Option<VReg> {
+        match self {
+            Inst::Const { dst, .. }
+            | Inst::Str { dst, .. }
+            | Inst::FrameAddr { dst, .. }
+            | Inst::StaticAddr { dst, .. }
+            | Inst::Load { dst, .. }
+            | Inst::Ext { dst, .. }
+            | Inst::Bin { dst, .. }
+            | Inst::CheckedBin { dst, .. }
+            | Inst::Cmp { dst, .. }
+            | Inst::Copy { dst, .. }
+            | Inst::Call { dst, .. }
+            | Inst::Syscall { dst, .. } => Some(*dst),
+            Inst::Store { .. }
+            | Inst::CheckIndex { .. }
+            | Inst::CheckDivisor { .. }
+            | Inst::CheckDivOverflow { .. }
+            | Inst::CheckShift { .. }
+            | Inst::CheckNeg { .. } => None,
+        }
+    }
+
+    /// The registers read by this instruction.
+    pub fn uses(&self) -> Vec<VReg> {
+        match self {
+            Inst::Const { .. }
+            | Inst::Str { .. }
+            | Inst::FrameAddr { .. }
+            | Inst::StaticAddr { .. } => vec![],
+            Inst::Load { addr, .. } => vec![*addr],
+            Inst::Store { addr, src, .. } => vec![*addr, *src],
+            Inst::Ext { src, .. } => vec![*src],
+            Inst::CheckIndex { index, .. } => vec![*index],
+            Inst::CheckDivisor { divisor, .. } => vec![*divisor],
+            Inst::CheckDivOverflow {
+                dividend, divisor, ..
+            } => vec![*dividend, *divisor],
+            Inst::CheckShift { amount, .. } => vec![*amount],
+            Inst::CheckNeg { src, .. } => vec![*src],
+            Inst::Bin { lhs, rhs, .. }
+            | Inst::CheckedBin { lhs, rhs, .. }
+            | Inst::Cmp { lhs, rhs, .. } => vec![*lhs, *rhs],
+            Inst::Copy { src, .. } => vec![*src],
+            Inst::Call { args, .. } | Inst::Syscall { args, .. } => args.clone(),
+        }
+    }
+}
+
+impl Terminator {
+    /// The blocks control may transfer to.
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch {
+                then_bb, else_bb, ..
+            }
+            | Terminator::CmpBranch {
+                then_bb, else_bb, ..
+            } => vec![*then_bb, *else_bb],
+            Terminator::Switch { cases, default, .. } => cases
+                .iter()
+                .map(|(_, target)| *target)
+                .chain(std::iter::once(*default))
+                .collect(),
+            Terminator::Return(_) => vec![],
+        }
+    }
+
+    /// The registers read by this terminator.
+    pub fn uses(&self) -> Vec<VReg> {
+        match self {
+            Terminator::Jump(_) => vec![],
+            Terminator::Branch { cond, .. } => vec![*cond],
+            Terminator::CmpBranch { lhs, rhs, .. } => vec![*lhs, *rhs],
+            Terminator::Switch { value, .. } => vec![*value],
+            Terminator::Return(v) => vec![*v],
+        }
+    }
+}
+
+impl fmt::Display for VReg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "%{}", self.0)
+    }
+}
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bb{}", self.0)
+    }
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ty::I64 => write!(f, "i64"),
+            Ty::Ptr => write!(f, "ptr"),
+        }
+    }
+}
+
+impl fmt::Display for IntTy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            IntTy::I8 => "i8",
+            IntTy::I16 => "i16",
+            IntTy::I32 => "i32",
+            IntTy::U8 => "u8",
+            IntTy::U16 => "u16",
+            IntTy::U32 => "u32",
+            IntTy::I64 => "i64",
+            IntTy::U64 => "u64",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl fmt::Display for BinOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            BinOp::Add => "add",
+            BinOp::Sub => "sub",
+            BinOp::Mul => "mul",
+            BinOp::Div => "div",
+            BinOp::Rem => "rem",
+            BinOp::UDiv => "udiv",
+            BinOp::URem => "urem",
+            BinOp::And => "and",
+            BinOp::Or => "or",
+            BinOp::Xor => "xor",
+            BinOp::Shl => "shl",
+            BinOp::Shr => "shr",
+            BinOp::LShr => "lshr",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl fmt::Display for CmpOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            CmpOp::Eq => "eq",
+            CmpOp::Ne => "ne",
+            CmpOp::Lt => "lt",
+            CmpOp::Gt => "gt",
+            CmpOp::Le => "le",
+            CmpOp::Ge => "ge",
+            CmpOp::ULt => "ult",
+            CmpOp::UGt => "ugt",
+            CmpOp::ULe => "ule",
+            CmpOp::UGe => "uge",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+// format a comma separated register list
+fn reg_list(regs: &[VReg]) -> String {
+    regs.iter()
+        .map(|r| r.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl fmt::Display for Inst {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Inst::Const { dst, value } => write!(f, "{} = const {}", dst, value),
+            Inst::Str { dst, id } => write!(f, "{} = str @str{}", dst, id),
+            Inst::FrameAddr { dst, offset } => write!(f, "{} = frame {}", dst, offset),
+            Inst::StaticAddr { dst, name } => write!(f, "{} = static @{}", dst, name),
+            // whole slots are the common case and leave out their width
+            Inst::Load {
+                dst,
+                addr,
+                ty: IntTy::I64 | IntTy::U64,
+            } => write!(f, "{} = load {}", dst, addr),
+            Inst::Load { dst, addr, ty } => write!(f, "{} = load {} {}", dst, ty, addr),
+            Inst::Store {
+                addr,
+                src,
+                ty: IntTy::I64 | IntTy::U64,
+            } => write!(f, "store {}, {}", addr, src),
+            Inst::Store { addr, src, ty } => write!(f, "store {} {}, {}", ty, addr, src),
+            Inst::Ext { dst, src, ty } => write!(f, "{} = ext {} {}", dst, ty, src),
+            Inst::Bin { op, dst, lhs, rhs } => write!(f, "{} = {} {}, {}", dst, op, lhs, rhs),
+            Inst::CheckedBin {
+                op,
+                dst,
+                lhs,
+                rhs,
+                ty,
+                loc,
+            } => write!(
+                f,
+                "{} = checked {} {} {}, {}, @str{}",
+                dst, op, ty, lhs, rhs, loc
+            ),
+            Inst::CheckIndex { index, len, loc } => {
+                write!(f, "check {} < {}, @str{}", index, len, loc)
+            }
+            Inst::CheckDivisor { op, divisor, loc } => {
+                write!(f, "check {} {} != 0, @str{}", op, divisor, loc)
+            }
+            Inst::CheckDivOverflow {
+                op,
+                dividend,
+                divisor,
+                ty,
+                loc,
+            } => write!(
+                f,
+                "check {} {} {}, {} fits, @str{}",
+                op, ty, dividend, divisor, loc
+            ),
+            Inst::CheckShift {
+                op,
+                amount,
+                bits,
+                loc,
+            } => write!(f, "check {} {} < {}, @str{}", op, amount, bits, loc),
+            Inst::CheckNeg { src, ty, loc } => {
+                write!(f, "check neg {} {} fits, @str{}", ty, src, loc)
+            }
+            Inst::Cmp { op, dst, lhs, rhs } => {
+                write!(f, "{} = cmp {} {}, {}", dst, op, lhs, rhs)
+            }
+            Inst::Copy { dst, src } => write!(f, "{} = copy {}", dst, src),
+            Inst::Call { dst, func, args } => {
+                write!(f, "{} = call {}({})", dst, func, reg_list(args))
+            }
+            Inst::Syscall { dst, name, args } => {
+                write!(f, "{} = syscall {}({})", dst, name, reg_list(args))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Terminator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Terminator::Jump(target) => write!(f, "jmp {}", target),
+            Terminator::Branch {
+                cond,
+                then_bb,
+                else_bb,
+            } => write!(f, "br {}, {}, {}", cond, then_bb, else_bb),
+            Terminator::CmpBranch {
+                op,
+                lhs,
+                rhs,
+                then_bb,
+                else_bb,
+            } => write!(f, "br {} {}, {}, {}, {}", op, lhs, rhs, then_bb, else_bb),
+            Terminator::Switch {
+                value,
+                cases,
+                default,
+            } => {
+                let cases: Vec<String> = cases
+                    .iter()
+                    .map(|(v, target)| format!("{}: {}", v, target))
+                    .collect();
+                write!(f, "switch {}, [{}], {}", value, cases.join(", "), default)
+            }
+            Terminator::Return(v) => write!(f, "ret {}", v),
+        }
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let params: Vec<String> = self
+            .params
+            .iter()
+            .map(|p| format!("{}: {}", p, self.vreg_types[p.0]))
+            .collect();
+        writeln!(
+            f,
+            "fn {}({}) locals {} {{",
+            self.name,
+            params.join(", "),
+            self.locals_size
+        )?;
+        for (i, block) in self.blocks.iter().enumerate() {
+            writeln!(f, "{}:", BlockId(i))?;
+            for inst in &block.insts {
+                // annotate definitions with their type
+                match inst.def() {
+                    Some(dst) => writeln!(f, "    {} ; {}", inst, self.vreg_types[dst.0])?,
+                    None => writeln!(f, "    {}", inst)?,
+                }
+            }
+            writeln!(f, "    {}", block.term)?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+impl fmt::Display for Data {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Data::Int(v) => write!(f, "{}", v),
+            Data::Str(id) => write!(f, "@str{}", id),
+        }
+    }
+}
+
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, s) in self.strings.iter().enumerate() {
+            writeln!(f, "@str{} = {:?}", i, s)?;
+        }
+        for s in &self.statics {
+            let data: Vec<String> = s.data.iter().map(|d| d.to_string()).collect();
+            writeln!(f, "@{} = [{}]", s.name, data.join(", "))?;
+        }
+        for (i, func) in self.functions.iter().enumerate() {
+            if i > 0 || !self.strings.is_empty() || !self.statics.is_empty() {
+                writeln!(f)?;
+            }
+            write!(f, "{}", func)?;
+        }
+        Ok(())
+    }
+}
+
+// A block under construction; the terminator is set once the block is finished
+struct PartialBlock {
+    insts: Vec<Inst>,
+    term: Option<Terminator>,
+}
+
+// Widest range a `match` pattern may cover for its values to be listed as
+// cases of a `Switch`
+const MAX_SWITCH_RANGE: i64 = 32;
+
+// Jump targets of a loop enclosing the code being lowered
+struct LoopTarget {
+    label: Option<String>,
+    break_bb: BlockId,
+    continue_bb: BlockId,
+    // register receiving the value of `break value`, for `loop` only
+    result: Option<VReg>,
+}
+
+// Lowers the body of one function into basic blocks
+struct Builder<'m> {
+    blocks: Vec<PartialBlock>,
+    // blocks in the order they were started, which becomes the layout order
+    order: Vec<BlockId>,
+    current: BlockId,
+    vreg_types: Vec<Ty>,
+    strings: &'m mut Vec<String>,
+    // types of the function's variables by slot offset, deciding how wide
+    // their loads and stores are
+    locals: &'m HashMap<u64, Type>,
+    // whether `+`, `-`, `*`, negation and shifts panic on overflow rather
+    // than wrap
+    overflow_checks: bool,
+    // the program's source, which failed checks report locations in
+    file: &'m SourceFile<'m>,
+    // enclosing loops, innermost last
+    loops: Vec<LoopTarget>,
+}
+
+impl<'m> Builder<'m> {
+    fn new(
+        strings: &'m mut Vec<String>,
+        locals: &'m HashMap<u64, Type>,
+        overflow_checks: bool,
+        file: &'m SourceFile<'m>,
+    ) -> Self {
+        let mut builder = Self {
+            blocks: Vec::new(),
+            order: Vec::new(),
+            current: BlockId(0),
+            vreg_types: Vec::new(),
+            strings,
+            locals,
+            overflow_checks,
+            file,
+            loops: Vec::new(),
+        };
+        let entry = builder.new_block();
+        builder.switch_to(entry);
+        builder
+    }
+
+    fn new_vreg(&mut self, ty: Ty) -> VReg {
+        self.vreg_types.push(ty);
+        VReg(self.vreg_types.len() - 1)
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push(PartialBlock {
+            insts: Vec::new(),
+            term: None,
+        });
+        BlockId(self.blocks.len() - 1)
+    }
+
+    fn switch_to(&mut self, block: BlockId) {
+        self.order.push(block);
+        self.current = block;
+    }
+
+    fn push(&mut self, inst: Inst) {
+        self.blocks[self.current.0].insts.push(inst);
+    }
+
+    // finish the current block; code lowered afterwards goes to a fresh unreachable block
+    fn terminate(&mut self, term: Terminator) {
+        self.end_block(term);
+        let next = self.new_block();
+        self.switch_to(next);
+    }
+
+    // finish the current block and continue in `next`
+    fn terminate_into(&mut self, term: Terminator, next: BlockId) {
+        self.end_block(term);
+        self.switch_to(next);
+    }
+
+    // finish the current block, leaving the caller to switch to another one
+    fn end_block(&mut self, term: Terminator) {
+        self.blocks[self.current.0].term = Some(term);
+    }
+
+    fn constant(&mut self, value: i64) -> VReg {
+        let dst = self.new_vreg(Ty::I64);
+        self.push(Inst::Const { dst, value });
+        dst
+    }
+
+    fn frame_addr(&mut self, offset: u64) -> VReg {
+        let dst = self.new_vreg(Ty::Ptr);
+        self.push(Inst::FrameAddr { dst, offset });
+        dst
+    }
+
+    fn static_addr(&mut self, name: &str) -> VReg {
+        let dst = self.new_vreg(Ty::Ptr);
+        self.push(Inst::StaticAddr {
+            dst,
+            name: name.to_string(),
+        });
+        dst
+    }
+
+    fn load(&mut self, addr: VReg, ty: IntTy) -> VReg {
+        let dst = self.new_vreg(Ty::I64);
+        self.push(Inst::Load { dst, addr, ty });
+        dst
+    }
+
+    fn store(&mut self, addr: VReg, src: VReg, ty: IntTy) {
+        self.push(Inst::Store { addr, src, ty });
+    }
+
+    // bring the result of an operation on a narrow integer back into range
+    fn wrap(&mut self, src: VReg, ty: &Type) -> VReg {
+        match IntTy::of(ty) {
+            ty if ty.is_wide() => src,
+            ty => {
+                let dst = self.new_vreg(Ty::I64);
+                self.push(Inst::Ext { dst, src, ty });
+                dst
+            }
+        }
+    }
+
+    // the width of the variable in slot `offset`; slots without a type of
+    // their own, like the fields of an enum value, are whole words
+    fn local_ty(&self, offset: u64) -> IntTy {
+        self.locals.get(&offset).map_or(IntTy::I64, IntTy::of)
+    }
+
+    // the type of an lvalue, if typeck recorded it
+    fn place_type<'n>(&'n self, node: &'n Node) -> Option<&'n Type> {
+        match node {
+            Node::Var { offset, .. } => self.locals.get(offset),
+            Node::Global { ty, .. }
+            | Node::Deref { ty, .. }
+            | Node::Field { ty, .. }
+            | Node::Index { ty, .. } => Some(ty),
+            _ => None,
+        }
+    }
+
+    // the width of an lvalue
+    fn place_ty(&self, node: &Node) -> IntTy {
+        self.place_type(node).map_or(IntTy::I64, IntTy::of)
+    }
+
+    // a string literal holding the source location of `span`, for a check
+    // to report
+    fn location(&mut self, span: Span) -> usize {
+        self.strings.push(self.file.location(span));
+        self.strings.len() - 1
+    }
+
+    // address of the array element `node`, `8 * index` bytes below the start
+    // of the array, checking the index against the length of the array when
+    // it is known
+    fn index_addr(&mut self, node: &Node) -> VReg {
+        let Node::Index {
+            expr: array,
+            index,
+            span,
+            ..
+        } = node
+        else {
+            unreachable!("not array indexing: {:?}", node);
+        };
+        let base = self.lower_addr(array);
+        let index = self.lower_expr(index);
+        if let Some(Type::Array(_, len)) = self.place_type(array) {
+            let len = *len;
+            let loc = self.location(*span);
+            self.push(Inst::CheckIndex { index, len, loc });
+        }
+        let size = self.constant(8);
+        let scaled = self.bin(BinOp::Mul, index, size);
+        self.bin(BinOp::Sub, base, scaled)
+    }
+
+    // check that the divisor `rhs`, held in `divisor`, of the `/` or `%` at
+    // `span` is not zero and, for a signed `ty`, that it is not -1 while
+    // `dividend` is the lowest value of `ty`; a literal divisor needs neither
+    // check, as it is never negative
+    fn check_divisor(
+        &mut self,
+        op: OpKind,
+        dividend: VReg,
+        rhs: &Node,
+        divisor: VReg,
+        ty: &Type,
+        span: Span,
+    ) {
+        let op = match op {
+            OpKind::Div => BinOp::Div,
+            OpKind::Rem => BinOp::Rem,
+            _ => return,
+        };
+        if matches!(rhs, Node::Num { value, .. } if *value != 0) {
+            return;
+        }
+        let loc = self.location(span);
+        self.push(Inst::CheckDivisor { op, divisor, loc });
+        if ty.int_bits().is_none_or(|(_, signed)| signed) {
+            self.push(Inst::CheckDivOverflow {
+                op,
+                dividend,
+                divisor,
+                ty: IntTy::of(ty),
+                loc,
+            });
+        }
+    }
+
+    // apply the arithmetic or bitwise operator at `span` to operands of type
+    // `ty`
+    fn arith(&mut self, op: OpKind, lhs: VReg, rhs: VReg, ty: &Type, span: Span) -> VReg {
+        let (bits, unsigned) = match ty.int_bits() {
+            Some((bits, signed)) => (bits, !signed),
+            None => (64, false),
+        };
+        let shift = match op {
+            OpKind::Shl => Some(BinOp::Shl),
+            OpKind::Shr => Some(BinOp::Shr),
+            _ => None,
+        };
+        // a shift by the operand width or more panics when checked, and
+        // otherwise the amount wraps at the width, which the hardware only
+        // does for 64 bits
+        let rhs = match shift {
+            Some(op) if self.overflow_checks => {
+                let loc = self.location(span);
+                self.push(Inst::CheckShift {
+                    op,
+                    amount: rhs,
+                    bits,
+                    loc,
+                });
+                rhs
+            }
+            Some(_) if bits < 64 => {
+                let mask = self.constant(bits as i64 - 1);
+                self.bin(BinOp::And, rhs, mask)
+            }
+            _ => rhs,
+        };
+        if self.overflow_checks && matches!(op, OpKind::Add | OpKind::Sub | OpKind::Mul) {
+            let dst = self.new_vreg(Ty::I64);
+            let loc = self.location(span);
+            self.push(Inst::CheckedBin {
+                op: arith_op(op, unsigned),
+                dst,
+                lhs,
+                rhs,
+                ty: IntTy::of(ty),
+                loc,
+            });
+            return dst;
+        }
+        let v = self.bin(arith_op(op, unsigned), lhs, rhs);
+        // only these leave the range of the operands; `MIN / -1` panics
+        match op {
+            OpKind::Add | OpKind::Sub | OpKind::Mul | OpKind::Shl => self.wrap(v, ty),
+            _ => v,
+        }
+    }
+
+    fn bin(&mut self, op: BinOp, lhs: VReg, rhs: VReg) -> VReg {
+        // pointer arithmetic keeps the pointer type
+        let ty = match (op, self.vreg_types[lhs.0], self.vreg_types[rhs.0]) {
+            (BinOp::Add | BinOp::Sub, Ty::Ptr, _) | (BinOp::Add, _, Ty::Ptr) => Ty::Ptr,
+            _ => Ty::I64,
+        };
+        let dst = self.new_vreg(ty);
+        self.push(Inst::Bin { op, dst, lhs, rhs });
+        dst
+    }
+
+    // lower a loop body with `break` and `continue` jumping to the given blocks
+    fn lower_loop_body(&mut self, body: &Node, target: LoopTarget) {
+        self.loops.push(target);
+        self.lower_stmt(body);
+        self.loops.pop();
+    }
+
+    // lower a statement whose value is not used
+    fn lower_stmt(&mut self, node: &Node) {
+        match node {
+            Node::Seq { first, second, .. } => {
+                self.lower_stmt(first);
+                self.lower_stmt(second);
+            }
+            Node::Semi { expr, .. } => {
+                self.lower_expr(expr);
+            }
+            _ => {
+                self.lower_expr(node);
+            }
+        }
+    }
+
+    // the loop a `break` or `continue` refers to; the type checker has
+    // rejected jumps outside of a loop and unknown labels
+    fn target_loop(&self, label: &Option<String>) -> &LoopTarget {
+        self.loops
+            .iter()
+            .rev()
+            .find(|target| label.is_none() || target.label == *label)
+            .expect("`break` or `continue` outside of a loop")
+    }
+
+    // lower an lvalue expression to the register holding its address
+    fn lower_addr(&mut self, node: &Node) -> VReg {
+        match node {
+            Node::Var { offset, .. } => self.frame_addr(*offset),
+            Node::Global { name, .. } => self.static_addr(name),
+            Node::Deref { expr, .. } => self.lower_expr(expr),
+            // a field lies `offset` bytes below the start of its struct
+            Node::Field { expr, offset, .. } => match expr.as_ref() {
+                // a field of a local struct is a frame slot of its own
+                Node::Var { offset: base, .. } => self.frame_addr(base + offset),
+                _ => {
+                    let base = self.lower_addr(expr);
+                    let offset = self.constant(*offset as i64);
+                    self.bin(BinOp::Sub, base, offset)
+                }
+            },
+            Node::Index { .. } => self.index_addr(node),
+            // a struct literal is built in its own slots
+            Node::StructLit { offset, .. } => {
+                self.lower_expr(node);
+                self.frame_addr(*offset)
+            }
+            _ => panic!("address-of not supported for {:?}", node),
+        }
+    }
+
+    // lower an expression or statement to the register holding its value
+    fn lower_expr(&mut self, node: &Node) -> VReg {
+        match node {
+            Node::Seq { first, second, .. } => {
+                self.lower_stmt(first);
+                self.lower_expr(second)
+            }
+            Node::Semi { expr, .. } => {
+                self.lower_expr(expr);
+                // the unit value
+                self.constant(0)
+            }
+            Node::Function { .. } => panic!("nested function definitions are not supported"),
+            Node::Struct { .. } | Node::Enum { .. } => {
+                panic!("nested type declarations are not supported")
+            }
+            Node::Const { .. } | Node::Static { .. } => {
+                panic!("nested `const` and `static` items are not supported")
+            }
+            Node::Num { value, .. } => self.constant(*value as i64),
+            Node::Bool { value, .. } => self.constant(*value as i64),
+            // the unit value
+            Node::Unit { .. } => self.constant(0),
+            Node::Variant { tag, .. } => self.constant(*tag as i64),
+            Node::StringLiteral { value, .. } => {
+                self.strings.push(value.clone());
+                let dst = self.new_vreg(Ty::Ptr);
+                self.push(Inst::Str {
+                    dst,
+                    id: self.strings.len() - 1,
+                });
+                dst
+            }
+            Node::Var { .. } | Node::Global { .. } | Node::Field { .. } | Node::Index { .. } => {
+                let addr = self.lower_addr(node);
+                self.load(addr, self.place_ty(node))
+            }
+            Node::Call { name, args, .. } | Node::Syscall { name, args, .. } => {
+                let args: Vec<VReg> = args.iter().map(|a| self.lower_expr(a)).collect();
+                let dst = self.new_vreg(Ty::I64);
+                if let Node::Call { .. } = node {
+                    self.push(Inst::Call {
+                        dst,
+                        func: name.clone(),
+                        args,
+                    });
+                } else {
+                    self.push(Inst::Syscall {
+                        dst,
+                        name: name.clone(),
+                        args,
+                    });
+                }
+                dst
+            }
+            // a struct literal is built in its own slots and copied into the
+            // place slot by slot
+            Node::Assign { lhs, rhs, .. } if matches!(**rhs, Node::StructLit { .. }) => {
+                let Node::StructLit { slots, .. } = rhs.as_ref() else {
+                    unreachable!();
+                };
+                let src = self.lower_addr(rhs);
+                let dst = self.lower_addr(lhs);
+                for k in 0..*slots {
+                    let step = self.constant(k as i64 * 8);
+                    let from = self.bin(BinOp::Sub, src, step);
+                    let to = self.bin(BinOp::Sub, dst, step);
+                    let value = self.load(from, IntTy::I64);
+                    self.store(to, value, IntTy::I64);
+                }
+                self.constant(0)
+            }
+            Node::Assign { lhs, rhs, .. } => {
+                let src = self.lower_expr(rhs);
+                let addr = self.lower_addr(lhs);
+                self.store(addr, src, self.place_ty(lhs));
+                src
+            }
+            Node::CompoundAssign {
+                op,
+                lhs,
+                rhs,
+                ty,
+                span,
+            } => {
+                let r = self.lower_expr(rhs);
+                let addr = self.lower_addr(lhs);
+                let old = self.load(addr, IntTy::of(ty));
+                self.check_divisor(*op, old, rhs, r, ty, *span);
+                let src = self.arith(*op, old, r, ty, *span);
+                self.store(addr, src, IntTy::of(ty));
+                src
+            }
+            Node::BinaryOp {
+                op: op @ (OpKind::And | OpKind::Or),
+                lhs,
+                rhs,
+                ..
+            } => {
+                // the left operand decides unless it is true for `&&` or false for `||`
+                let result = self.new_vreg(Ty::I64);
+                let l = self.lower_expr(lhs);
+                self.push(Inst::Copy {
+                    dst: result,
+                    src: l,
+                });
+                let rhs_bb = self.new_block();
+                let end_bb = self.new_block();
+                let (then_bb, else_bb) = if *op == OpKind::And {
+                    (rhs_bb, end_bb)
+                } else {
+                    (end_bb, rhs_bb)
+                };
+                self.terminate_into(
+                    Terminator::Branch {
+                        cond: l,
+                        then_bb,
+                        else_bb,
+                    },
+                    rhs_bb,
+                );
+                let r = self.lower_expr(rhs);
+                self.push(Inst::Copy {
+                    dst: result,
+                    src: r,
+                });
+                self.terminate_into(Terminator::Jump(end_bb), end_bb);
+                result
+            }
+            // a negative literal needs no check
+            Node::Neg { expr, ty, .. } if matches!(**expr, Node::Num { .. }) => {
+                let Node::Num { value, .. } = expr.as_ref() else {
+                    unreachable!();
+                };
+                self.constant(ty.wrap((*value as i64).wrapping_neg()))
+            }
+            Node::Neg { expr, ty, span } => {
+                let src = self.lower_expr(expr);
+                let zero = self.constant(0);
+                if !self.overflow_checks {
+                    let v = self.bin(BinOp::Sub, zero, src);
+                    return self.wrap(v, ty);
+                }
+                let loc = self.location(*span);
+                let ty = IntTy::of(ty);
+                self.push(Inst::CheckNeg { src, ty, loc });
+                self.bin(BinOp::Sub, zero, src)
+            }
+            Node::Not {
+                expr, bitwise, ty, ..
+            } => {
+                let v = self.lower_expr(expr);
+                if *bitwise {
+                    let ones = self.constant(-1);
+                    let v = self.bin(BinOp::Xor, v, ones);
+                    // flipping the upper bits leaves a narrow unsigned value out of range
+                    self.wrap(v, ty)
+                } else {
+                    let zero = self.constant(0);
+                    let dst = self.new_vreg(Ty::I64);
+                    self.push(Inst::Cmp {
+                        op: CmpOp::Eq,
+                        dst,
+                        lhs: v,
+                        rhs: zero,
+                    });
+                    dst
+                }
+            }
+            Node::BinaryOp {
+                op,
+                lhs,
+                rhs,
+                ty,
+                span,
+            } => {
+                let l = self.lower_expr(lhs);
+                let r = self.lower_expr(rhs);
+                if let Some(op) = cmp_op(*op, ty) {
+                    let dst = self.new_vreg(Ty::I64);
+                    self.push(Inst::Cmp {
+                        op,
+                        dst,
+                        lhs: l,
+                        rhs: r,
+                    });
+                    dst
+                } else {
+                    self.check_divisor(*op, l, rhs, r, ty, *span);
+                    self.arith(*op, l, r, ty, *span)
+                }
+            }
+            Node::Cast { expr, ty, .. } => match expr.as_ref() {
+                Node::Num { value, .. } => self.constant(ty.wrap(*value as i64)),
+                _ => {
+                    let v = self.lower_expr(expr);
+                    self.wrap(v, ty)
+                }
+            },
+            Node::Return { expr, .. } => {
+                let v = match expr {
+                    Some(expr) => self.lower_expr(expr),
+                    None => self.constant(0),
+                };
+                self.terminate(Terminator::Return(v));
+                // the value of the unreachable code after a return is never used
+                self.constant(0)
+            }
+            Node::If {
+                cond,
+                then_stmt,
+                else_stmt,
+                ..
+            } => {
+                let result = self.new_vreg(Ty::I64);
+                let then_bb = self.new_block();
+                let else_bb = self.new_block();
+                let end_bb = self.new_block();
+                self.lower_cond(cond, then_bb, else_bb);
+                self.switch_to(then_bb);
+                let v = self.lower_expr(then_stmt);
+                self.push(Inst::Copy {
+                    dst: result,
+                    src: v,
+                });
+                self.terminate_into(Terminator::Jump(end_bb), else_bb);
+                // without an else branch the if evaluates to zero
+                let v = match else_stmt {
+                    Some(es) => self.lower_expr(es),
+                    None => self.constant(0),
+                };
+                self.push(Inst::Copy {
+                    dst: result,
+                    src: v,
+                });
+                self.terminate_into(Terminator::Jump(end_bb), end_bb);
+                result
+            }
+            Node::While {
+                cond, body, label, ..
+            } => {
+                let cond_bb = self.new_block();
+                let body_bb = self.new_block();
+                let end_bb = self.new_block();
+                self.terminate_into(Terminator::Jump(cond_bb), cond_bb);
+                self.lower_cond(cond, body_bb, end_bb);
+                self.switch_to(body_bb);
+                self.lower_loop_body(
+                    body,
+                    LoopTarget {
+                        label: label.clone(),
+                        break_bb: end_bb,
+                        continue_bb: cond_bb,
+                        result: None,
+                    },
+                );
+                self.terminate_into(Terminator::Jump(cond_bb), end_bb);
+                self.constant(0)
+            }
+            Node::For {
+                init,
+                cond,
+                update,
+                body,
+                label,
+                ..
+            } => {
+                self.lower_expr(init);
+                let cond_bb = self.new_block();
+                let body_bb = self.new_block();
+                let update_bb = self.new_block();
+                let end_bb = self.new_block();
+                self.terminate_into(Terminator::Jump(cond_bb), cond_bb);
+                self.lower_cond(cond, body_bb, end_bb);
+                self.switch_to(body_bb);
+                self.lower_loop_body(
+                    body,
+                    LoopTarget {
+                        label: label.clone(),
+                        break_bb: end_bb,
+                        continue_bb: update_bb,
+                        result: None,
+                    },
+                );
+                self.terminate_into(Terminator::Jump(update_bb), update_bb);
+                self.lower_expr(update);
+                self.terminate_into(Terminator::Jump(cond_bb), end_bb);
+                self.constant(0)
+            }
+            Node::ForRange {
+                offset,
+                start,
+                end,
+                inclusive,
+                body,
+                label,
+                ..
+            } => {
+                // the counter lives in a register so that assigning to the loop
+                // variable does not change the iteration
+                let counter = self.new_vreg(Ty::I64);
+                let first = self.lower_expr(start);
+                self.push(Inst::Copy {
+                    dst: counter,
+                    src: first,
+                });
+                let limit = self.lower_expr(end);
+                let cond_bb = self.new_block();
+                let body_bb = self.new_block();
+                let step_bb = self.new_block();
+                let end_bb = self.new_block();
+                self.terminate_into(Terminator::Jump(cond_bb), cond_bb);
+                let unsigned = matches!(
+                    self.locals.get(offset).and_then(Type::int_bits),
+                    Some((_, false))
+                );
+                let op = match (*inclusive, unsigned) {
+                    (true, false) => CmpOp::Le,
+                    (false, false) => CmpOp::Lt,
+                    (true, true) => CmpOp::ULe,
+                    (false, true) => CmpOp::ULt,
+                };
+                self.terminate_into(
+                    Terminator::CmpBranch {
+                        op,
+                        lhs: counter,
+                        rhs: limit,
+                        then_bb: body_bb,
+                        else_bb: end_bb,
+                    },
+                    body_bb,
+                );
+                let addr = self.frame_addr(*offset);
+                self.store(addr, counter, self.local_ty(*offset));
+                self.lower_loop_body(
+                    body,
+                    LoopTarget {
+                        label: label.clone(),
+                        break_bb: end_bb,
+                        continue_bb: step_bb,
+                        result: None,
+                    },
+                );
+                self.terminate_into(Terminator::Jump(step_bb), step_bb);
+                let one = self.constant(1);
+                let next = self.bin(BinOp::Add, counter, one);
+                self.push(Inst::Copy {
+                    dst: counter,
+                    src: next,
+                });
+                self.terminate_into(Terminator::Jump(cond_bb), end_bb);
+                self.constant(0)
+            }
+            Node::Loop { body, label, .. } => {
+                // a loop left by a plain `break` evaluates to zero
+                let result = self.new_vreg(Ty::I64);
+                let zero = self.constant(0);
+                self.push(Inst::Copy {
+                    dst: result,
+                    src: zero,
+                });
+                let body_bb = self.new_block();
+                let end_bb = self.new_block();
+                self.terminate_into(Terminator::Jump(body_bb), body_bb);
+                self.lower_loop_body(
+                    body,
+                    LoopTarget {
+                        label: label.clone(),
+                        break_bb: end_bb,
+                        continue_bb: body_bb,
+                        result: Some(result),
+                    },
+                );
+                self.terminate_into(Terminator::Jump(body_bb), end_bb);
+                result
+            }
+            Node::Break { label, value, .. } => {
+                let v = value.as_deref().map(|v| self.lower_expr(v));
+                let target = self.target_loop(label);
+                let (break_bb, result) = (target.break_bb, target.result);
+                if let (Some(dst), Some(src)) = (result, v) {
+                    self.push(Inst::Copy { dst, src });
+                }
+                self.terminate(Terminator::Jump(break_bb));
+                // the value of the unreachable code after a jump is never used
+                self.constant(0)
+            }
+            Node::Continue { label, .. } => {
+                let continue_bb = self.target_loop(label).continue_bb;
+                self.terminate(Terminator::Jump(continue_bb));
+                self.constant(0)
+            }
+            Node::Deref { expr, ty, .. } => {
+                let addr = self.lower_expr(expr);
+                self.load(addr, IntTy::of(ty))
+            }
+            Node::Addr { expr, .. } => self.lower_addr(expr),
+            Node::Let { init, .. } => self.lower_expr(init),
+            Node::ArrayAssign {
+                offset, elements, ..
+            } => {
+                for (i, elem) in elements.iter().enumerate() {
+                    let src = self.lower_expr(elem);
+                    let addr = self.frame_addr(offset + (i as u64) * 8);
+                    self.store(addr, src, IntTy::I64);
+                }
+                self.constant(0)
+            }
+            Node::ArrayRepeat {
+                offset, value, len, ..
+            } => {
+                let src = self.lower_expr(value);
+                for i in 0..*len {
+                    let addr = self.frame_addr(offset + i * 8);
+                    self.store(addr, src, IntTy::I64);
+                }
+                self.constant(0)
+            }
+            Node::StructLit { fields, .. } => {
+                for (_, init) in fields {
+                    self.lower_expr(init);
+                }
+                self.constant(0)
+            }
+            Node::VariantLit {
+                tag,
+                offset,
+                fields,
+                ..
+            } => {
+                let src = self.constant(*tag as i64);
+                let addr = self.frame_addr(*offset);
+                self.store(addr, src, IntTy::I64);
+                for init in fields {
+                    self.lower_expr(init);
+                }
+                self.constant(0)
+            }
+            Node::Match {
+                expr, arms, by_ref, ..
+            } => self.lower_match(expr, arms, *by_ref),
+        }
+    }
+
+    // lower a `match` to a `Switch` over the values its patterns list, or to
+    // a chain of comparisons when a range is too wide to list. Control
+    // enters an arm through a block storing what its pattern binds, if any;
+    // a match `by_ref` binds the addresses of the value and its fields.
+    fn lower_match(&mut self, expr: &Node, arms: &[MatchArm], by_ref: bool) -> VReg {
+        let result = self.new_vreg(Ty::I64);
+        // an enum whose fields are bound is matched in place, with its tag
+        // in the first slot
+        let (value, addr) = if by_ref || arms.iter().any(MatchArm::binds_fields) {
+            let addr = self.lower_addr(expr);
+            (self.load(addr, IntTy::I64), Some(addr))
+        } else {
+            (self.lower_expr(expr), None)
+        };
+        let arm_bbs: Vec<BlockId> = arms.iter().map(|_| self.new_block()).collect();
+        let end_bb = self.new_block();
+        // every pattern in order, with the block control goes to when it matches
+        let mut tests = Vec::new();
+        let mut binders = Vec::new();
+        for (arm, &arm_bb) in arms.iter().zip(&arm_bbs) {
+            for pat in &arm.pats {
+                let binds = match pat {
+                    Pattern::Bind { .. } => true,
+                    Pattern::Variant { fields, .. } => fields.iter().any(Option::is_some),
+                    Pattern::Wild | Pattern::Range { .. } | Pattern::Bool { .. } => false,
+                };
+                if binds {
+                    let bind_bb = self.new_block();
+                    tests.push((pat, bind_bb));
+                    binders.push((pat, bind_bb, arm_bb));
+                } else {
+                    tests.push((pat, arm_bb));
+                }
+            }
+        }
+        self.lower_dispatch(value, &tests, end_bb);
+        for (pat, bind_bb, arm_bb) in binders {
+            self.switch_to(bind_bb);
+            match pat {
+                Pattern::Bind { offset } => {
+                    let slot = self.frame_addr(*offset);
+                    match addr {
+                        Some(addr) if by_ref => self.store(slot, addr, IntTy::I64),
+                        _ => self.store(slot, value, self.local_ty(*offset)),
+                    }
+                }
+                Pattern::Variant { fields, .. } => {
+                    let base = addr.expect("fields are bound from the matched place");
+                    // the fields follow the tag
+                    for (i, field) in fields.iter().enumerate() {
+                        let Some(offset) = field else { continue };
+                        let delta = self.constant(8 * (i as i64 + 1));
+                        let field_addr = self.bin(BinOp::Sub, base, delta);
+                        let slot = self.frame_addr(*offset);
+                        if by_ref {
+                            self.store(slot, field_addr, IntTy::I64);
+                        } else {
+                            let ty = self.local_ty(*offset);
+                            let src = self.load(field_addr, ty);
+                            self.store(slot, src, ty);
+                        }
+                    }
+                }
+                Pattern::Wild | Pattern::Range { .. } | Pattern::Bool { .. } => {
+                    unreachable!("binds nothing")
+                }
+            }
+            self.end_block(Terminator::Jump(arm_bb));
+        }
+        for (arm, arm_bb) in arms.iter().zip(arm_bbs) {
+            self.switch_to(arm_bb);
+            let v = self.lower_expr(&arm.body);
+            self.push(Inst::Copy {
+                dst: result,
+                src: v,
+            });
+            self.end_block(Terminator::Jump(end_bb));
+        }
+        self.switch_to(end_bb);
+        result
+    }
+
+    // end the current block by jumping to the target of the first of `tests`
+    // that matches `value`. The patterns cover every value, so the last one
+    // needs no test of its own.
+    fn lower_dispatch(&mut self, value: VReg, tests: &[(&Pattern, BlockId)], end_bb: BlockId) {
+        let Some(&(_, last)) = tests.last() else {
+            // nothing to match on an enum without variants
+            self.end_block(Terminator::Jump(end_bb));
+            return;
+        };
+        let listable = tests.iter().all(|(pat, _)| {
+            !matches!(pat, Pattern::Range { lo, hi } if hi.saturating_sub(*lo) >= MAX_SWITCH_RANGE)
+        });
+        if listable {
+            let mut cases: Vec<(i64, BlockId)> = Vec::new();
+            let mut default = last;
+            for &(pat, target) in tests {
+                let values = match pat {
+                    Pattern::Wild | Pattern::Bind { .. } => {
+                        default = target;
+                        break;
+                    }
+                    Pattern::Range { lo, hi } => *lo..=*hi,
+                    Pattern::Bool { value } => *value as i64..=*value as i64,
+                    Pattern::Variant { tag, .. } => *tag as i64..=*tag as i64,
+                };
+                // an earlier arm wins a value listed twice
+                for v in values {
+                    if !cases.iter().any(|(c, _)| *c == v) {
+                        cases.push((v, target));
+                    }
+                }
+            }
+            self.end_block(Terminator::Switch {
+                value,
+                cases,
+                default,
+            });
+            return;
+        }
+        for &(pat, target) in &tests[..tests.len() - 1] {
+            let (lo, hi) = match pat {
+                Pattern::Wild | Pattern::Bind { .. } => {
+                    self.end_block(Terminator::Jump(target));
+                    return;
+                }
+                Pattern::Range { lo, hi } => (*lo, *hi),
+                Pattern::Bool { value } => (*value as i64, *value as i64),
+                Pattern::Variant { tag, .. } => (*tag as i64, *tag as i64),
+            };
+            let next_bb = self.new_block();
+            if lo == hi {
+                self.branch_if(CmpOp::Eq, value, lo, target, next_bb);
+            } else {
+                // at least `lo`, then at most `hi`
+                let upper_bb = self.new_block();
+                self.branch_if(CmpOp::Ge, value, lo, upper_bb, next_bb);
+                self.switch_to(upper_bb);
+                self.branch_if(CmpOp::Le, value, hi, target, next_bb);
+            }
+            self.switch_to(next_bb);
+        }
+        self.end_block(Terminator::Jump(last));
+    }
+
+    // end the current block with a jump to `then_bb` if `value op constant`
+    // holds, otherwise to `else_bb`
+    fn branch_if(
+        &mut self,
+        op: CmpOp,
+        value: VReg,
+        constant: i64,
+        then_bb: BlockId,
+        else_bb: BlockId,
+    ) {
+        let rhs = self.constant(constant);
+        self.end_block(Terminator::CmpBranch {
+            op,
+            lhs: value,
+            rhs,
+            then_bb,
+            else_bb,
+        });
+    }
+
+    // end the current block with a jump to `then_bb` if the boolean `cond`
+    // holds, otherwise to `else_bb`. Comparisons branch on their own flags,
+    // and `&&`, `||` and `!` only redirect the jumps of their operands, so
+    // none of them materialises a 0 or 1.
+    fn lower_cond(&mut self, cond: &Node, then_bb: BlockId, else_bb: BlockId) {
+        match cond {
+            Node::Bool { value, .. } => {
+                let target = if *value { then_bb } else { else_bb };
+                self.end_block(Terminator::Jump(target));
+            }
+            Node::Not {
+                expr,
+                bitwise: false,
+                ..
+            } => self.lower_cond(expr, else_bb, then_bb),
+            Node::BinaryOp {
+                op: op @ (OpKind::And | OpKind::Or),
+                lhs,
+                rhs,
+                ..
+            } => {
+                // the right operand is only evaluated when the left one does
+                // not decide
+                let rhs_bb = self.new_block();
+                if *op == OpKind::And {
+                    self.lower_cond(lhs, rhs_bb, else_bb);
+                } else {
+                    self.lower_cond(lhs, then_bb, rhs_bb);
+                }
+                self.switch_to(rhs_bb);
+                self.lower_cond(rhs, then_bb, else_bb);
+            }
+            Node::BinaryOp {
+                op, lhs, rhs, ty, ..
+            } if cmp_op(*op, ty).is_some() => {
+                let l = self.lower_expr(lhs);
+                let r = self.lower_expr(rhs);
+                self.end_block(Terminator::CmpBranch {
+                    op: cmp_op(*op, ty).unwrap(),
+                    lhs: l,
+                    rhs: r,
+                    then_bb,
+                    else_bb,
+                });
+            }
+            _ => {
+                let c = self.lower_expr(cond);
+                self.end_block(Terminator::Branch {
+                    cond: c,
+                    then_bb,
+                    else_bb,
+                });
+            }
+        }
+    }
+
+    // lay the blocks out in the order they were started, dropping unreachable ones
+    fn finish(mut self) -> (Vec<Block>, Vec<Ty>) {
+        let mut reachable = vec![false; self.blocks.len()];
+        let mut work = vec![BlockId(0)];
+        while let Some(b) = work.pop() {
+            if reachable[b.0] {
+                continue;
+            }
+            reachable[b.0] = true;
+            if let Some(term) = &self.blocks[b.0].term {
+                work.extend(term.successors());
+            }
+        }
+        let layout: Vec<BlockId> = self
+            .order
+            .iter()
+            .copied()
+            .filter(|b| reachable[b.0])
+            .collect();
+        let mut new_id = vec![BlockId(usize::MAX); self.blocks.len()];
+        for (i, b) in layout.iter().enumerate() {
+            new_id[b.0] = BlockId(i);
+        }
+        let blocks = layout
+            .iter()
+            .map(|b| {
+                let block = &mut self.blocks[b.0];
+                let term = match block.term.take().expect("reachable block is terminated") {
+                    Terminator::Jump(t) => Terminator::Jump(new_id[t.0]),
+                    Terminator::Branch {
+                        cond,
+                        then_bb,
+                        else_bb,
+                    } => Terminator::Branch {
+                        cond,
+                        then_bb: new_id[then_bb.0],
+                        else_bb: new_id[else_bb.0],
+                    },
+                    Terminator::CmpBranch {
+                        op,
+                        lhs,
+                        rhs,
+                        then_bb,
+                        else_bb,
+                    } => Terminator::CmpBranch {
+                        op,
+                        lhs,
+                        rhs,
+                        then_bb: new_id[then_bb.0],
+                        else_bb: new_id[else_bb.0],
+                    },
+                    Terminator::Switch {
+                        value,
+                        cases,
+                        default,
+                    } => Terminator::Switch {
+                        value,
+                        cases: cases.into_iter().map(|(v, t)| (v, new_id[t.0])).collect(),
+                        default: new_id[default.0],
+                    },
+                    Terminator::Return(v) => Terminator::Return(v),
+                };
+                Block {
+                    insts: std::mem::take(&mut block.insts),
+                    term,
+                }
+            })
+            .collect();
+        (blocks, self.vreg_types)
+    }
+}
+
+// the IR comparison for a comparison operator on operands of type `ty`
+fn cmp_op(op: OpKind, ty: &Type) -> Option<CmpOp> {
+    let unsigned = matches!(ty.int_bits(), Some((_, false)));
+    match (op, unsigned) {
+        (OpKind::Eq, _) => Some(CmpOp::Eq),
+        (OpKind::Ne, _) => Some(CmpOp::Ne),
+        (OpKind::Lt, false) => Some(CmpOp::Lt),
+        (OpKind::Gt, false) => Some(CmpOp::Gt),
+        (OpKind::Le, false) => Some(CmpOp::Le),
+        (OpKind::Ge, false) => Some(CmpOp::Ge),
+        (OpKind::Lt, true) => Some(CmpOp::ULt),
+        (OpKind::Gt, true) => Some(CmpOp::UGt),
+        (OpKind::Le, true) => Some(CmpOp::ULe),
+        (OpKind::Ge, true) => Some(CmpOp::UGe),
+        _ => None,
+    }
+}
+
+// the IR instruction for an arithmetic or bitwise operator
+fn arith_op(op: OpKind, unsigned: bool) -> BinOp {
+    match op {
+        OpKind::Add => BinOp::Add,
+        OpKind::Sub => BinOp::Sub,
+        OpKind::Mul => BinOp::Mul,
+        OpKind::Div if unsigned => BinOp::UDiv,
+        OpKind::Div => BinOp::Div,
+        OpKind::Rem if unsigned => BinOp::URem,
+        OpKind::Rem => BinOp::Rem,
+        OpKind::BitAnd => BinOp::And,
+        OpKind::BitOr => BinOp::Or,
+        OpKind::BitXor => BinOp::Xor,
+        OpKind::Shl => BinOp::Shl,
+        OpKind::Shr if unsigned => BinOp::LShr,
+        OpKind::Shr => BinOp::Shr,
+        _ => unreachable!("not an arithmetic operator: {:?}", op),
+    }
+}
+
+// lower one function definition
+fn lower_function(
+    name: &str,
+    args: &[Node],
+    body: &Node,
+    locals: &HashMap<u64, Type>,
+    overflow_checks: bool,
+    file: &SourceFile,
+    strings: &mut Vec<String>,
+) -> Function {
+    let mut builder = Builder::new(strings, locals, overflow_checks, file);
+    // incoming arguments arrive in registers and are spilled to their slots
+    let params: Vec<VReg> = args.iter().map(|_| builder.new_vreg(Ty::I64)).collect();
+    for (param, arg) in params.iter().zip(args) {
+        if let Node::Var { offset, .. } = arg {
+            let addr = builder.frame_addr(*offset);
+            builder.store(addr, *param, builder.local_ty(*offset));
+        }
+    }
+    // the value of the body is the function's return value
+    let v = builder.lower_expr(body);
+    builder.terminate(Terminator::Return(v));
+    let (blocks, vreg_types) = builder.finish();
+    Function {
+        name: name.to_string(),
+        params,
+        locals_size: frame_size(args, body),
+        scalar_slots: Vec::new(),
+        blocks,
+        vreg_types,
+    }
+}
+
+// collect the struct and enum declarations of a program into `types`
+fn collect_types(node: &Node, types: &mut TypeDefs) {
+    match node {
+        Node::Seq { first, second, .. } => {
+            collect_types(first, types);
+            collect_types(second, types);
+        }
+        Node::Struct { name, fields, .. } => {
+            types.structs.insert(name.clone(), fields.clone());
+        }
+        Node::Enum { name, variants, .. } => {
+            types.enums.insert(name.clone(), variants.clone());
+        }
+        _ => {}
+    }
+}
+
+// offsets of the variables in `locals` that take one slot and do not lie
+// within a larger variable, like the fields of a local struct do
+fn scalar_slots(locals: &HashMap<u64, Type>, types: &TypeDefs) -> Vec<u64> {
+    // the slots of the variables taking more than one
+    let aggregates: Vec<(u64, u64)> = locals
+        .iter()
+        .filter_map(|(offset, ty)| match ty.slots(types) {
+            Some(n) if n > 1 => Some((*offset, offset + n * 8)),
+            _ => None,
+        })
+        .collect();
+    let mut slots: Vec<u64> = locals
+        .iter()
+        .filter(|(offset, ty)| {
+            ty.slots(types) == Some(1)
+                && !aggregates
+                    .iter()
+                    .any(|(start, end)| (start..end).contains(offset))
+        })
+        .map(|(offset, _)| *offset)
+        .collect();
+    slots.sort();
+    slots
+}
+
+// collect the function definitions and `static` items of a program into the module
+fn lower_items(
+    node: &Node,
+    file: &SourceFile,
+    overflow_checks: bool,
+    types: &TypeDefs,
+    module: &mut Module,
+) {
+    match node {
+        Node::Seq { first, second, .. } => {
+            lower_items(first, file, overflow_checks, types, module);
+            lower_items(second, file, overflow_checks, types, module);
+        }
+        Node::Function {
+            name,
+            args,
+            body,
+            locals,
+            ..
+        } => {
+            let mut func = lower_function(
+                name,
+                args,
+                body,
+                locals,
+                overflow_checks,
+                file,
+                &mut module.strings,
+            );
+            func.scalar_slots = scalar_slots(locals, types);
+            module.functions.push(func);
+        }
+        Node::Static { name, data, .. } => {
+            let data = data
+                .iter()
+                .map(|value| match value {
+                    ConstValue::Int(v) => Data::Int(*v),
+                    ConstValue::Str(s) => {
+                        module.strings.push(s.clone());
+                        Data::Str(module.strings.len() - 1)
+                    }
+                })
+                .collect();
+            module.statics.push(Static {
+                name: name.clone(),
+                data,
+            });
+        }
+        // a type declaration only describes a layout, and the uses of a
+        // `const` were replaced by its value
+        Node::Struct { .. } | Node::Enum { .. } | Node::Const { .. } => {}
+        // a program without items is parsed to the placeholder `()`
+        Node::Unit { .. } => {}
+        other => panic!("expected function definition, found {:?}", other),
+    }
+}
+
+/// Lower a program produced by `node::program`, read from `file`, to the
+/// three-address IR. Array indexing and division are always checked, and
+/// failed checks report where in `file` they happened. With
+/// `overflow_checks`, as in a debug build of rustc, integer `+`, `-`, `*` and
+/// negation panic when the result does not fit their type, as do shifts by
+/// at least its width; otherwise they wrap at its width.
+pub fn lower(program: &Node, file: &SourceFile, overflow_checks: bool) -> Module {
+    let mut module = Module {
+        functions: Vec::new(),
+        statics: Vec::new(),
+        strings: Vec::new(),
+    };
+    let mut types = TypeDefs::default();
+    collect_types(program, &mut types);
+    lower_items(program, file, overflow_checks, &types, &mut module);
+    module
+}
+
+/// Keep the variables of `func.scalar_slots` in a virtual register of their
+/// own rather than in their frame slot, when the slot's address is only ever
+/// loaded from and stored to, all at one width. Its loads and stores become
+/// copies from and to that register.
+pub fn promote_locals(func: &mut Function) {
+    // the slot each `FrameAddr` of a variable that may be promoted points to
+    let mut addrs = HashMap::new();
+    for inst in func.blocks.iter().flat_map(|b| &b.insts) {
+        if let Inst::FrameAddr { dst, offset } = inst
+            && func.scalar_slots.contains(offset)
+        {
+            addrs.insert(*dst, *offset);
+        }
+    }
+    // the width each slot is accessed at, and the slots whose address is used
+    // otherwise or that are accessed at different widths
+    let mut widths = HashMap::new();
+    let mut escaped = HashSet::new();
+    for block in &func.blocks {
+        for inst in &block.insts {
+            let (addr, ty, others) = match inst {
+                Inst::Load { addr, ty, .. } => (Some(addr), *ty, vec![]),
+                Inst::Store { addr, src, ty } => (Some(addr), *ty, vec![*src]),
+                _ => (None, IntTy::I64, inst.uses()),
+            };
+            if let Some(&offset) = addr.and_then(|a| addrs.get(a))
+                && *widths.entry(offset).or_insert(ty) != ty
+            {
+                escaped.insert(offset);
+            }
+            escaped.extend(others.iter().filter_map(|v| addrs.get(v)));
+        }
+        escaped.extend(block.term.uses().iter().filter_map(|v| addrs.get(v)));
+    }
+    // a register for every promoted variable
+    let mut vars = HashMap::new();
+    for offset in func.scalar_slots.clone() {
+        if widths.contains_key(&offset) && !escaped.contains(&offset) {
+            func.vreg_types.push(Ty::I64);
+            vars.insert(offset, VReg(func.vreg_types.len() - 1));
+        }
+    }
+    let var = |addr: &VReg| addrs.get(addr).and_then(|offset| vars.get(offset)).copied();
+    for block in &mut func.blocks {
+        let insts = std::mem::take(&mut block.insts);
+        for inst in insts {
+            let inst = match inst {
+                Inst::FrameAddr { dst, .. } if var(&dst).is_some() => continue,
+                Inst::Load { dst, addr, .. } if let Some(src) = var(&addr) => {
+                    Inst::Copy { dst, src }
+                }
+                Inst::Store { addr, src, .. } if let Some(dst) = var(&addr) => {
+                    Inst::Copy { dst, src }
+                }
+                inst => inst,
+            };
+            block.insts.push(inst);
+        }
+    }
+}
+
+/// Remove the instructions of `func` that only compute a value which is
+/// never used, like the unit value of a statement.
+pub fn remove_dead_code(func: &mut Function) {
+    loop {
+        let mut used = vec![false; func.vreg_types.len()];
+        for block in &func.blocks {
+            let uses = block.insts.iter().flat_map(|inst| inst.uses());
+            for v in uses.chain(block.term.uses()) {
+                used[v.0] = true;
+            }
+        }
+        let mut changed = false;
+        for block in &mut func.blocks {
+            let before = block.insts.len();
+            block.insts.retain(|inst| {
+                // instructions without effects besides defining their result
+                let pure = matches!(
+                    inst,
+                    Inst::Const { .. }
+                        | Inst::Str { .. }
+                        | Inst::FrameAddr { .. }
+                        | Inst::StaticAddr { .. }
+                        | Inst::Load { .. }
+                        | Inst::Ext { .. }
+                        | Inst::Bin { .. }
+                        | Inst::Cmp { .. }
+                        | Inst::Copy { .. }
+                );
+                !pure || inst.def().is_some_and(|dst| used[dst.0])
+            });
+            changed |= block.insts.len() != before;
+        }
+        if !changed {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::program;
+    use crate::symbol::SymbolTable;
+    use crate::token::tokenize;
+    use crate::typeck;
+
+    // lower without overflow checks, which get a test of their own
+    fn lower_src(src: &str) -> Module {
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = program(&mut iter, &mut vars).unwrap();
+        let file = SourceFile {
+            path: "test.rs",
+            text: src,
+        };
+        lower(&node, &file, false)
+    }
+
+    // lower after type checking, which gives loads and stores their widths
+    // and arrays their lengths
+    fn lower_typed(src: &str, overflow_checks: bool) -> Module {
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let mut node = program(&mut iter, &mut vars).unwrap();
+        typeck::check(&mut node).unwrap();
+        let file = SourceFile {
+            path: "test.rs",
+            text: src,
+        };
+        lower(&node, &file, overflow_checks)
+    }
+
+    #[test]
+    fn test_promote_locals() {
+        let src = "fn main() -> i32 { let a = [1, 2, 3]; let mut sum = 0;
+                   for i in 0..3 { sum += a[i]; } sum }";
+        let mut module = lower_typed(src, false);
+        let func = &mut module.functions[0];
+        promote_locals(func);
+        remove_dead_code(func);
+        // the loop keeps `sum` and `i` in registers: its one memory access
+        // reads the element
+        let accesses: Vec<&Inst> = func.blocks[1..4]
+            .iter()
+            .flat_map(|b| &b.insts)
+            .filter(|inst| matches!(inst, Inst::Load { .. } | Inst::Store { .. }))
+            .collect();
+        assert!(matches!(accesses[..], [Inst::Load { ty: IntTy::I32, .. }]));
+        // the unit values of the statements before the loop are gone, leaving
+        // the initial values of `sum` and `i`
+        let zeros = func.blocks[0]
+            .insts
+            .iter()
+            .filter(|inst| matches!(inst, Inst::Const { value: 0, .. }))
+            .count();
+        assert_eq!(zeros, 2);
+        // a variable whose address is taken stays in its slot
+        let src = "fn main() -> i32 { let mut x = 1; let p = &mut x; *p = 2; x }";
+        let mut module = lower_typed(src, false);
+        let func = &mut module.functions[0];
+        promote_locals(func);
+        let stores = func
+            .blocks
+            .iter()
+            .flat_map(|b| &b.insts)
+            .filter(|inst| matches!(inst, Inst::Store { .. }))
+            .count();
+        assert_eq!(stores, 2);
+    }
+
+    #[test]
+    fn test_lower_arithmetic() {
+        let module = lower_src("fn main() { 1 + 2 * 3 }");
+        assert_eq!(
+            module.to_string(),
+            "\
+fn main() locals 48 {
+bb0:
+    %0 = const 1 ; i64
+    %1 = const 2 ; i64
+    %2 = const 3 ; i64
+    %3 = mul %1, %2 ; i64
+    %4 = add %0, %3 ; i64
+    ret %4
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_lower_empty_program() {
+        let module = lower_src("// nothing but a comment\n");
+        assert!(module.functions.is_empty());
+        assert_eq!(module.to_string(), "");
+    }
+
+    #[test]
+    fn test_lower_params_and_call() {
+        let module = lower_src("fn id(a: i32) -> i32 { return a; } fn main() { id(7) }");
+        assert_eq!(
+            module.to_string(),
+            "\
+fn id(%0: i64) locals 48 {
+bb0:
+    %1 = frame 8 ; ptr
+    store %1, %0
+    %2 = frame 8 ; ptr
+    %3 = load %2 ; i64
+    ret %3
+}
+
+fn main() locals 48 {
+bb0:
+    %0 = const 7 ; i64
+    %1 = call id(%0) ; i64
+    ret %1
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_lower_loop_break_value() {
+        let module = lower_src("fn main() { loop { break 7; } }");
+        assert_eq!(
+            module.to_string(),
+            "\
+fn main() locals 48 {
+bb0:
+    %1 = const 0 ; i64
+    %0 = copy %1 ; i64
+    jmp bb1
+bb1:
+    %2 = const 7 ; i64
+    %0 = copy %2 ; i64
+    jmp bb2
+bb2:
+    ret %0
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_lower_short_circuit_and() {
+        let module = lower_src("fn main() { 1 < 2 && 3 > 4 }");
+        assert_eq!(
+            module.to_string(),
+            "\
+fn main() locals 48 {
+bb0:
+    %1 = const 1 ; i64
+    %2 = const 2 ; i64
+    %3 = cmp lt %1, %2 ; i64
+    %0 = copy %3 ; i64
+    br %3, bb1, bb2
+bb1:
+    %4 = const 3 ; i64
+    %5 = const 4 ; i64
+    %6 = cmp gt %4, %5 ; i64
+    %0 = copy %6 ; i64
+    jmp bb2
+bb2:
+    ret %0
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_lower_compound_assign_to_deref() {
+        let module = lower_src("fn main() { let a = 1; let p = &a; *p += 2 }");
+        assert_eq!(
+            module.to_string(),
+            "\
+fn main() locals 48 {
+bb0:
+    %0 = const 1 ; i64
+    %1 = frame 8 ; ptr
+    store %1, %0
+    %2 = frame 8 ; ptr
+    %3 = frame 16 ; ptr
+    store %3, %2
+    %4 = const 2 ; i64
+    %5 = frame 16 ; ptr
+    %6 = load %5 ; i64
+    %7 = load %6 ; i64
+    %8 = add %7, %4 ; i64
+    store %6, %8
+    ret %8
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_lower_if_else_blocks() {
+        let module = lower_src("fn main() { if 1 < 2 { 3 } else { 4 } }");
+        assert_eq!(
+            module.to_string(),
+            "\
+fn main() locals 48 {
+bb0:
+    %1 = const 1 ; i64
+    %2 = const 2 ; i64
+    br lt %1, %2, bb1, bb2
+bb1:
+    %3 = const 3 ; i64
+    %0 = copy %3 ; i64
+    jmp bb3
+bb2:
+    %4 = const 4 ; i64
+    %0 = copy %4 ; i64
+    jmp bb3
+bb3:
+    ret %0
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_lower_short_circuit_condition() {
+        let module =
+            lower_src("fn f(a: i32, b: i32) -> i32 { if a < b && !(a == 0) { 1 } else { 2 } }");
+        assert_eq!(
+            module.to_string(),
+            "\
+fn f(%0: i64, %1: i64) locals 48 {
+bb0:
+    %2 = frame 8 ; ptr
+    store %2, %0
+    %3 = frame 16 ; ptr
+    store %3, %1
+    %5 = frame 8 ; ptr
+    %6 = load %5 ; i64
+    %7 = frame 16 ; ptr
+    %8 = load %7 ; i64
+    br lt %6, %8, bb1, bb3
+bb1:
+    %9 = frame 8 ; ptr
+    %10 = load %9 ; i64
+    %11 = const 0 ; i64
+    br eq %10, %11, bb3, bb2
+bb2:
+    %12 = const 1 ; i64
+    %4 = copy %12 ; i64
+    jmp bb4
+bb3:
+    %13 = const 2 ; i64
+    %4 = copy %13 ; i64
+    jmp bb4
+bb4:
+    ret %4
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_lower_while_loop_blocks() {
+        let module = lower_src("fn main() { let mut a = 0; while (a < 10) a = a + 1; return a; }");
+        let func = &module.functions[0];
+        // entry, condition, body, exit
+        assert_eq!(func.blocks.len(), 4);
+        assert_eq!(func.blocks[0].term, Terminator::Jump(BlockId(1)));
+        assert!(matches!(
+            func.blocks[1].term,
+            Terminator::CmpBranch {
+                op: CmpOp::Lt,
+                then_bb: BlockId(2),
+                else_bb: BlockId(3),
+                ..
+            }
+        ));
+        assert_eq!(func.blocks[2].term, Terminator::Jump(BlockId(1)));
+    }
+
+    #[test]
+    fn test_lower_drops_code_after_return() {
+        let module = lower_src("fn main() { return 1; 2; }");
+        assert_eq!(
+            module.to_string(),
+            "\
+fn main() locals 48 {
+bb0:
+    %0 = const 1 ; i64
+    ret %0
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_lower_strings_and_array_index() {
+        let module = lower_src(r#"fn main() { let s = ["a", "b"]; write(s[1]) }"#);
+        assert_eq!(module.strings, vec!["a".to_string(), "b".to_string()]);
+        let insts = &module.functions[0].blocks[0].insts;
+        assert!(insts.contains(&Inst::Str {
+            dst: VReg(0),
+            id: 0
+        }));
+        assert!(matches!(insts.last(), Some(Inst::Syscall { name, .. }) if name == "write"));
+    }
+
+    #[test]
+    fn test_lower_match_switch() {
+        let module = lower_src(
+            "enum Dir { North, East, South, West }
+             fn f(d: Dir) -> i32 { match d { Dir::North => 1, Dir::East | Dir::West => 2, _ => 3 } }",
+        );
+        assert_eq!(
+            module.to_string(),
+            "\
+fn f(%0: i64) locals 48 {
+bb0:
+    %1 = frame 8 ; ptr
+    store %1, %0
+    %3 = frame 8 ; ptr
+    %4 = load %3 ; i64
+    switch %4, [0: bb1, 1: bb2, 3: bb2], bb3
+bb1:
+    %5 = const 1 ; i64
+    %2 = copy %5 ; i64
+    jmp bb4
+bb2:
+    %6 = const 2 ; i64
+    %2 = copy %6 ; i64
+    jmp bb4
+bb3:
+    %7 = const 3 ; i64
+    %2 = copy %7 ; i64
+    jmp bb4
+bb4:
+    ret %2
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_lower_statics() {
+        let module = lower_src(
+            "static mut COUNT: i32 = 0; static NAMES: [&str; 2] = [\"a\", \"b\"];
+             fn f() -> i32 { COUNT += 1; COUNT }",
+        );
+        assert_eq!(
+            module.to_string(),
+            "\
+@str0 = \"a\"
+@str1 = \"b\"
+@COUNT = [0]
+@NAMES = [@str0, @str1]
+
+fn f() locals 48 {
+bb0:
+    %0 = const 1 ; i64
+    %1 = static @COUNT ; ptr
+    %2 = load %1 ; i64
+    %3 = add %2, %0 ; i64
+    store %1, %3
+    %4 = static @COUNT ; ptr
+    %5 = load %4 ; i64
+    ret %5
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_lower_integer_types() {
+        let module = lower_typed("fn f(a: u8, b: u8) -> bool { a / b < a + b }", false);
+        assert_eq!(
+            module.to_string(),
+            "\
+@str0 = \"test.rs:1:30\"
+
+fn f(%0: i64, %1: i64) locals 48 {
+bb0:
+    %2 = frame 8 ; ptr
+    store u8 %2, %0
+    %3 = frame 16 ; ptr
+    store u8 %3, %1
+    %4 = frame 8 ; ptr
+    %5 = load u8 %4 ; i64
+    %6 = frame 16 ; ptr
+    %7 = load u8 %6 ; i64
+    check div %7 != 0, @str0
+    %8 = udiv %5, %7 ; i64
+    %9 = frame 8 ; ptr
+    %10 = load u8 %9 ; i64
+    %11 = frame 16 ; ptr
+    %12 = load u8 %11 ; i64
+    %13 = add %10, %12 ; i64
+    %14 = ext u8 %13 ; i64
+    %15 = cmp ult %8, %14 ; i64
+    ret %15
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_lower_overflow_checks() {
+        let module = lower_typed(
+            "fn f(a: i32, b: u8) -> i32 { a * 2 + (b - 1) as i32 }",
+            true,
+        );
+        assert_eq!(
+            module.to_string(),
+            "\
+@str0 = \"test.rs:1:30\"
+@str1 = \"test.rs:1:39\"
+@str2 = \"test.rs:1:30\"
+
+fn f(%0: i64, %1: i64) locals 48 {
+bb0:
+    %2 = frame 8 ; ptr
+    store i32 %2, %0
+    %3 = frame 16 ; ptr
+    store u8 %3, %1
+    %4 = frame 8 ; ptr
+    %5 = load i32 %4 ; i64
+    %6 = const 2 ; i64
+    %7 = checked mul i32 %5, %6, @str0 ; i64
+    %8 = frame 16 ; ptr
+    %9 = load u8 %8 ; i64
+    %10 = const 1 ; i64
+    %11 = checked sub u8 %9, %10, @str1 ; i64
+    %12 = ext i32 %11 ; i64
+    %13 = checked add i32 %7, %12, @str2 ; i64
+    ret %13
+}
+"
+        );
+        // one panic routine per operator, in the order they first appear
+        assert_eq!(
+            module.panics(),
+            vec![
+                Panic::Overflow(BinOp::Mul),
+                Panic::Overflow(BinOp::Sub),
+                Panic::Overflow(BinOp::Add)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lower_shift_and_negate_checks() {
+        // with overflow checks the shift amount is checked against the width
+        // instead of wrapping, and negation against the lowest value; a
+        // negative literal is a constant
+        let src = "fn f(a: i32, b: u8) -> i32 { -a << b as i32 ^ -3 }";
+        let module = lower_typed(src, true);
+        assert_eq!(
+            module.to_string(),
+            "\
+@str0 = \"test.rs:1:30\"
+@str1 = \"test.rs:1:30\"
+
+fn f(%0: i64, %1: i64) locals 48 {
+bb0:
+    %2 = frame 8 ; ptr
+    store i32 %2, %0
+    %3 = frame 16 ; ptr
+    store u8 %3, %1
+    %4 = frame 8 ; ptr
+    %5 = load i32 %4 ; i64
+    %6 = const 0 ; i64
+    check neg i32 %5 fits, @str0
+    %7 = sub %6, %5 ; i64
+    %8 = frame 16 ; ptr
+    %9 = load u8 %8 ; i64
+    %10 = ext i32 %9 ; i64
+    check shl %10 < 32, @str1
+    %11 = shl %7, %10 ; i64
+    %12 = ext i32 %11 ; i64
+    %13 = const -3 ; i64
+    %14 = xor %12, %13 ; i64
+    ret %14
+}
+"
+        );
+        assert_eq!(
+            module.panics(),
+            vec![Panic::NegOverflow, Panic::Overflow(BinOp::Shl)]
+        );
+        let module = lower_typed(src, false);
+        assert_eq!(
+            module.to_string(),
+            "\
+fn f(%0: i64, %1: i64) locals 48 {
+bb0:
+    %2 = frame 8 ; ptr
+    store i32 %2, %0
+    %3 = frame 16 ; ptr
+    store u8 %3, %1
+    %4 = frame 8 ; ptr
+    %5 = load i32 %4 ; i64
+    %6 = const 0 ; i64
+    %7 = sub %6, %5 ; i64
+    %8 = ext i32 %7 ; i64
+    %9 = frame 16 ; ptr
+    %10 = load u8 %9 ; i64
+    %11 = ext i32 %10 ; i64
+    %12 = const 31 ; i64
+    %13 = and %11, %12 ; i64
+    %14 = shl %8, %13 ; i64
+    %15 = ext i32 %14 ; i64
+    %16 = const -3 ; i64
+    %17 = xor %15, %16 ; i64
+    ret %17
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_lower_runtime_checks() {
+        // the index is checked against the length of the array and the
+        // divisor against zero and against -1 for the lowest `i32`, all
+        // reporting where they are
+        let module = lower_typed(
+            "fn main() -> i32 { let a = [1, 2, 3]; let i = 2; a[i] / i }",
+            false,
+        );
+        assert_eq!(
+            module.to_string(),
+            "\
+@str0 = \"test.rs:1:50\"
+@str1 = \"test.rs:1:50\"
+
+fn main() locals 48 {
+bb0:
+    %0 = const 1 ; i64
+    %1 = frame 8 ; ptr
+    store %1, %0
+    %2 = const 2 ; i64
+    %3 = frame 16 ; ptr
+    store %3, %2
+    %4 = const 3 ; i64
+    %5 = frame 24 ; ptr
+    store %5, %4
+    %6 = const 0 ; i64
+    %7 = const 2 ; i64
+    %8 = frame 32 ; ptr
+    store i32 %8, %7
+    %9 = frame 8 ; ptr
+    %10 = frame 32 ; ptr
+    %11 = load i32 %10 ; i64
+    check %11 < 3, @str0
+    %12 = const 8 ; i64
+    %13 = mul %11, %12 ; i64
+    %14 = sub %9, %13 ; ptr
+    %15 = load i32 %14 ; i64
+    %16 = frame 32 ; ptr
+    %17 = load i32 %16 ; i64
+    check div %17 != 0, @str1
+    check div i32 %15, %17 fits, @str1
+    %18 = div %15, %17 ; i64
+    ret %18
+}
+"
+        );
+        assert_eq!(
+            module.panics(),
+            vec![
+                Panic::Bounds,
+                Panic::ZeroDivisor(BinOp::Div),
+                Panic::Overflow(BinOp::Div)
+            ]
+        );
+    }
+}
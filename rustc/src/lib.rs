@@ -1,5 +1,11 @@
 pub mod node;
 pub mod token;
 pub mod codegen;
-pub mod variable;
+pub mod symbol;
 pub mod check;
+mod arm64;
+mod x86_64;
+mod regalloc;
+pub mod interp;
+pub mod ir;
+pub mod typeck;
@@ -1,20 +1,145 @@
+use rustc::check::SourceFile;
 use rustc::codegen::*;
+use rustc::interp;
+use rustc::ir;
 use rustc::node::*;
+use rustc::symbol::SymbolTable;
 use rustc::token::*;
-use rustc::variable::Variable;
+use rustc::typeck;
 use std::env;
 use std::fs;
+use std::io::{self, Write};
+use std::process;
+
+const USAGE: &str = "Usage: program [--target <aarch64-macos|aarch64-linux|x86_64-linux> | --interp | --emit=<asm|ir>] [-O] [--legacy-implicit-declarations] <file>";
+
+// Give up after reporting `count` errors
+fn abort(filename: &str, count: usize) -> ! {
+    let plural = if count == 1 { "" } else { "s" };
+    eprintln!(
+        "error: could not compile `{}` due to {} previous error{}",
+        filename, count, plural
+    );
+    process::exit(1);
+}
+
+// Whether the program defines a `main` function to start at
+fn has_main(node: &Node) -> bool {
+    match node {
+        Node::Seq { first, second, .. } => has_main(first) || has_main(second),
+        Node::Function { name, .. } => name == "main",
+        _ => false,
+    }
+}
 
 fn main() {
-    let filename = env::args().nth(1).expect("Usage: program <file>");
+    // parse command line options
+    let mut target = Target::default();
+    let mut run_interp = false;
+    let mut emit_ir = false;
+    let mut implicit_declarations = false;
+    let mut overflow_checks = true;
+    let mut filename = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = if arg == "--target" {
+            Some(args.next().expect(USAGE))
+        } else {
+            arg.strip_prefix("--target=").map(|s| s.to_string())
+        };
+        if let Some(value) = value {
+            target = value.parse().unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            });
+        } else if arg == "--interp" {
+            run_interp = true;
+        } else if arg == "-O" {
+            // release mode: integer overflow wraps instead of panicking
+            overflow_checks = false;
+        } else if arg == "--legacy-implicit-declarations" {
+            // old behaviour: unknown names are declared on first use
+            implicit_declarations = true;
+        } else if let Some(kind) = arg.strip_prefix("--emit=") {
+            match kind {
+                "asm" => emit_ir = false,
+                "ir" => emit_ir = true,
+                _ => {
+                    eprintln!("unknown emit kind: {}", kind);
+                    process::exit(1);
+                }
+            }
+        } else {
+            filename = Some(arg);
+        }
+    }
+    let filename = filename.expect(USAGE);
     let exp = fs::read_to_string(&filename).expect("Failed to read file");
 
-    let mut iter = tokenize(&exp).unwrap().into_iter().peekable();
+    let tokens = tokenize(&exp).unwrap_or_else(|e| {
+        eprintln!("error: {}", e.msg);
+        eprintln!("{}", e.render(&exp));
+        abort(&filename, 1);
+    });
+    let mut iter = tokens.into_iter().peekable();
 
     // variable context for parsing
-    let mut vars = Variable::new("".to_string(), 0, None);
+    let mut vars = if implicit_declarations {
+        SymbolTable::with_implicit_declarations()
+    } else {
+        SymbolTable::new()
+    };
     // parse the program
-    let node = program(&mut iter, &mut vars).unwrap();
+    let mut node = program(&mut iter, &mut vars).unwrap_or_else(|errors| {
+        for e in &errors {
+            eprintln!("error: {}", e.msg);
+            eprintln!("{}", e.render(&exp));
+        }
+        abort(&filename, errors.len());
+    });
+    // check the types before running or compiling the program
+    if let Err(errors) = typeck::check(&mut node) {
+        for e in &errors {
+            eprintln!("error: {}", e.msg);
+            eprintln!("{}", e.render(&exp));
+        }
+        abort(&filename, errors.len());
+    }
+    if !has_main(&node) {
+        let name = std::path::Path::new(&filename)
+            .file_stem()
+            .map_or(filename.clone(), |stem| stem.to_string_lossy().into_owned());
+        eprintln!("error: `main` function not found in crate `{}`", name);
+        eprintln!(
+            "  = note: consider adding a `main` function to `{}`",
+            filename
+        );
+        abort(&filename, 1);
+    }
+    // failed runtime checks report their location in the file
+    let file = SourceFile {
+        path: &filename,
+        text: &exp,
+    };
+    if run_interp {
+        // Run the program directly and exit with main's return value
+        match interp::run(&node, overflow_checks) {
+            Ok(outcome) => {
+                io::stdout().write_all(&outcome.stdout).unwrap();
+                io::stdout().flush().unwrap();
+                process::exit(outcome.exit_code as i32);
+            }
+            Err(e) => {
+                eprintln!("{}", e.render(&file));
+                process::exit(101);
+            }
+        }
+    }
+    if emit_ir {
+        // Dump the intermediate representation instead of assembly
+        print!("{}", ir::lower(&node, &file, overflow_checks));
+        return;
+    }
     // Generate the program
-    generate(&node);
+    generate(&node, &file, target, overflow_checks);
 }
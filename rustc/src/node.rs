@@ -1,6 +1,9 @@
-use crate::check::{ParseError, error_tok, expect_token};
+use crate::check::{ParseError, Span, error_at, error_tok, expect_token};
+use crate::symbol::{Global, SymbolTable};
 use crate::token::*;
-use crate::variable::Variable;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
 use std::iter::Peekable;
 
 // Introduce OpKind for binary operator kinds
@@ -10,6 +13,15 @@ pub enum OpKind {
     Sub,
     Mul,
     Div,
+    Rem,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    // short-circuiting `&&` and `||`; the right operand may not be evaluated
+    And,
+    Or,
     Eq,
     Ne,
     Lt,
@@ -18,78 +30,580 @@ pub enum OpKind {
     Ge,
 }
 
+impl OpKind {
+    /// What a panic reports when the operator overflows, worded like rustc.
+    pub fn overflow_message(self) -> &'static str {
+        match self {
+            OpKind::Add => "attempt to add with overflow",
+            OpKind::Sub => "attempt to subtract with overflow",
+            OpKind::Mul => "attempt to multiply with overflow",
+            OpKind::Div => "attempt to divide with overflow",
+            OpKind::Rem => "attempt to calculate the remainder with overflow",
+            OpKind::Shl => "attempt to shift left with overflow",
+            OpKind::Shr => "attempt to shift right with overflow",
+            _ => unreachable!("`{:?}` cannot overflow", self),
+        }
+    }
+
+    /// What a panic reports when the operand of `-` is the lowest value of
+    /// its type, worded like rustc.
+    pub const NEG_OVERFLOW: &'static str = "attempt to negate with overflow";
+
+    /// What a panic reports when `/` or `%` is given a zero divisor.
+    pub fn zero_divisor_message(self) -> &'static str {
+        match self {
+            OpKind::Div => "attempt to divide by zero",
+            OpKind::Rem => "attempt to calculate the remainder with a divisor of zero",
+            _ => unreachable!("`{:?}` has no divisor", self),
+        }
+    }
+}
+
+// Static types of values
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Type {
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    Usize,
+    Bool,
+    // string slice, only usable behind a reference
+    Str,
+    // value of a statement such as a loop
+    Unit,
+    // type of `return`, `break` and `continue`, which never produce a value
+    Never,
+    Ref(Box<Type>),
+    // `&mut T`, which also coerces to `&T`
+    RefMut(Box<Type>),
+    Array(Box<Type>, u64),
+    // a struct, by name
+    Struct(String),
+    // an enum, by name
+    Enum(String),
+}
+
+/// Declared structs and enums, by name.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct TypeDefs {
+    /// the fields of each struct, in declaration order
+    pub structs: HashMap<String, Vec<(String, Type)>>,
+    /// the variants of each enum with the types of their fields, in
+    /// declaration order; a variant's tag is its index
+    pub enums: HashMap<String, Vec<(String, Vec<Type>)>>,
+}
+
+impl TypeDefs {
+    /// Whether a struct or an enum is called `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.structs.contains_key(name) || self.enums.contains_key(name)
+    }
+
+    /// Tag and field types of `variant` of enum `name`.
+    pub fn variant(&self, name: &str, variant: &str) -> Option<(u64, &[Type])> {
+        self.enums
+            .get(name)?
+            .iter()
+            .enumerate()
+            .find(|(_, (v, _))| v == variant)
+            .map(|(tag, (_, fields))| (tag as u64, fields.as_slice()))
+    }
+}
+
+impl Type {
+    /// Number of 8-byte slots a value of this type occupies: one per scalar,
+    /// with arrays and structs laid out element by element. An enum takes a
+    /// slot for its tag followed by room for its largest variant, whose
+    /// fields take a slot each. `None` if it names an undeclared type.
+    pub fn slots(&self, defs: &TypeDefs) -> Option<u64> {
+        match self {
+            Type::Array(elem, len) => Some(elem.slots(defs)? * len),
+            Type::Struct(name) => defs
+                .structs
+                .get(name)?
+                .iter()
+                .map(|(_, ty)| ty.slots(defs))
+                .sum(),
+            Type::Enum(name) => {
+                let fields = defs.enums.get(name)?.iter().map(|(_, f)| f.len());
+                Some(1 + fields.max().unwrap_or(0) as u64)
+            }
+            _ => Some(1),
+        }
+    }
+
+    /// Width in bits and signedness of an integer type; `None` for any
+    /// other type.
+    pub fn int_bits(&self) -> Option<(u32, bool)> {
+        match self {
+            Type::I8 => Some((8, true)),
+            Type::I16 => Some((16, true)),
+            Type::I32 => Some((32, true)),
+            Type::I64 => Some((64, true)),
+            Type::U8 => Some((8, false)),
+            Type::U16 => Some((16, false)),
+            Type::U32 => Some((32, false)),
+            Type::U64 | Type::Usize => Some((64, false)),
+            _ => None,
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.int_bits().is_some()
+    }
+
+    /// Smallest and largest value of an integer type.
+    pub fn int_range(&self) -> Option<(i128, i128)> {
+        let (bits, signed) = self.int_bits()?;
+        Some(match signed {
+            true => (-(1 << (bits - 1)), (1 << (bits - 1)) - 1),
+            false => (0, (1 << bits) - 1),
+        })
+    }
+
+    /// `value` truncated to the width of an integer type and sign- or
+    /// zero-extended back to 64 bits, the way registers hold such values.
+    /// Values of other types are returned unchanged.
+    pub fn wrap(&self, value: i64) -> i64 {
+        match self.int_bits() {
+            Some((bits, true)) if bits < 64 => value << (64 - bits) >> (64 - bits),
+            Some((bits, false)) if bits < 64 => value & ((1 << bits) - 1),
+            _ => value,
+        }
+    }
+}
+
+/// Byte offset and type of `field` within struct `name`. Fields follow one
+/// another from the struct's first slot, like array elements do.
+pub fn field_layout<'a>(defs: &'a TypeDefs, name: &str, field: &str) -> Option<(u64, &'a Type)> {
+    let mut offset = 0;
+    for (f, ty) in defs.structs.get(name)? {
+        if f == field {
+            return Some((offset, ty));
+        }
+        offset += ty.slots(defs)? * 8;
+    }
+    None
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::I8 => write!(f, "i8"),
+            Type::I16 => write!(f, "i16"),
+            Type::I32 => write!(f, "i32"),
+            Type::I64 => write!(f, "i64"),
+            Type::U8 => write!(f, "u8"),
+            Type::U16 => write!(f, "u16"),
+            Type::U32 => write!(f, "u32"),
+            Type::U64 => write!(f, "u64"),
+            Type::Usize => write!(f, "usize"),
+            Type::Bool => write!(f, "bool"),
+            Type::Str => write!(f, "str"),
+            Type::Unit => write!(f, "()"),
+            Type::Never => write!(f, "!"),
+            Type::Ref(inner) => write!(f, "&{}", inner),
+            Type::RefMut(inner) => write!(f, "&mut {}", inner),
+            Type::Array(elem, len) => write!(f, "[{}; {}]", elem, len),
+            Type::Struct(name) | Type::Enum(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+// Value computed at compile time for a `const` item or a slot of a `static`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ConstValue {
+    Int(i64),
+    // the address of a string literal
+    Str(String),
+}
+
+// Pattern of a `match` arm
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Pattern {
+    // `_`
+    Wild,
+    // a name, bound to the whole value in the slot at `offset`
+    Bind {
+        offset: u64,
+    },
+    // integers from `lo` to `hi` inclusive; a literal `n` is `n..=n` and
+    // `lo..hi` is `lo..=hi - 1`
+    Range {
+        lo: i64,
+        hi: i64,
+    },
+    // `true` or `false`
+    Bool {
+        value: bool,
+    },
+    // `Enum::Variant` or `Enum::Variant(a, _)`; each field is bound to the
+    // slot at the given offset, or ignored
+    Variant {
+        name: String,
+        variant: String,
+        tag: u64,
+        fields: Vec<Option<u64>>,
+    },
+}
+
+// One arm of a `match`: patterns separated by `|`, and the arm's value
+#[derive(Debug, PartialEq, Eq)]
+pub struct MatchArm {
+    pub pats: Vec<Pattern>,
+    pub body: Node,
+    // source range of the patterns
+    pub span: Span,
+}
+
+impl MatchArm {
+    /// Whether a pattern of this arm binds fields of an enum variant, which
+    /// are then read from the matched place.
+    pub fn binds_fields(&self) -> bool {
+        self.pats.iter().any(|pat| {
+            matches!(pat, Pattern::Variant { fields, .. } if fields.iter().any(Option::is_some))
+        })
+    }
+}
+
+// Syntax tree; every node records the source range it was parsed from
 #[derive(Debug, PartialEq, Eq)]
 pub enum Node {
-    // Sequence of two statements: execute first, then second
+    // Sequence of two statements: execute first, then second; a block's
+    // value is that of its last statement
     Seq {
         first: Box<Node>,
         second: Box<Node>,
+        span: Span,
+    },
+    // Statement terminated by `;`, whose value is discarded for `()`
+    Semi {
+        expr: Box<Node>,
+        span: Span,
     },
     // Literals
     Num {
         value: u64,
+        span: Span,
     },
     StringLiteral {
         value: String,
+        span: Span,
+    },
+    // `true` or `false`
+    Bool {
+        value: bool,
+        span: Span,
     },
     // Variables and functions
     Var {
         offset: u64,
+        span: Span,
+    },
+    // A `static` item used as a place, like a variable; the type checker
+    // sets `ty` to the item's type
+    Global {
+        name: String,
+        ty: Type,
+        span: Span,
+    },
+    // `struct` declaration: the fields in declaration order
+    Struct {
+        name: String,
+        fields: Vec<(String, Type)>,
+        span: Span,
+    },
+    // `enum` declaration: the variants with their field types
+    Enum {
+        name: String,
+        variants: Vec<(String, Vec<Type>)>,
+        span: Span,
+    },
+    // `const` item; every use was replaced by its value, so only the type of
+    // `init` is left to check
+    Const {
+        name: String,
+        ty: Type,
+        init: Box<Node>,
+        span: Span,
+    },
+    // `static` item; `init` stores the value like a `let` initializer would,
+    // and `data` is that value computed for each slot
+    Static {
+        name: String,
+        ty: Type,
+        mutable: bool,
+        init: Box<Node>,
+        data: Vec<ConstValue>,
+        span: Span,
     },
     Function {
         name: String,
         args: Vec<Node>,
+        // declared parameter types, parallel to `args`
+        arg_types: Vec<Type>,
+        // declared return type, if any
+        ret: Option<Type>,
         body: Box<Node>,
+        // type of every variable, keyed by slot offset; filled in by the type
+        // checker
+        locals: HashMap<u64, Type>,
+        span: Span,
     },
     Call {
         name: String,
         args: Vec<Node>,
+        span: Span,
     },
     Syscall {
         name: String,
         args: Vec<Node>,
+        span: Span,
     },
-    // Assignment
+    // Assignment to a variable, array element or dereferenced pointer
     Assign {
         lhs: Box<Node>,
         rhs: Box<Node>,
+        span: Span,
+    },
+    // `lhs op= rhs`; the place is evaluated once. `ty` is the type of the
+    // place, set by the type checker
+    CompoundAssign {
+        op: OpKind,
+        lhs: Box<Node>,
+        rhs: Box<Node>,
+        ty: Type,
+        span: Span,
     },
-    // Binary operations (arithmetic and comparison)
+    // Binary operations (arithmetic, bitwise, logical and comparison). The
+    // type checker sets `ty` to the type of the operands; until then it is
+    // `i64`, a whole slot
     BinaryOp {
         op: OpKind,
         lhs: Box<Node>,
         rhs: Box<Node>,
+        ty: Type,
+        span: Span,
     },
-    // Control flow
-    Return {
+    // `expr as ty`, a conversion between integer types, or from `bool` or a
+    // fieldless enum to an integer
+    Cast {
+        expr: Box<Node>,
+        ty: Type,
+        span: Span,
+    },
+    // `!expr`: logical not of a `bool`, bitwise not of an integer; the type
+    // checker sets `bitwise` and `ty` once it knows the operand type
+    Not {
+        expr: Box<Node>,
+        bitwise: bool,
+        ty: Type,
+        span: Span,
+    },
+    // `-expr` on a signed integer; the type checker sets `ty` to the operand
+    // type, which is `i64` until then
+    Neg {
         expr: Box<Node>,
+        ty: Type,
+        span: Span,
+    },
+    // `()`, the value of an empty block
+    Unit {
+        span: Span,
+    },
+    // Control flow; `return` without a value returns `()`
+    Return {
+        expr: Option<Box<Node>>,
+        span: Span,
     },
     If {
         cond: Box<Node>,
         then_stmt: Box<Node>,
         else_stmt: Option<Box<Node>>,
+        span: Span,
     },
+    // Loops may carry a label such as `'outer` for `break` and `continue`
     While {
         cond: Box<Node>,
         body: Box<Node>,
+        label: Option<String>,
+        span: Span,
     },
     For {
         init: Box<Node>,
         cond: Box<Node>,
         update: Box<Node>,
         body: Box<Node>,
+        label: Option<String>,
+        span: Span,
+    },
+    // `for x in start..end`, or `..=end` when inclusive; `offset` is the slot of `x`
+    ForRange {
+        offset: u64,
+        start: Box<Node>,
+        end: Box<Node>,
+        inclusive: bool,
+        body: Box<Node>,
+        label: Option<String>,
+        span: Span,
+    },
+    Loop {
+        body: Box<Node>,
+        label: Option<String>,
+        span: Span,
     },
-    // Pointer operations
+    // Leave the labelled loop, or the innermost one, with an optional value
+    Break {
+        label: Option<String>,
+        value: Option<Box<Node>>,
+        span: Span,
+    },
+    Continue {
+        label: Option<String>,
+        span: Span,
+    },
+    // Pointer operations; the type checker sets `ty` to the type pointed to,
+    // which is `i64` until then
     Deref {
         expr: Box<Node>,
+        ty: Type,
+        span: Span,
     },
+    // `&expr`, or `&mut expr` when `mutable`
     Addr {
         expr: Box<Node>,
+        mutable: bool,
+        span: Span,
+    },
+    // `let` binding; `init` stores the initial value into the new variable's
+    // slots and `ty` is the declared type, if one was written
+    Let {
+        ty: Option<Type>,
+        init: Box<Node>,
+        span: Span,
     },
+    // Array literals in a `let`, stored into the slots starting at `offset`
     ArrayAssign {
         offset: u64,
         elements: Vec<Node>,
+        span: Span,
+    },
+    // `[value; len]`, with `value` evaluated once
+    ArrayRepeat {
+        offset: u64,
+        value: Box<Node>,
+        len: u64,
+        span: Span,
+    },
+    // Struct literal, stored into the `slots` slots starting at `offset`:
+    // those of the variable it initializes, or temporary ones when it is used
+    // elsewhere; each field is initialized like a variable, array or nested
+    // struct
+    StructLit {
+        name: String,
+        offset: u64,
+        slots: u64,
+        fields: Vec<(String, Node)>,
+        span: Span,
+    },
+    // `expr[index]`, an element of the array `expr`; the type checker sets
+    // `ty` to the element type, which is `i64` until then
+    Index {
+        expr: Box<Node>,
+        index: Box<Node>,
+        ty: Type,
+        span: Span,
+    },
+    // `expr.name`; the type checker sets `offset`, the field's byte offset
+    // from the start of the struct, and `ty`, the field's type
+    Field {
+        expr: Box<Node>,
+        name: String,
+        offset: u64,
+        ty: Type,
+        span: Span,
+    },
+    // `Enum::Variant` of an enum without fields, whose value is the tag
+    Variant {
+        name: String,
+        variant: String,
+        tag: u64,
+        span: Span,
+    },
+    // `Enum::Variant(a, b)` of an enum with fields, stored like a struct
+    // literal: the tag goes into the slot at `offset` and the fields into the
+    // slots after it
+    VariantLit {
+        name: String,
+        variant: String,
+        tag: u64,
+        offset: u64,
+        fields: Vec<Node>,
+        span: Span,
     },
+    // `match expr { pat => value, ... }`; the first arm whose pattern
+    // matches is taken. `by_ref` is set by the type checker when the
+    // scrutinee is reached through a reference; the names bound by the
+    // patterns then hold addresses instead of values
+    Match {
+        expr: Box<Node>,
+        arms: Vec<MatchArm>,
+        by_ref: bool,
+        span: Span,
+    },
+}
+
+impl Node {
+    /// Source range this node was parsed from.
+    pub fn span(&self) -> Span {
+        match self {
+            Node::Seq { span, .. } => *span,
+            Node::Semi { span, .. } => *span,
+            Node::Num { span, .. } => *span,
+            Node::Unit { span } => *span,
+            Node::Bool { span, .. } => *span,
+            Node::StringLiteral { span, .. } => *span,
+            Node::Var { span, .. } => *span,
+            Node::Global { span, .. } => *span,
+            Node::Struct { span, .. } => *span,
+            Node::Enum { span, .. } => *span,
+            Node::Const { span, .. } => *span,
+            Node::Static { span, .. } => *span,
+            Node::Function { span, .. } => *span,
+            Node::Call { span, .. } => *span,
+            Node::Syscall { span, .. } => *span,
+            Node::Assign { span, .. } => *span,
+            Node::CompoundAssign { span, .. } => *span,
+            Node::BinaryOp { span, .. } => *span,
+            Node::Cast { span, .. } => *span,
+            Node::Not { span, .. } => *span,
+            Node::Neg { span, .. } => *span,
+            Node::Return { span, .. } => *span,
+            Node::If { span, .. } => *span,
+            Node::While { span, .. } => *span,
+            Node::For { span, .. } => *span,
+            Node::ForRange { span, .. } => *span,
+            Node::Loop { span, .. } => *span,
+            Node::Break { span, .. } => *span,
+            Node::Continue { span, .. } => *span,
+            Node::Deref { span, .. } => *span,
+            Node::Addr { span, .. } => *span,
+            Node::Let { span, .. } => *span,
+            Node::ArrayAssign { span, .. } => *span,
+            Node::ArrayRepeat { span, .. } => *span,
+            Node::StructLit { span, .. } => *span,
+            Node::Index { span, .. } => *span,
+            Node::Field { span, .. } => *span,
+            Node::Variant { span, .. } => *span,
+            Node::VariantLit { span, .. } => *span,
+            Node::Match { span, .. } => *span,
+        }
+    }
 }
 
 fn expect_next(toks: &mut Peekable<TokenIter>, expected: TokenKind) -> Result<Token, ParseError> {
@@ -102,11 +616,15 @@ fn expect_next(toks: &mut Peekable<TokenIter>, expected: TokenKind) -> Result<To
 }
 
 // Add helper to fold a Vec<Node> into nested Seq nodes
-fn fold_seq(nodes: Vec<Node>) -> Node {
+fn fold_seq(nodes: Vec<Node>, span: Span) -> Node {
     let mut iter = nodes.into_iter();
-    let mut node = iter.next().unwrap();
+    // an empty sequence evaluates to `()`, located at `span`
+    let Some(mut node) = iter.next() else {
+        return Node::Unit { span };
+    };
     for next in iter {
         node = Node::Seq {
+            span: node.span().to(next.span()),
             first: Box::new(node),
             second: Box::new(next),
         };
@@ -114,1366 +632,4948 @@ fn fold_seq(nodes: Vec<Node>) -> Node {
     node
 }
 
-// program ::= function*
-pub fn program(toks: &mut Peekable<TokenIter>, vars: &mut Variable) -> Result<Node, ParseError> {
+thread_local! {
+    // errors recovered from while parsing the current program
+    static DIAGNOSTICS: RefCell<Vec<ParseError>> = const { RefCell::new(Vec::new()) };
+}
+
+// Record an error so parsing can carry on and report it later
+fn report(err: ParseError) {
+    DIAGNOSTICS.with(|d| d.borrow_mut().push(err));
+}
+
+// Whether `kind` begins a top-level item, or is the end of input
+fn starts_item(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Fn
+            | TokenKind::Struct
+            | TokenKind::Enum
+            | TokenKind::Const
+            | TokenKind::Static
+            | TokenKind::Eof
+    )
+}
+
+// Panic-mode recovery: skip tokens up to the end of the broken statement.
+// Stops after a `;` or a balanced `{ ... }` block, and before a `}` closing
+// the enclosing block, the next item or the end of input. A `;` within
+// brackets or parentheses, like that of `[0; 3]`, does not end the statement,
+// even when they were opened before the error.
+fn synchronize(toks: &mut Peekable<TokenIter>) {
+    let mut depth = 0;
+    let mut nesting = 0usize;
+    while let Some(kind) = toks.peek().map(|tok| tok.kind.clone()) {
+        match kind {
+            _ if starts_item(&kind) => return,
+            TokenKind::RBrace if depth == 0 => return,
+            TokenKind::Semicolon if depth == 0 && nesting == 0 && !closes_bracket(toks) => {
+                toks.next();
+                return;
+            }
+            TokenKind::LBracket | TokenKind::LParen => nesting += 1,
+            TokenKind::RBracket | TokenKind::RParen => nesting = nesting.saturating_sub(1),
+            TokenKind::LBrace => depth += 1,
+            TokenKind::RBrace => {
+                depth -= 1;
+                if depth == 0 {
+                    toks.next();
+                    return;
+                }
+            }
+            _ => {}
+        }
+        toks.next();
+    }
+}
+
+// Whether the tokens after the `;` at the front of `toks` close a bracket or
+// a parenthesis before the statement ends, which the `;` then lies within
+fn closes_bracket(toks: &Peekable<TokenIter>) -> bool {
+    let mut nesting = 0usize;
+    for tok in toks.clone().skip(1) {
+        match tok.kind {
+            _ if starts_item(&tok.kind) => return false,
+            TokenKind::Semicolon | TokenKind::LBrace | TokenKind::RBrace => return false,
+            TokenKind::LBracket | TokenKind::LParen => nesting += 1,
+            TokenKind::RBracket | TokenKind::RParen if nesting == 0 => return true,
+            TokenKind::RBracket | TokenKind::RParen => nesting -= 1,
+            _ => {}
+        }
+    }
+    false
+}
+
+// program ::= (function | struct_item | enum_item | global_item)*
+// Parsing continues past errors; every error found is returned in source order.
+pub fn program(
+    toks: &mut Peekable<TokenIter>,
+    vars: &mut SymbolTable,
+) -> Result<Node, Vec<ParseError>> {
+    DIAGNOSTICS.with(|d| d.borrow_mut().clear());
+    let mut declared = declarations(toks, vars);
     let mut funcs = Vec::new();
     while let Some(tok) = toks.peek() {
         if let TokenKind::Eof = tok.kind {
             break;
         }
-        // parse a function definition
-        funcs.push(function(toks, vars)?);
+        // a type or global item already parsed ahead of the others
+        if let Some(item) = declared.remove(&tok.pos) {
+            let end = item.span().end;
+            while toks.next_if(|tok| tok.pos < end).is_some() {}
+            funcs.push(item);
+            continue;
+        }
+        // parse a struct or enum declaration, a `const` or `static` item, or
+        // a function definition
+        let item = match tok.kind {
+            TokenKind::Struct => struct_item(toks, vars),
+            TokenKind::Enum => enum_item(toks, vars),
+            TokenKind::Const | TokenKind::Static => global_item(toks, vars),
+            TokenKind::Fn => function(toks, vars),
+            // statements outside of any function, such as the rest of a body
+            // that an earlier error closed too early. They are parsed up to
+            // the `}` ending them, so that their own errors are reported too.
+            _ => {
+                report(error_tok(tok, "expected Fn"));
+                if block_stmts(toks, vars).is_ok() {
+                    toks.next_if(|tok| tok.kind == TokenKind::RBrace);
+                }
+                continue;
+            }
+        };
+        match item {
+            Ok(item) => funcs.push(item),
+            Err(err) => {
+                report(err);
+                // resume at the next item
+                while let Some(tok) = toks.peek() {
+                    if starts_item(&tok.kind) {
+                        break;
+                    }
+                    toks.next();
+                }
+            }
+        }
+    }
+    let errors = DIAGNOSTICS.with(|d| d.take());
+    if !errors.is_empty() {
+        return Err(errors);
     }
-    // Fold functions into nested Seq nodes
-    Ok(fold_seq(funcs))
+    // Fold items into nested Seq nodes
+    Ok(fold_seq(funcs, Span::default()))
 }
 
-// function ::= 'fn' ident '(' function_args? ')' ('->' type)? '{' stmt* '}'
-fn function(toks: &mut Peekable<TokenIter>, vars: &mut Variable) -> Result<Node, ParseError> {
-    // consume 'fn'
-    expect_next(toks, TokenKind::Fn)?;
-    // parse function name
-    let tok = toks.next().ok_or_else(|| ParseError {
-        msg: "expected identifier".into(),
-        pos: 0,
-    })?;
-    let name = if let TokenKind::Ident { name } = tok.kind.clone() {
-        name
-    } else {
-        return Err(error_tok(&tok, "expected identifier"));
-    };
-    // expect '('
-    expect_next(toks, TokenKind::LParen)?;
-    // parse optional parameters only if the next token is an identifier
-    let mut args_vec = Vec::new();
-    if let Some(peek) = toks.peek() {
-        if let TokenKind::Ident { name: _ } = peek.kind {
-            args_vec = function_args(toks, vars)?;
+// Parse the top-level struct, enum, `const` and `static` items ahead of the
+// functions, keyed by the position of their first token, so that any item may
+// use one declared after it. An item that fails is retried once more items are
+// known; one that never succeeds is left for the main pass to report.
+fn declarations(toks: &Peekable<TokenIter>, vars: &mut SymbolTable) -> HashMap<usize, Node> {
+    let mut pending = Vec::new();
+    let mut scan = toks.clone();
+    let mut depth = 0usize;
+    while let Some(tok) = scan.peek() {
+        match tok.kind {
+            TokenKind::Struct | TokenKind::Enum | TokenKind::Const | TokenKind::Static
+                if depth == 0 =>
+            {
+                pending.push(scan.clone())
+            }
+            TokenKind::LBrace => depth += 1,
+            TokenKind::RBrace => depth = depth.saturating_sub(1),
+            _ => {}
         }
+        scan.next();
     }
-    // expect ')'
-    expect_next(toks, TokenKind::RParen)?;
-    // optional return type '-> type'
-    if let Some(peek) = toks.peek() {
-        if peek.kind == TokenKind::Arrow {
-            toks.next();
-            // parse type (only i32 supported)
-            expect_next(toks, TokenKind::I32)?;
-        }
+    let mut items = HashMap::new();
+    let mut progress = true;
+    while progress {
+        progress = false;
+        pending.retain(|item| {
+            let mut item = item.clone();
+            let reported = DIAGNOSTICS.with(|d| d.borrow().len());
+            let saved = vars.clone();
+            let result = match item.peek().map(|tok| &tok.kind) {
+                Some(TokenKind::Struct) => struct_item(&mut item, vars),
+                Some(TokenKind::Enum) => enum_item(&mut item, vars),
+                _ => global_item(&mut item, vars),
+            };
+            // errors are only reported by the main pass
+            let clean = DIAGNOSTICS.with(|d| {
+                let mut d = d.borrow_mut();
+                let clean = d.len() == reported;
+                d.truncate(reported);
+                clean
+            });
+            match result {
+                Ok(node) if clean => {
+                    items.insert(node.span().start, node);
+                    progress = true;
+                    false
+                }
+                // a failed attempt leaves nothing behind
+                _ => {
+                    *vars = saved;
+                    true
+                }
+            }
+        });
     }
-    // expect '{'
-    expect_next(toks, TokenKind::LBrace)?;
-    // parse body statements
+    items
+}
+
+// Parse statements up to the closing '}' of a block, which is left unconsumed.
+// A statement with an error is reported and skipped.
+fn block_stmts(
+    toks: &mut Peekable<TokenIter>,
+    vars: &mut SymbolTable,
+) -> Result<Vec<Node>, ParseError> {
     let mut stmts = Vec::new();
     while let Some(peek) = toks.peek() {
-        if peek.kind == TokenKind::RBrace {
-            break;
-        }
         // error if EOF reached before closing brace
         if peek.kind == TokenKind::Eof {
             return Err(error_tok(peek, "expected RBrace"));
         }
-        stmts.push(stmt(toks, vars)?);
+        // an item means this block was never closed
+        if peek.kind == TokenKind::RBrace || starts_item(&peek.kind) {
+            break;
+        }
+        match stmt(toks, vars) {
+            Ok(node) => stmts.push(node),
+            Err(err) => {
+                report(err);
+                synchronize(toks);
+            }
+        }
     }
-    // expect '}'
-    expect_next(toks, TokenKind::RBrace)?;
-    // fold into a single Node, default to 0 if empty
-    let body = if stmts.is_empty() {
-        Node::Num { value: 0 }
-    } else {
-        fold_seq(stmts)
-    };
-    Ok(Node::Function {
-        name: name,
-        args: args_vec,
-        body: Box::new(body),
-    })
+    Ok(stmts)
 }
 
-// stmt ::= expr ';' |
-//          '{' stmt* '}' |
-//          'let' ident '=' expr ';' |
-//          'return' expr ';' |
-//          'if' '(' expr ')' stmt ('else' stmt)? |
-//          'while' '(' expr ')' stmt |
-//          'for' '(' expr ';' expr ';' expr ')' stmt
-fn stmt(toks: &mut Peekable<TokenIter>, vars: &mut Variable) -> Result<Node, ParseError> {
-    // detect EOF as missing statement
-    if let Some(tok) = toks.peek() {
-        if tok.kind == TokenKind::Eof {
-            return Err(error_tok(tok, "expected statement"));
+// struct_item ::= 'struct' ident '{' (ident ':' type (',' ident ':' type)* ','?)? '}'
+// Field types may name structs declared later, but not ones that refer back
+// to this struct.
+fn struct_item(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    let struct_tok = expect_next(toks, TokenKind::Struct)?;
+    let name_tok = toks.next().ok_or_else(|| ParseError {
+        msg: "expected identifier".into(),
+        pos: 0,
+    })?;
+    let TokenKind::Ident { name } = name_tok.kind.clone() else {
+        return Err(error_tok(&name_tok, "expected identifier"));
+    };
+    expect_next(toks, TokenKind::LBrace)?;
+    let mut fields: Vec<(String, Type)> = Vec::new();
+    while let Some(peek) = toks.peek()
+        && peek.kind != TokenKind::RBrace
+    {
+        let field_tok = toks.next().unwrap();
+        let TokenKind::Ident { name: field } = field_tok.kind.clone() else {
+            return Err(error_tok(&field_tok, "expected field name"));
+        };
+        expect_next(toks, TokenKind::Colon)?;
+        let field_ty = ty(toks, vars)?;
+        if fields.iter().any(|(f, _)| *f == field) {
+            report(error_tok(
+                &field_tok,
+                &format!("field `{}` is already declared", field),
+            ));
+        } else {
+            fields.push((field, field_ty));
         }
-    }
-    if let Some(tok) = toks.peek() {
-        match tok.kind {
-            TokenKind::Return => {
-                toks.next();
-                let node = expr(toks, vars)?;
-                expect_next(toks, TokenKind::Semicolon)?;
-                return Ok(Node::Return {
-                    expr: Box::new(node),
-                });
-            }
-            TokenKind::LBrace => {
+        // fields are separated by commas, with an optional trailing one
+        match toks.peek() {
+            Some(tok) if tok.kind == TokenKind::Comma => {
                 toks.next();
-                let mut stmts = Vec::new();
-                while let Some(tok) = toks.peek() {
-                    if tok.kind == TokenKind::RBrace {
-                        break;
-                    }
-                    // error if EOF before closing brace
-                    if tok.kind == TokenKind::Eof {
-                        return Err(error_tok(tok, "expected RBrace"));
-                    }
-                    stmts.push(stmt(toks, vars)?);
-                }
-                expect_next(toks, TokenKind::RBrace)?;
-                return Ok(fold_seq(stmts));
             }
-            TokenKind::If => {
-                // parse if statement: 'if' '(' expr ')' stmt ('else' stmt)?
-                toks.next();
-                // expect '('
-                expect_next(toks, TokenKind::LParen)?;
-                // error if no condition expression
-                if let Some(peek) = toks.peek() {
-                    if peek.kind == TokenKind::RParen || peek.kind == TokenKind::Eof {
-                        return Err(error_tok(peek, "expected expression"));
-                    }
+            _ => break,
+        }
+    }
+    let rbrace = expect_next(toks, TokenKind::RBrace)?;
+    if vars.types().contains(&name) {
+        report(error_tok(
+            &name_tok,
+            &format!("the name `{}` is defined multiple times", name),
+        ));
+    }
+    vars.define_struct(&name, fields.clone());
+    Ok(Node::Struct {
+        name,
+        fields,
+        span: struct_tok.span().to(rbrace.span()),
+    })
+}
+
+// enum_item ::= 'enum' ident '{' (variant (',' variant)* ','?)? '}'
+// variant ::= ident ('(' type (',' type)* ','? ')')?
+// Each field of a variant takes one slot, so it cannot be an array or a
+// struct; those are stored behind a reference instead.
+fn enum_item(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    let enum_tok = expect_next(toks, TokenKind::Enum)?;
+    let name_tok = toks.next().ok_or_else(|| ParseError {
+        msg: "expected identifier".into(),
+        pos: 0,
+    })?;
+    let TokenKind::Ident { name } = name_tok.kind.clone() else {
+        return Err(error_tok(&name_tok, "expected identifier"));
+    };
+    expect_next(toks, TokenKind::LBrace)?;
+    let mut variants: Vec<(String, Vec<Type>)> = Vec::new();
+    while let Some(peek) = toks.peek()
+        && peek.kind != TokenKind::RBrace
+    {
+        let variant_tok = toks.next().unwrap();
+        let TokenKind::Ident { name: variant } = variant_tok.kind.clone() else {
+            return Err(error_tok(&variant_tok, "expected variant name"));
+        };
+        let mut fields = Vec::new();
+        if let Some(tok) = toks.peek()
+            && tok.kind == TokenKind::LParen
+        {
+            toks.next();
+            while let Some(peek) = toks.peek()
+                && peek.kind != TokenKind::RParen
+            {
+                let pos = peek.pos;
+                let field_ty = ty(toks, vars)?;
+                if field_ty.slots(vars.types()) != Some(1) {
+                    report(error_at(
+                        pos,
+                        &format!(
+                            "field of variant `{}::{}` cannot be of type `{}`; use a reference instead",
+                            name, variant, field_ty
+                        ),
+                    ));
                 }
-                // parse condition
-                let cond = expr(toks, vars)?;
-                // expect ')'
-                expect_next(toks, TokenKind::RParen)?;
-                // parse then branch
-                let then_stmt = stmt(toks, vars)?;
-                // parse optional else branch
-                let else_stmt = if let Some(tok) = toks.peek() {
-                    if tok.kind == TokenKind::Else {
+                fields.push(field_ty);
+                match toks.peek() {
+                    Some(tok) if tok.kind == TokenKind::Comma => {
                         toks.next();
-                        Some(Box::new(stmt(toks, vars)?))
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                };
-                return Ok(Node::If {
-                    cond: Box::new(cond),
-                    then_stmt: Box::new(then_stmt),
-                    else_stmt: else_stmt,
-                });
-            }
-            TokenKind::While => {
-                // parse while statement: 'while' '(' expr ')' stmt
-                toks.next();
-                // expect '('
-                expect_next(toks, TokenKind::LParen)?;
-                // error if no condition expression
-                if let Some(peek) = toks.peek() {
-                    if peek.kind == TokenKind::RParen || peek.kind == TokenKind::Eof {
-                        return Err(error_tok(peek, "expected expression"));
                     }
+                    _ => break,
                 }
-                // parse condition
-                let cond = expr(toks, vars)?;
-                // expect ')'
-                expect_next(toks, TokenKind::RParen)?;
-                // parse body
-                let body = stmt(toks, vars)?;
-                return Ok(Node::While {
-                    cond: Box::new(cond),
-                    body: Box::new(body),
-                });
             }
-            TokenKind::For => {
-                // parse for statement: 'for' '(' expr ';' expr ';' expr ')' stmt
+            expect_next(toks, TokenKind::RParen)?;
+        }
+        if variants.iter().any(|(v, _)| *v == variant) {
+            report(error_tok(
+                &variant_tok,
+                &format!("the name `{}` is defined multiple times", variant),
+            ));
+        } else {
+            variants.push((variant, fields));
+        }
+        // variants are separated by commas, with an optional trailing one
+        match toks.peek() {
+            Some(tok) if tok.kind == TokenKind::Comma => {
                 toks.next();
-                // expect '('
-                expect_next(toks, TokenKind::LParen)?;
-                // error if missing init expression
-                if let Some(peek) = toks.peek() {
-                    if peek.kind == TokenKind::Semicolon || peek.kind == TokenKind::Eof {
-                        return Err(error_tok(peek, "expected expression"));
-                    }
-                }
-                // parse init
-                let init = expr(toks, vars)?;
-                expect_next(toks, TokenKind::Semicolon)?;
-                // error if missing condition expression
-                if let Some(peek) = toks.peek() {
-                    if peek.kind == TokenKind::Semicolon || peek.kind == TokenKind::Eof {
-                        return Err(error_tok(peek, "expected expression"));
-                    }
-                }
-                // parse condition
-                let cond = expr(toks, vars)?;
-                expect_next(toks, TokenKind::Semicolon)?;
-                // error if missing update expression
-                if let Some(peek) = toks.peek() {
-                    if peek.kind == TokenKind::RParen || peek.kind == TokenKind::Eof {
-                        return Err(error_tok(peek, "expected expression"));
-                    }
-                }
-                // parse update
-                let update = expr(toks, vars)?;
-                // expect ')'
-                expect_next(toks, TokenKind::RParen)?;
-                // parse body
-                let body = stmt(toks, vars)?;
-                return Ok(Node::For {
-                    init: Box::new(init),
-                    cond: Box::new(cond),
-                    update: Box::new(update),
-                    body: Box::new(body),
-                });
             }
-            TokenKind::Let => {
-                // parse let statement: 'let' ident '=' expr ';'
-                toks.next();
-                // expect identifier
-                let tok_ident = toks.next().ok_or_else(|| ParseError {
-                    msg: "expected identifier after 'let'".into(),
-                    pos: 0,
-                })?;
-                let name = if let TokenKind::Ident { name } = tok_ident.kind.clone() {
-                    name
-                } else {
-                    return Err(error_tok(&tok_ident, "expected identifier after 'let'"));
-                };
-                // expect '='
-                expect_next(toks, TokenKind::Assign)?;
-                // array literal assignment: let name = [expr, ...];
-                if let Some(peek) = toks.peek() {
-                    if peek.kind == TokenKind::LBracket {
-                        toks.next(); // consume '['
-                        let mut elements = Vec::new();
-                        // parse elements if not empty
-                        if let Some(peek2) = toks.peek() {
-                            if peek2.kind != TokenKind::RBracket {
-                                elements.push(expr(toks, vars)?);
-                                while let Some(tok2) = toks.peek() {
-                                    if tok2.kind == TokenKind::Comma {
-                                        toks.next();
-                                        elements.push(expr(toks, vars)?);
-                                    } else {
-                                        break;
-                                    }
-                                }
-                            }
-                        }
-                        expect_next(toks, TokenKind::RBracket)?;
-                        expect_next(toks, TokenKind::Semicolon)?;
-                        // check for duplicate variable
-                        if vars.find(&name).is_some() {
-                            return Err(error_tok(&tok_ident, "variable already declared"));
-                        }
-                        // allocate new variable offset
-                        let last = vars.next.as_ref().map(|v| v.offset).unwrap_or(vars.offset);
-                        let arr_offset = last + 8;
-                        // determine region end offset for array to avoid overlapping subsequent vars
-                        let region_end = if elements.is_empty() {
-                            arr_offset
-                        } else {
-                            arr_offset + (elements.len() as u64 - 1) * 8
-                        };
-                        // push array variable mapping
-                        vars.push(name.clone(), arr_offset);
-                        // push dummy mapping for region end to allocate array space
-                        vars.push("".to_string(), region_end);
-                        return Ok(Node::ArrayAssign {
-                            offset: arr_offset,
-                            elements,
-                        });
-                    }
-                }
-                // parse expression
-                let rhs = expr(toks, vars)?;
-                // expect ';'
-                expect_next(toks, TokenKind::Semicolon)?;
-                // check for duplicate variable
-                if vars.find(&name).is_some() {
-                    return Err(error_tok(&tok_ident, "variable already declared"));
-                }
-                // allocate new variable offset
-                let last = vars.next.as_ref().map(|v| v.offset).unwrap_or(vars.offset);
-                let new_off = last + 8;
-                vars.push(name.clone(), new_off);
-                // return assignment node
-                return Ok(Node::Assign {
-                    lhs: Box::new(Node::Var { offset: new_off }),
-                    rhs: Box::new(rhs),
-                });
-            }
-            _ => {}
+            _ => break,
         }
     }
-    // expression statement
-    let node = expr(toks, vars)?;
-    expect_next(toks, TokenKind::Semicolon)?;
-    Ok(node)
+    let rbrace = expect_next(toks, TokenKind::RBrace)?;
+    if vars.types().contains(&name) {
+        report(error_tok(
+            &name_tok,
+            &format!("the name `{}` is defined multiple times", name),
+        ));
+    }
+    vars.define_enum(&name, variants.clone());
+    Ok(Node::Enum {
+        name,
+        variants,
+        span: enum_tok.span().to(rbrace.span()),
+    })
 }
 
-// expr ::= assign
-fn expr(toks: &mut Peekable<TokenIter>, vars: &mut Variable) -> Result<Node, ParseError> {
-    assign(toks, vars)
+// global_item ::= 'const' ident ':' type '=' expr ';' |
+//                 'static' 'mut'? ident ':' type '=' initializer ';'
+// Both are computed at compile time. A `const` is replaced by its value
+// wherever it is used, while a `static` lives at a fixed address in the data
+// section.
+fn global_item(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    let item_tok = toks.next().unwrap();
+    let is_static = item_tok.kind == TokenKind::Static;
+    let mutable = is_static && eat_mut(toks);
+    let name_tok = toks.next().ok_or_else(|| ParseError {
+        msg: "expected identifier".into(),
+        pos: 0,
+    })?;
+    let TokenKind::Ident { name } = name_tok.kind.clone() else {
+        return Err(error_tok(&name_tok, "expected identifier"));
+    };
+    expect_next(toks, TokenKind::Colon)?;
+    let declared = ty(toks, vars)?;
+    expect_next(toks, TokenKind::Assign)?;
+    // no local variable is visible to the initializer
+    vars.enter_function();
+    let (init, global, data) = if is_static {
+        // the initializer fills slots as if it were a local of the declared type
+        let slots = declared.slots(vars.types()).unwrap_or(1);
+        let base = vars.reserve(slots);
+        let init = initializer(toks, vars, base, name_tok.span())?;
+        let mut data = vec![ConstValue::Int(0); slots as usize];
+        static_data(&init, &declared, vars.types(), base, &mut data)?;
+        let global = Global::Static {
+            ty: declared.clone(),
+            mutable,
+        };
+        (init, global, Some(data))
+    } else {
+        let init = expr(toks, vars)?;
+        let global = Global::Const {
+            ty: declared.clone(),
+            value: const_value(&init, &declared, "constants")?,
+        };
+        (init, global, None)
+    };
+    let semi = expect_next(toks, TokenKind::Semicolon)?;
+    if vars.global(&name).is_some() {
+        report(error_tok(
+            &name_tok,
+            &format!("the name `{}` is defined multiple times", name),
+        ));
+    }
+    vars.define_global(&name, global);
+    let init = Box::new(init);
+    let span = item_tok.span().to(semi.span());
+    Ok(match data {
+        Some(data) => Node::Static {
+            name,
+            ty: declared,
+            mutable,
+            init,
+            data,
+            span,
+        },
+        None => Node::Const {
+            name,
+            ty: declared,
+            init,
+            span,
+        },
+    })
 }
 
-// assign ::= equality ('=' assign)?
-fn assign(toks: &mut Peekable<TokenIter>, vars: &mut Variable) -> Result<Node, ParseError> {
-    let mut lhs = equality(toks, vars)?;
-    if let Some(tok) = toks.peek() {
-        match tok.kind {
-            TokenKind::Assign => {
-                toks.next();
-                let rhs = assign(toks, vars)?;
-                lhs = Node::Assign {
-                    lhs: Box::new(lhs),
-                    rhs: Box::new(rhs),
-                };
-            }
-            _ => {}
-        }
+// The value of a constant expression of type `ty`, or of a string literal;
+// `context` names the items being initialized for messages
+fn const_value(node: &Node, ty: &Type, context: &str) -> Result<ConstValue, ParseError> {
+    match node {
+        Node::StringLiteral { value, .. } => Ok(ConstValue::Str(value.clone())),
+        _ => Ok(ConstValue::Int(const_eval(node, ty, context)?)),
     }
-    Ok(lhs)
 }
 
-// equality ::= relational (( '==' | '!=' ) relational)*
-fn equality(toks: &mut Peekable<TokenIter>, vars: &mut Variable) -> Result<Node, ParseError> {
-    let mut lhs = relational(toks, vars)?;
-    while let Some(tok) = toks.peek() {
-        match tok.kind {
-            TokenKind::EqEq => {
-                toks.next();
-                let rhs = relational(toks, vars)?;
-                lhs = Node::BinaryOp {
-                    op: OpKind::Eq,
-                    lhs: Box::new(lhs),
-                    rhs: Box::new(rhs),
-                };
+// Whether a constant expression is a boolean: a literal, a comparison, or a
+// logical or bitwise operation on booleans. Boolean constants have been
+// replaced by their literal.
+fn is_bool(node: &Node) -> bool {
+    match node {
+        Node::Bool { .. } => true,
+        Node::Not { expr, .. } => is_bool(expr),
+        Node::BinaryOp { op, lhs, .. } => match op {
+            OpKind::BitAnd | OpKind::BitOr | OpKind::BitXor => is_bool(lhs),
+            OpKind::And
+            | OpKind::Or
+            | OpKind::Eq
+            | OpKind::Ne
+            | OpKind::Lt
+            | OpKind::Gt
+            | OpKind::Le
+            | OpKind::Ge => true,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+// Compute a constant expression of literals, enum variants, operators and
+// other constants, with the arithmetic of the integer type `ty`; booleans and
+// enums compute with `i32`
+fn const_eval(node: &Node, ty: &Type, context: &str) -> Result<i64, ParseError> {
+    let span = node.span();
+    let fail = |msg: &str| {
+        Err(error_at(
+            span.start,
+            &format!("evaluation of constant value failed: {}", msg),
+        ))
+    };
+    let ty = if ty.is_integer() { ty } else { &Type::I32 };
+    let (min, max) = ty.int_range().unwrap();
+    // a `u64` above `i64::MAX` is held in an `i64` with the same bits
+    let widen = |v: i64| match ty.int_bits() {
+        Some((64, false)) => v as u64 as i128,
+        _ => v as i128,
+    };
+    match node {
+        Node::Num { value, .. } if (*value as i128) <= max => Ok(*value as i64),
+        Node::Num { .. } => Err(error_at(
+            span.start,
+            &format!("literal out of range for `{}`", ty),
+        )),
+        Node::Bool { value, .. } => Ok(*value as i64),
+        Node::Variant { tag, .. } => Ok(*tag as i64),
+        // a literal under a cast, which includes a use of another constant,
+        // is truncated to the target type
+        Node::Cast {
+            expr, ty: target, ..
+        } if matches!(expr.as_ref(), Node::Num { .. }) => {
+            let Node::Num { value, .. } = expr.as_ref() else {
+                unreachable!();
+            };
+            Ok(target.wrap(*value as i64))
+        }
+        Node::Cast {
+            expr, ty: target, ..
+        } => Ok(target.wrap(const_eval(expr, ty, context)?)),
+        // `!` is logical on booleans and bitwise on integers
+        Node::Not { expr, .. } if is_bool(expr) => Ok((const_eval(expr, ty, context)? == 0) as i64),
+        Node::Not { expr, .. } => Ok(ty.wrap(!const_eval(expr, ty, context)?)),
+        // the minimum, like `-128` for `i8`, negates a literal that is out
+        // of range on its own
+        Node::Neg { expr, .. } if matches!(expr.as_ref(), Node::Num { value, .. } if *value as i128 == -min) => {
+            Ok(min as i64)
+        }
+        Node::Neg { expr, .. } => {
+            let v = -widen(const_eval(expr, ty, context)?);
+            match (min..=max).contains(&v) {
+                true => Ok(v as i64),
+                false => fail(OpKind::NEG_OVERFLOW),
             }
-            TokenKind::Ne => {
-                toks.next();
-                let rhs = relational(toks, vars)?;
-                lhs = Node::BinaryOp {
-                    op: OpKind::Ne,
-                    lhs: Box::new(lhs),
-                    rhs: Box::new(rhs),
-                };
+        }
+        Node::BinaryOp { op, lhs, rhs, .. } => {
+            let l = widen(const_eval(lhs, ty, context)?);
+            let r = widen(const_eval(rhs, ty, context)?);
+            let bits = ty.int_bits().unwrap().0 as i128;
+            let in_range = |v: i128| (min..=max).contains(&v).then_some(v);
+            let v = match op {
+                OpKind::Add => in_range(l + r).ok_or(op.overflow_message()),
+                OpKind::Sub => in_range(l - r).ok_or(op.overflow_message()),
+                OpKind::Mul => l
+                    .checked_mul(r)
+                    .and_then(in_range)
+                    .ok_or(op.overflow_message()),
+                OpKind::Div | OpKind::Rem if r == 0 => Err(op.zero_divisor_message()),
+                OpKind::Div => in_range(l / r).ok_or(op.overflow_message()),
+                OpKind::Rem if l == min && r == -1 => Err(op.overflow_message()),
+                OpKind::Rem => Ok(l % r),
+                OpKind::Shl if (0..bits).contains(&r) => Ok(ty.wrap((l << r) as i64) as i128),
+                OpKind::Shl => Err(op.overflow_message()),
+                OpKind::Shr if (0..bits).contains(&r) => Ok(l >> r),
+                OpKind::Shr => Err(op.overflow_message()),
+                OpKind::BitAnd => Ok(l & r),
+                OpKind::BitOr => Ok(l | r),
+                OpKind::BitXor => Ok(l ^ r),
+                OpKind::And => Ok((l != 0 && r != 0) as i128),
+                OpKind::Or => Ok((l != 0 || r != 0) as i128),
+                OpKind::Eq => Ok((l == r) as i128),
+                OpKind::Ne => Ok((l != r) as i128),
+                OpKind::Lt => Ok((l < r) as i128),
+                OpKind::Gt => Ok((l > r) as i128),
+                OpKind::Le => Ok((l <= r) as i128),
+                OpKind::Ge => Ok((l >= r) as i128),
+            };
+            match v {
+                Ok(v) => Ok(v as i64),
+                Err(msg) => fail(msg),
             }
-            _ => break,
         }
+        Node::Call { name, .. } => Err(error_at(
+            span.start,
+            &format!("cannot call non-const function `{}` in {}", name, context),
+        )),
+        Node::Global { .. } => Err(error_at(
+            span.start,
+            &format!("{} cannot refer to statics", context),
+        )),
+        _ => Err(error_at(
+            span.start,
+            &format!("expected a constant expression in {}", context),
+        )),
     }
-    Ok(lhs)
 }
 
-// relational ::= add (('<' | '>' | '<=' | '>=') add)*
-fn relational(toks: &mut Peekable<TokenIter>, vars: &mut Variable) -> Result<Node, ParseError> {
-    let mut lhs = add(toks, vars)?;
-    while let Some(tok) = toks.peek() {
-        match tok.kind {
-            TokenKind::Lt => {
-                toks.next();
-                let rhs = add(toks, vars)?;
-                lhs = Node::BinaryOp {
-                    op: OpKind::Lt,
-                    lhs: Box::new(lhs),
-                    rhs: Box::new(rhs),
-                };
+// Compute the value of every slot of a `static` from its initializer, which
+// stores a value of type `ty` with its first slot at offset `base`
+fn static_data(
+    init: &Node,
+    ty: &Type,
+    types: &TypeDefs,
+    base: u64,
+    data: &mut Vec<ConstValue>,
+) -> Result<(), ParseError> {
+    // a mistyped initializer may not fit the slots; the type checker reports it
+    let set = |data: &mut Vec<ConstValue>, offset: u64, value: ConstValue| {
+        let slot = ((offset - base) / 8) as usize;
+        if slot >= data.len() {
+            data.resize(slot + 1, ConstValue::Int(0));
+        }
+        data[slot] = value;
+    };
+    match init {
+        Node::Assign { lhs, rhs, .. } => {
+            let Node::Var { offset, .. } = lhs.as_ref() else {
+                unreachable!("an initializer stores into a slot");
+            };
+            set(data, *offset, const_value(rhs, ty, "statics")?);
+        }
+        Node::ArrayAssign {
+            offset, elements, ..
+        } => {
+            for (i, elem) in elements.iter().enumerate() {
+                let value = const_value(elem, elem_ty(ty), "statics")?;
+                set(data, offset + i as u64 * 8, value);
             }
-            TokenKind::Gt => {
-                toks.next();
-                let rhs = add(toks, vars)?;
-                lhs = Node::BinaryOp {
-                    op: OpKind::Gt,
-                    lhs: Box::new(lhs),
-                    rhs: Box::new(rhs),
-                };
+        }
+        Node::ArrayRepeat {
+            offset, value, len, ..
+        } => {
+            let value = const_value(value, elem_ty(ty), "statics")?;
+            for i in 0..*len {
+                set(data, offset + i * 8, value.clone());
             }
-            TokenKind::Le => {
-                toks.next();
-                let rhs = add(toks, vars)?;
-                lhs = Node::BinaryOp {
-                    op: OpKind::Le,
-                    lhs: Box::new(lhs),
-                    rhs: Box::new(rhs),
-                };
+        }
+        Node::StructLit { name, fields, .. } => {
+            for (field_name, field) in fields {
+                let field_ty = field_layout(types, name, field_name).map_or(&Type::I32, |f| f.1);
+                static_data(field, field_ty, types, base, data)?;
             }
-            TokenKind::Ge => {
-                toks.next();
-                let rhs = add(toks, vars)?;
-                lhs = Node::BinaryOp {
-                    op: OpKind::Ge,
-                    lhs: Box::new(lhs),
-                    rhs: Box::new(rhs),
-                };
+        }
+        Node::VariantLit {
+            name,
+            variant,
+            tag,
+            offset,
+            fields,
+            ..
+        } => {
+            set(data, *offset, ConstValue::Int(*tag as i64));
+            let field_tys = types.variant(name, variant).map_or(&[][..], |v| v.1);
+            for (i, field) in fields.iter().enumerate() {
+                let field_ty = field_tys.get(i).unwrap_or(&Type::I32);
+                static_data(field, field_ty, types, base, data)?;
             }
-            _ => break,
         }
+        _ => unreachable!("not an initializer: {:?}", init),
     }
-    Ok(lhs)
+    Ok(())
 }
 
-// add ::= mul (('+' | '-') mul)*
-fn add(toks: &mut Peekable<TokenIter>, vars: &mut Variable) -> Result<Node, ParseError> {
-    let mut lhs = mul(toks, vars)?;
-    while let Some(tok) = toks.peek() {
-        match tok.kind {
-            TokenKind::Plus => {
-                toks.next();
-                let rhs = mul(toks, vars)?;
-                lhs = Node::BinaryOp {
-                    op: OpKind::Add,
-                    lhs: Box::new(lhs),
-                    rhs: Box::new(rhs),
-                };
-            }
-            TokenKind::Minus => {
-                toks.next();
-                let rhs = mul(toks, vars)?;
-                lhs = Node::BinaryOp {
-                    op: OpKind::Sub,
-                    lhs: Box::new(lhs),
-                    rhs: Box::new(rhs),
-                };
-            }
-            _ => break,
-        }
+// element type of an array type; a mistyped initializer is left for the type
+// checker
+fn elem_ty(ty: &Type) -> &Type {
+    match ty {
+        Type::Array(elem, _) => elem,
+        _ => &Type::I32,
     }
-    Ok(lhs)
 }
 
-// mul ::= unary (('*' | '/') unary)*
-fn mul(toks: &mut Peekable<TokenIter>, vars: &mut Variable) -> Result<Node, ParseError> {
-    let mut lhs = unary(toks, vars)?;
-    while let Some(tok) = toks.peek() {
-        match tok.kind {
-            TokenKind::Star => {
-                toks.next();
-                let rhs = unary(toks, vars)?;
-                lhs = Node::BinaryOp {
-                    op: OpKind::Mul,
-                    lhs: Box::new(lhs),
-                    rhs: Box::new(rhs),
-                };
-            }
-            TokenKind::Slash => {
-                toks.next();
-                let rhs = unary(toks, vars)?;
-                lhs = Node::BinaryOp {
-                    op: OpKind::Div,
-                    lhs: Box::new(lhs),
-                    rhs: Box::new(rhs),
-                };
-            }
-            _ => break,
+// function ::= 'fn' ident '(' function_args? ')' ('->' type)? '{' stmt* '}'
+fn function(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    // consume 'fn'
+    let fn_tok = expect_next(toks, TokenKind::Fn)?;
+    // parse function name
+    let tok = toks.next().ok_or_else(|| ParseError {
+        msg: "expected identifier".into(),
+        pos: 0,
+    })?;
+    let name = if let TokenKind::Ident { name } = tok.kind.clone() {
+        name
+    } else {
+        return Err(error_tok(&tok, "expected identifier"));
+    };
+    // expect '('
+    expect_next(toks, TokenKind::LParen)?;
+    // every function starts with a fresh frame
+    vars.enter_function();
+    // the `{` opening the body, as no type contains one
+    let body = toks
+        .clone()
+        .take_while(|tok| !starts_item(&tok.kind))
+        .find(|tok| tok.kind == TokenKind::LBrace);
+    let (args_vec, arg_types, ret, lbrace) = match signature(toks, vars) {
+        Ok((args, types, ret)) => (args, types, ret, expect_next(toks, TokenKind::LBrace)?),
+        // the rest of a broken signature is skipped, and the body parsed
+        // anyway so that the errors in its statements are reported too
+        Err(err) => {
+            let Some(lbrace) = body else {
+                return Err(err);
+            };
+            report(err);
+            while toks.next_if(|tok| tok.pos <= lbrace.pos).is_some() {}
+            (Vec::new(), Vec::new(), None, lbrace)
         }
+    };
+    // parse body statements
+    let stmts = block_stmts(toks, vars)?;
+    // expect '}'
+    let rbrace = expect_next(toks, TokenKind::RBrace)?;
+    // fold into a single Node, default to 0 if empty
+    let body = fold_seq(stmts, lbrace.span().to(rbrace.span()));
+    Ok(Node::Function {
+        name,
+        args: args_vec,
+        arg_types,
+        ret,
+        body: Box::new(body),
+        locals: HashMap::new(),
+        span: fn_tok.span().to(rbrace.span()),
+    })
+}
+
+// The parameters of a function, their types and its return type
+type Signature = (Vec<Node>, Vec<Type>, Option<Type>);
+
+// The parameters and return type of a function, after its '('
+fn signature(
+    toks: &mut Peekable<TokenIter>,
+    vars: &mut SymbolTable,
+) -> Result<Signature, ParseError> {
+    // parse optional parameters only if the next token is an identifier
+    let mut args = Vec::new();
+    let mut types = Vec::new();
+    if let Some(peek) = toks.peek()
+        && matches!(peek.kind, TokenKind::Ident { .. } | TokenKind::Mut)
+    {
+        (args, types) = function_args(toks, vars)?;
     }
-    Ok(lhs)
+    // expect ')'
+    expect_next(toks, TokenKind::RParen)?;
+    // optional return type '-> type'
+    let mut ret = None;
+    if let Some(peek) = toks.peek()
+        && peek.kind == TokenKind::Arrow
+    {
+        toks.next();
+        ret = Some(ty(toks, vars)?);
+    }
+    Ok((args, types, ret))
 }
 
-// unary ::= ('+' | '-')? primary | ('*' | '&') unary
-fn unary(toks: &mut Peekable<TokenIter>, vars: &mut Variable) -> Result<Node, ParseError> {
+// stmt ::= expr ';' |
+//          expr (before '}', as the block's tail value) |
+//          (block | unsafe_block | if_stmt | match_expr | loop_stmt) ';'? |
+//          'let' 'mut'? ident (':' type)? '=' (expr | array | struct_lit | variant_lit) ';' |
+//          'break' label? expr? ';' |
+//          'continue' label? ';'
+fn stmt(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    // detect EOF as missing statement
+    if let Some(tok) = toks.peek()
+        && tok.kind == TokenKind::Eof
+    {
+        return Err(error_tok(tok, "expected statement"));
+    }
     if let Some(tok) = toks.peek() {
+        // the statement starts at its first token
+        let start = tok.span();
         match tok.kind {
-            TokenKind::Plus => {
-                toks.next();
-                return primary(toks, vars);
+            TokenKind::LBrace
+            | TokenKind::Unsafe
+            | TokenKind::If
+            | TokenKind::Match
+            | TokenKind::While
+            | TokenKind::For
+            | TokenKind::Loop
+            | TokenKind::Label { .. } => {
+                let node = match tok.kind {
+                    TokenKind::LBrace => block(toks, vars)?,
+                    TokenKind::Unsafe => unsafe_block(toks, vars)?,
+                    TokenKind::If => if_stmt(toks, vars)?,
+                    TokenKind::Match => match_expr(toks, vars)?,
+                    _ => loop_stmt(toks, vars)?,
+                };
+                // block-like expressions need no `;`, but may have one
+                return Ok(semi(toks, node));
             }
-            TokenKind::Minus => {
+            TokenKind::Break => {
+                // parse break statement: 'break' label? expr? ';'
                 toks.next();
-                let node = primary(toks, vars)?;
-                return Ok(Node::BinaryOp {
-                    op: OpKind::Sub,
-                    lhs: Box::new(Node::Num { value: 0 }),
-                    rhs: Box::new(node),
+                let label = loop_label(toks);
+                let value = match toks.peek() {
+                    Some(tok) if matches!(tok.kind, TokenKind::Semicolon | TokenKind::RBrace) => {
+                        None
+                    }
+                    _ => Some(Box::new(expr(toks, vars)?)),
+                };
+                let semi = expect_next(toks, TokenKind::Semicolon)?;
+                return Ok(Node::Break {
+                    label,
+                    value,
+                    span: start.to(semi.span()),
                 });
             }
-            TokenKind::Star => {
+            TokenKind::Continue => {
+                // parse continue statement: 'continue' label? ';'
                 toks.next();
-                let expr = unary(toks, vars)?;
-                return Ok(Node::Deref {
-                    expr: Box::new(expr),
+                let label = loop_label(toks);
+                let semi = expect_next(toks, TokenKind::Semicolon)?;
+                return Ok(Node::Continue {
+                    label,
+                    span: start.to(semi.span()),
                 });
             }
-            TokenKind::Amp => {
+            TokenKind::Let => {
+                // parse let statement: 'let' 'mut'? ident (':' type)? '=' expr ';'
                 toks.next();
-                let expr = unary(toks, vars)?;
-                return Ok(Node::Addr {
-                    expr: Box::new(expr),
-                });
+                let mutable = eat_mut(toks);
+                // expect identifier
+                let tok_ident = toks.next().ok_or_else(|| ParseError {
+                    msg: "expected identifier after 'let'".into(),
+                    pos: 0,
+                })?;
+                let name = if let TokenKind::Ident { name } = tok_ident.kind.clone() {
+                    name
+                } else {
+                    return Err(error_tok(&tok_ident, "expected identifier after 'let'"));
+                };
+                let result = let_rest(toks, vars, start, &name, tok_ident.span(), mutable);
+                // a broken `let` still binds its name, so that its uses do
+                // not report it missing as well
+                if result.is_err() {
+                    vars.declare(&name, 1, mutable);
+                }
+                return result;
             }
             _ => {}
         }
     }
-    primary(toks, vars)
+    // expression statement
+    let node = expr(toks, vars)?;
+    // without `;` the last expression of a block is its value
+    if let Some(tok) = toks.peek()
+        && tok.kind == TokenKind::RBrace
+    {
+        return Ok(node);
+    }
+    let semi = expect_next(toks, TokenKind::Semicolon)?;
+    Ok(Node::Semi {
+        span: node.span().to(semi.span()),
+        expr: Box::new(node),
+    })
 }
 
-// primary ::= number |
-//             ident '[' args? ']' |
-//             ident ('(' args? ')')? |
-//             '(' expr ')' |
-//             string |
-fn primary(toks: &mut Peekable<TokenIter>, vars: &mut Variable) -> Result<Node, ParseError> {
-    let tok = toks.next().unwrap();
-    match tok.kind {
-        TokenKind::Number { num } => Ok(Node::Num { value: num }),
-        TokenKind::String { value } => Ok(Node::StringLiteral { value }),
-        TokenKind::LParen => {
-            // Parse sub-expression
-            let node = expr(toks, vars)?;
-            expect_next(toks, TokenKind::RParen)?;
-            Ok(node)
+// array ::= '[' (expr (',' expr)* | expr ';' number)? ']'
+// Returns the elements, the length of a `[value; N]` repeat and the span of
+// the literal.
+fn array_lit(
+    toks: &mut Peekable<TokenIter>,
+    vars: &mut SymbolTable,
+) -> Result<(Vec<Node>, Option<u64>, Span), ParseError> {
+    let lbracket = expect_next(toks, TokenKind::LBracket)?;
+    let mut elements = Vec::new();
+    let mut repeat = None;
+    // parse elements if not empty
+    if let Some(peek) = toks.peek()
+        && peek.kind != TokenKind::RBracket
+    {
+        elements.push(expr(toks, vars)?);
+        if let Some(tok) = toks.peek()
+            && tok.kind == TokenKind::Semicolon
+        {
+            toks.next();
+            repeat = Some(array_len(toks, vars)?);
         }
-        TokenKind::Ident { name } => {
-            let name = name.clone();
-            // array indexing: name[expr]
-            if let Some(tok2) = toks.peek() {
-                if tok2.kind == TokenKind::LBracket {
-                    toks.next(); // consume '['
-                    // parse index expression
-                    let idx = expr(toks, vars)?;
-                    // expect ']'
-                    expect_next(toks, TokenKind::RBracket)?;
-                    // determine variable offset (allocate if not exist)
-                    let offset = if let Some(off) = vars.find(&name) {
-                        off
-                    } else {
-                        let last = vars.next.as_ref().map(|v| v.offset).unwrap_or(vars.offset);
-                        let new_off = last + 8;
-                        vars.push(name.clone(), new_off);
-                        new_off
-                    };
-                    // compute address: &name - idx * 8 (array indexing)
-                    return Ok(Node::BinaryOp {
-                        op: OpKind::Sub,
-                        lhs: Box::new(Node::Addr {
-                            expr: Box::new(Node::Var { offset }),
-                        }),
-                        rhs: Box::new(Node::BinaryOp {
-                            op: OpKind::Mul,
-                            lhs: Box::new(idx),
-                            rhs: Box::new(Node::Num { value: 8 }),
-                        }),
-                    });
-                }
+        while let Some(tok) = toks.peek() {
+            if repeat.is_none() && tok.kind == TokenKind::Comma {
+                toks.next();
+                elements.push(expr(toks, vars)?);
+            } else {
+                break;
             }
-            // function call: name(args?)
-            if let Some(tok2) = toks.peek() {
-                if tok2.kind == TokenKind::LParen {
-                    toks.next(); // consume '('
-                    // parse zero or more args
-                    let args_vec = if let Some(peek) = toks.peek() {
-                        if peek.kind == TokenKind::RParen {
-                            Vec::new()
-                        } else {
-                            args(toks, vars)?
-                        }
-                    } else {
-                        Vec::new()
-                    };
-                    // expect closing ')'
-                    expect_next(toks, TokenKind::RParen)?;
-                    // Special handling for write as a system call
-                    if name == "write" {
-                        return Ok(Node::Syscall {
-                            name: "write".to_string(),
-                            args: args_vec,
-                        });
-                    }
-                    return Ok(Node::Call {
-                        name,
-                        args: args_vec,
-                    });
+        }
+    }
+    let rbracket = expect_next(toks, TokenKind::RBracket)?;
+    Ok((elements, repeat, lbracket.span().to(rbracket.span())))
+}
+
+// Store an array literal into the slots starting at `offset`
+fn array_init(offset: u64, mut elements: Vec<Node>, repeat: Option<u64>, span: Span) -> Node {
+    match repeat {
+        Some(len) => Node::ArrayRepeat {
+            offset,
+            value: Box::new(elements.pop().unwrap()),
+            len,
+            span,
+        },
+        None => Node::ArrayAssign {
+            offset,
+            elements,
+            span,
+        },
+    }
+}
+
+// struct_lit ::= ident '{' (ident ':' initializer (',' ident ':' initializer)* ','?)? '}'
+// The fields are stored into the slots starting at `offset`. Unknown,
+// repeated and missing fields are reported without stopping the parse.
+fn struct_lit(
+    toks: &mut Peekable<TokenIter>,
+    vars: &mut SymbolTable,
+    offset: u64,
+) -> Result<Node, ParseError> {
+    let name_tok = toks.next().unwrap();
+    let TokenKind::Ident { name } = name_tok.kind.clone() else {
+        return Err(error_tok(&name_tok, "expected struct name"));
+    };
+    expect_next(toks, TokenKind::LBrace)?;
+    let mut fields: Vec<(String, Node)> = Vec::new();
+    while let Some(peek) = toks.peek()
+        && peek.kind != TokenKind::RBrace
+    {
+        let field_tok = toks.next().unwrap();
+        let TokenKind::Ident { name: field } = field_tok.kind.clone() else {
+            return Err(error_tok(&field_tok, "expected field name"));
+        };
+        expect_next(toks, TokenKind::Colon)?;
+        let field_offset = match field_layout(vars.types(), &name, &field) {
+            Some((field_offset, _)) => field_offset,
+            None => {
+                report(error_tok(
+                    &field_tok,
+                    &format!("struct `{}` has no field named `{}`", name, field),
+                ));
+                0
+            }
+        };
+        if fields.iter().any(|(f, _)| *f == field) {
+            report(error_tok(
+                &field_tok,
+                &format!("field `{}` specified more than once", field),
+            ));
+        }
+        let value = initializer(toks, vars, offset + field_offset, field_tok.span())?;
+        fields.push((field, value));
+        match toks.peek() {
+            Some(tok) if tok.kind == TokenKind::Comma => {
+                toks.next();
+            }
+            _ => break,
+        }
+    }
+    let rbrace = expect_next(toks, TokenKind::RBrace)?;
+    for (field, _) in &vars.types().structs[&name] {
+        if !fields.iter().any(|(f, _)| f == field) {
+            report(error_tok(
+                &name_tok,
+                &format!("missing field `{}` in initializer of `{}`", field, name),
+            ));
+        }
+    }
+    let slots = Type::Struct(name.clone()).slots(vars.types()).unwrap_or(1);
+    Ok(Node::StructLit {
+        name,
+        offset,
+        slots,
+        fields,
+        span: name_tok.span().to(rbrace.span()),
+    })
+}
+
+// initializer ::= array | struct_lit | variant_lit | expr
+// The value of a struct field, stored into the slots starting at `offset`;
+// `target` is where the field is named.
+fn initializer(
+    toks: &mut Peekable<TokenIter>,
+    vars: &mut SymbolTable,
+    offset: u64,
+    target: Span,
+) -> Result<Node, ParseError> {
+    match aggregate_ty(toks.peek(), vars) {
+        Some(Type::Struct(_)) => return struct_lit(toks, vars, offset),
+        Some(_) => return variant_lit(toks, vars, offset),
+        None => {}
+    }
+    match toks.peek() {
+        Some(tok) if tok.kind == TokenKind::LBracket => {
+            let (elements, repeat, span) = array_lit(toks, vars)?;
+            Ok(array_init(offset, elements, repeat, span))
+        }
+        _ => {
+            let rhs = expr(toks, vars)?;
+            Ok(Node::Assign {
+                lhs: Box::new(Node::Var {
+                    offset,
+                    span: target,
+                }),
+                span: target.to(rhs.span()),
+                rhs: Box::new(rhs),
+            })
+        }
+    }
+}
+
+// The struct, or enum with fields, whose literal starts at `tok`. Their
+// values fill several slots; those of an enum are only written as
+// initializers.
+fn aggregate_ty(tok: Option<&Token>, vars: &SymbolTable) -> Option<Type> {
+    let TokenKind::Ident { name } = &tok?.kind else {
+        return None;
+    };
+    if vars.types().structs.contains_key(name) {
+        return Some(Type::Struct(name.clone()));
+    }
+    let variants = vars.types().enums.get(name)?;
+    let has_fields = variants.iter().any(|(_, fields)| !fields.is_empty());
+    has_fields.then(|| Type::Enum(name.clone()))
+}
+
+// variant_path ::= ident '::' ident
+// Resolves the enum named by `first` and the variant after it. Returns the
+// enum and variant names, the variant's tag and number of fields, and the
+// span of the path.
+fn variant_path(
+    first: &Token,
+    toks: &mut Peekable<TokenIter>,
+    vars: &SymbolTable,
+) -> Result<(String, String, u64, usize, Span), ParseError> {
+    let TokenKind::Ident { name } = first.kind.clone() else {
+        return Err(error_tok(first, "expected enum name"));
+    };
+    expect_next(toks, TokenKind::ColonColon)?;
+    let variant_tok = toks.next().ok_or_else(|| ParseError {
+        msg: "expected variant name".into(),
+        pos: 0,
+    })?;
+    let TokenKind::Ident { name: variant } = variant_tok.kind.clone() else {
+        return Err(error_tok(&variant_tok, "expected variant name"));
+    };
+    if !vars.types().enums.contains_key(&name) {
+        return Err(error_tok(
+            first,
+            &format!("failed to resolve: use of undeclared type `{}`", name),
+        ));
+    }
+    let Some((tag, fields)) = vars.types().variant(&name, &variant) else {
+        return Err(error_tok(
+            &variant_tok,
+            &format!("no variant named `{}` found for enum `{}`", variant, name),
+        ));
+    };
+    let arity = fields.len();
+    Ok((
+        name,
+        variant,
+        tag,
+        arity,
+        first.span().to(variant_tok.span()),
+    ))
+}
+
+// variant_lit ::= variant_path ('(' args? ')')?
+// A variant of an enum with fields: the tag is stored into the slot at
+// `offset` and each field into a slot of its own after it.
+fn variant_lit(
+    toks: &mut Peekable<TokenIter>,
+    vars: &mut SymbolTable,
+    offset: u64,
+) -> Result<Node, ParseError> {
+    let first = toks.next().unwrap();
+    let (name, variant, tag, arity, mut span) = variant_path(&first, toks, vars)?;
+    let mut values = Vec::new();
+    if let Some(tok) = toks.peek()
+        && tok.kind == TokenKind::LParen
+    {
+        toks.next();
+        values = args(toks, vars)?;
+        span = span.to(expect_next(toks, TokenKind::RParen)?.span());
+    }
+    if values.len() != arity {
+        report(error_at(
+            span.start,
+            &format!(
+                "variant `{}::{}` takes {} field(s) but {} were supplied",
+                name,
+                variant,
+                arity,
+                values.len()
+            ),
+        ));
+    }
+    let fields = values
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| {
+            let value_span = value.span();
+            Node::Assign {
+                lhs: Box::new(Node::Var {
+                    offset: offset + 8 * (i as u64 + 1),
+                    span: value_span,
+                }),
+                rhs: Box::new(value),
+                span: value_span,
+            }
+        })
+        .collect();
+    Ok(Node::VariantLit {
+        name,
+        variant,
+        tag,
+        offset,
+        fields,
+        span,
+    })
+}
+
+// The rest of a `let` statement after 'let' 'mut'? ident, binding `name`
+// found at `name_span`: (':' type)? '=' expr ';'
+fn let_rest(
+    toks: &mut Peekable<TokenIter>,
+    vars: &mut SymbolTable,
+    start: Span,
+    name: &str,
+    name_span: Span,
+    mutable: bool,
+) -> Result<Node, ParseError> {
+    // optional type ascription
+    let mut declared = None;
+    if let Some(peek) = toks.peek()
+        && peek.kind == TokenKind::Colon
+    {
+        toks.next();
+        declared = Some(ty(toks, vars)?);
+    }
+    // expect '='
+    expect_next(toks, TokenKind::Assign)?;
+    // array literal assignment: let name = [expr, ...]; or [expr; N];
+    if let Some(peek) = toks.peek()
+        && peek.kind == TokenKind::LBracket
+    {
+        let (elements, repeat, _) = array_lit(toks, vars)?;
+        let semi = expect_next(toks, TokenKind::Semicolon)?;
+        let span = start.to(semi.span());
+        // the array occupies one slot per element, shadowing any earlier binding
+        let len = repeat.unwrap_or(elements.len() as u64);
+        let offset = vars.reserve(len);
+        // the element type is left to the type checker
+        let array_ty = Type::Array(Box::new(Type::I64), len);
+        vars.bind(name, offset, mutable, declared.clone().or(Some(array_ty)));
+        let init = array_init(offset, elements, repeat, span);
+        return Ok(let_stmt(declared, init, span));
+    }
+    // struct literal: let name = Name { field: value, ... };
+    // or a variant of an enum with fields: let name = Enum::Variant(value, ...);
+    if let Some(agg_ty) = aggregate_ty(toks.peek(), vars) {
+        // the slots are taken before the fields are parsed, but the
+        // name is only bound afterwards, as for any initializer
+        let slots = agg_ty.slots(vars.types()).unwrap_or(1);
+        let offset = vars.reserve(slots);
+        let init = match agg_ty {
+            Type::Struct(_) => struct_lit(toks, vars, offset)?,
+            _ => variant_lit(toks, vars, offset)?,
+        };
+        let semi = expect_next(toks, TokenKind::Semicolon)?;
+        vars.bind(name, offset, mutable, Some(agg_ty));
+        return Ok(let_stmt(declared, init, start.to(semi.span())));
+    }
+    // parse expression
+    let rhs = expr(toks, vars)?;
+    // expect ';'
+    let semi = expect_next(toks, TokenKind::Semicolon)?;
+    // bind the name after the initializer so `let x = x + 1;` sees the outer `x`
+    let new_off = vars.reserve(1);
+    vars.bind(name, new_off, mutable, declared.clone());
+    let span = start.to(semi.span());
+    let init = Node::Assign {
+        lhs: Box::new(Node::Var {
+            offset: new_off,
+            span: name_span,
+        }),
+        rhs: Box::new(rhs),
+        span,
+    };
+    Ok(let_stmt(declared, init, span))
+}
+
+// A `let` statement; it yields `()` like any statement ending in `;`
+fn let_stmt(ty: Option<Type>, init: Node, span: Span) -> Node {
+    Node::Semi {
+        expr: Box::new(Node::Let {
+            ty,
+            init: Box::new(init),
+            span,
+        }),
+        span,
+    }
+}
+
+// Wrap a block-like statement in `Semi` if a `;` follows it
+fn semi(toks: &mut Peekable<TokenIter>, node: Node) -> Node {
+    match toks.peek() {
+        Some(tok) if tok.kind == TokenKind::Semicolon => {
+            let span = node.span().to(toks.next().unwrap().span());
+            Node::Semi {
+                expr: Box::new(node),
+                span,
+            }
+        }
+        _ => node,
+    }
+}
+
+// block ::= '{' stmt* '}'
+fn block(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    let lbrace = expect_next(toks, TokenKind::LBrace)?;
+    // variables declared in the block go out of scope at its end
+    vars.enter_scope();
+    let stmts = block_stmts(toks, vars)?;
+    let rbrace = expect_next(toks, TokenKind::RBrace)?;
+    vars.leave_scope();
+    Ok(fold_seq(stmts, lbrace.span().to(rbrace.span())))
+}
+
+// unsafe_block ::= 'unsafe' block
+// Nothing this compiler accepts needs `unsafe`, so the block is parsed like
+// any other; it lets code touching a `static mut` compile as in Rust.
+fn unsafe_block(
+    toks: &mut Peekable<TokenIter>,
+    vars: &mut SymbolTable,
+) -> Result<Node, ParseError> {
+    expect_next(toks, TokenKind::Unsafe)?;
+    block(toks, vars)
+}
+
+// cond_body ::= expr block | '(' expr ')' stmt
+// The condition of `if` and `while` is a plain expression followed by a block.
+// The old C-style form with a parenthesised condition may still have any
+// statement as its body; the returned flag tells whether it was used.
+fn cond_body(
+    toks: &mut Peekable<TokenIter>,
+    vars: &mut SymbolTable,
+) -> Result<(Node, Node, bool), ParseError> {
+    // error if no condition expression
+    if let Some(peek) = toks.peek()
+        && (peek.kind == TokenKind::LBrace || peek.kind == TokenKind::Eof)
+    {
+        return Err(error_tok(peek, "expected expression"));
+    }
+    let parenthesised = matches!(toks.peek(), Some(tok) if tok.kind == TokenKind::LParen);
+    // parse condition
+    let cond = expr(toks, vars)?;
+    // parse body
+    let legacy =
+        parenthesised && !matches!(toks.peek(), Some(tok) if tok.kind == TokenKind::LBrace);
+    let body = if legacy {
+        stmt(toks, vars)?
+    } else {
+        block(toks, vars)?
+    };
+    Ok((cond, body, legacy))
+}
+
+// if_stmt ::= 'if' cond_body ('else' (if_stmt | block))?
+// After the old form `if (cond) stmt`, `else` may be followed by any statement.
+fn if_stmt(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    let start = expect_next(toks, TokenKind::If)?.span();
+    let (cond, then_stmt, legacy) = cond_body(toks, vars)?;
+    // parse optional else branch
+    let mut else_stmt = None;
+    if let Some(tok) = toks.peek()
+        && tok.kind == TokenKind::Else
+    {
+        toks.next();
+        let node = match toks.peek() {
+            // `else if` chains another conditional
+            Some(tok) if tok.kind == TokenKind::If => if_stmt(toks, vars)?,
+            Some(_) if legacy => stmt(toks, vars)?,
+            _ => block(toks, vars)?,
+        };
+        else_stmt = Some(Box::new(node));
+    }
+    let end = else_stmt.as_deref().unwrap_or(&then_stmt).span();
+    Ok(Node::If {
+        cond: Box::new(cond),
+        then_stmt: Box::new(then_stmt),
+        else_stmt,
+        span: start.to(end),
+    })
+}
+
+// match_expr ::= 'match' expr '{' (pattern '=>' expr ','?)* '}'
+// The `,` after an arm may be left out after a block-like value and after
+// the last arm. The names a pattern binds are only visible in its arm.
+fn match_expr(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    let start = expect_next(toks, TokenKind::Match)?.span();
+    let scrutinee = expr(toks, vars)?;
+    expect_next(toks, TokenKind::LBrace)?;
+    let mut arms = Vec::new();
+    while let Some(peek) = toks.peek()
+        && peek.kind != TokenKind::RBrace
+    {
+        // an item means this match was never closed; the arm errors are
+        // already reported, so leave the recovery to the enclosing block
+        if starts_item(&peek.kind) {
+            return Err(error_tok(peek, "expected RBrace"));
+        }
+        vars.enter_scope();
+        let arm = match_arm(toks, vars);
+        vars.leave_scope();
+        match arm {
+            Ok(arm) => arms.push(arm),
+            Err(err) => {
+                report(err);
+                skip_arm(toks);
+            }
+        }
+    }
+    let rbrace = expect_next(toks, TokenKind::RBrace)?;
+    Ok(Node::Match {
+        expr: Box::new(scrutinee),
+        arms,
+        by_ref: false,
+        span: start.to(rbrace.span()),
+    })
+}
+
+// Recovery inside a match: skip the rest of a broken arm, up to and
+// including its `,`, or up to the `}` closing the match
+fn skip_arm(toks: &mut Peekable<TokenIter>) {
+    let mut depth = 0;
+    while let Some(tok) = toks.peek() {
+        match tok.kind {
+            _ if starts_item(&tok.kind) => return,
+            TokenKind::RBrace if depth == 0 => return,
+            TokenKind::Comma if depth == 0 => {
+                toks.next();
+                return;
+            }
+            TokenKind::LBrace => depth += 1,
+            TokenKind::RBrace => depth -= 1,
+            _ => {}
+        }
+        toks.next();
+    }
+}
+
+// One `pattern '=>' expr ','?` of a match, with the separating comma
+fn match_arm(
+    toks: &mut Peekable<TokenIter>,
+    vars: &mut SymbolTable,
+) -> Result<MatchArm, ParseError> {
+    let (pats, span) = pattern(toks, vars)?;
+    expect_next(toks, TokenKind::FatArrow)?;
+    let block_like = matches!(
+        toks.peek(),
+        Some(tok) if matches!(
+            tok.kind,
+            TokenKind::LBrace
+                | TokenKind::Unsafe
+                | TokenKind::If
+                | TokenKind::Match
+                | TokenKind::Loop
+                | TokenKind::Label { .. }
+        )
+    );
+    let body = expr(toks, vars)?;
+    match toks.peek() {
+        Some(tok) if tok.kind == TokenKind::Comma => {
+            toks.next();
+        }
+        Some(tok) if tok.kind == TokenKind::RBrace || block_like => {}
+        Some(tok) => return Err(error_tok(tok, "expected `,` after a match arm")),
+        None => {}
+    }
+    Ok(MatchArm { pats, body, span })
+}
+
+// pattern ::= alternative ('|' alternative)*
+// Every alternative must bind the same names, which share their slots.
+fn pattern(
+    toks: &mut Peekable<TokenIter>,
+    vars: &mut SymbolTable,
+) -> Result<(Vec<Pattern>, Span), ParseError> {
+    let start = toks.peek().map_or(Span::default(), Token::span);
+    // names bound by the first alternative, with their slots
+    let mut bindings: Vec<(String, u64)> = Vec::new();
+    let mut pats = Vec::new();
+    let mut span = start;
+    loop {
+        let mut names = Vec::new();
+        let (pat, alt_span) = alternative(toks, vars, &mut bindings, &mut names)?;
+        if !pats.is_empty() {
+            for (name, _) in &bindings {
+                if !names.contains(name) {
+                    report(error_at(
+                        alt_span.start,
+                        &format!("variable `{}` is not bound in all patterns", name),
+                    ));
                 }
             }
-            // variable
-            let offset = if let Some(off) = vars.find(&name) {
-                off
-            } else {
-                let last = vars.next.as_ref().map(|v| v.offset).unwrap_or(vars.offset);
-                let new_off = last + 8;
-                vars.push(name.clone(), new_off);
-                new_off
+        }
+        pats.push(pat);
+        span = span.to(alt_span);
+        match toks.peek() {
+            Some(tok) if tok.kind == TokenKind::Pipe => {
+                toks.next();
+            }
+            _ => break,
+        }
+    }
+    Ok((pats, span))
+}
+
+// alternative ::= binding | 'true' | 'false' | int (('..=' | '..') int)? |
+//                 variant_path ('(' binding (',' binding)* ','? ')')?
+// int ::= '-'? number
+// Names bound so far are recorded in `names`; `bindings` holds the slots of
+// the names bound by earlier alternatives of the same pattern.
+fn alternative(
+    toks: &mut Peekable<TokenIter>,
+    vars: &mut SymbolTable,
+    bindings: &mut Vec<(String, u64)>,
+    names: &mut Vec<String>,
+) -> Result<(Pattern, Span), ParseError> {
+    let Some(tok) = toks.peek() else {
+        return Err(error_at(0, "expected pattern"));
+    };
+    let start = tok.span();
+    match &tok.kind {
+        TokenKind::True | TokenKind::False => {
+            let value = tok.kind == TokenKind::True;
+            toks.next();
+            Ok((Pattern::Bool { value }, start))
+        }
+        TokenKind::Number { .. } | TokenKind::Minus => {
+            let (lo, mut span) = pattern_int(toks)?;
+            let inclusive = match toks.peek() {
+                Some(tok) if tok.kind == TokenKind::DotDotEq => true,
+                Some(tok) if tok.kind == TokenKind::DotDot => false,
+                _ => return Ok((Pattern::Range { lo, hi: lo }, span)),
+            };
+            toks.next();
+            let (end, end_span) = pattern_int(toks)?;
+            span = span.to(end_span);
+            let hi = if inclusive { end } else { end - 1 };
+            if lo > hi {
+                let msg = if inclusive {
+                    "lower range bound must be less than or equal to upper"
+                } else {
+                    "lower range bound must be less than upper"
+                };
+                report(error_at(span.start, msg));
+            }
+            Ok((Pattern::Range { lo, hi }, span))
+        }
+        TokenKind::Ident { .. } | TokenKind::Mut => {
+            let first = toks.next().unwrap();
+            if let Some(tok) = toks.peek()
+                && tok.kind == TokenKind::ColonColon
+            {
+                let (name, variant, tag, arity, mut span) = variant_path(&first, toks, vars)?;
+                let mut fields = Vec::new();
+                if let Some(tok) = toks.peek()
+                    && tok.kind == TokenKind::LParen
+                {
+                    toks.next();
+                    while let Some(peek) = toks.peek()
+                        && peek.kind != TokenKind::RParen
+                    {
+                        fields.push(binding(None, toks, vars, bindings, names)?);
+                        match toks.peek() {
+                            Some(tok) if tok.kind == TokenKind::Comma => {
+                                toks.next();
+                            }
+                            _ => break,
+                        }
+                    }
+                    span = span.to(expect_next(toks, TokenKind::RParen)?.span());
+                }
+                if fields.len() != arity {
+                    report(error_at(
+                        span.start,
+                        &format!(
+                            "this pattern has {} field(s), but variant `{}::{}` has {} field(s)",
+                            fields.len(),
+                            name,
+                            variant,
+                            arity
+                        ),
+                    ));
+                }
+                let pat = Pattern::Variant {
+                    name,
+                    variant,
+                    tag,
+                    fields,
+                };
+                return Ok((pat, span));
+            }
+            let pat = match binding(Some(first), toks, vars, bindings, names)? {
+                Some(offset) => Pattern::Bind { offset },
+                None => Pattern::Wild,
             };
-            Ok(Node::Var { offset })
+            Ok((pat, start))
+        }
+        _ => Err(error_tok(tok, "expected pattern")),
+    }
+}
+
+// binding ::= 'mut'? ident | '_'
+// Declares the name, or reuses its slot when an earlier alternative bound it,
+// and returns the slot; `_` binds nothing. `first` is the binding's first
+// token when it has already been consumed.
+fn binding(
+    first: Option<Token>,
+    toks: &mut Peekable<TokenIter>,
+    vars: &mut SymbolTable,
+    bindings: &mut Vec<(String, u64)>,
+    names: &mut Vec<String>,
+) -> Result<Option<u64>, ParseError> {
+    let mut tok = match first {
+        Some(tok) => tok,
+        None => toks.next().ok_or_else(|| ParseError {
+            msg: "expected identifier".into(),
+            pos: 0,
+        })?,
+    };
+    let mutable = tok.kind == TokenKind::Mut;
+    if mutable {
+        tok = toks.next().ok_or_else(|| ParseError {
+            msg: "expected identifier".into(),
+            pos: 0,
+        })?;
+    }
+    let TokenKind::Ident { name } = tok.kind.clone() else {
+        return Err(error_tok(&tok, "expected identifier"));
+    };
+    if name == "_" {
+        return Ok(None);
+    }
+    if names.contains(&name) {
+        report(error_tok(
+            &tok,
+            &format!(
+                "identifier `{}` is bound more than once in the same pattern",
+                name
+            ),
+        ));
+    }
+    names.push(name.clone());
+    if let Some((_, offset)) = bindings.iter().find(|(bound, _)| *bound == name) {
+        return Ok(Some(*offset));
+    }
+    let offset = vars.declare(&name, 1, mutable);
+    bindings.push((name, offset));
+    Ok(Some(offset))
+}
+
+// An integer in a pattern, which may be negative
+fn pattern_int(toks: &mut Peekable<TokenIter>) -> Result<(i64, Span), ParseError> {
+    let tok = toks.next().ok_or_else(|| ParseError {
+        msg: "expected integer".into(),
+        pos: 0,
+    })?;
+    let (sign, tok) = match tok.kind {
+        TokenKind::Minus => {
+            let num_tok = toks.next().ok_or_else(|| ParseError {
+                msg: "expected integer".into(),
+                pos: 0,
+            })?;
+            (
+                -1,
+                Token {
+                    pos: tok.pos,
+                    ..num_tok
+                },
+            )
+        }
+        _ => (1, tok),
+    };
+    let TokenKind::Number { num } = tok.kind else {
+        return Err(error_tok(&tok, "expected integer"));
+    };
+    // `-9223372036854775808` is `i64::MIN`, whose magnitude only fits as `u64`
+    let value = match sign {
+        -1 => (num as i64).wrapping_neg(),
+        _ => num as i64,
+    };
+    Ok((value, tok.span()))
+}
+
+// Consume an optional loop label such as `'outer` after `break` or `continue`
+fn loop_label(toks: &mut Peekable<TokenIter>) -> Option<String> {
+    if let Some(tok) = toks.peek()
+        && let TokenKind::Label { name } = &tok.kind
+    {
+        let name = name.clone();
+        toks.next();
+        return Some(name);
+    }
+    None
+}
+
+// loop_stmt ::= (label ':')? ('while' cond_body | 'for' for_stmt | 'loop' block)
+fn loop_stmt(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    let start = toks.peek().unwrap().span();
+    let label = loop_label(toks);
+    if label.is_some() {
+        expect_next(toks, TokenKind::Colon)?;
+    }
+    let tok = toks.next().unwrap();
+    match tok.kind {
+        TokenKind::While => {
+            let (cond, body, _) = cond_body(toks, vars)?;
+            Ok(Node::While {
+                span: start.to(body.span()),
+                cond: Box::new(cond),
+                body: Box::new(body),
+                label,
+            })
+        }
+        TokenKind::For => for_stmt(toks, vars, label, start),
+        TokenKind::Loop => {
+            let body = block(toks, vars)?;
+            Ok(Node::Loop {
+                span: start.to(body.span()),
+                body: Box::new(body),
+                label,
+            })
         }
         _ => Err(error_tok(
             &tok,
-            &format!("unexpected token: {:?}", tok.kind),
+            "expected `while`, `for` or `loop` after a label",
         )),
     }
 }
 
-// function_args ::= ident ':' type (',' ident ':' type)*
-fn function_args(
+// for_stmt ::= 'mut'? ident 'in' expr ('..' | '..=') expr block |
+//              '(' expr ';' expr ';' expr ')' stmt
+fn for_stmt(
     toks: &mut Peekable<TokenIter>,
-    vars: &mut Variable,
-) -> Result<Vec<Node>, ParseError> {
-    let mut args = Vec::new();
-    // Parse one or more function_arg ::= ident ':' type, separated by commas
-    loop {
-        // identifier
+    vars: &mut SymbolTable,
+    label: Option<String>,
+    start: Span,
+) -> Result<Node, ParseError> {
+    let mutable = eat_mut(toks);
+    if let Some(peek) = toks.peek()
+        && let TokenKind::Ident { name } = &peek.kind
+    {
+        let name = name.clone();
+        toks.next();
+        expect_next(toks, TokenKind::In)?;
+        let range_start = expr(toks, vars)?;
         let tok = toks.next().unwrap();
-        let name = if let TokenKind::Ident { name } = &tok.kind {
-            name.clone()
-        } else {
-            return Err(error_tok(&tok, "expected identifier"));
+        let inclusive = match tok.kind {
+            TokenKind::DotDot => false,
+            TokenKind::DotDotEq => true,
+            _ => return Err(error_tok(&tok, "expected `..` or `..=`")),
+        };
+        let range_end = expr(toks, vars)?;
+        // the loop variable is only visible in the body
+        vars.enter_scope();
+        let offset = vars.declare(&name, 1, mutable);
+        let body = block(toks, vars)?;
+        vars.leave_scope();
+        return Ok(Node::ForRange {
+            offset,
+            start: Box::new(range_start),
+            end: Box::new(range_end),
+            inclusive,
+            span: start.to(body.span()),
+            body: Box::new(body),
+            label,
+        });
+    }
+    // C-style loop: 'for' '(' expr ';' expr ';' expr ')' stmt
+    // expect '('
+    expect_next(toks, TokenKind::LParen)?;
+    // error if missing init expression
+    if let Some(peek) = toks.peek()
+        && (peek.kind == TokenKind::Semicolon || peek.kind == TokenKind::Eof)
+    {
+        return Err(error_tok(peek, "expected expression"));
+    }
+    // parse init
+    let init = expr(toks, vars)?;
+    expect_next(toks, TokenKind::Semicolon)?;
+    // error if missing condition expression
+    if let Some(peek) = toks.peek()
+        && (peek.kind == TokenKind::Semicolon || peek.kind == TokenKind::Eof)
+    {
+        return Err(error_tok(peek, "expected expression"));
+    }
+    // parse condition
+    let cond = expr(toks, vars)?;
+    expect_next(toks, TokenKind::Semicolon)?;
+    // error if missing update expression
+    if let Some(peek) = toks.peek()
+        && (peek.kind == TokenKind::RParen || peek.kind == TokenKind::Eof)
+    {
+        return Err(error_tok(peek, "expected expression"));
+    }
+    // parse update
+    let update = expr(toks, vars)?;
+    // expect ')'
+    expect_next(toks, TokenKind::RParen)?;
+    // parse body
+    let body = stmt(toks, vars)?;
+    Ok(Node::For {
+        span: start.to(body.span()),
+        init: Box::new(init),
+        cond: Box::new(cond),
+        update: Box::new(update),
+        body: Box::new(body),
+        label,
+    })
+}
+
+// expr ::= assign
+fn expr(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    assign(toks, vars)
+}
+
+// assign ::= logic_or (('=' | '+=' | '-=' | '*=' | '/=' | '%=' |
+//                       '&=' | '|=' | '^=' | '<<=' | '>>=') assign)?
+fn assign(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    let lhs = logic_or(toks, vars)?;
+    let Some(tok) = toks.peek() else {
+        return Ok(lhs);
+    };
+    let op = match tok.kind {
+        TokenKind::Assign => None,
+        TokenKind::PlusEq => Some(OpKind::Add),
+        TokenKind::MinusEq => Some(OpKind::Sub),
+        TokenKind::StarEq => Some(OpKind::Mul),
+        TokenKind::SlashEq => Some(OpKind::Div),
+        TokenKind::PercentEq => Some(OpKind::Rem),
+        TokenKind::AmpEq => Some(OpKind::BitAnd),
+        TokenKind::PipeEq => Some(OpKind::BitOr),
+        TokenKind::CaretEq => Some(OpKind::BitXor),
+        TokenKind::ShlEq => Some(OpKind::Shl),
+        TokenKind::ShrEq => Some(OpKind::Shr),
+        _ => return Ok(lhs),
+    };
+    toks.next();
+    if let Some((name, part)) = immutable_root(&lhs, vars) {
+        let msg = match part {
+            Some(part) => format!(
+                "cannot assign to `{}`, as `{}` is not declared as mutable",
+                part, name
+            ),
+            None => format!("cannot assign twice to immutable variable `{}`", name),
+        };
+        report(mutability_error(&lhs, msg, name));
+    }
+    if let Some(name) = immutable_static(&lhs, vars) {
+        report(error_at(
+            lhs.span().start,
+            &format!("cannot assign to immutable static item `{}`", name),
+        ));
+    }
+    let rhs = assign(toks, vars)?;
+    let span = lhs.span().to(rhs.span());
+    let lhs = Box::new(lhs);
+    let rhs = Box::new(rhs);
+    Ok(match op {
+        None => Node::Assign { lhs, rhs, span },
+        Some(op) => Node::CompoundAssign {
+            op,
+            lhs,
+            rhs,
+            ty: Type::I64,
+            span,
+        },
+    })
+}
+
+// logic_or ::= logic_and ('||' logic_and)*
+fn logic_or(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    let mut lhs = logic_and(toks, vars)?;
+    while let Some(tok) = toks.peek()
+        && tok.kind == TokenKind::OrOr
+    {
+        toks.next();
+        let rhs = logic_and(toks, vars)?;
+        lhs = Node::BinaryOp {
+            op: OpKind::Or,
+            span: lhs.span().to(rhs.span()),
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+            ty: Type::I64,
+        };
+    }
+    Ok(lhs)
+}
+
+// logic_and ::= comparison ('&&' comparison)*
+fn logic_and(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    let mut lhs = comparison(toks, vars)?;
+    while let Some(tok) = toks.peek()
+        && tok.kind == TokenKind::AndAnd
+    {
+        toks.next();
+        let rhs = comparison(toks, vars)?;
+        lhs = Node::BinaryOp {
+            op: OpKind::And,
+            span: lhs.span().to(rhs.span()),
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+            ty: Type::I64,
+        };
+    }
+    Ok(lhs)
+}
+
+// comparison ::= bit_or (('==' | '!=' | '<' | '>' | '<=' | '>=') bit_or)?
+// As in Rust, all comparisons share one precedence level and do not chain:
+// `a < b < c` and `a == b == c` are errors, while `(a < b) == c` is fine.
+fn comparison(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    let lhs = bit_or(toks, vars)?;
+    let Some(op) = toks.peek().and_then(|tok| comparison_op(&tok.kind)) else {
+        return Ok(lhs);
+    };
+    toks.next();
+    let rhs = bit_or(toks, vars)?;
+    if let Some(tok) = toks.peek()
+        && comparison_op(&tok.kind).is_some()
+    {
+        return Err(error_tok(tok, "comparison operators cannot be chained"));
+    }
+    Ok(Node::BinaryOp {
+        op,
+        span: lhs.span().to(rhs.span()),
+        lhs: Box::new(lhs),
+        rhs: Box::new(rhs),
+        ty: Type::I64,
+    })
+}
+
+fn comparison_op(kind: &TokenKind) -> Option<OpKind> {
+    match kind {
+        TokenKind::EqEq => Some(OpKind::Eq),
+        TokenKind::Ne => Some(OpKind::Ne),
+        TokenKind::Lt => Some(OpKind::Lt),
+        TokenKind::Gt => Some(OpKind::Gt),
+        TokenKind::Le => Some(OpKind::Le),
+        TokenKind::Ge => Some(OpKind::Ge),
+        _ => None,
+    }
+}
+
+// bit_or ::= bit_xor ('|' bit_xor)*
+fn bit_or(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    let mut lhs = bit_xor(toks, vars)?;
+    while let Some(tok) = toks.peek()
+        && tok.kind == TokenKind::Pipe
+    {
+        toks.next();
+        let rhs = bit_xor(toks, vars)?;
+        lhs = Node::BinaryOp {
+            op: OpKind::BitOr,
+            span: lhs.span().to(rhs.span()),
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+            ty: Type::I64,
+        };
+    }
+    Ok(lhs)
+}
+
+// bit_xor ::= bit_and ('^' bit_and)*
+fn bit_xor(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    let mut lhs = bit_and(toks, vars)?;
+    while let Some(tok) = toks.peek()
+        && tok.kind == TokenKind::Caret
+    {
+        toks.next();
+        let rhs = bit_and(toks, vars)?;
+        lhs = Node::BinaryOp {
+            op: OpKind::BitXor,
+            span: lhs.span().to(rhs.span()),
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+            ty: Type::I64,
+        };
+    }
+    Ok(lhs)
+}
+
+// bit_and ::= shift ('&' shift)*
+// A `&` reached here follows a complete operand, so it is the binary
+// operator; in operand position `unary` reads it as taking an address.
+fn bit_and(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    let mut lhs = shift(toks, vars)?;
+    while let Some(tok) = toks.peek()
+        && tok.kind == TokenKind::Amp
+    {
+        toks.next();
+        let rhs = shift(toks, vars)?;
+        lhs = Node::BinaryOp {
+            op: OpKind::BitAnd,
+            span: lhs.span().to(rhs.span()),
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+            ty: Type::I64,
+        };
+    }
+    Ok(lhs)
+}
+
+// shift ::= add (('<<' | '>>') add)*
+fn shift(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    let mut lhs = add(toks, vars)?;
+    while let Some(tok) = toks.peek() {
+        let op = match tok.kind {
+            TokenKind::Shl => OpKind::Shl,
+            TokenKind::Shr => OpKind::Shr,
+            _ => break,
+        };
+        toks.next();
+        let rhs = add(toks, vars)?;
+        lhs = Node::BinaryOp {
+            op,
+            span: lhs.span().to(rhs.span()),
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+            ty: Type::I64,
+        };
+    }
+    Ok(lhs)
+}
+
+// add ::= mul (('+' | '-') mul)*
+fn add(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    let mut lhs = mul(toks, vars)?;
+    while let Some(tok) = toks.peek() {
+        match tok.kind {
+            TokenKind::Plus => {
+                toks.next();
+                let rhs = mul(toks, vars)?;
+                lhs = Node::BinaryOp {
+                    op: OpKind::Add,
+                    span: lhs.span().to(rhs.span()),
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                    ty: Type::I64,
+                };
+            }
+            TokenKind::Minus => {
+                toks.next();
+                let rhs = mul(toks, vars)?;
+                lhs = Node::BinaryOp {
+                    op: OpKind::Sub,
+                    span: lhs.span().to(rhs.span()),
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                    ty: Type::I64,
+                };
+            }
+            _ => break,
+        }
+    }
+    Ok(lhs)
+}
+
+// mul ::= cast (('*' | '/' | '%') cast)*
+fn mul(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    let mut lhs = cast(toks, vars)?;
+    while let Some(tok) = toks.peek() {
+        match tok.kind {
+            TokenKind::Star => {
+                toks.next();
+                let rhs = cast(toks, vars)?;
+                lhs = Node::BinaryOp {
+                    op: OpKind::Mul,
+                    span: lhs.span().to(rhs.span()),
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                    ty: Type::I64,
+                };
+            }
+            TokenKind::Slash => {
+                toks.next();
+                let rhs = cast(toks, vars)?;
+                lhs = Node::BinaryOp {
+                    op: OpKind::Div,
+                    span: lhs.span().to(rhs.span()),
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                    ty: Type::I64,
+                };
+            }
+            TokenKind::Percent => {
+                toks.next();
+                let rhs = cast(toks, vars)?;
+                lhs = Node::BinaryOp {
+                    op: OpKind::Rem,
+                    span: lhs.span().to(rhs.span()),
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                    ty: Type::I64,
+                };
+            }
+            _ => break,
+        }
+    }
+    Ok(lhs)
+}
+
+// cast ::= unary ('as' type)*
+fn cast(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    let mut node = unary(toks, vars)?;
+    while let Some(tok) = toks.peek()
+        && tok.kind == TokenKind::As
+    {
+        toks.next();
+        let ty_tok = toks.peek().map(|tok| tok.span());
+        let ty = ty(toks, vars)?;
+        let end = ty_tok.unwrap_or(node.span());
+        node = Node::Cast {
+            span: node.span().to(end),
+            expr: Box::new(node),
+            ty,
+        };
+    }
+    Ok(node)
+}
+
+// unary ::= ('+' | '-')? postfix | ('*' | '!') unary | ('&' | '&&') 'mut'? unary
+fn unary(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    if let Some(tok) = toks.peek() {
+        match tok.kind {
+            TokenKind::Plus => {
+                toks.next();
+                return postfix(toks, vars);
+            }
+            TokenKind::Minus => {
+                let start = tok.span();
+                toks.next();
+                let node = postfix(toks, vars)?;
+                return Ok(Node::Neg {
+                    span: start.to(node.span()),
+                    expr: Box::new(node),
+                    ty: Type::I64,
+                });
+            }
+            TokenKind::Star => {
+                let start = tok.span();
+                toks.next();
+                let expr = unary(toks, vars)?;
+                return Ok(Node::Deref {
+                    span: start.to(expr.span()),
+                    expr: Box::new(expr),
+                    ty: Type::I64,
+                });
+            }
+            TokenKind::Amp => {
+                let start = tok.span();
+                toks.next();
+                let mutable = eat_mut(toks);
+                let expr = unary(toks, vars)?;
+                if mutable {
+                    check_borrow_mut(&expr, vars);
+                }
+                return Ok(Node::Addr {
+                    span: start.to(expr.span()),
+                    expr: Box::new(expr),
+                    mutable,
+                });
+            }
+            TokenKind::AndAnd => {
+                // `&&x` is a reference to a reference, which is kept in a
+                // slot of its own as if written `{ let tmp = &x; &tmp }`
+                let start = tok.span();
+                toks.next();
+                let mutable = eat_mut(toks);
+                let expr = unary(toks, vars)?;
+                if mutable {
+                    check_borrow_mut(&expr, vars);
+                }
+                let span = start.to(expr.span());
+                let offset = vars.reserve(1);
+                let init = Node::Assign {
+                    lhs: Box::new(Node::Var { offset, span }),
+                    rhs: Box::new(Node::Addr {
+                        expr: Box::new(expr),
+                        mutable,
+                        span,
+                    }),
+                    span,
+                };
+                return Ok(Node::Seq {
+                    first: Box::new(let_stmt(None, init, span)),
+                    second: Box::new(Node::Addr {
+                        expr: Box::new(Node::Var { offset, span }),
+                        mutable: false,
+                        span,
+                    }),
+                    span,
+                });
+            }
+            TokenKind::Bang => {
+                let start = tok.span();
+                toks.next();
+                let expr = unary(toks, vars)?;
+                return Ok(Node::Not {
+                    span: start.to(expr.span()),
+                    expr: Box::new(expr),
+                    bitwise: false,
+                    ty: Type::I64,
+                });
+            }
+            _ => {}
+        }
+    }
+    postfix(toks, vars)
+}
+
+// postfix ::= primary ('.' ident | '[' expr ']')*
+fn postfix(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    let mut node = primary(toks, vars)?;
+    while let Some(tok) = toks.peek() {
+        match tok.kind {
+            // field access: expr.name
+            TokenKind::Dot => {
+                toks.next();
+                let field_tok = toks.next().ok_or_else(|| ParseError {
+                    msg: "expected field name".into(),
+                    pos: 0,
+                })?;
+                let TokenKind::Ident { name } = field_tok.kind.clone() else {
+                    return Err(error_tok(&field_tok, "expected field name"));
+                };
+                node = Node::Field {
+                    span: node.span().to(field_tok.span()),
+                    expr: Box::new(node),
+                    name,
+                    offset: 0,
+                    ty: Type::I64,
+                };
+            }
+            // array indexing: expr[idx]
+            TokenKind::LBracket => {
+                toks.next(); // consume '['
+                let idx = expr(toks, vars)?;
+                let rbracket = expect_next(toks, TokenKind::RBracket)?;
+                node = Node::Index {
+                    span: node.span().to(rbracket.span()),
+                    expr: Box::new(node),
+                    index: Box::new(idx),
+                    ty: Type::I64,
+                };
+            }
+            _ => break,
+        }
+    }
+    Ok(node)
+}
+
+// primary ::= number |
+//             ident ('(' args? ')')? |
+//             variant_path |
+//             '(' expr ')' |
+//             string |
+//             block | unsafe_block | if_stmt | match_expr | loop_stmt |
+//             return_expr
+fn primary(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    // leave a statement terminator in place so error recovery can stop at it
+    if let Some(tok) = toks.peek()
+        && matches!(
+            tok.kind,
+            TokenKind::Semicolon | TokenKind::RBrace | TokenKind::Eof
+        )
+    {
+        return Err(error_tok(tok, &format!("unexpected token: {:?}", tok.kind)));
+    }
+    // block-like expressions used as values, such as
+    // `let x = if c { 1 } else { 2 };` or `let y = { let a = 1; a + 1 };`
+    if let Some(tok) = toks.peek() {
+        match tok.kind {
+            TokenKind::Loop | TokenKind::Label { .. } => return loop_stmt(toks, vars),
+            TokenKind::If => return if_stmt(toks, vars),
+            TokenKind::Match => return match_expr(toks, vars),
+            TokenKind::LBrace => return block(toks, vars),
+            TokenKind::Unsafe => return unsafe_block(toks, vars),
+            TokenKind::Return => return return_expr(toks, vars),
+            _ => {}
+        }
+    }
+    // a struct literal outside of a `let`, such as `p = P { x: 1 }` or
+    // `&P { x: 1 }`, is built in slots of its own
+    if let Some(Type::Struct(name)) = aggregate_ty(toks.peek(), vars) {
+        let slots = Type::Struct(name).slots(vars.types()).unwrap_or(1);
+        let offset = vars.reserve(slots);
+        return struct_lit(toks, vars, offset);
+    }
+    let tok = toks.next().unwrap();
+    match tok.kind {
+        TokenKind::Number { num } => Ok(Node::Num {
+            value: num,
+            span: tok.span(),
+        }),
+        TokenKind::String { ref value } => Ok(Node::StringLiteral {
+            value: value.clone(),
+            span: tok.span(),
+        }),
+        TokenKind::True | TokenKind::False => Ok(Node::Bool {
+            value: tok.kind == TokenKind::True,
+            span: tok.span(),
+        }),
+        TokenKind::LParen => {
+            // `()` has no value to compute
+            if let Some(peek) = toks.peek()
+                && peek.kind == TokenKind::RParen
+            {
+                return Err(error_tok(peek, "expected expression"));
+            }
+            // Parse sub-expression
+            let node = expr(toks, vars)?;
+            expect_next(toks, TokenKind::RParen)?;
+            Ok(node)
+        }
+        TokenKind::Ident { ref name } => {
+            let name = name.clone();
+            // function call: name(args?)
+            if let Some(tok2) = toks.peek()
+                && tok2.kind == TokenKind::LParen
+            {
+                toks.next(); // consume '('
+                // parse zero or more args
+                let args_vec = if let Some(peek) = toks.peek() {
+                    if peek.kind == TokenKind::RParen {
+                        Vec::new()
+                    } else {
+                        args(toks, vars)?
+                    }
+                } else {
+                    Vec::new()
+                };
+                // expect closing ')'
+                let rparen = expect_next(toks, TokenKind::RParen)?;
+                let span = tok.span().to(rparen.span());
+                // Special handling for write as a system call
+                if name == "write" {
+                    return Ok(Node::Syscall {
+                        name: "write".to_string(),
+                        args: args_vec,
+                        span,
+                    });
+                }
+                return Ok(Node::Call {
+                    name,
+                    args: args_vec,
+                    span,
+                });
+            }
+            // enum variant: Enum::Variant
+            if let Some(tok2) = toks.peek()
+                && tok2.kind == TokenKind::ColonColon
+            {
+                let (name, variant, tag, _, span) = variant_path(&tok, toks, vars)?;
+                if aggregate_ty(Some(&tok), vars).is_some() {
+                    return Err(error_at(
+                        span.start,
+                        &format!(
+                            "`{}::{}` can only initialize a variable or a field, as `{}` has fields",
+                            name, variant, name
+                        ),
+                    ));
+                }
+                return Ok(Node::Variant {
+                    name,
+                    variant,
+                    tag,
+                    span,
+                });
+            }
+            // `const` or `static` item, unless a local variable shadows it
+            if vars.find(&name).is_none()
+                && let Some(global) = vars.global(&name)
+            {
+                return Ok(global_node(name, global, vars.types(), tok.span()));
+            }
+            // variable
+            let offset = resolve(&tok, &name, vars)?;
+            Ok(Node::Var {
+                offset,
+                span: tok.span(),
+            })
+        }
+        _ => Err(error_tok(
+            &tok,
+            &format!("unexpected token: {:?}", tok.kind),
+        )),
+    }
+}
+
+// return_expr ::= 'return' expr?
+// The value is left out when the `return` ends a statement, a block, a match
+// arm or an argument list.
+fn return_expr(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Node, ParseError> {
+    let start = expect_next(toks, TokenKind::Return)?.span();
+    let value = match toks.peek() {
+        Some(tok)
+            if matches!(
+                tok.kind,
+                TokenKind::Semicolon
+                    | TokenKind::RBrace
+                    | TokenKind::Comma
+                    | TokenKind::RParen
+                    | TokenKind::RBracket
+                    | TokenKind::Eof
+            ) =>
+        {
+            None
+        }
+        _ => Some(Box::new(expr(toks, vars)?)),
+    };
+    let end = value.as_deref().map_or(start, Node::span);
+    Ok(Node::Return {
+        expr: value,
+        span: start.to(end),
+    })
+}
+
+// A use of the `const` or `static` item `name` at `span`: a `static` is a
+// place like a variable, while a `const` stands for its value
+fn global_node(name: String, global: &Global, types: &TypeDefs, span: Span) -> Node {
+    match global {
+        Global::Static { .. } => Node::Global {
+            name,
+            ty: Type::I64,
+            span,
+        },
+        Global::Const {
+            value: ConstValue::Str(value),
+            ..
+        } => Node::StringLiteral {
+            value: value.clone(),
+            span,
+        },
+        Global::Const {
+            ty,
+            value: ConstValue::Int(value),
+        } => {
+            // a variant of an enum or a boolean keeps its type; a mistyped
+            // value is left for the type checker
+            if *ty == Type::Bool {
+                return Node::Bool {
+                    value: *value != 0,
+                    span,
+                };
+            }
+            if let Type::Enum(enum_name) = ty
+                && let Some((variant, _)) = types.enums[enum_name].get(*value as usize)
+            {
+                return Node::Variant {
+                    name: enum_name.clone(),
+                    variant: variant.clone(),
+                    tag: *value as u64,
+                    span,
+                };
+            }
+            // other constants keep their declared type through a cast
+            let num = Node::Num {
+                value: *value as u64,
+                span,
+            };
+            if !ty.is_integer() {
+                return num;
+            }
+            Node::Cast {
+                expr: Box::new(num),
+                ty: ty.clone(),
+                span,
+            }
+        }
+    }
+}
+
+// Resolve a variable name to its slot offset. Unknown names are an error
+// unless the table is in legacy mode, which declares them for the whole
+// function on first use.
+fn resolve(tok: &Token, name: &str, vars: &mut SymbolTable) -> Result<u64, ParseError> {
+    if let Some(offset) = vars.find(name) {
+        return Ok(offset);
+    }
+    if vars.implicit_declarations() {
+        return Ok(vars.declare_in_function(name));
+    }
+    let mut msg = format!("cannot find value `{}` in this scope", name);
+    if let Some(candidate) = vars.suggest(name) {
+        msg.push_str(&format!("; did you mean `{}`?", candidate));
+    }
+    Err(error_tok(tok, &msg))
+}
+
+// Consume a `mut` keyword if one comes next
+fn eat_mut(toks: &mut Peekable<TokenIter>) -> bool {
+    match toks.peek() {
+        Some(tok) if tok.kind == TokenKind::Mut => {
+            toks.next();
+            true
+        }
+        _ => false,
+    }
+}
+
+// The variable a place is part of: its slot offset and the path from it to
+// the place such as `.pos.x` or `[_]`.
+fn place_root(place: &Node) -> Option<(u64, String)> {
+    match place {
+        Node::Var { offset, .. } => Some((*offset, String::new())),
+        Node::Field { expr, name, .. } => {
+            let (offset, path) = place_root(expr)?;
+            Some((offset, format!("{}.{}", path, name)))
+        }
+        Node::Index { expr, .. } => {
+            let (offset, path) = place_root(expr)?;
+            Some((offset, format!("{}[_]", path)))
+        }
+        _ => None,
+    }
+}
+
+// The variable a place expression writes to, if it was not declared `mut`:
+// the name, and the place itself such as `a[_]` or `p.x` when it is part of
+// the variable rather than the whole of it. Writes through a dereferenced
+// pointer, including fields and elements reached through a reference, are
+// left to the type checker.
+fn immutable_root<'a>(place: &Node, vars: &'a SymbolTable) -> Option<(&'a str, Option<String>)> {
+    let (offset, path) = place_root(place)?;
+    let sym = vars.binding(offset)?;
+    // a field of anything but a struct variable, or an element of anything
+    // but an array variable, is reached through a reference
+    let through_ref = match path.chars().next() {
+        Some('.') => !matches!(sym.ty, Some(Type::Struct(_))),
+        Some(_) => !matches!(sym.ty, Some(Type::Array(..))),
+        None => false,
+    };
+    if sym.mutable || through_ref {
+        return None;
+    }
+    let part = (!path.is_empty()).then(|| format!("{}{}", sym.name, path));
+    Some((&sym.name, part))
+}
+
+// The `static` item a place is part of, if it was not declared `mut`
+fn immutable_static<'a>(place: &'a Node, vars: &SymbolTable) -> Option<&'a str> {
+    match place {
+        Node::Global { name, .. } => match vars.global(name) {
+            Some(Global::Static { mutable: false, .. }) => Some(name),
+            _ => None,
+        },
+        Node::Field { expr, .. } | Node::Index { expr, .. } => immutable_static(expr, vars),
+        _ => None,
+    }
+}
+
+// An error at `place` with rustc's hint to declare `name` with `mut`
+fn mutability_error(place: &Node, msg: String, name: &str) -> ParseError {
+    ParseError {
+        msg: format!(
+            "{}; consider making this binding mutable: `mut {}`",
+            msg, name
+        ),
+        pos: place.span().start,
+    }
+}
+
+// Report `&mut place` of a variable or `static` that was not declared `mut`
+fn check_borrow_mut(place: &Node, vars: &SymbolTable) {
+    if let Some(name) = immutable_static(place, vars) {
+        report(error_at(
+            place.span().start,
+            &format!("cannot borrow immutable static item `{}` as mutable", name),
+        ));
+    }
+    if let Some((name, part)) = immutable_root(place, vars) {
+        let msg = match part {
+            Some(part) => format!(
+                "cannot borrow `{}` as mutable, as `{}` is not declared as mutable",
+                part, name
+            ),
+            None => format!(
+                "cannot borrow `{}` as mutable, as it is not declared as mutable",
+                name
+            ),
+        };
+        report(mutability_error(place, msg, name));
+    }
+}
+
+// function_args ::= 'mut'? ident ':' type (',' 'mut'? ident ':' type)*
+fn function_args(
+    toks: &mut Peekable<TokenIter>,
+    vars: &mut SymbolTable,
+) -> Result<(Vec<Node>, Vec<Type>), ParseError> {
+    let mut args = Vec::new();
+    let mut types = Vec::new();
+    // Parse one or more function_arg ::= 'mut'? ident ':' type, separated by commas
+    loop {
+        let mutable = eat_mut(toks);
+        // identifier
+        let tok = toks.next().unwrap();
+        let name = if let TokenKind::Ident { name } = &tok.kind {
+            name.clone()
+        } else {
+            return Err(error_tok(&tok, "expected identifier"));
+        };
+        // ':'
+        expect_next(toks, TokenKind::Colon)?;
+        // type (e.g., 'i32')
+        let param_ty = ty(toks, vars)?;
+        // assign new offset for this parameter
+        let off = vars.reserve(1);
+        vars.bind(&name, off, mutable, Some(param_ty.clone()));
+        types.push(param_ty);
+        // represent parameter as a Var node
+        args.push(Node::Var {
+            offset: off,
+            span: tok.span(),
+        });
+        // if a comma follows, consume it and continue parsing
+        if let Some(peek) = toks.peek()
+            && peek.kind == TokenKind::Comma
+        {
+            toks.next();
+            continue;
+        }
+        break;
+    }
+    Ok((args, types))
+}
+
+// type ::= 'i32' | 'bool' | 'str' | ident | '&' 'mut'? type | '[' type ';' number ']'
+// A name must be that of a struct or enum declared in the program.
+fn ty(toks: &mut Peekable<TokenIter>, vars: &SymbolTable) -> Result<Type, ParseError> {
+    let tok = toks.next().ok_or_else(|| ParseError {
+        msg: "expected type".into(),
+        pos: 0,
+    })?;
+    match &tok.kind {
+        TokenKind::I8 => Ok(Type::I8),
+        TokenKind::I16 => Ok(Type::I16),
+        TokenKind::I32 => Ok(Type::I32),
+        TokenKind::I64 => Ok(Type::I64),
+        TokenKind::U8 => Ok(Type::U8),
+        TokenKind::U16 => Ok(Type::U16),
+        TokenKind::U32 => Ok(Type::U32),
+        TokenKind::U64 => Ok(Type::U64),
+        TokenKind::Usize => Ok(Type::Usize),
+        TokenKind::Bool => Ok(Type::Bool),
+        TokenKind::Ident { name } if name == "str" => Ok(Type::Str),
+        TokenKind::Ident { name } if vars.types().structs.contains_key(name) => {
+            Ok(Type::Struct(name.clone()))
+        }
+        TokenKind::Ident { name } if vars.types().enums.contains_key(name) => {
+            Ok(Type::Enum(name.clone()))
+        }
+        // reported, but parsing carries on as the tokens are well-formed
+        TokenKind::Ident { name } => {
+            report(error_tok(
+                &tok,
+                &format!("cannot find type `{}` in this scope", name),
+            ));
+            Ok(Type::I64)
+        }
+        TokenKind::Amp => ref_ty(toks, vars),
+        // `&&T` lexes as one token
+        TokenKind::AndAnd => Ok(Type::Ref(Box::new(ref_ty(toks, vars)?))),
+        TokenKind::LBracket => {
+            let elem = ty(toks, vars)?;
+            expect_next(toks, TokenKind::Semicolon)?;
+            let len = array_len(toks, vars)?;
+            expect_next(toks, TokenKind::RBracket)?;
+            Ok(Type::Array(Box::new(elem), len))
+        }
+        _ => Err(error_tok(&tok, "expected type")),
+    }
+}
+
+// The length of an array type or repeat literal, which must be a number or
+// the name of a non-negative integer `const`
+fn array_len(toks: &mut Peekable<TokenIter>, vars: &SymbolTable) -> Result<u64, ParseError> {
+    let len_tok = toks.next().ok_or_else(|| ParseError {
+        msg: "expected array length".into(),
+        pos: 0,
+    })?;
+    match &len_tok.kind {
+        TokenKind::Number { num } => Ok(*num),
+        TokenKind::Ident { name } => match vars.global(name) {
+            Some(Global::Const {
+                ty,
+                value: ConstValue::Int(len),
+            }) if ty.is_integer() && *len >= 0 => Ok(*len as u64),
+            _ => Err(error_tok(&len_tok, "expected array length")),
+        },
+        _ => Err(error_tok(&len_tok, "expected array length")),
+    }
+}
+
+// The referenced type after a '&': `&T` or `&mut T`
+fn ref_ty(toks: &mut Peekable<TokenIter>, vars: &SymbolTable) -> Result<Type, ParseError> {
+    if eat_mut(toks) {
+        Ok(Type::RefMut(Box::new(ty(toks, vars)?)))
+    } else {
+        Ok(Type::Ref(Box::new(ty(toks, vars)?)))
+    }
+}
+
+// args ::= expr (',' expr)*
+fn args(toks: &mut Peekable<TokenIter>, vars: &mut SymbolTable) -> Result<Vec<Node>, ParseError> {
+    let mut args = Vec::new();
+    while let Some(tok) = toks.peek() {
+        match tok.kind {
+            TokenKind::RParen => break,
+            _ => {
+                args.push(expr(toks, vars)?);
+                if let Some(tok2) = toks.peek()
+                    && tok2.kind == TokenKind::Comma
+                {
+                    toks.next();
+                    continue;
+                }
+            }
+        }
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //=== Function parsing (happy path) ===
+    #[test]
+    fn test_program_single_function() {
+        let mut iter = tokenize("fn main() { 42 }").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = program(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            node,
+            Node::Function {
+                name: "main".to_string(),
+                args: vec![],
+                arg_types: vec![],
+                ret: None,
+                body: Box::new(Node::Num {
+                    value: 42,
+                    span: Span::default()
+                }),
+                locals: HashMap::new(),
+                span: Span::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_program_two_functions() {
+        let mut iter = tokenize("fn main() { 1 } fn foo() { 2 }")
+            .unwrap()
+            .into_iter()
+            .peekable();
+        let mut vars = SymbolTable::new();
+        let node = program(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            node,
+            Node::Seq {
+                first: Box::new(Node::Function {
+                    name: "main".to_string(),
+                    args: vec![],
+                    arg_types: vec![],
+                    ret: None,
+                    body: Box::new(Node::Num {
+                        value: 1,
+                        span: Span::default()
+                    }),
+                    locals: HashMap::new(),
+                    span: Span::default(),
+                }),
+                second: Box::new(Node::Function {
+                    name: "foo".to_string(),
+                    args: vec![],
+                    arg_types: vec![],
+                    ret: None,
+                    body: Box::new(Node::Num {
+                        value: 2,
+                        span: Span::default()
+                    }),
+                    locals: HashMap::new(),
+                    span: Span::default(),
+                }),
+                span: Span::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_program_return_in_function() {
+        let mut iter = tokenize("fn main() { return 3; }")
+            .unwrap()
+            .into_iter()
+            .peekable();
+        let mut vars = SymbolTable::new();
+        let node = program(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            node,
+            Node::Function {
+                name: "main".to_string(),
+                args: vec![],
+                arg_types: vec![],
+                ret: None,
+                body: Box::new(Node::Semi {
+                    expr: Box::new(Node::Return {
+                        expr: Some(Box::new(Node::Num {
+                            value: 3,
+                            span: Span::default()
+                        })),
+                        span: Span::default(),
+                    }),
+                    span: Span::default(),
+                }),
+                locals: HashMap::new(),
+                span: Span::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_program_two_functions_with_return() {
+        let mut iter = tokenize("fn mainA() { 1 } fn mainB() { return 2; }")
+            .unwrap()
+            .into_iter()
+            .peekable();
+        let mut vars = SymbolTable::new();
+        let node = program(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            node,
+            Node::Seq {
+                first: Box::new(Node::Function {
+                    name: "mainA".to_string(),
+                    args: vec![],
+                    arg_types: vec![],
+                    ret: None,
+                    body: Box::new(Node::Num {
+                        value: 1,
+                        span: Span::default()
+                    }),
+                    locals: HashMap::new(),
+                    span: Span::default(),
+                }),
+                second: Box::new(Node::Function {
+                    name: "mainB".to_string(),
+                    args: vec![],
+                    arg_types: vec![],
+                    ret: None,
+                    body: Box::new(Node::Semi {
+                        expr: Box::new(Node::Return {
+                            expr: Some(Box::new(Node::Num {
+                                value: 2,
+                                span: Span::default()
+                            })),
+                            span: Span::default(),
+                        }),
+                        span: Span::default(),
+                    }),
+                    locals: HashMap::new(),
+                    span: Span::default(),
+                }),
+                span: Span::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_program_return_without_value() {
+        let mut iter = tokenize("fn f() { return; }")
+            .unwrap()
+            .into_iter()
+            .peekable();
+        let mut vars = SymbolTable::new();
+        let node = program(&mut iter, &mut vars).unwrap();
+        let Node::Function { body, .. } = node else {
+            panic!("expected a function, got {:?}", node);
+        };
+        assert_eq!(
+            *body,
+            Node::Semi {
+                expr: Box::new(Node::Return {
+                    expr: None,
+                    span: Span::default(),
+                }),
+                span: Span::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_return_in_match_arm() {
+        let src = "fn f(n: i32) -> i32 { let v = match n { 0 => return, _ => return 9 }; v }";
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        program(&mut iter, &mut vars).unwrap();
+    }
+
+    //=== Function parsing error tests ===
+    #[test]
+    #[should_panic(expected = "expected identifier")]
+    fn test_error_fn_missing_ident() {
+        let mut iter = tokenize("fn() {}").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        program(&mut iter, &mut vars).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "expected LParen")]
+    fn test_error_fn_missing_lparen() {
+        let mut iter = tokenize("fn main) { }").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        program(&mut iter, &mut vars).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "expected RParen")]
+    fn test_error_fn_missing_rparen() {
+        let mut iter = tokenize("fn main( {}").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        program(&mut iter, &mut vars).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "expected LBrace")]
+    fn test_error_fn_missing_lbrace() {
+        let mut iter = tokenize("fn main() )").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        program(&mut iter, &mut vars).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "expected RBrace")]
+    fn test_error_fn_missing_rbrace() {
+        let mut iter = tokenize("fn main() { 1;").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        program(&mut iter, &mut vars).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "expected Colon")]
+    fn test_error_fn_args_missing_colon() {
+        let mut iter = tokenize("fn foo(a i32) {}").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        program(&mut iter, &mut vars).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "expected type")]
+    fn test_error_fn_args_missing_type() {
+        let mut iter = tokenize("fn foo(a:)").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        program(&mut iter, &mut vars).unwrap();
+    }
+
+    //=== If parsing (happy path) ===
+    #[test]
+    fn test_stmt_if() {
+        let mut iter = tokenize("if (1) 2;").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = stmt(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            node,
+            Node::If {
+                cond: Box::new(Node::Num {
+                    value: 1,
+                    span: Span::default()
+                }),
+                then_stmt: Box::new(Node::Semi {
+                    expr: Box::new(Node::Num {
+                        value: 2,
+                        span: Span::default()
+                    }),
+                    span: Span::default()
+                }),
+                else_stmt: None,
+                span: Span::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_stmt_if_else() {
+        let mut iter = tokenize("if (1) 2; else 3;")
+            .unwrap()
+            .into_iter()
+            .peekable();
+        let mut vars = SymbolTable::new();
+        let node = stmt(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            node,
+            Node::If {
+                cond: Box::new(Node::Num {
+                    value: 1,
+                    span: Span::default()
+                }),
+                then_stmt: Box::new(Node::Semi {
+                    expr: Box::new(Node::Num {
+                        value: 2,
+                        span: Span::default()
+                    }),
+                    span: Span::default()
+                }),
+                else_stmt: Some(Box::new(Node::Semi {
+                    expr: Box::new(Node::Num {
+                        value: 3,
+                        span: Span::default()
+                    }),
+                    span: Span::default()
+                })),
+                span: Span::default(),
+            }
+        );
+    }
+
+    //=== If parsing error tests ===
+    #[test]
+    #[should_panic(expected = "expected LBrace")]
+    fn test_error_if_missing_block() {
+        let mut iter = tokenize("if 1) 2;").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        stmt(&mut iter, &mut vars).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "expected RParen")]
+    fn test_error_if_missing_rparen() {
+        let mut iter = tokenize("if (1 2 3;").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        stmt(&mut iter, &mut vars).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "expected expression")]
+    fn test_error_if_missing_condition() {
+        let mut iter = tokenize("if () 1;").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        stmt(&mut iter, &mut vars).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "expected statement")]
+    fn test_error_if_missing_then_branch() {
+        let mut iter = tokenize("if (1)").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        stmt(&mut iter, &mut vars).unwrap();
+    }
+
+    #[test]
+    fn test_stmt_if_without_parens() {
+        let mut iter = tokenize("if 1 < 2 { 3 }").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = stmt(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            node,
+            Node::If {
+                cond: Box::new(Node::BinaryOp {
+                    op: OpKind::Lt,
+                    lhs: Box::new(Node::Num {
+                        value: 1,
+                        span: Span::default()
+                    }),
+                    rhs: Box::new(Node::Num {
+                        value: 2,
+                        span: Span::default()
+                    }),
+                    ty: Type::I64,
+                    span: Span::default(),
+                }),
+                then_stmt: Box::new(Node::Num {
+                    value: 3,
+                    span: Span::default()
+                }),
+                else_stmt: None,
+                span: Span::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_stmt_else_if_chain() {
+        let mut iter = tokenize("if 1 { 2 } else if 3 { 4 } else { 5 }")
+            .unwrap()
+            .into_iter()
+            .peekable();
+        let mut vars = SymbolTable::new();
+        let node = stmt(&mut iter, &mut vars).unwrap();
+        let num = |value| {
+            Box::new(Node::Num {
+                value,
+                span: Span::default(),
+            })
+        };
+        assert_eq!(
+            node,
+            Node::If {
+                cond: num(1),
+                then_stmt: num(2),
+                else_stmt: Some(Box::new(Node::If {
+                    cond: num(3),
+                    then_stmt: num(4),
+                    else_stmt: Some(num(5)),
+                    span: Span::default()
+                })),
+                span: Span::default()
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "expected LBrace")]
+    fn test_error_else_requires_block() {
+        let mut iter = tokenize("if 1 { 2; } else 3;")
+            .unwrap()
+            .into_iter()
+            .peekable();
+        let mut vars = SymbolTable::new();
+        stmt(&mut iter, &mut vars).unwrap();
+    }
+
+    //=== While parsing (happy path) ===
+    #[test]
+    fn test_stmt_while() {
+        let mut iter = tokenize("while (1) 2;").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = stmt(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            node,
+            Node::While {
+                cond: Box::new(Node::Num {
+                    value: 1,
+                    span: Span::default()
+                }),
+                body: Box::new(Node::Semi {
+                    expr: Box::new(Node::Num {
+                        value: 2,
+                        span: Span::default()
+                    }),
+                    span: Span::default()
+                }),
+                label: None,
+                span: Span::default(),
+            }
+        );
+    }
+
+    //=== While parsing error tests ===
+    #[test]
+    #[should_panic(expected = "expected LBrace")]
+    fn test_error_while_missing_block() {
+        let mut iter = tokenize("while 1) 2;").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        stmt(&mut iter, &mut vars).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "expected RParen")]
+    fn test_error_while_missing_rparen() {
+        let mut iter = tokenize("while (1 2;").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        stmt(&mut iter, &mut vars).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "expected expression")]
+    fn test_error_while_missing_condition() {
+        let mut iter = tokenize("while () 2;").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        stmt(&mut iter, &mut vars).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "expected statement")]
+    fn test_error_while_missing_body() {
+        let mut iter = tokenize("while (1)").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        stmt(&mut iter, &mut vars).unwrap();
+    }
+
+    #[test]
+    fn test_stmt_while_without_parens() {
+        let mut iter = tokenize("while 1 { 2; }").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = stmt(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            node,
+            Node::While {
+                cond: Box::new(Node::Num {
+                    value: 1,
+                    span: Span::default()
+                }),
+                body: Box::new(Node::Semi {
+                    expr: Box::new(Node::Num {
+                        value: 2,
+                        span: Span::default()
+                    }),
+                    span: Span::default()
+                }),
+                label: None,
+                span: Span::default()
+            }
+        );
+    }
+
+    //=== For parsing (happy path) ===
+    #[test]
+    fn test_stmt_for() {
+        let mut iter = tokenize("for (1;2;3) 4;").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = stmt(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            node,
+            Node::For {
+                init: Box::new(Node::Num {
+                    value: 1,
+                    span: Span::default()
+                }),
+                cond: Box::new(Node::Num {
+                    value: 2,
+                    span: Span::default()
+                }),
+                update: Box::new(Node::Num {
+                    value: 3,
+                    span: Span::default()
+                }),
+                body: Box::new(Node::Semi {
+                    expr: Box::new(Node::Num {
+                        value: 4,
+                        span: Span::default()
+                    }),
+                    span: Span::default()
+                }),
+                label: None,
+                span: Span::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_stmt_for_range() {
+        let mut iter = tokenize("for i in 0..=3 { i; }")
+            .unwrap()
+            .into_iter()
+            .peekable();
+        let mut vars = SymbolTable::new();
+        let node = stmt(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            node,
+            Node::ForRange {
+                offset: 8,
+                start: Box::new(Node::Num {
+                    value: 0,
+                    span: Span::default()
+                }),
+                end: Box::new(Node::Num {
+                    value: 3,
+                    span: Span::default()
+                }),
+                inclusive: true,
+                body: Box::new(Node::Semi {
+                    expr: Box::new(Node::Var {
+                        offset: 8,
+                        span: Span::default()
+                    }),
+                    span: Span::default()
+                }),
+                label: None,
+                span: Span::default()
+            }
+        );
+        // the loop variable goes out of scope after the body
+        assert_eq!(vars.find("i"), None);
+    }
+
+    #[test]
+    fn test_stmt_labelled_loop_with_break_value() {
+        let mut iter = tokenize("'outer: loop { break 'outer 5; continue; }")
+            .unwrap()
+            .into_iter()
+            .peekable();
+        let mut vars = SymbolTable::new();
+        let node = stmt(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            node,
+            Node::Loop {
+                body: Box::new(Node::Seq {
+                    first: Box::new(Node::Break {
+                        label: Some("outer".to_string()),
+                        value: Some(Box::new(Node::Num {
+                            value: 5,
+                            span: Span::default()
+                        })),
+                        span: Span::default()
+                    }),
+                    second: Box::new(Node::Continue {
+                        label: None,
+                        span: Span::default()
+                    }),
+                    span: Span::default()
+                }),
+                label: Some("outer".to_string()),
+                span: Span::default()
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "expected `..` or `..=`")]
+    fn test_error_for_range_missing_dots() {
+        let mut iter = tokenize("for i in 0 5 { }").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        stmt(&mut iter, &mut vars).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "expected `while`, `for` or `loop` after a label")]
+    fn test_error_label_without_loop() {
+        let mut iter = tokenize("'a: { }").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        stmt(&mut iter, &mut vars).unwrap();
+    }
+
+    //=== For parsing error tests ===
+    #[test]
+    #[should_panic(expected = "expected LParen")]
+    fn test_error_for_missing_lparen() {
+        let mut iter = tokenize("for 1;2;3) 4;").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        stmt(&mut iter, &mut vars).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "expected Semicolon")]
+    fn test_error_for_missing_semicolon1() {
+        let mut iter = tokenize("for (1 2;3) 4;").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        stmt(&mut iter, &mut vars).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "expected Semicolon")]
+    fn test_error_for_missing_semicolon2() {
+        let mut iter = tokenize("for (1;2 3) 4;").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        stmt(&mut iter, &mut vars).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "expected expression")]
+    fn test_error_for_missing_init() {
+        let mut iter = tokenize("for (;2;3) 4;").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        stmt(&mut iter, &mut vars).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "expected expression")]
+    fn test_error_for_missing_cond() {
+        let mut iter = tokenize("for (1;;3) 4;").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        stmt(&mut iter, &mut vars).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "expected expression")]
+    fn test_error_for_missing_update() {
+        let mut iter = tokenize("for (1;2;) 4;").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        stmt(&mut iter, &mut vars).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "expected RParen")]
+    fn test_error_for_missing_rparen() {
+        let mut iter = tokenize("for (1;2;3 4;").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        stmt(&mut iter, &mut vars).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "expected statement")]
+    fn test_error_for_missing_body() {
+        let mut iter = tokenize("for (1;2;3)").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        stmt(&mut iter, &mut vars).unwrap();
+    }
+
+    //=== Miscellaneous parsing tests ===
+    #[test]
+    fn test_primary() {
+        let mut iter = tokenize("42").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = primary(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            node,
+            Node::Num {
+                value: 42,
+                span: Span::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_expr_add_sub() {
+        let mut iter = tokenize("1+2").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = expr(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            node,
+            Node::BinaryOp {
+                op: OpKind::Add,
+                lhs: Box::new(Node::Num {
+                    value: 1,
+                    span: Span::default()
+                }),
+                rhs: Box::new(Node::Num {
+                    value: 2,
+                    span: Span::default()
+                }),
+                ty: Type::I64,
+                span: Span::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_expr_precedence() {
+        let mut iter = tokenize("1+2*3").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = expr(&mut iter, &mut vars).unwrap();
+        let expected = Node::BinaryOp {
+            op: OpKind::Add,
+            lhs: Box::new(Node::Num {
+                value: 1,
+                span: Span::default(),
+            }),
+            rhs: Box::new(Node::BinaryOp {
+                op: OpKind::Mul,
+                lhs: Box::new(Node::Num {
+                    value: 2,
+                    span: Span::default(),
+                }),
+                rhs: Box::new(Node::Num {
+                    value: 3,
+                    span: Span::default(),
+                }),
+                ty: Type::I64,
+                span: Span::default(),
+            }),
+            ty: Type::I64,
+            span: Span::default(),
+        };
+        assert_eq!(node, expected);
+    }
+
+    #[test]
+    fn test_expr_logical_precedence() {
+        // `&&` binds tighter than `||`, comparisons tighter than both
+        let mut iter = tokenize("1 < 2 || 3 == 4 && 5 > 6")
+            .unwrap()
+            .into_iter()
+            .peekable();
+        let mut vars = SymbolTable::new();
+        let node = expr(&mut iter, &mut vars).unwrap();
+        let num = |value| {
+            Box::new(Node::Num {
+                value,
+                span: Span::default(),
+            })
+        };
+        let cmp = |op, lhs, rhs| {
+            Box::new(Node::BinaryOp {
+                op,
+                lhs,
+                rhs,
+                ty: Type::I64,
+                span: Span::default(),
+            })
+        };
+        let expected = Node::BinaryOp {
+            op: OpKind::Or,
+            lhs: cmp(OpKind::Lt, num(1), num(2)),
+            rhs: cmp(
+                OpKind::And,
+                cmp(OpKind::Eq, num(3), num(4)),
+                cmp(OpKind::Gt, num(5), num(6)),
+            ),
+            ty: Type::I64,
+            span: Span::default(),
+        };
+        assert_eq!(node, expected);
+    }
+
+    #[test]
+    fn test_expr_bitwise_precedence() {
+        // Rust order: shifts, then `&`, `^`, `|`, all above comparisons
+        let mut iter = tokenize("1 | 2 ^ 3 & 4 << 5 % 6 == 7")
+            .unwrap()
+            .into_iter()
+            .peekable();
+        let mut vars = SymbolTable::new();
+        let node = expr(&mut iter, &mut vars).unwrap();
+        let num = |value| {
+            Box::new(Node::Num {
+                value,
+                span: Span::default(),
+            })
         };
-        // ':'
-        expect_next(toks, TokenKind::Colon)?;
-        // type (e.g., 'i32')
-        expect_next(toks, TokenKind::I32)?;
-        // assign new offset for this parameter
-        let off = if let Some(off) = vars.find(&name) {
-            off
-        } else {
-            let last = vars.next.as_ref().map(|v| v.offset).unwrap_or(vars.offset);
-            let new_off = last + 8;
-            vars.push(name.clone(), new_off);
-            new_off
+        let bin = |op, lhs, rhs| {
+            Box::new(Node::BinaryOp {
+                op,
+                lhs,
+                rhs,
+                ty: Type::I64,
+                span: Span::default(),
+            })
         };
-        // represent parameter as a Var node
-        args.push(Node::Var { offset: off });
-        // if a comma follows, consume it and continue parsing
-        if let Some(peek) = toks.peek() {
-            if peek.kind == TokenKind::Comma {
-                toks.next();
-                continue;
-            }
-        }
-        break;
+        let expected = bin(
+            OpKind::Eq,
+            bin(
+                OpKind::BitOr,
+                num(1),
+                bin(
+                    OpKind::BitXor,
+                    num(2),
+                    bin(
+                        OpKind::BitAnd,
+                        num(3),
+                        bin(OpKind::Shl, num(4), bin(OpKind::Rem, num(5), num(6))),
+                    ),
+                ),
+            ),
+            num(7),
+        );
+        assert_eq!(node, *expected);
     }
-    Ok(args)
-}
 
-// args ::= expr (',' expr)*
-fn args(toks: &mut Peekable<TokenIter>, vars: &mut Variable) -> Result<Vec<Node>, ParseError> {
-    let mut args = Vec::new();
-    while let Some(tok) = toks.peek() {
-        match tok.kind {
-            TokenKind::RParen => break,
-            _ => {
-                args.push(expr(toks, vars)?);
-                if let Some(tok2) = toks.peek() {
-                    match tok2.kind {
-                        TokenKind::Comma => {
-                            toks.next();
-                            continue;
-                        }
-                        _ => {}
-                    }
-                }
-            }
-        }
+    #[test]
+    fn test_expr_binary_and_after_operand() {
+        // the first `&` follows an operand, the second starts one
+        let mut iter = tokenize("1 & &2").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = expr(&mut iter, &mut vars).unwrap();
+        let expected = Node::BinaryOp {
+            op: OpKind::BitAnd,
+            lhs: Box::new(Node::Num {
+                value: 1,
+                span: Span::default(),
+            }),
+            rhs: Box::new(Node::Addr {
+                expr: Box::new(Node::Num {
+                    value: 2,
+                    span: Span::default(),
+                }),
+                mutable: false,
+                span: Span::default(),
+            }),
+            ty: Type::I64,
+            span: Span::default(),
+        };
+        assert_eq!(node, expected);
     }
-    Ok(args)
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_expr_parens_mul() {
+        let mut iter = tokenize("(1+2)*3").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = expr(&mut iter, &mut vars).unwrap();
+        let expected = Node::BinaryOp {
+            op: OpKind::Mul,
+            lhs: Box::new(Node::BinaryOp {
+                op: OpKind::Add,
+                lhs: Box::new(Node::Num {
+                    value: 1,
+                    span: Span::default(),
+                }),
+                rhs: Box::new(Node::Num {
+                    value: 2,
+                    span: Span::default(),
+                }),
+                ty: Type::I64,
+                span: Span::default(),
+            }),
+            rhs: Box::new(Node::Num {
+                value: 3,
+                span: Span::default(),
+            }),
+            ty: Type::I64,
+            span: Span::default(),
+        };
+        assert_eq!(node, expected);
+    }
 
-    //=== Function parsing (happy path) ===
     #[test]
-    fn test_program_single_function() {
-        let mut iter = tokenize("fn main() { 42; }")
-            .unwrap()
-            .into_iter()
-            .peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        let node = program(&mut iter, &mut vars).unwrap();
+    fn test_primary_parens() {
+        let mut iter = tokenize("(42)").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = primary(&mut iter, &mut vars).unwrap();
         assert_eq!(
             node,
-            Node::Function {
-                name: "main".to_string(),
-                args: vec![],
-                body: Box::new(Node::Num { value: 42 }),
+            Node::Num {
+                value: 42,
+                span: Span::default()
             }
         );
     }
 
     #[test]
-    fn test_program_two_functions() {
-        let mut iter = tokenize("fn main() { 1; } fn foo() { 2; }")
-            .unwrap()
-            .into_iter()
-            .peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        let node = program(&mut iter, &mut vars).unwrap();
+    fn test_expr_nested_parens() {
+        let mut iter = tokenize("((1+2))").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = expr(&mut iter, &mut vars).unwrap();
         assert_eq!(
             node,
-            Node::Seq {
-                first: Box::new(Node::Function {
-                    name: "main".to_string(),
-                    args: vec![],
-                    body: Box::new(Node::Num { value: 1 }),
+            Node::BinaryOp {
+                op: OpKind::Add,
+                lhs: Box::new(Node::Num {
+                    value: 1,
+                    span: Span::default()
                 }),
-                second: Box::new(Node::Function {
-                    name: "foo".to_string(),
-                    args: vec![],
-                    body: Box::new(Node::Num { value: 2 }),
+                rhs: Box::new(Node::Num {
+                    value: 2,
+                    span: Span::default()
                 }),
+                ty: Type::I64,
+                span: Span::default(),
             }
         );
     }
 
     #[test]
-    fn test_program_return_in_function() {
-        let mut iter = tokenize("fn main() { return 3; }")
-            .unwrap()
-            .into_iter()
-            .peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        let node = program(&mut iter, &mut vars).unwrap();
+    fn test_expr_assign() {
+        let mut iter = tokenize("1=2").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = expr(&mut iter, &mut vars).unwrap();
         assert_eq!(
             node,
-            Node::Function {
-                name: "main".to_string(),
-                args: vec![],
-                body: Box::new(Node::Return {
-                    expr: Box::new(Node::Num { value: 3 }),
+            Node::Assign {
+                lhs: Box::new(Node::Num {
+                    value: 1,
+                    span: Span::default()
+                }),
+                rhs: Box::new(Node::Num {
+                    value: 2,
+                    span: Span::default()
                 }),
+                span: Span::default(),
             }
         );
     }
 
     #[test]
-    fn test_program_two_functions_with_return() {
-        let mut iter = tokenize("fn mainA() { 1; } fn mainB() { return 2; }")
-            .unwrap()
-            .into_iter()
-            .peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        let node = program(&mut iter, &mut vars).unwrap();
+    fn test_expr_compound_assign() {
+        let mut iter = tokenize("1 <<= 2 + 3").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = expr(&mut iter, &mut vars).unwrap();
         assert_eq!(
             node,
-            Node::Seq {
-                first: Box::new(Node::Function {
-                    name: "mainA".to_string(),
-                    args: vec![],
-                    body: Box::new(Node::Num { value: 1 }),
+            Node::CompoundAssign {
+                op: OpKind::Shl,
+                lhs: Box::new(Node::Num {
+                    value: 1,
+                    span: Span::default()
                 }),
-                second: Box::new(Node::Function {
-                    name: "mainB".to_string(),
-                    args: vec![],
-                    body: Box::new(Node::Return {
-                        expr: Box::new(Node::Num { value: 2 }),
+                rhs: Box::new(Node::BinaryOp {
+                    op: OpKind::Add,
+                    lhs: Box::new(Node::Num {
+                        value: 2,
+                        span: Span::default()
                     }),
+                    rhs: Box::new(Node::Num {
+                        value: 3,
+                        span: Span::default()
+                    }),
+                    ty: Type::I64,
+                    span: Span::default(),
                 }),
+                ty: Type::I64,
+                span: Span::default(),
             }
         );
     }
 
-    //=== Function parsing error tests ===
-    #[test]
-    #[should_panic(expected = "expected identifier")]
-    fn test_error_fn_missing_ident() {
-        let mut iter = tokenize("fn() {}").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        program(&mut iter, &mut vars).unwrap();
-    }
-
-    #[test]
-    #[should_panic(expected = "expected LParen")]
-    fn test_error_fn_missing_lparen() {
-        let mut iter = tokenize("fn main) { }").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        program(&mut iter, &mut vars).unwrap();
-    }
-
     #[test]
-    #[should_panic(expected = "expected RParen")]
-    fn test_error_fn_missing_rparen() {
-        let mut iter = tokenize("fn main( {}").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        program(&mut iter, &mut vars).unwrap();
+    fn test_expr_eq_ne() {
+        let mut it1 = tokenize("1==2").unwrap().into_iter().peekable();
+        let mut vars1 = SymbolTable::new();
+        let n1 = expr(&mut it1, &mut vars1).unwrap();
+        assert_eq!(
+            n1,
+            Node::BinaryOp {
+                op: OpKind::Eq,
+                lhs: Box::new(Node::Num {
+                    value: 1,
+                    span: Span::default()
+                }),
+                rhs: Box::new(Node::Num {
+                    value: 2,
+                    span: Span::default()
+                }),
+                ty: Type::I64,
+                span: Span::default(),
+            }
+        );
+        let mut it2 = tokenize("1!=2").unwrap().into_iter().peekable();
+        let mut vars2 = SymbolTable::new();
+        let n2 = expr(&mut it2, &mut vars2).unwrap();
+        assert_eq!(
+            n2,
+            Node::BinaryOp {
+                op: OpKind::Ne,
+                lhs: Box::new(Node::Num {
+                    value: 1,
+                    span: Span::default()
+                }),
+                rhs: Box::new(Node::Num {
+                    value: 2,
+                    span: Span::default()
+                }),
+                ty: Type::I64,
+                span: Span::default(),
+            }
+        );
     }
 
     #[test]
-    #[should_panic(expected = "expected LBrace")]
-    fn test_error_fn_missing_lbrace() {
-        let mut iter = tokenize("fn main() )").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        program(&mut iter, &mut vars).unwrap();
+    fn test_expr_relational() {
+        let mut vars = SymbolTable::new();
+        let mut it_lt = tokenize("1<2").unwrap().into_iter().peekable();
+        assert_eq!(
+            expr(&mut it_lt, &mut vars).unwrap(),
+            Node::BinaryOp {
+                op: OpKind::Lt,
+                lhs: Box::new(Node::Num {
+                    value: 1,
+                    span: Span::default()
+                }),
+                rhs: Box::new(Node::Num {
+                    value: 2,
+                    span: Span::default()
+                }),
+                ty: Type::I64,
+                span: Span::default(),
+            }
+        );
+        let mut vars2 = SymbolTable::new();
+        let mut it_gt = tokenize("2>1").unwrap().into_iter().peekable();
+        assert_eq!(
+            expr(&mut it_gt, &mut vars2).unwrap(),
+            Node::BinaryOp {
+                op: OpKind::Gt,
+                lhs: Box::new(Node::Num {
+                    value: 2,
+                    span: Span::default()
+                }),
+                rhs: Box::new(Node::Num {
+                    value: 1,
+                    span: Span::default()
+                }),
+                ty: Type::I64,
+                span: Span::default(),
+            }
+        );
+        let mut vars3 = SymbolTable::new();
+        let mut it_le = tokenize("1<=1").unwrap().into_iter().peekable();
+        assert_eq!(
+            expr(&mut it_le, &mut vars3).unwrap(),
+            Node::BinaryOp {
+                op: OpKind::Le,
+                lhs: Box::new(Node::Num {
+                    value: 1,
+                    span: Span::default()
+                }),
+                rhs: Box::new(Node::Num {
+                    value: 1,
+                    span: Span::default()
+                }),
+                ty: Type::I64,
+                span: Span::default(),
+            }
+        );
+        let mut vars4 = SymbolTable::new();
+        let mut it_ge = tokenize("2>=2").unwrap().into_iter().peekable();
+        assert_eq!(
+            expr(&mut it_ge, &mut vars4).unwrap(),
+            Node::BinaryOp {
+                op: OpKind::Ge,
+                lhs: Box::new(Node::Num {
+                    value: 2,
+                    span: Span::default()
+                }),
+                rhs: Box::new(Node::Num {
+                    value: 2,
+                    span: Span::default()
+                }),
+                ty: Type::I64,
+                span: Span::default(),
+            }
+        );
     }
 
     #[test]
-    #[should_panic(expected = "expected RBrace")]
-    fn test_error_fn_missing_rbrace() {
-        let mut iter = tokenize("fn main() { 1;").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        program(&mut iter, &mut vars).unwrap();
+    fn test_error_chained_comparison() {
+        for src in [
+            "x == 1 == true",
+            "1 < 2 < 3",
+            "1 < 2 == true",
+            "true == 1 < 2",
+        ] {
+            let mut iter = tokenize(src).unwrap().into_iter().peekable();
+            let mut vars = SymbolTable::new();
+            vars.declare("x", 1, false);
+            let err = expr(&mut iter, &mut vars).unwrap_err();
+            assert_eq!(err.msg, "comparison operators cannot be chained", "{}", src);
+        }
+        // parentheses make the order explicit
+        let mut iter = tokenize("(1 < 2) == true").unwrap().into_iter().peekable();
+        expr(&mut iter, &mut SymbolTable::new()).unwrap();
     }
 
     #[test]
-    #[should_panic(expected = "expected Colon")]
-    fn test_error_fn_args_missing_colon() {
-        let mut iter = tokenize("fn foo(a i32) {}").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        program(&mut iter, &mut vars).unwrap();
+    fn test_ident_offset() {
+        let mut iter = tokenize("a").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::with_implicit_declarations();
+        let node = primary(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            node,
+            Node::Var {
+                offset: 8,
+                span: Span::default()
+            }
+        );
     }
 
     #[test]
-    #[should_panic(expected = "expected I32")]
-    fn test_error_fn_args_missing_type() {
-        let mut iter = tokenize("fn foo(a:)").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        program(&mut iter, &mut vars).unwrap();
+    fn test_ident_repeated_offset() {
+        let mut iter = tokenize("a a").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::with_implicit_declarations();
+        let first = primary(&mut iter, &mut vars).unwrap();
+        let second = primary(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            first,
+            Node::Var {
+                offset: 8,
+                span: Span::default()
+            }
+        );
+        assert_eq!(
+            second,
+            Node::Var {
+                offset: 8,
+                span: Span::default()
+            }
+        );
     }
 
-    //=== If parsing (happy path) ===
     #[test]
-    fn test_stmt_if() {
-        let mut iter = tokenize("if (1) 2;").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        let node = stmt(&mut iter, &mut vars).unwrap();
+    fn test_assign_ident() {
+        let mut iter = tokenize("a=1").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::with_implicit_declarations();
+        let node = expr(&mut iter, &mut vars).unwrap();
         assert_eq!(
             node,
-            Node::If {
-                cond: Box::new(Node::Num { value: 1 }),
-                then_stmt: Box::new(Node::Num { value: 2 }),
-                else_stmt: None,
+            Node::Assign {
+                lhs: Box::new(Node::Var {
+                    offset: 8,
+                    span: Span::default()
+                }),
+                rhs: Box::new(Node::Num {
+                    value: 1,
+                    span: Span::default()
+                }),
+                span: Span::default(),
             }
         );
     }
 
     #[test]
-    fn test_stmt_if_else() {
-        let mut iter = tokenize("if (1) 2; else 3;")
+    fn test_error_undeclared_variable() {
+        let mut iter = tokenize("a").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let err = primary(&mut iter, &mut vars).unwrap_err();
+        assert_eq!(err.msg, "cannot find value `a` in this scope");
+        assert_eq!(err.pos, 0);
+    }
+
+    #[test]
+    fn test_error_undeclared_variable_suggestion() {
+        let mut iter = tokenize("fn main() { let sum = 0; let arr = [1]; sun = sum + arr[0]; }")
             .unwrap()
             .into_iter()
             .peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        let node = stmt(&mut iter, &mut vars).unwrap();
+        let mut vars = SymbolTable::new();
+        let errs = program(&mut iter, &mut vars).unwrap_err();
+        assert_eq!(errs.len(), 1);
         assert_eq!(
-            node,
-            Node::If {
-                cond: Box::new(Node::Num { value: 1 }),
-                then_stmt: Box::new(Node::Num { value: 2 }),
-                else_stmt: Some(Box::new(Node::Num { value: 3 })),
-            }
+            errs[0].msg,
+            "cannot find value `sun` in this scope; did you mean `sum`?"
         );
+        assert_eq!(errs[0].pos, 40);
+    }
+
+    #[test]
+    fn test_spans_cover_source() {
+        let src = "fn main() { let x = 1 + f(2); return x; }";
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = program(&mut iter, &mut vars).unwrap();
+        let text = |span: Span| &src[span.start..span.end];
+        assert_eq!(text(node.span()), src);
+        let Node::Function { body, .. } = node else {
+            panic!("expected a function");
+        };
+        let Node::Seq { first, second, .. } = *body else {
+            panic!("expected two statements");
+        };
+        assert_eq!(text(first.span()), "let x = 1 + f(2);");
+        assert_eq!(text(second.span()), "return x;");
+        let Node::Semi { expr, .. } = *first else {
+            panic!("expected a statement");
+        };
+        let Node::Let { init, .. } = *expr else {
+            panic!("expected a `let`");
+        };
+        let Node::Assign { lhs, rhs, .. } = *init else {
+            panic!("expected an assignment");
+        };
+        assert_eq!(text(lhs.span()), "x");
+        assert_eq!(text(rhs.span()), "1 + f(2)");
     }
 
-    //=== If parsing error tests ===
     #[test]
-    #[should_panic(expected = "expected LParen")]
-    fn test_error_if_missing_lparen() {
-        let mut iter = tokenize("if 1) 2;").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        stmt(&mut iter, &mut vars).unwrap();
+    fn test_error_recovery_collects_every_error() {
+        let src = "fn f() { a; let b = 1 b; } fn g( { } fn main() { c = 1; return 0; }";
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let errs = program(&mut iter, &mut vars).unwrap_err();
+        let msgs: Vec<&str> = errs.iter().map(|e| e.msg.as_str()).collect();
+        assert_eq!(
+            msgs,
+            vec![
+                "cannot find value `a` in this scope",
+                "expected Semicolon",
+                "expected RParen",
+                "cannot find value `c` in this scope",
+            ]
+        );
     }
 
     #[test]
-    #[should_panic(expected = "expected RParen")]
-    fn test_error_if_missing_rparen() {
-        let mut iter = tokenize("if (1 2 3;").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        stmt(&mut iter, &mut vars).unwrap();
+    fn test_error_recovery_stops_match_at_item() {
+        for src in [
+            "fn main() -> i32 { match 1 { _ => 1 fn } }",
+            "fn main() -> i32 { match 1 { static _ => 1 } } fn f() {}",
+            "fn main() { match 1 { 1 => { struct }, _ => 2 } }",
+        ] {
+            let mut iter = tokenize(src).unwrap().into_iter().peekable();
+            let mut vars = SymbolTable::new();
+            let errs = program(&mut iter, &mut vars).unwrap_err();
+            assert!(!errs.is_empty(), "{}", src);
+        }
     }
 
     #[test]
-    #[should_panic(expected = "expected expression")]
-    fn test_error_if_missing_condition() {
-        let mut iter = tokenize("if () 1;").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        stmt(&mut iter, &mut vars).unwrap();
+    fn test_error_recovery_reports_two_errors_in_one_function() {
+        // the body is parsed after a broken signature, and the statements
+        // after a body closed too early are parsed as well
+        for (src, second) in [
+            (
+                "fn f(a: Foo) -> i32 { let x = ; a }",
+                "unexpected token: Semicolon",
+            ),
+            (
+                "fn f(a: i32 -> i32 { a + b }",
+                "cannot find value `b` in this scope; did you mean `a`?",
+            ),
+        ] {
+            let mut iter = tokenize(src).unwrap().into_iter().peekable();
+            let mut vars = SymbolTable::new();
+            let errs = program(&mut iter, &mut vars).unwrap_err();
+            assert_eq!(errs[1].msg, second, "{}", src);
+        }
+        let src = "fn f() { let a = 1; if (a { 1 } a + b; } fn main() { c; }";
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let errs = program(&mut iter, &mut vars).unwrap_err();
+        let msgs: Vec<&str> = errs.iter().map(|e| e.msg.as_str()).collect();
+        assert_eq!(
+            msgs,
+            vec![
+                "expected RParen",
+                "expected Fn",
+                "cannot find value `b` in this scope; did you mean `a`?",
+                "cannot find value `c` in this scope",
+            ]
+        );
     }
 
     #[test]
-    #[should_panic(expected = "expected statement")]
-    fn test_error_if_missing_then_branch() {
-        let mut iter = tokenize("if (1)").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        stmt(&mut iter, &mut vars).unwrap();
+    fn test_error_assign_to_immutable() {
+        let src = "fn main() { let x = 1; x = 2; let a = [1]; a[0] += 1; let p = &mut x; }";
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let errs = program(&mut iter, &mut vars).unwrap_err();
+        let msgs: Vec<&str> = errs.iter().map(|e| e.msg.as_str()).collect();
+        assert_eq!(
+            msgs,
+            vec![
+                "cannot assign twice to immutable variable `x`; consider making this binding mutable: `mut x`",
+                "cannot assign to `a[_]`, as `a` is not declared as mutable; consider making this binding mutable: `mut a`",
+                "cannot borrow `x` as mutable, as it is not declared as mutable; consider making this binding mutable: `mut x`",
+            ]
+        );
+        assert_eq!(&src[errs[0].pos..errs[0].pos + 5], "x = 2");
     }
 
-    //=== While parsing (happy path) ===
     #[test]
-    fn test_stmt_while() {
-        let mut iter = tokenize("while (1) 2;").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        let node = stmt(&mut iter, &mut vars).unwrap();
-        assert_eq!(
-            node,
-            Node::While {
-                cond: Box::new(Node::Num { value: 1 }),
-                body: Box::new(Node::Num { value: 2 }),
-            }
-        );
+    fn test_mut_bindings_may_be_assigned() {
+        let src = "fn f(mut n: i32) -> i32 { n += 1; n } fn main() { let mut x = 1; x = 2; let mut a = [1]; a[0] = x; let p = &mut a[0]; for mut i in 0..2 { i += 1; } }
+                   fn g(a: &mut [i32; 1]) { a[0] = 1; let r = &mut *a; r[0] += 1; }";
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        assert!(program(&mut iter, &mut vars).is_ok());
     }
 
-    //=== While parsing error tests ===
     #[test]
-    #[should_panic(expected = "expected LParen")]
-    fn test_error_while_missing_lparen() {
-        let mut iter = tokenize("while 1) 2;").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        stmt(&mut iter, &mut vars).unwrap();
+    fn test_error_recovery_skips_nested_block() {
+        let src = "fn main() { let x = ; if (1) { y; } let z = x; z + w; }";
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let errs = program(&mut iter, &mut vars).unwrap_err();
+        // `x` is declared despite its initializer, and `y`, `z` and `w` are
+        // still checked
+        let msgs: Vec<&str> = errs.iter().map(|e| e.msg.as_str()).collect();
+        assert_eq!(msgs.len(), 3);
+        assert!(msgs[1].contains("`y`"));
+        assert!(msgs[2].contains("`w`"));
     }
 
     #[test]
-    #[should_panic(expected = "expected RParen")]
-    fn test_error_while_missing_rparen() {
-        let mut iter = tokenize("while (1 2;").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        stmt(&mut iter, &mut vars).unwrap();
+    fn test_error_recovery_skips_brackets_of_broken_let() {
+        // the `;` of the inner repeat does not end the statement
+        let src = "fn main() -> i32 { let mut a = [[0; 3]; 2]; a[1][2] = 5; a[1][2] }";
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let errs = program(&mut iter, &mut vars).unwrap_err();
+        let msgs: Vec<&str> = errs.iter().map(|e| e.msg.as_str()).collect();
+        assert_eq!(msgs, vec!["unexpected token: LBracket"]);
     }
 
     #[test]
-    #[should_panic(expected = "expected expression")]
-    fn test_error_while_missing_condition() {
-        let mut iter = tokenize("while () 2;").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        stmt(&mut iter, &mut vars).unwrap();
+    fn test_error_undeclared_array() {
+        let mut iter = tokenize("ar[0]").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        vars.declare("arr", 2, false);
+        let err = primary(&mut iter, &mut vars).unwrap_err();
+        assert_eq!(
+            err.msg,
+            "cannot find value `ar` in this scope; did you mean `arr`?"
+        );
     }
 
     #[test]
-    #[should_panic(expected = "expected statement")]
-    fn test_error_while_missing_body() {
-        let mut iter = tokenize("while (1)").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        stmt(&mut iter, &mut vars).unwrap();
+    fn test_call_no_args() {
+        let mut iter = tokenize("foo()").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        assert_eq!(
+            primary(&mut iter, &mut vars).unwrap(),
+            Node::Call {
+                name: "foo".to_string(),
+                args: vec![],
+                span: Span::default(),
+            }
+        );
     }
 
-    //=== For parsing (happy path) ===
     #[test]
-    fn test_stmt_for() {
-        let mut iter = tokenize("for (1;2;3) 4;").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        let node = stmt(&mut iter, &mut vars).unwrap();
+    fn test_call_one_arg() {
+        let mut iter = tokenize("foo(42)").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
         assert_eq!(
-            node,
-            Node::For {
-                init: Box::new(Node::Num { value: 1 }),
-                cond: Box::new(Node::Num { value: 2 }),
-                update: Box::new(Node::Num { value: 3 }),
-                body: Box::new(Node::Num { value: 4 }),
+            primary(&mut iter, &mut vars).unwrap(),
+            Node::Call {
+                name: "foo".to_string(),
+                args: vec![Node::Num {
+                    value: 42,
+                    span: Span::default()
+                }],
+                span: Span::default(),
             }
         );
     }
 
-    //=== For parsing error tests ===
     #[test]
-    #[should_panic(expected = "expected LParen")]
-    fn test_error_for_missing_lparen() {
-        let mut iter = tokenize("for 1;2;3) 4;").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        stmt(&mut iter, &mut vars).unwrap();
+    fn test_call_multiple_args() {
+        let mut iter = tokenize("foo(1,2)").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        assert_eq!(
+            primary(&mut iter, &mut vars).unwrap(),
+            Node::Call {
+                name: "foo".to_string(),
+                args: vec![
+                    Node::Num {
+                        value: 1,
+                        span: Span::default()
+                    },
+                    Node::Num {
+                        value: 2,
+                        span: Span::default()
+                    }
+                ],
+                span: Span::default(),
+            }
+        );
     }
 
+    //=== Miscellaneous error tests ===
     #[test]
-    #[should_panic(expected = "expected Semicolon")]
-    fn test_error_for_missing_semicolon1() {
-        let mut iter = tokenize("for (1 2;3) 4;").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        stmt(&mut iter, &mut vars).unwrap();
+    #[should_panic(expected = "expected RParen")]
+    fn test_error_primary_missing_rparen() {
+        let mut iter = tokenize("(1+2").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        primary(&mut iter, &mut vars).unwrap();
     }
 
     #[test]
     #[should_panic(expected = "expected Semicolon")]
-    fn test_error_for_missing_semicolon2() {
-        let mut iter = tokenize("for (1;2 3) 4;").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
+    fn test_error_stmt_missing_semicolon() {
+        let mut iter = tokenize("42").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
         stmt(&mut iter, &mut vars).unwrap();
     }
 
     #[test]
-    #[should_panic(expected = "expected expression")]
-    fn test_error_for_missing_init() {
-        let mut iter = tokenize("for (;2;3) 4;").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        stmt(&mut iter, &mut vars).unwrap();
+    fn test_unary_plus() {
+        let mut iter = tokenize("+42").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = expr(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            node,
+            Node::Num {
+                value: 42,
+                span: Span::default()
+            }
+        );
     }
 
     #[test]
-    #[should_panic(expected = "expected expression")]
-    fn test_error_for_missing_cond() {
-        let mut iter = tokenize("for (1;;3) 4;").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        stmt(&mut iter, &mut vars).unwrap();
+    fn test_unary_minus() {
+        let mut iter = tokenize("-42").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = expr(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            node,
+            Node::Neg {
+                expr: Box::new(Node::Num {
+                    value: 42,
+                    span: Span::default()
+                }),
+                ty: Type::I64,
+                span: Span::default(),
+            }
+        );
     }
 
     #[test]
-    #[should_panic(expected = "expected expression")]
-    fn test_error_for_missing_update() {
-        let mut iter = tokenize("for (1;2;) 4;").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        stmt(&mut iter, &mut vars).unwrap();
+    fn test_unary_deref() {
+        let mut iter = tokenize("*42").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = expr(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            node,
+            Node::Deref {
+                expr: Box::new(Node::Num {
+                    value: 42,
+                    span: Span::default()
+                }),
+                ty: Type::I64,
+                span: Span::default(),
+            }
+        );
     }
 
     #[test]
-    #[should_panic(expected = "expected RParen")]
-    fn test_error_for_missing_rparen() {
-        let mut iter = tokenize("for (1;2;3 4;").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        stmt(&mut iter, &mut vars).unwrap();
+    fn test_unary_addr() {
+        let mut iter = tokenize("&42").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = expr(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            node,
+            Node::Addr {
+                expr: Box::new(Node::Num {
+                    value: 42,
+                    span: Span::default()
+                }),
+                mutable: false,
+                span: Span::default(),
+            }
+        );
     }
 
     #[test]
-    #[should_panic(expected = "expected statement")]
-    fn test_error_for_missing_body() {
-        let mut iter = tokenize("for (1;2;3)").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        stmt(&mut iter, &mut vars).unwrap();
+    fn test_unary_addr_mut() {
+        let mut iter = tokenize("&mut x").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        vars.declare("x", 1, true);
+        let node = expr(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            node,
+            Node::Addr {
+                expr: Box::new(Node::Var {
+                    offset: 8,
+                    span: Span::default()
+                }),
+                mutable: true,
+                span: Span::default(),
+            }
+        );
     }
 
-    //=== Miscellaneous parsing tests ===
     #[test]
-    fn test_primary() {
-        let mut iter = tokenize("42").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        let node = primary(&mut iter, &mut vars).unwrap();
-        assert_eq!(node, Node::Num { value: 42 });
+    fn test_unary_not() {
+        let mut iter = tokenize("!!1").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = expr(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            node,
+            Node::Not {
+                expr: Box::new(Node::Not {
+                    expr: Box::new(Node::Num {
+                        value: 1,
+                        span: Span::default()
+                    }),
+                    bitwise: false,
+                    ty: Type::I64,
+                    span: Span::default(),
+                }),
+                bitwise: false,
+                ty: Type::I64,
+                span: Span::default(),
+            }
+        );
     }
 
     #[test]
-    fn test_expr_add_sub() {
-        let mut iter = tokenize("1+2").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
+    fn test_cast() {
+        // `as` binds looser than unary minus and tighter than `*`
+        let mut iter = tokenize("-1 as u8 as i32 * 2")
+            .unwrap()
+            .into_iter()
+            .peekable();
+        let mut vars = SymbolTable::new();
         let node = expr(&mut iter, &mut vars).unwrap();
         assert_eq!(
             node,
             Node::BinaryOp {
-                op: OpKind::Add,
-                lhs: Box::new(Node::Num { value: 1 }),
-                rhs: Box::new(Node::Num { value: 2 }),
+                op: OpKind::Mul,
+                lhs: Box::new(Node::Cast {
+                    expr: Box::new(Node::Cast {
+                        expr: Box::new(Node::Neg {
+                            expr: Box::new(Node::Num {
+                                value: 1,
+                                span: Span::default()
+                            }),
+                            ty: Type::I64,
+                            span: Span::default(),
+                        }),
+                        ty: Type::U8,
+                        span: Span::default(),
+                    }),
+                    ty: Type::I32,
+                    span: Span::default(),
+                }),
+                rhs: Box::new(Node::Num {
+                    value: 2,
+                    span: Span::default()
+                }),
+                ty: Type::I64,
+                span: Span::default(),
             }
         );
     }
 
     #[test]
-    fn test_expr_precedence() {
-        let mut iter = tokenize("1+2*3").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        let node = expr(&mut iter, &mut vars).unwrap();
-        let expected = Node::BinaryOp {
-            op: OpKind::Add,
-            lhs: Box::new(Node::Num { value: 1 }),
-            rhs: Box::new(Node::BinaryOp {
-                op: OpKind::Mul,
-                lhs: Box::new(Node::Num { value: 2 }),
-                rhs: Box::new(Node::Num { value: 3 }),
-            }),
-        };
-        assert_eq!(node, expected);
+    fn test_primary_string() {
+        let mut iter = tokenize(r#""Hello, world!""#)
+            .unwrap()
+            .into_iter()
+            .peekable();
+        let mut vars = SymbolTable::new();
+        let node = primary(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            node,
+            Node::StringLiteral {
+                value: "Hello, world!".to_string(),
+                span: Span::default()
+            }
+        );
     }
 
     #[test]
-    fn test_expr_parens_mul() {
-        let mut iter = tokenize("(1+2)*3").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        let node = expr(&mut iter, &mut vars).unwrap();
-        let expected = Node::BinaryOp {
-            op: OpKind::Mul,
-            lhs: Box::new(Node::BinaryOp {
-                op: OpKind::Add,
-                lhs: Box::new(Node::Num { value: 1 }),
-                rhs: Box::new(Node::Num { value: 2 }),
-            }),
-            rhs: Box::new(Node::Num { value: 3 }),
-        };
-        assert_eq!(node, expected);
+    fn test_array_assignment_and_indexing() {
+        let mut iter = tokenize("fn main() { let arr = [1, 2, 3]; return arr[2]; }")
+            .unwrap()
+            .into_iter()
+            .peekable();
+        let mut vars = SymbolTable::new();
+        let node = program(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            node,
+            Node::Function {
+                name: "main".to_string(),
+                args: vec![],
+                arg_types: vec![],
+                ret: None,
+                body: Box::new(Node::Seq {
+                    first: Box::new(Node::Semi {
+                        expr: Box::new(Node::Let {
+                            ty: None,
+                            init: Box::new(Node::ArrayAssign {
+                                offset: 8,
+                                elements: vec![
+                                    Node::Num {
+                                        value: 1,
+                                        span: Span::default()
+                                    },
+                                    Node::Num {
+                                        value: 2,
+                                        span: Span::default()
+                                    },
+                                    Node::Num {
+                                        value: 3,
+                                        span: Span::default()
+                                    },
+                                ],
+                                span: Span::default(),
+                            }),
+                            span: Span::default()
+                        }),
+                        span: Span::default()
+                    }),
+                    second: Box::new(Node::Semi {
+                        expr: Box::new(Node::Return {
+                            expr: Some(Box::new(Node::Index {
+                                expr: Box::new(Node::Var {
+                                    offset: 8,
+                                    span: Span::default()
+                                }),
+                                index: Box::new(Node::Num {
+                                    value: 2,
+                                    span: Span::default()
+                                }),
+                                ty: Type::I64,
+                                span: Span::default(),
+                            })),
+                            span: Span::default(),
+                        }),
+                        span: Span::default(),
+                    }),
+                    span: Span::default(),
+                }),
+                locals: HashMap::new(),
+                span: Span::default(),
+            }
+        );
     }
 
     #[test]
-    fn test_primary_parens() {
-        let mut iter = tokenize("(42)").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        let node = primary(&mut iter, &mut vars).unwrap();
-        assert_eq!(node, Node::Num { value: 42 });
+    fn test_program_functions_have_separate_frames() {
+        let mut iter = tokenize("fn foo(a: i32) { let b = a; } fn main() { let b = 1; }")
+            .unwrap()
+            .into_iter()
+            .peekable();
+        let mut vars = SymbolTable::new();
+        let node = program(&mut iter, &mut vars).unwrap();
+        assert_eq!(
+            node,
+            Node::Seq {
+                first: Box::new(Node::Function {
+                    name: "foo".to_string(),
+                    args: vec![Node::Var {
+                        offset: 8,
+                        span: Span::default()
+                    }],
+                    arg_types: vec![Type::I32],
+                    ret: None,
+                    body: Box::new(Node::Semi {
+                        expr: Box::new(Node::Let {
+                            ty: None,
+                            init: Box::new(Node::Assign {
+                                lhs: Box::new(Node::Var {
+                                    offset: 16,
+                                    span: Span::default()
+                                }),
+                                rhs: Box::new(Node::Var {
+                                    offset: 8,
+                                    span: Span::default()
+                                }),
+                                span: Span::default(),
+                            }),
+                            span: Span::default()
+                        }),
+                        span: Span::default()
+                    }),
+                    locals: HashMap::new(),
+                    span: Span::default(),
+                }),
+                second: Box::new(Node::Function {
+                    name: "main".to_string(),
+                    args: vec![],
+                    arg_types: vec![],
+                    ret: None,
+                    body: Box::new(Node::Semi {
+                        expr: Box::new(Node::Let {
+                            ty: None,
+                            init: Box::new(Node::Assign {
+                                lhs: Box::new(Node::Var {
+                                    offset: 8,
+                                    span: Span::default()
+                                }),
+                                rhs: Box::new(Node::Num {
+                                    value: 1,
+                                    span: Span::default()
+                                }),
+                                span: Span::default(),
+                            }),
+                            span: Span::default()
+                        }),
+                        span: Span::default()
+                    }),
+                    locals: HashMap::new(),
+                    span: Span::default(),
+                }),
+                span: Span::default(),
+            }
+        );
     }
 
     #[test]
-    fn test_expr_nested_parens() {
-        let mut iter = tokenize("((1+2))").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        let node = expr(&mut iter, &mut vars).unwrap();
+    fn test_stmt_block_scope_ends() {
+        let mut iter = tokenize("{ { let x = 1; } let x = 2; x }")
+            .unwrap()
+            .into_iter()
+            .peekable();
+        let mut vars = SymbolTable::new();
+        let node = stmt(&mut iter, &mut vars).unwrap();
         assert_eq!(
             node,
-            Node::BinaryOp {
-                op: OpKind::Add,
-                lhs: Box::new(Node::Num { value: 1 }),
-                rhs: Box::new(Node::Num { value: 2 }),
+            Node::Seq {
+                first: Box::new(Node::Seq {
+                    first: Box::new(Node::Semi {
+                        expr: Box::new(Node::Let {
+                            ty: None,
+                            init: Box::new(Node::Assign {
+                                lhs: Box::new(Node::Var {
+                                    offset: 8,
+                                    span: Span::default()
+                                }),
+                                rhs: Box::new(Node::Num {
+                                    value: 1,
+                                    span: Span::default()
+                                }),
+                                span: Span::default(),
+                            }),
+                            span: Span::default()
+                        }),
+                        span: Span::default()
+                    }),
+                    second: Box::new(Node::Semi {
+                        expr: Box::new(Node::Let {
+                            ty: None,
+                            init: Box::new(Node::Assign {
+                                lhs: Box::new(Node::Var {
+                                    offset: 16,
+                                    span: Span::default()
+                                }),
+                                rhs: Box::new(Node::Num {
+                                    value: 2,
+                                    span: Span::default()
+                                }),
+                                span: Span::default(),
+                            }),
+                            span: Span::default()
+                        }),
+                        span: Span::default()
+                    }),
+                    span: Span::default(),
+                }),
+                second: Box::new(Node::Var {
+                    offset: 16,
+                    span: Span::default()
+                }),
+                span: Span::default(),
             }
         );
     }
 
     #[test]
-    fn test_expr_assign() {
-        let mut iter = tokenize("1=2").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        let node = expr(&mut iter, &mut vars).unwrap();
+    fn test_stmt_block_shadowing() {
+        let mut iter = tokenize("{ let x = 1; { let x = x; x } x }")
+            .unwrap()
+            .into_iter()
+            .peekable();
+        let mut vars = SymbolTable::new();
+        let node = stmt(&mut iter, &mut vars).unwrap();
         assert_eq!(
             node,
-            Node::Assign {
-                lhs: Box::new(Node::Num { value: 1 }),
-                rhs: Box::new(Node::Num { value: 2 }),
+            Node::Seq {
+                first: Box::new(Node::Seq {
+                    first: Box::new(Node::Semi {
+                        expr: Box::new(Node::Let {
+                            ty: None,
+                            init: Box::new(Node::Assign {
+                                lhs: Box::new(Node::Var {
+                                    offset: 8,
+                                    span: Span::default()
+                                }),
+                                rhs: Box::new(Node::Num {
+                                    value: 1,
+                                    span: Span::default()
+                                }),
+                                span: Span::default(),
+                            }),
+                            span: Span::default()
+                        }),
+                        span: Span::default()
+                    }),
+                    second: Box::new(Node::Seq {
+                        first: Box::new(Node::Semi {
+                            expr: Box::new(Node::Let {
+                                ty: None,
+                                init: Box::new(Node::Assign {
+                                    lhs: Box::new(Node::Var {
+                                        offset: 16,
+                                        span: Span::default()
+                                    }),
+                                    rhs: Box::new(Node::Var {
+                                        offset: 8,
+                                        span: Span::default()
+                                    }),
+                                    span: Span::default(),
+                                }),
+                                span: Span::default()
+                            }),
+                            span: Span::default()
+                        }),
+                        second: Box::new(Node::Var {
+                            offset: 16,
+                            span: Span::default()
+                        }),
+                        span: Span::default(),
+                    }),
+                    span: Span::default(),
+                }),
+                second: Box::new(Node::Var {
+                    offset: 8,
+                    span: Span::default()
+                }),
+                span: Span::default(),
             }
         );
     }
 
     #[test]
-    fn test_expr_eq_ne() {
-        let mut it1 = tokenize("1==2").unwrap().into_iter().peekable();
-        let mut vars1 = Variable::new("".to_string(), 0, None);
-        let n1 = expr(&mut it1, &mut vars1).unwrap();
-        assert_eq!(
-            n1,
-            Node::BinaryOp {
-                op: OpKind::Eq,
-                lhs: Box::new(Node::Num { value: 1 }),
-                rhs: Box::new(Node::Num { value: 2 }),
-            }
-        );
-        let mut it2 = tokenize("1!=2").unwrap().into_iter().peekable();
-        let mut vars2 = Variable::new("".to_string(), 0, None);
-        let n2 = expr(&mut it2, &mut vars2).unwrap();
+    fn test_stmt_let_if_expression() {
+        let mut iter = tokenize("let x = if 1 < 2 { 3 } else { 4 };")
+            .unwrap()
+            .into_iter()
+            .peekable();
+        let mut vars = SymbolTable::new();
+        let node = stmt(&mut iter, &mut vars).unwrap();
         assert_eq!(
-            n2,
-            Node::BinaryOp {
-                op: OpKind::Ne,
-                lhs: Box::new(Node::Num { value: 1 }),
-                rhs: Box::new(Node::Num { value: 2 }),
+            node,
+            Node::Semi {
+                expr: Box::new(Node::Let {
+                    ty: None,
+                    init: Box::new(Node::Assign {
+                        lhs: Box::new(Node::Var {
+                            offset: 8,
+                            span: Span::default()
+                        }),
+                        rhs: Box::new(Node::If {
+                            cond: Box::new(Node::BinaryOp {
+                                op: OpKind::Lt,
+                                lhs: Box::new(Node::Num {
+                                    value: 1,
+                                    span: Span::default()
+                                }),
+                                rhs: Box::new(Node::Num {
+                                    value: 2,
+                                    span: Span::default()
+                                }),
+                                ty: Type::I64,
+                                span: Span::default(),
+                            }),
+                            then_stmt: Box::new(Node::Num {
+                                value: 3,
+                                span: Span::default()
+                            }),
+                            else_stmt: Some(Box::new(Node::Num {
+                                value: 4,
+                                span: Span::default()
+                            })),
+                            span: Span::default(),
+                        }),
+                        span: Span::default(),
+                    }),
+                    span: Span::default()
+                }),
+                span: Span::default(),
             }
         );
     }
 
     #[test]
-    fn test_expr_relational() {
-        let mut vars = Variable::new("".to_string(), 0, None);
-        let mut it_lt = tokenize("1<2").unwrap().into_iter().peekable();
-        assert_eq!(
-            expr(&mut it_lt, &mut vars).unwrap(),
-            Node::BinaryOp {
-                op: OpKind::Lt,
-                lhs: Box::new(Node::Num { value: 1 }),
-                rhs: Box::new(Node::Num { value: 2 })
-            }
-        );
-        let mut vars2 = Variable::new("".to_string(), 0, None);
-        let mut it_gt = tokenize("2>1").unwrap().into_iter().peekable();
+    fn test_stmt_trailing_semicolon_discards_value() {
+        let mut iter = tokenize("{ 1; 2 }").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = stmt(&mut iter, &mut vars).unwrap();
         assert_eq!(
-            expr(&mut it_gt, &mut vars2).unwrap(),
-            Node::BinaryOp {
-                op: OpKind::Gt,
-                lhs: Box::new(Node::Num { value: 2 }),
-                rhs: Box::new(Node::Num { value: 1 })
+            node,
+            Node::Seq {
+                first: Box::new(Node::Semi {
+                    expr: Box::new(Node::Num {
+                        value: 1,
+                        span: Span::default()
+                    }),
+                    span: Span::default(),
+                }),
+                second: Box::new(Node::Num {
+                    value: 2,
+                    span: Span::default()
+                }),
+                span: Span::default(),
             }
         );
-        let mut vars3 = Variable::new("".to_string(), 0, None);
-        let mut it_le = tokenize("1<=1").unwrap().into_iter().peekable();
+    }
+
+    #[test]
+    fn test_stmt_let_type_annotation() {
+        let mut iter = tokenize("let x: i32 = 5;").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = stmt(&mut iter, &mut vars).unwrap();
         assert_eq!(
-            expr(&mut it_le, &mut vars3).unwrap(),
-            Node::BinaryOp {
-                op: OpKind::Le,
-                lhs: Box::new(Node::Num { value: 1 }),
-                rhs: Box::new(Node::Num { value: 1 })
+            node,
+            Node::Semi {
+                expr: Box::new(Node::Let {
+                    ty: Some(Type::I32),
+                    init: Box::new(Node::Assign {
+                        lhs: Box::new(Node::Var {
+                            offset: 8,
+                            span: Span::default()
+                        }),
+                        rhs: Box::new(Node::Num {
+                            value: 5,
+                            span: Span::default()
+                        }),
+                        span: Span::default(),
+                    }),
+                    span: Span::default(),
+                }),
+                span: Span::default(),
             }
         );
-        let mut vars4 = Variable::new("".to_string(), 0, None);
-        let mut it_ge = tokenize("2>=2").unwrap().into_iter().peekable();
+    }
+
+    #[test]
+    fn test_stmt_let_array_repeat() {
+        let mut iter = tokenize("let a: [i32; 8] = [0; 8];")
+            .unwrap()
+            .into_iter()
+            .peekable();
+        let mut vars = SymbolTable::new();
+        let node = stmt(&mut iter, &mut vars).unwrap();
         assert_eq!(
-            expr(&mut it_ge, &mut vars4).unwrap(),
-            Node::BinaryOp {
-                op: OpKind::Ge,
-                lhs: Box::new(Node::Num { value: 2 }),
-                rhs: Box::new(Node::Num { value: 2 })
+            node,
+            Node::Semi {
+                expr: Box::new(Node::Let {
+                    ty: Some(Type::Array(Box::new(Type::I32), 8)),
+                    init: Box::new(Node::ArrayRepeat {
+                        offset: 8,
+                        value: Box::new(Node::Num {
+                            value: 0,
+                            span: Span::default()
+                        }),
+                        len: 8,
+                        span: Span::default(),
+                    }),
+                    span: Span::default(),
+                }),
+                span: Span::default(),
             }
         );
+        // the array takes one slot per element
+        assert_eq!(vars.declare("b", 1, false), 72);
     }
 
+    //=== Structs ===
     #[test]
-    fn test_ident_offset() {
-        let mut iter = tokenize("a").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        let node = primary(&mut iter, &mut vars).unwrap();
-        assert_eq!(node, Node::Var { offset: 8 });
+    fn test_struct_item() {
+        let src = "struct Pos { x: i32, y: i32 } struct Piece { pos: Pos, moves: [i32; 4] }";
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        program(&mut iter, &mut vars).unwrap();
+        let piece = Type::Struct("Piece".to_string());
+        assert_eq!(piece.slots(vars.types()), Some(6));
+        assert_eq!(
+            field_layout(vars.types(), "Piece", "moves"),
+            Some((16, &Type::Array(Box::new(Type::I32), 4)))
+        );
     }
 
     #[test]
-    fn test_ident_repeated_offset() {
-        let mut iter = tokenize("a a").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        let first = primary(&mut iter, &mut vars).unwrap();
-        let second = primary(&mut iter, &mut vars).unwrap();
-        assert_eq!(first, Node::Var { offset: 8 });
-        assert_eq!(second, Node::Var { offset: 8 });
+    fn test_stmt_let_struct_literal() {
+        let mut iter = tokenize("let p = P { cells: [1, 2], x: 3 };")
+            .unwrap()
+            .into_iter()
+            .peekable();
+        let mut vars = SymbolTable::new();
+        vars.define_struct(
+            "P",
+            vec![
+                ("x".to_string(), Type::I32),
+                ("cells".to_string(), Type::Array(Box::new(Type::I32), 2)),
+            ],
+        );
+        let node = stmt(&mut iter, &mut vars).unwrap();
+        let num = |value| Node::Num {
+            value,
+            span: Span::default(),
+        };
+        assert_eq!(
+            node,
+            Node::Semi {
+                expr: Box::new(Node::Let {
+                    ty: None,
+                    init: Box::new(Node::StructLit {
+                        name: "P".to_string(),
+                        offset: 8,
+                        slots: 3,
+                        fields: vec![
+                            (
+                                "cells".to_string(),
+                                Node::ArrayAssign {
+                                    offset: 16,
+                                    elements: vec![num(1), num(2)],
+                                    span: Span::default(),
+                                }
+                            ),
+                            (
+                                "x".to_string(),
+                                Node::Assign {
+                                    lhs: Box::new(Node::Var {
+                                        offset: 8,
+                                        span: Span::default()
+                                    }),
+                                    rhs: Box::new(num(3)),
+                                    span: Span::default(),
+                                }
+                            ),
+                        ],
+                        span: Span::default(),
+                    }),
+                    span: Span::default(),
+                }),
+                span: Span::default(),
+            }
+        );
+        // the struct takes one slot per field element
+        assert_eq!(vars.find("p"), Some(8));
+        assert_eq!(vars.declare("b", 1, false), 32);
     }
 
     #[test]
-    fn test_assign_ident() {
-        let mut iter = tokenize("a=1").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
+    fn test_postfix_field_and_index() {
+        let mut iter = tokenize("p.cells[1]").unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        vars.declare("p", 3, false);
         let node = expr(&mut iter, &mut vars).unwrap();
         assert_eq!(
             node,
-            Node::Assign {
-                lhs: Box::new(Node::Var { offset: 8 }),
-                rhs: Box::new(Node::Num { value: 1 }),
+            Node::Index {
+                expr: Box::new(Node::Field {
+                    expr: Box::new(Node::Var {
+                        offset: 8,
+                        span: Span::default()
+                    }),
+                    name: "cells".to_string(),
+                    offset: 0,
+                    ty: Type::I64,
+                    span: Span::default(),
+                }),
+                index: Box::new(Node::Num {
+                    value: 1,
+                    span: Span::default()
+                }),
+                ty: Type::I64,
+                span: Span::default(),
             }
         );
     }
 
     #[test]
-    fn test_call_no_args() {
-        let mut iter = tokenize("foo()").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
+    fn test_error_struct_literal_fields() {
+        let src = "struct P { x: i32, y: i32 }
+                   fn main() { let p = P { x: 1, z: 2, x: 3 }; p.x = 4; let r = &mut p.y; }";
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let errs = program(&mut iter, &mut vars).unwrap_err();
+        let msgs: Vec<&str> = errs.iter().map(|e| e.msg.as_str()).collect();
         assert_eq!(
-            primary(&mut iter, &mut vars).unwrap(),
-            Node::Call {
-                name: "foo".to_string(),
-                args: vec![],
-            }
+            msgs,
+            vec![
+                "struct `P` has no field named `z`",
+                "field `x` specified more than once",
+                "missing field `y` in initializer of `P`",
+                "cannot assign to `p.x`, as `p` is not declared as mutable; consider making this binding mutable: `mut p`",
+                "cannot borrow `p.y` as mutable, as `p` is not declared as mutable; consider making this binding mutable: `mut p`",
+            ]
         );
     }
 
     #[test]
-    fn test_call_one_arg() {
-        let mut iter = tokenize("foo(42)").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
+    fn test_error_unknown_type() {
+        let src = "struct P { q: Q } fn f(r: &Q) {}";
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let errs = program(&mut iter, &mut vars).unwrap_err();
+        let msgs: Vec<&str> = errs.iter().map(|e| e.msg.as_str()).collect();
         assert_eq!(
-            primary(&mut iter, &mut vars).unwrap(),
-            Node::Call {
-                name: "foo".to_string(),
-                args: vec![Node::Num { value: 42 }],
-            }
+            msgs,
+            vec![
+                "cannot find type `Q` in this scope",
+                "cannot find type `Q` in this scope",
+            ]
         );
     }
 
+    //=== Enums and match ===
     #[test]
-    fn test_call_multiple_args() {
-        let mut iter = tokenize("foo(1,2)").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
+    fn test_enum_item() {
+        let src = "enum Cell { Empty, Stone(i32, i32) } fn main() { let c = Cell::Stone(1, 2); }";
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        program(&mut iter, &mut vars).unwrap();
+        let cell = Type::Enum("Cell".to_string());
+        // the tag and the largest variant's fields
+        assert_eq!(cell.slots(vars.types()), Some(3));
         assert_eq!(
-            primary(&mut iter, &mut vars).unwrap(),
-            Node::Call {
-                name: "foo".to_string(),
-                args: vec![Node::Num { value: 1 }, Node::Num { value: 2 }],
-            }
+            vars.types().variant("Cell", "Stone"),
+            Some((1, &[Type::I32, Type::I32][..]))
         );
+        assert_eq!(vars.types().variant("Cell", "Wall"), None);
     }
 
-    //=== Miscellaneous error tests ===
     #[test]
-    #[should_panic(expected = "expected RParen")]
-    fn test_error_primary_missing_rparen() {
-        let mut iter = tokenize("(1+2").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        primary(&mut iter, &mut vars).unwrap();
+    fn test_match_patterns() {
+        let mut iter = tokenize("match n { 1 | 3..=5 => 1, x => x }")
+            .unwrap()
+            .into_iter()
+            .peekable();
+        let mut vars = SymbolTable::new();
+        vars.declare("n", 1, false);
+        let node = expr(&mut iter, &mut vars).unwrap();
+        let Node::Match { arms, .. } = node else {
+            panic!("expected a match");
+        };
+        assert_eq!(
+            arms[0].pats,
+            vec![
+                Pattern::Range { lo: 1, hi: 1 },
+                Pattern::Range { lo: 3, hi: 5 }
+            ]
+        );
+        assert_eq!(arms[1].pats, vec![Pattern::Bind { offset: 16 }]);
+        // the binding is gone after its arm
+        assert_eq!(vars.find("x"), None);
     }
 
     #[test]
-    #[should_panic(expected = "expected Semicolon")]
-    fn test_error_stmt_missing_semicolon() {
-        let mut iter = tokenize("42").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        stmt(&mut iter, &mut vars).unwrap();
+    fn test_bool_literals_and_patterns() {
+        let src = "const ON: bool = !false && 1 < 2; const MASK: i32 = !0;
+                   fn main(b: bool) { match b { true => 1, false => ON } }";
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = program(&mut iter, &mut vars).unwrap();
+        // `!` is logical on a boolean and bitwise on an integer
+        let constant = |value| Global::Const {
+            ty: Type::Bool,
+            value: ConstValue::Int(value),
+        };
+        assert_eq!(vars.global("ON"), Some(&constant(1)));
+        assert_eq!(
+            vars.global("MASK"),
+            Some(&Global::Const {
+                ty: Type::I32,
+                value: ConstValue::Int(-1)
+            })
+        );
+        let Node::Seq { second, .. } = node else {
+            panic!("expected items")
+        };
+        let Node::Function { body, .. } = *second else {
+            panic!("expected function")
+        };
+        let Node::Match { arms, .. } = *body else {
+            panic!("expected match")
+        };
+        assert_eq!(arms[0].pats, vec![Pattern::Bool { value: true }]);
+        assert_eq!(arms[1].pats, vec![Pattern::Bool { value: false }]);
+        assert!(matches!(arms[1].body, Node::Bool { value: true, .. }));
     }
 
     #[test]
-    fn test_unary_plus() {
-        let mut iter = tokenize("+42").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        let node = expr(&mut iter, &mut vars).unwrap();
-        assert_eq!(node, Node::Num { value: 42 });
+    fn test_error_patterns() {
+        let src = "enum E { A(i32), B }
+                   fn main(n: i32) { match n { 5..=1 => 0, E::A(x, y) => 1, E::C => 2,
+                   E::A(x) | E::B => x, _ => 3 }; }";
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let errs = program(&mut iter, &mut vars).unwrap_err();
+        let msgs: Vec<&str> = errs.iter().map(|e| e.msg.as_str()).collect();
+        assert_eq!(
+            msgs,
+            vec![
+                "lower range bound must be less than or equal to upper",
+                "this pattern has 2 field(s), but variant `E::A` has 1 field(s)",
+                "no variant named `C` found for enum `E`",
+                "variable `x` is not bound in all patterns",
+            ]
+        );
     }
 
     #[test]
-    fn test_unary_minus() {
-        let mut iter = tokenize("-42").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        let node = expr(&mut iter, &mut vars).unwrap();
+    fn test_const_and_static_items() {
+        let src = "const N: i32 = 2 * 4; const CELLS: i32 = N * N; const NAME: &str = \"x\";
+                   static mut GRID: [i32; CELLS] = [0; CELLS]; static T: [i32; 2] = [N, 3];
+                   fn main() { let n = N; GRID[n] = 1; }";
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        program(&mut iter, &mut vars).unwrap();
         assert_eq!(
-            node,
-            Node::BinaryOp {
-                op: OpKind::Sub,
-                lhs: Box::new(Node::Num { value: 0 }),
-                rhs: Box::new(Node::Num { value: 42 }),
-            }
+            vars.global("CELLS"),
+            Some(&Global::Const {
+                ty: Type::I32,
+                value: ConstValue::Int(64)
+            })
+        );
+        assert_eq!(
+            vars.global("NAME"),
+            Some(&Global::Const {
+                ty: Type::Ref(Box::new(Type::Str)),
+                value: ConstValue::Str("x".to_string())
+            })
+        );
+        assert_eq!(
+            vars.global("GRID"),
+            Some(&Global::Static {
+                ty: Type::Array(Box::new(Type::I32), 64),
+                mutable: true
+            })
         );
     }
 
     #[test]
-    fn test_unary_deref() {
-        let mut iter = tokenize("*42").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        let node = expr(&mut iter, &mut vars).unwrap();
+    fn test_const_refers_to_later_const() {
+        let src = "const A: i32 = B + 1; fn main() -> i32 { A + C } const B: i32 = C * 2;
+                   const C: i32 = 2;";
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = program(&mut iter, &mut vars).unwrap();
         assert_eq!(
-            node,
-            Node::Deref {
-                expr: Box::new(Node::Num { value: 42 }),
-            }
+            vars.global("A"),
+            Some(&Global::Const {
+                ty: Type::I32,
+                value: ConstValue::Int(5)
+            })
         );
+        // the items keep their source order
+        let Node::Seq { first, .. } = node else {
+            panic!("expected a sequence of items");
+        };
+        let Node::Seq { first, second, .. } = *first else {
+            panic!("expected a sequence of items");
+        };
+        assert!(matches!(*first, Node::Seq { .. }));
+        assert!(matches!(*second, Node::Const { ref name, .. } if name == "B"));
     }
 
     #[test]
-    fn test_unary_addr() {
-        let mut iter = tokenize("&42").unwrap().into_iter().peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        let node = expr(&mut iter, &mut vars).unwrap();
+    fn test_items_used_before_their_declaration() {
+        let src = "fn main() -> i32 { let p = Point { x: 1, y: 2 }; unsafe { N += p.x; } N }
+                   enum Shape { Dot, At(&Point) } struct Point { x: i32, y: i32 }
+                   static mut N: i32 = M; const M: i32 = 4;";
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        program(&mut iter, &mut vars).unwrap();
         assert_eq!(
-            node,
-            Node::Addr {
-                expr: Box::new(Node::Num { value: 42 }),
-            }
+            vars.global("N"),
+            Some(&Global::Static {
+                ty: Type::I32,
+                mutable: true
+            })
         );
+        assert!(vars.types().contains("Point"));
+        assert!(vars.types().contains("Shape"));
     }
 
     #[test]
-    fn test_primary_string() {
-        let mut iter = tokenize(r#""Hello, world!""#)
-            .unwrap()
-            .into_iter()
-            .peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        let node = primary(&mut iter, &mut vars).unwrap();
+    fn test_unsafe_block() {
+        let src = "static mut N: i32 = 0; fn main() -> i32 { unsafe { N += 1; } let n = unsafe { N }; n }";
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        assert!(program(&mut iter, &mut vars).is_ok());
+    }
+
+    #[test]
+    fn test_error_const_and_static_items() {
+        let src = "const A: i32 = 2147483647 + 1; const B: i32 = 7 % 0; fn f() -> i32 { 1 }
+                   const C: i32 = f(); static S: i32 = 1; const D: i32 = S; static S: i32 = 0;
+                   fn main() { S = 2; let r = &mut S; }";
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let errs = program(&mut iter, &mut vars).unwrap_err();
+        let msgs: Vec<&str> = errs.iter().map(|e| e.msg.as_str()).collect();
         assert_eq!(
-            node,
-            Node::StringLiteral {
-                value: "Hello, world!".to_string()
-            }
+            msgs,
+            vec![
+                "evaluation of constant value failed: attempt to add with overflow",
+                "evaluation of constant value failed: attempt to calculate the remainder with a divisor of zero",
+                "cannot call non-const function `f` in constants",
+                "constants cannot refer to statics",
+                "the name `S` is defined multiple times",
+                "cannot assign to immutable static item `S`",
+                "cannot borrow immutable static item `S` as mutable",
+            ]
         );
     }
 
     #[test]
-    fn test_array_assignment_and_indexing() {
-        let mut iter = tokenize("fn main() { let arr = [1, 2, 3]; return arr[2]; }")
-            .unwrap()
-            .into_iter()
-            .peekable();
-        let mut vars = Variable::new("".to_string(), 0, None);
-        let node = program(&mut iter, &mut vars).unwrap();
+    fn test_integer_const_items() {
+        let src =
+            "const A: u8 = 200 + 55; const B: i8 = -128; const C: u8 = (300 as u8) + !0 as u8;
+                   const D: u16 = 65535 * 2; const E: i8 = 128; fn main() {}";
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let errs = program(&mut iter, &mut vars).unwrap_err();
+        let msgs: Vec<&str> = errs.iter().map(|e| e.msg.as_str()).collect();
         assert_eq!(
-            node,
-            Node::Function {
-                name: "main".to_string(),
-                args: vec![],
-                body: Box::new(Node::Seq {
-                    first: Box::new(Node::ArrayAssign {
-                        offset: 8,
-                        elements: vec![
-                            Node::Num { value: 1 },
-                            Node::Num { value: 2 },
-                            Node::Num { value: 3 },
-                        ],
-                    }),
-                    second: Box::new(Node::Return {
-                        expr: Box::new(Node::BinaryOp {
-                            op: OpKind::Sub,
-                            lhs: Box::new(Node::Addr {
-                                expr: Box::new(Node::Var { offset: 8 }),
-                            }),
-                            rhs: Box::new(Node::BinaryOp {
-                                op: OpKind::Mul,
-                                lhs: Box::new(Node::Num { value: 2 }),
-                                rhs: Box::new(Node::Num { value: 8 }),
-                            }),
-                        }),
-                    }),
-                }),
-            }
+            msgs,
+            vec![
+                "evaluation of constant value failed: attempt to add with overflow",
+                "evaluation of constant value failed: attempt to multiply with overflow",
+                "literal out of range for `i8`",
+            ]
+        );
+        assert_eq!(
+            vars.global("A"),
+            Some(&Global::Const {
+                ty: Type::U8,
+                value: ConstValue::Int(255)
+            })
+        );
+        assert_eq!(
+            vars.global("B"),
+            Some(&Global::Const {
+                ty: Type::I8,
+                value: ConstValue::Int(-128)
+            })
         );
     }
 }
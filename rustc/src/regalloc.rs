@@ -0,0 +1,329 @@
+use crate::ir::{Function, Inst, VReg};
+use std::collections::BTreeSet;
+
+/// Where a virtual register lives for its whole lifetime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Location {
+    /// index into the register list handed to `allocate`
+    Reg(usize),
+    /// index of an 8-byte spill slot in the frame
+    Spill(usize),
+}
+
+/// Machine registers available to the allocator, split by calling convention.
+pub(crate) struct RegisterSet {
+    /// registers a call may clobber
+    pub caller_saved: &'static [&'static str],
+    /// registers a call preserves; the function must save them before use
+    pub callee_saved: &'static [&'static str],
+}
+
+impl RegisterSet {
+    pub(crate) fn name(&self, reg: usize) -> &'static str {
+        if reg < self.caller_saved.len() {
+            self.caller_saved[reg]
+        } else {
+            self.callee_saved[reg - self.caller_saved.len()]
+        }
+    }
+
+    fn is_callee_saved(&self, reg: usize) -> bool {
+        reg >= self.caller_saved.len()
+    }
+
+    fn len(&self) -> usize {
+        self.caller_saved.len() + self.callee_saved.len()
+    }
+}
+
+/// Result of register allocation for one function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Allocation {
+    /// location of every virtual register, indexed by `VReg.0`
+    pub locations: Vec<Location>,
+    /// callee-saved registers written by the function, in register order
+    pub callee_saved: Vec<usize>,
+    /// number of spill slots needed
+    pub spill_slots: usize,
+}
+
+impl Allocation {
+    pub(crate) fn location(&self, v: VReg) -> Location {
+        self.locations[v.0]
+    }
+}
+
+// Live range of a virtual register over the linearised instruction positions.
+#[derive(Debug, Clone, Copy)]
+struct Interval {
+    vreg: VReg,
+    start: usize,
+    end: usize,
+    // whether a call happens while the value is live
+    crosses_call: bool,
+}
+
+// Compute one interval per virtual register. Position 0 is the function entry
+// where parameters are defined; every instruction and terminator takes the
+// next position in block layout order.
+fn live_intervals(func: &Function) -> Vec<Interval> {
+    let nblocks = func.blocks.len();
+    // per-block upward-exposed uses and definitions
+    let mut gen_sets = vec![BTreeSet::new(); nblocks];
+    let mut kill_sets = vec![BTreeSet::new(); nblocks];
+    for (b, block) in func.blocks.iter().enumerate() {
+        let uses = block
+            .insts
+            .iter()
+            .map(|inst| (inst.uses(), inst.def()))
+            .chain(std::iter::once((block.term.uses(), None)));
+        for (uses, def) in uses {
+            for u in uses {
+                if !kill_sets[b].contains(&u) {
+                    gen_sets[b].insert(u);
+                }
+            }
+            if let Some(d) = def {
+                kill_sets[b].insert(d);
+            }
+        }
+    }
+    // iterate live-in/live-out to a fixed point
+    let mut live_in: Vec<BTreeSet<VReg>> = vec![BTreeSet::new(); nblocks];
+    let mut live_out: Vec<BTreeSet<VReg>> = vec![BTreeSet::new(); nblocks];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for b in (0..nblocks).rev() {
+            let mut out = BTreeSet::new();
+            for s in func.blocks[b].term.successors() {
+                out.extend(live_in[s.0].iter().copied());
+            }
+            let mut inn = gen_sets[b].clone();
+            inn.extend(out.difference(&kill_sets[b]).copied());
+            if out != live_out[b] || inn != live_in[b] {
+                live_out[b] = out;
+                live_in[b] = inn;
+                changed = true;
+            }
+        }
+    }
+
+    let mut range: Vec<Option<(usize, usize)>> = vec![None; func.vreg_types.len()];
+    let mut extend = |v: VReg, pos: usize| {
+        let r = range[v.0].get_or_insert((pos, pos));
+        r.0 = r.0.min(pos);
+        r.1 = r.1.max(pos);
+    };
+    for p in &func.params {
+        extend(*p, 0);
+    }
+    let mut calls = Vec::new();
+    let mut pos = 1;
+    for (b, block) in func.blocks.iter().enumerate() {
+        let block_start = pos;
+        for v in &live_in[b] {
+            extend(*v, block_start);
+        }
+        for inst in &block.insts {
+            for u in inst.uses() {
+                extend(u, pos);
+            }
+            if let Some(d) = inst.def() {
+                extend(d, pos);
+            }
+            if let Inst::Call { .. } = inst {
+                calls.push(pos);
+            }
+            pos += 1;
+        }
+        for u in block.term.uses() {
+            extend(u, pos);
+        }
+        for v in &live_out[b] {
+            extend(*v, pos);
+        }
+        pos += 1;
+    }
+
+    range
+        .iter()
+        .enumerate()
+        .filter_map(|(v, r)| {
+            r.map(|(start, end)| Interval {
+                vreg: VReg(v),
+                start,
+                end,
+                // arguments are read and the result written around the call itself
+                crosses_call: calls.iter().any(|&c| start < c && c < end),
+            })
+        })
+        .collect()
+}
+
+/// Assign every virtual register of `func` to a machine register or a spill
+/// slot using linear scan. Values live across a call only get callee-saved
+/// registers; when no register is free the interval ending last is spilled.
+pub(crate) fn allocate(func: &Function, regs: &RegisterSet) -> Allocation {
+    let mut intervals = live_intervals(func);
+    intervals.sort_by_key(|i| (i.start, i.end));
+
+    // vregs that are never used nor defined still get a slot so emitters can index them
+    let mut locations = vec![Location::Spill(usize::MAX); func.vreg_types.len()];
+    let mut spill_slots = 0;
+    let mut spill = |locations: &mut Vec<Location>, v: VReg| {
+        locations[v.0] = Location::Spill(spill_slots);
+        spill_slots += 1;
+    };
+    let mut free = vec![true; regs.len()];
+    let mut used_callee = vec![false; regs.len()];
+    // intervals currently holding a register, with that register
+    let mut active: Vec<(Interval, usize)> = Vec::new();
+
+    for cur in intervals {
+        // release registers whose interval ended; an instruction may reuse an
+        // operand's register for its result
+        active.retain(|(iv, reg)| {
+            if iv.end <= cur.start {
+                free[*reg] = true;
+                false
+            } else {
+                true
+            }
+        });
+        let usable = |reg: usize| !cur.crosses_call || regs.is_callee_saved(reg);
+        if let Some(reg) = (0..regs.len()).find(|&r| free[r] && usable(r)) {
+            free[reg] = false;
+            used_callee[reg] |= regs.is_callee_saved(reg);
+            locations[cur.vreg.0] = Location::Reg(reg);
+            active.push((cur, reg));
+            continue;
+        }
+        // no register left: spill whichever interval lives longest
+        let victim = active
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, reg))| usable(*reg))
+            .max_by_key(|(_, (iv, _))| iv.end)
+            .map(|(i, _)| i);
+        match victim {
+            Some(i) if active[i].0.end > cur.end => {
+                let (old, reg) = active.remove(i);
+                spill(&mut locations, old.vreg);
+                locations[cur.vreg.0] = Location::Reg(reg);
+                active.push((cur, reg));
+            }
+            _ => spill(&mut locations, cur.vreg),
+        }
+    }
+    // they share one, as there may be many of them: the addresses of the
+    // variables `ir::promote_locals` keeps in registers
+    if locations.contains(&Location::Spill(usize::MAX)) {
+        for loc in locations.iter_mut() {
+            if *loc == Location::Spill(usize::MAX) {
+                *loc = Location::Spill(spill_slots);
+            }
+        }
+        spill_slots += 1;
+    }
+
+    Allocation {
+        locations,
+        callee_saved: (0..regs.len()).filter(|&r| used_callee[r]).collect(),
+        spill_slots,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::SourceFile;
+    use crate::ir::lower;
+    use crate::node::program;
+    use crate::symbol::SymbolTable;
+    use crate::token::tokenize;
+
+    const REGS: RegisterSet = RegisterSet {
+        caller_saved: &["c0", "c1"],
+        callee_saved: &["s0", "s1"],
+    };
+
+    fn allocate_src(src: &str, name: &str) -> (Function, Allocation) {
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let node = program(&mut iter, &mut vars).unwrap();
+        let file = SourceFile {
+            path: "test.rs",
+            text: src,
+        };
+        let func = lower(&node, &file, false)
+            .functions
+            .into_iter()
+            .find(|f| f.name == name)
+            .unwrap();
+        let alloc = allocate(&func, &REGS);
+        (func, alloc)
+    }
+
+    // no two overlapping intervals may share a register
+    fn assert_no_conflicts(func: &Function, alloc: &Allocation) {
+        let intervals = live_intervals(func);
+        for a in &intervals {
+            for b in &intervals {
+                if a.vreg == b.vreg || a.end <= b.start || b.end <= a.start {
+                    continue;
+                }
+                if let (Location::Reg(ra), Location::Reg(rb)) =
+                    (alloc.location(a.vreg), alloc.location(b.vreg))
+                {
+                    assert_ne!(ra, rb, "{:?} and {:?} share a register", a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_allocate_straight_line_in_registers() {
+        let (func, alloc) = allocate_src("fn main() { 1 * 2 + 3; }", "main");
+        assert!(
+            alloc
+                .locations
+                .iter()
+                .all(|l| matches!(l, Location::Reg(_)))
+        );
+        assert_eq!(alloc.spill_slots, 0);
+        assert!(alloc.callee_saved.is_empty());
+        assert_no_conflicts(&func, &alloc);
+    }
+
+    #[test]
+    fn test_allocate_value_across_call_is_callee_saved() {
+        let (func, alloc) = allocate_src(
+            "fn foo() -> i32 { return 1; } fn main() { 5 + foo(); }",
+            "main",
+        );
+        // %0 = const 5 stays live across the call
+        match alloc.location(VReg(0)) {
+            Location::Reg(r) => assert!(REGS.is_callee_saved(r)),
+            other => panic!("expected a register, got {:?}", other),
+        }
+        assert_eq!(alloc.callee_saved.len(), 1);
+        assert_no_conflicts(&func, &alloc);
+    }
+
+    #[test]
+    fn test_allocate_spills_under_pressure() {
+        let (func, alloc) = allocate_src("fn main() { 1 + (2 + (3 + (4 + (5 + 6)))); }", "main");
+        assert!(alloc.spill_slots > 0);
+        assert_no_conflicts(&func, &alloc);
+    }
+
+    #[test]
+    fn test_allocate_loop_keeps_values_live() {
+        let (func, alloc) = allocate_src(
+            "fn main() { let mut a = 0; let b = 1; while (a < 10) { a = a + b; } a; }",
+            "main",
+        );
+        assert_no_conflicts(&func, &alloc);
+    }
+}
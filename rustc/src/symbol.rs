@@ -0,0 +1,308 @@
+use crate::node::{ConstValue, Type, TypeDefs};
+use std::collections::HashMap;
+
+/// A local variable bound to a frame slot.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub offset: u64,
+    /// declared with `mut`, so it may be assigned and borrowed mutably
+    pub mutable: bool,
+    /// type written in the declaration or given by a struct or array literal,
+    /// if any
+    pub ty: Option<Type>,
+}
+
+/// A `const` or `static` item.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Global {
+    /// a `const`, whose uses are replaced by its value
+    Const { ty: Type, value: ConstValue },
+    /// a `static`, which lives at a fixed address for the whole program
+    Static { ty: Type, mutable: bool },
+}
+
+/// Scoped symbol table for the locals of the function being parsed.
+///
+/// Each function starts a fresh frame whose outermost scope holds the
+/// parameters; blocks push nested scopes that may shadow outer names and are
+/// dropped at the closing brace. Slot offsets grow monotonically within a
+/// function, so a shadowed or out-of-scope variable keeps its slot. Type
+/// declarations are kept for the whole program so that their sizes are known,
+/// and so are `const` and `static` items.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SymbolTable {
+    scopes: Vec<Vec<Symbol>>,
+    types: TypeDefs,
+    globals: HashMap<String, Global>,
+    // offset of the last slot handed out in the current function
+    last_offset: u64,
+    // legacy mode: unknown names are declared on first use instead of rejected
+    implicit_declarations: bool,
+}
+
+impl Default for SymbolTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self {
+            scopes: vec![Vec::new()],
+            types: TypeDefs::default(),
+            globals: HashMap::new(),
+            last_offset: 0,
+            implicit_declarations: false,
+        }
+    }
+
+    /// A table in legacy mode, where using an unknown name declares it for
+    /// the rest of the function instead of being an error.
+    pub fn with_implicit_declarations() -> Self {
+        Self {
+            implicit_declarations: true,
+            ..Self::new()
+        }
+    }
+
+    /// Whether unknown names are implicitly declared on first use.
+    pub fn implicit_declarations(&self) -> bool {
+        self.implicit_declarations
+    }
+
+    /// Start the frame of a new function, forgetting every previous local.
+    pub fn enter_function(&mut self) {
+        self.scopes = vec![Vec::new()];
+        self.last_offset = 0;
+    }
+
+    /// Open a block scope.
+    pub fn enter_scope(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+
+    /// Close the innermost block scope; its names are no longer visible.
+    pub fn leave_scope(&mut self) {
+        if self.scopes.len() > 1 {
+            self.scopes.pop();
+        }
+    }
+
+    /// Declare `name` in the innermost scope with `slots` 8-byte slots,
+    /// shadowing any visible variable of the same name. Returns the offset of
+    /// the first slot.
+    pub fn declare(&mut self, name: &str, slots: u64, mutable: bool) -> u64 {
+        let offset = self.reserve(slots);
+        self.bind(name, offset, mutable, None);
+        offset
+    }
+
+    /// Hand out `slots` fresh 8-byte slots without naming them yet. Returns
+    /// the offset of the first slot.
+    pub fn reserve(&mut self, slots: u64) -> u64 {
+        let offset = self.last_offset + 8;
+        self.last_offset += 8 * slots.max(1);
+        offset
+    }
+
+    /// Name the slots starting at `offset` in the innermost scope, shadowing
+    /// any visible variable of the same name.
+    pub fn bind(&mut self, name: &str, offset: u64, mutable: bool, ty: Option<Type>) {
+        self.scopes.last_mut().unwrap().push(Symbol {
+            name: name.to_string(),
+            offset,
+            mutable,
+            ty,
+        });
+    }
+
+    /// Declare `name` in the function's outermost scope, as an implicit
+    /// declaration by first use does. Such variables are always mutable.
+    pub fn declare_in_function(&mut self, name: &str) -> u64 {
+        let offset = self.last_offset + 8;
+        self.last_offset = offset;
+        self.scopes[0].push(Symbol {
+            name: name.to_string(),
+            offset,
+            mutable: true,
+            ty: None,
+        });
+        offset
+    }
+
+    /// Look up the innermost visible variable called `name`.
+    pub fn find(&self, name: &str) -> Option<u64> {
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|sym| sym.name == name)
+            .map(|sym| sym.offset)
+    }
+
+    /// The visible variable whose first slot is `offset`.
+    pub fn binding(&self, offset: u64) -> Option<&Symbol> {
+        self.scopes
+            .iter()
+            .flat_map(|scope| scope.iter())
+            .find(|sym| sym.offset == offset)
+    }
+
+    /// Record the fields of struct `name`.
+    pub fn define_struct(&mut self, name: &str, fields: Vec<(String, Type)>) {
+        self.types.structs.insert(name.to_string(), fields);
+    }
+
+    /// Record the variants of enum `name`.
+    pub fn define_enum(&mut self, name: &str, variants: Vec<(String, Vec<Type>)>) {
+        self.types.enums.insert(name.to_string(), variants);
+    }
+
+    /// Every struct and enum declared so far.
+    pub fn types(&self) -> &TypeDefs {
+        &self.types
+    }
+
+    /// Record the `const` or `static` item `name`.
+    pub fn define_global(&mut self, name: &str, global: Global) {
+        self.globals.insert(name.to_string(), global);
+    }
+
+    /// The `const` or `static` item called `name`, if one was declared.
+    pub fn global(&self, name: &str) -> Option<&Global> {
+        self.globals.get(name)
+    }
+
+    /// The visible name closest to `name`, if any is close enough to be a
+    /// likely typo.
+    pub fn suggest(&self, name: &str) -> Option<&str> {
+        // same threshold as rustc: a third of the length, but at least one edit
+        let max_dist = (name.chars().count() / 3).max(1);
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .map(|sym| (edit_distance(name, &sym.name), sym.name.as_str()))
+            .filter(|(dist, _)| *dist <= max_dist)
+            .min_by_key(|(dist, _)| *dist)
+            .map(|(_, candidate)| candidate)
+    }
+}
+
+// Levenshtein distance between two names
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_declare_and_find() {
+        let mut vars = SymbolTable::new();
+        assert_eq!(vars.declare("a", 1, false), 8);
+        assert_eq!(vars.declare("b", 1, false), 16);
+        assert_eq!(vars.find("a"), Some(8));
+        assert_eq!(vars.find("b"), Some(16));
+        assert_eq!(vars.find("c"), None);
+    }
+
+    #[test]
+    fn test_shadowing_and_end_of_scope() {
+        let mut vars = SymbolTable::new();
+        vars.declare("x", 1, false);
+        vars.enter_scope();
+        assert_eq!(vars.declare("x", 1, false), 16);
+        assert_eq!(vars.find("x"), Some(16));
+        vars.leave_scope();
+        assert_eq!(vars.find("x"), Some(8));
+        // slots are not reused after the scope ends
+        assert_eq!(vars.declare("y", 1, false), 24);
+    }
+
+    #[test]
+    fn test_array_slots() {
+        let mut vars = SymbolTable::new();
+        assert_eq!(vars.declare("arr", 3, false), 8);
+        assert_eq!(vars.declare("x", 1, false), 32);
+    }
+
+    #[test]
+    fn test_implicit_declaration_outlives_block() {
+        let mut vars = SymbolTable::new();
+        vars.enter_scope();
+        assert_eq!(vars.declare_in_function("x"), 8);
+        vars.leave_scope();
+        assert_eq!(vars.find("x"), Some(8));
+    }
+
+    #[test]
+    fn test_suggest_nearest_name() {
+        let mut vars = SymbolTable::new();
+        vars.declare("sum", 1, false);
+        vars.declare("arr", 3, false);
+        assert_eq!(vars.suggest("sun"), Some("sum"));
+        assert_eq!(vars.suggest("ar"), Some("arr"));
+        assert_eq!(vars.suggest("total"), None);
+    }
+
+    #[test]
+    fn test_suggest_ignores_out_of_scope_names() {
+        let mut vars = SymbolTable::new();
+        vars.enter_scope();
+        vars.declare("count", 1, false);
+        vars.leave_scope();
+        assert_eq!(vars.suggest("cont"), None);
+    }
+
+    #[test]
+    fn test_binding_records_mutability() {
+        let mut vars = SymbolTable::new();
+        vars.declare("a", 1, false);
+        vars.declare("b", 1, true);
+        assert!(!vars.binding(8).unwrap().mutable);
+        assert!(vars.binding(16).unwrap().mutable);
+        assert_eq!(vars.binding(24), None);
+    }
+
+    #[test]
+    fn test_reserve_then_bind() {
+        let mut vars = SymbolTable::new();
+        let offset = vars.reserve(3);
+        assert_eq!(vars.find("p"), None);
+        vars.bind("p", offset, false, Some(Type::Struct("P".to_string())));
+        assert_eq!(vars.find("p"), Some(8));
+        assert_eq!(
+            vars.binding(8).unwrap().ty,
+            Some(Type::Struct("P".to_string()))
+        );
+        assert_eq!(vars.declare("x", 1, false), 32);
+    }
+
+    #[test]
+    fn test_enter_function_resets_frame() {
+        let mut vars = SymbolTable::new();
+        vars.declare("a", 1, false);
+        vars.define_struct("P", vec![("x".to_string(), Type::I32)]);
+        vars.define_enum("E", vec![("A".to_string(), vec![])]);
+        vars.enter_function();
+        assert_eq!(vars.find("a"), None);
+        // type declarations outlive the function
+        assert!(vars.types().contains("P"));
+        assert!(vars.types().contains("E"));
+        assert_eq!(vars.declare("b", 1, false), 8);
+    }
+}
@@ -4,10 +4,13 @@ pub enum TokenKind {
     Number { num: u64 },
     Ident { name: String },
     String { value: String },
+    // loop label such as `'outer`, stored without the quote
+    Label { name: String },
     Plus,
     Minus,
     Star,
     Slash,
+    Percent,
     EqEq,
     Ne,
     Lt,
@@ -15,6 +18,17 @@ pub enum TokenKind {
     Gt,
     Ge,
     Assign,
+    // compound assignment operators such as `+=`
+    PlusEq,
+    MinusEq,
+    StarEq,
+    SlashEq,
+    PercentEq,
+    AmpEq,
+    PipeEq,
+    CaretEq,
+    ShlEq,
+    ShrEq,
     Semicolon,
     Comma,
     Colon,
@@ -31,59 +45,132 @@ pub enum TokenKind {
     RBrace,
     LBracket,
     RBracket,
+    I8,
+    I16,
     I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    Usize,
+    Bool,
+    True,
+    False,
     Arrow,
     Amp,
+    AndAnd,
+    Pipe,
+    OrOr,
+    Caret,
+    Bang,
+    Shl,
+    Shr,
     Let,
+    Mut,
+    In,
+    Loop,
+    Break,
+    Continue,
+    DotDot,
+    DotDotEq,
+    Dot,
+    Struct,
+    Enum,
+    Match,
+    Const,
+    Static,
+    Unsafe,
+    As,
+    // `::` between an enum and its variant
+    ColonColon,
+    // `=>` between a match pattern and its arm
+    FatArrow,
 }
 
 #[derive(Debug)]
 pub struct Token {
     pub kind: TokenKind,
     pub pos: usize, // byte index in the input string
+    pub end: usize, // byte index just past the token
     pub next: Option<Box<Token>>,
 }
 
 impl Token {
     /// Append a new token of the given kind and position after this one and return a mutable reference to it.
-    pub fn push(&mut self, kind: TokenKind, pos: usize) -> &mut Token {
+    pub fn push(&mut self, kind: TokenKind, pos: usize, end: usize) -> &mut Token {
         self.next = Some(Box::new(Token {
             kind,
             pos,
+            end,
             next: None,
         }));
         self.next.as_mut().unwrap()
     }
+
+    /// The source range covered by this token.
+    pub fn span(&self) -> Span {
+        Span::new(self.pos, self.end)
+    }
+}
+
+// Cloning copies the rest of the list; the links are rebuilt in a loop, as a
+// derived clone would recurse once per token.
+impl Clone for Token {
+    fn clone(&self) -> Self {
+        let mut rest = Vec::new();
+        let mut cur = self.next.as_deref();
+        while let Some(tok) = cur {
+            rest.push((tok.kind.clone(), tok.pos, tok.end));
+            cur = tok.next.as_deref();
+        }
+        let mut next = None;
+        for (kind, pos, end) in rest.into_iter().rev() {
+            next = Some(Box::new(Token {
+                kind,
+                pos,
+                end,
+                next,
+            }));
+        }
+        Token {
+            kind: self.kind.clone(),
+            pos: self.pos,
+            end: self.end,
+            next,
+        }
+    }
 }
 
 use crate::check::ParseError;
-use crate::check::{error_at, set_current_exp};
+use crate::check::Span;
+use crate::check::error_at;
 
 use std::iter::Peekable;
 use std::str::CharIndices;
 
-/// Reads a sequence of digits and returns the parsed number.
-fn read_number(chars: &mut Peekable<CharIndices>) -> u64 {
-    let mut num = 0u64;
+/// Reads a sequence of digits starting at `start` and returns the parsed number.
+/// Returns an error if it does not fit in a `u64`.
+fn read_number(chars: &mut Peekable<CharIndices>, start: usize) -> Result<u64, ParseError> {
+    let mut num = Some(0u64);
     while let Some(&(_, ch)) = chars.peek() {
         if ch.is_ascii_digit() {
             num = num
-                .checked_mul(10)
-                .and_then(|n| n.checked_add(ch.to_digit(10).unwrap() as u64))
-                .unwrap();
+                .and_then(|n| n.checked_mul(10))
+                .and_then(|n| n.checked_add(ch.to_digit(10).unwrap() as u64));
             chars.next();
         } else {
             break;
         }
     }
-    num
+    num.ok_or_else(|| error_at(start, "整数リテラルが大きすぎます"))
 }
 
-/// Reads an alphanumeric sequence and returns it as a string.
+/// Reads an alphanumeric sequence, which may contain underscores, and returns it as a string.
 fn read_ident(chars: &mut Peekable<CharIndices>) -> String {
     let mut word = String::new();
     while let Some(&(_, ch)) = chars.peek() {
-        if ch.is_ascii_alphanumeric() {
+        if ch.is_ascii_alphanumeric() || ch == '_' {
             word.push(ch);
             chars.next();
         } else {
@@ -102,7 +189,30 @@ const KEYWORDS: &[(&str, TokenKind)] = &[
     ("for", TokenKind::For),
     ("fn", TokenKind::Fn),
     ("let", TokenKind::Let),
+    ("mut", TokenKind::Mut),
+    ("in", TokenKind::In),
+    ("loop", TokenKind::Loop),
+    ("break", TokenKind::Break),
+    ("continue", TokenKind::Continue),
+    ("struct", TokenKind::Struct),
+    ("enum", TokenKind::Enum),
+    ("match", TokenKind::Match),
+    ("const", TokenKind::Const),
+    ("static", TokenKind::Static),
+    ("unsafe", TokenKind::Unsafe),
+    ("as", TokenKind::As),
+    ("i8", TokenKind::I8),
+    ("i16", TokenKind::I16),
     ("i32", TokenKind::I32),
+    ("i64", TokenKind::I64),
+    ("u8", TokenKind::U8),
+    ("u16", TokenKind::U16),
+    ("u32", TokenKind::U32),
+    ("u64", TokenKind::U64),
+    ("usize", TokenKind::Usize),
+    ("bool", TokenKind::Bool),
+    ("true", TokenKind::True),
+    ("false", TokenKind::False),
 ];
 
 /// Looks up a word in the keyword table and returns the corresponding TokenKind.
@@ -117,11 +227,29 @@ fn lookup_keyword(word: &str) -> Option<TokenKind> {
 
 /// Slice of operator lexemes mapped to their TokenKind, sorted by descending length to match longest first.
 const OPERATORS: &[(&str, TokenKind)] = &[
+    ("..=", TokenKind::DotDotEq),
+    ("<<=", TokenKind::ShlEq),
+    (">>=", TokenKind::ShrEq),
+    ("..", TokenKind::DotDot),
     ("==", TokenKind::EqEq),
     ("!=", TokenKind::Ne),
+    ("<<", TokenKind::Shl),
+    (">>", TokenKind::Shr),
     ("<=", TokenKind::Le),
     (">=", TokenKind::Ge),
     ("->", TokenKind::Arrow),
+    ("=>", TokenKind::FatArrow),
+    ("::", TokenKind::ColonColon),
+    ("&&", TokenKind::AndAnd),
+    ("||", TokenKind::OrOr),
+    ("+=", TokenKind::PlusEq),
+    ("-=", TokenKind::MinusEq),
+    ("*=", TokenKind::StarEq),
+    ("/=", TokenKind::SlashEq),
+    ("%=", TokenKind::PercentEq),
+    ("&=", TokenKind::AmpEq),
+    ("|=", TokenKind::PipeEq),
+    ("^=", TokenKind::CaretEq),
     ("<", TokenKind::Lt),
     (">", TokenKind::Gt),
     ("=", TokenKind::Assign),
@@ -129,6 +257,7 @@ const OPERATORS: &[(&str, TokenKind)] = &[
     ("-", TokenKind::Minus),
     ("*", TokenKind::Star),
     ("/", TokenKind::Slash),
+    ("%", TokenKind::Percent),
     (";", TokenKind::Semicolon),
     (",", TokenKind::Comma),
     (":", TokenKind::Colon),
@@ -138,7 +267,11 @@ const OPERATORS: &[(&str, TokenKind)] = &[
     ("}", TokenKind::RBrace),
     ("[", TokenKind::LBracket),
     ("]", TokenKind::RBracket),
+    (".", TokenKind::Dot),
     ("&", TokenKind::Amp),
+    ("|", TokenKind::Pipe),
+    ("^", TokenKind::Caret),
+    ("!", TokenKind::Bang),
 ];
 
 /// Reads an operator or delimiter and returns the TokenKind by matching against `OPERATORS`.
@@ -158,7 +291,7 @@ fn read_operator(
         }
     }
     // no operator matched; report error
-    Err(error_at(exp, pos, "無効な文字です"))
+    Err(error_at(pos, "無効な文字です"))
 }
 
 /// Skips characters until the end of the current line (including newline), assuming the next two chars are "//".
@@ -166,7 +299,7 @@ fn skip_line_comment(chars: &mut Peekable<CharIndices>) {
     // consume "//"
     chars.next();
     chars.next();
-    while let Some((_, ch)) = chars.next() {
+    for (_, ch) in chars.by_ref() {
         if ch == '\n' {
             break;
         }
@@ -180,14 +313,13 @@ fn skip_block_comment(chars: &mut Peekable<CharIndices>, _exp: &str, start_pos:
     chars.next();
     let mut found_end = false;
     while let Some((_, ch)) = chars.next() {
-        if ch == '*' {
-            if let Some(&(_, next_ch)) = chars.peek() {
-                if next_ch == '/' {
-                    chars.next();
-                    found_end = true;
-                    break;
-                }
-            }
+        if ch == '*'
+            && let Some(&(_, next_ch)) = chars.peek()
+            && next_ch == '/'
+        {
+            chars.next();
+            found_end = true;
+            break;
         }
     }
     if !found_end {
@@ -198,34 +330,105 @@ fn skip_block_comment(chars: &mut Peekable<CharIndices>, _exp: &str, start_pos:
     }
 }
 
-/// Reads a string literal and returns it as a string.
-fn read_string(
-    chars: &mut Peekable<CharIndices>,
-    exp: &str,
-    start_pos: usize,
-) -> Result<String, ParseError> {
+/// Reads a string literal and returns its contents with the escape sequences decoded.
+fn read_string(chars: &mut Peekable<CharIndices>, start_pos: usize) -> Result<String, ParseError> {
     let mut s = String::new();
     // Skip opening quote
     chars.next();
-    while let Some((_, ch)) = chars.next() {
-        if ch == '"' {
-            return Ok(s);
+    while let Some((pos, ch)) = chars.next() {
+        match ch {
+            '"' => return Ok(s),
+            '\\' => {
+                if let Some(decoded) = read_escape(chars, pos)? {
+                    s.push(decoded);
+                }
+            }
+            _ => s.push(ch),
         }
-        s.push(ch);
     }
     // If we get here, we hit EOF before finding closing quote
-    Err(error_at(exp, start_pos, "文字列が閉じられていません"))
+    Err(error_at(start_pos, "文字列が閉じられていません"))
+}
+
+/// Reads the escape sequence after the backslash at `pos` and returns the character it
+/// stands for, or `None` for a line continuation, which skips the newline and the
+/// whitespace at the start of the next line.
+fn read_escape(chars: &mut Peekable<CharIndices>, pos: usize) -> Result<Option<char>, ParseError> {
+    let Some((_, ch)) = chars.next() else {
+        return Err(error_at(pos, "文字列が閉じられていません"));
+    };
+    let decoded = match ch {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        '0' => '\0',
+        '\\' | '"' | '\'' => ch,
+        'x' => {
+            // exactly two hex digits, at most 0x7f
+            let mut value = 0;
+            for _ in 0..2 {
+                let digit = chars.next().and_then(|(_, d)| d.to_digit(16));
+                let Some(digit) = digit else {
+                    return Err(error_at(pos, "\\x エスケープには 16 進数字が 2 つ必要です"));
+                };
+                value = value * 16 + digit;
+            }
+            if value > 0x7f {
+                return Err(error_at(
+                    pos,
+                    "\\x エスケープは \\x7f 以下でなければなりません",
+                ));
+            }
+            value as u8 as char
+        }
+        'u' => {
+            // one to six hex digits in braces, naming a Unicode scalar value
+            if chars.next_if(|&(_, c)| c == '{').is_none() {
+                return Err(error_at(pos, "\\u エスケープには { が必要です"));
+            }
+            let mut value = 0u32;
+            let mut digits = 0;
+            while let Some((_, c)) = chars.next_if(|&(_, c)| c != '}' && c != '"') {
+                let Some(digit) = c.to_digit(16).filter(|_| digits < 6) else {
+                    return Err(error_at(pos, "不正な \\u エスケープです"));
+                };
+                value = value * 16 + digit;
+                digits += 1;
+            }
+            if digits == 0 || chars.next_if(|&(_, c)| c == '}').is_none() {
+                return Err(error_at(pos, "不正な \\u エスケープです"));
+            }
+            char::from_u32(value)
+                .ok_or_else(|| error_at(pos, "無効な Unicode 文字エスケープです"))?
+        }
+        '\n' => {
+            while chars.next_if(|&(_, c)| c.is_whitespace()).is_some() {}
+            return Ok(None);
+        }
+        _ => {
+            return Err(error_at(
+                pos,
+                &format!("不明な文字エスケープです: \\{}", ch),
+            ));
+        }
+    };
+    Ok(Some(decoded))
+}
+
+// Byte index of the next unread character
+fn offset(chars: &mut Peekable<CharIndices>, exp: &str) -> usize {
+    chars.peek().map_or(exp.len(), |&(i, _)| i)
 }
 
 /// Tokenizes an arithmetic expression into a linked list of tokens.
 /// Supports positive integers, identifiers, operators, and delimiters.
 /// Returns the head `Token`, whose chained `next` pointers end with an `Eof` token.
 pub fn tokenize(exp: &str) -> Result<Token, ParseError> {
-    set_current_exp(exp);
     // Build linked list with a sentinel head (pos=0)
     let mut head = Token {
         kind: TokenKind::Start,
         pos: 0,
+        end: 0,
         next: None,
     };
     let mut tail = &mut head;
@@ -247,33 +450,46 @@ pub fn tokenize(exp: &str) -> Result<Token, ParseError> {
         } else if c == '"' {
             // Handle string literal
             let start = i;
-            let s = read_string(&mut chars, exp, start)?;
-            tail = tail.push(TokenKind::String { value: s }, start);
+            let s = read_string(&mut chars, start)?;
+            let end = offset(&mut chars, exp);
+            tail = tail.push(TokenKind::String { value: s }, start, end);
             continue;
         } else if c.is_ascii_digit() {
             let start = i;
-            let num = read_number(&mut chars);
-            tail = tail.push(TokenKind::Number { num }, start);
+            let num = read_number(&mut chars, start)?;
+            let end = offset(&mut chars, exp);
+            tail = tail.push(TokenKind::Number { num }, start, end);
+            continue;
+        } else if c == '\'' && exp[i + 1..].starts_with(|ch: char| ch.is_ascii_alphabetic()) {
+            // loop label: a quote followed by an identifier
+            let start = i;
+            chars.next();
+            let name = read_ident(&mut chars);
+            let end = offset(&mut chars, exp);
+            tail = tail.push(TokenKind::Label { name }, start, end);
             continue;
-        } else if c.is_ascii_alphabetic() {
+        } else if c.is_ascii_alphabetic() || c == '_' {
             let start = i;
             let word = read_ident(&mut chars);
             let kind = lookup_keyword(&word).unwrap_or(TokenKind::Ident { name: word });
-            tail = tail.push(kind, start);
+            let end = offset(&mut chars, exp);
+            tail = tail.push(kind, start, end);
             continue;
         } else {
             // Operators and delimiters
             let pos = i;
             let kind = read_operator(&mut chars, exp, pos)?;
-            tail = tail.push(kind, pos);
+            let end = offset(&mut chars, exp);
+            tail = tail.push(kind, pos, end);
         }
     }
     // Append EOF token at end of input
-    tail.push(TokenKind::Eof, exp.len());
+    tail.push(TokenKind::Eof, exp.len(), exp.len());
     Ok(head)
 }
 
 /// An iterator over tokens (skips the initial Start sentinel)
+#[derive(Clone)]
 pub struct TokenIter {
     current: Option<Token>,
 }
@@ -318,7 +534,7 @@ mod tests {
     #[test]
     #[should_panic(expected = "無効な文字です")]
     fn test_tokenize_panic_on_invalid_char() {
-        let _ = tokenize("?").unwrap_err().unwrap();
+        tokenize("?").unwrap_err().unwrap();
     }
 
     // === Number & Arithmetic Tests ===
@@ -594,6 +810,63 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_tokenize_logical_and_bitwise_operators() {
+        let kinds: Vec<TokenKind> = tokenize("% && || ! != & | ^ << >> <= &&x")
+            .unwrap()
+            .into_iter()
+            .map(|tok| tok.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Percent,
+                TokenKind::AndAnd,
+                TokenKind::OrOr,
+                TokenKind::Bang,
+                TokenKind::Ne,
+                TokenKind::Amp,
+                TokenKind::Pipe,
+                TokenKind::Caret,
+                TokenKind::Shl,
+                TokenKind::Shr,
+                TokenKind::Le,
+                TokenKind::AndAnd,
+                TokenKind::Ident {
+                    name: "x".to_string()
+                },
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_compound_assignment() {
+        let kinds: Vec<TokenKind> = tokenize("+= -= *= /= %= &= |= ^= <<= >>= -> <<")
+            .unwrap()
+            .into_iter()
+            .map(|tok| tok.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::PlusEq,
+                TokenKind::MinusEq,
+                TokenKind::StarEq,
+                TokenKind::SlashEq,
+                TokenKind::PercentEq,
+                TokenKind::AmpEq,
+                TokenKind::PipeEq,
+                TokenKind::CaretEq,
+                TokenKind::ShlEq,
+                TokenKind::ShrEq,
+                TokenKind::Arrow,
+                TokenKind::Shl,
+                TokenKind::Eof,
+            ]
+        );
+    }
+
     #[test]
     fn test_tokenize_assignment_and_punctuation() {
         let kinds: Vec<TokenKind> = tokenize("= ; , :")
@@ -656,10 +929,295 @@ mod tests {
     #[test]
     #[should_panic(expected = "文字列が閉じられていません")]
     fn test_tokenize_unclosed_string() {
-        let _ = tokenize(r#""Hello, world!"#).unwrap_err().unwrap();
+        tokenize(r#""Hello, world!"#).unwrap_err().unwrap();
+    }
+
+    #[test]
+    fn test_tokenize_string_escapes() {
+        let src = r#""a\n\t\\\"\0\x7f\u{1F600}\
+                   b""#;
+        let kinds: Vec<TokenKind> = tokenize(src)
+            .unwrap()
+            .into_iter()
+            .map(|tok| tok.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::String {
+                    value: "a\n\t\\\"\0\x7f\u{1F600}b".to_string()
+                },
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_invalid_escape_position() {
+        let err = tokenize(r#"let s = "ab\q";"#).unwrap_err();
+        assert_eq!(err.pos, 11);
+        assert_eq!(err.msg, "不明な文字エスケープです: \\q");
+    }
+
+    #[test]
+    #[should_panic(expected = "\\x エスケープは \\x7f 以下でなければなりません")]
+    fn test_tokenize_escape_out_of_range() {
+        tokenize(r#""\x80""#).unwrap_err().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "無効な Unicode 文字エスケープです")]
+    fn test_tokenize_escape_surrogate() {
+        tokenize(r#""\u{D800}""#).unwrap_err().unwrap();
     }
 
     // === Bracket & Indexing Tests ===
+    #[test]
+    fn test_tokenize_mut() {
+        let kinds: Vec<TokenKind> = tokenize("let mut x = &mut y;")
+            .unwrap()
+            .into_iter()
+            .map(|tok| tok.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Let,
+                TokenKind::Mut,
+                TokenKind::Ident {
+                    name: "x".to_string()
+                },
+                TokenKind::Assign,
+                TokenKind::Amp,
+                TokenKind::Mut,
+                TokenKind::Ident {
+                    name: "y".to_string()
+                },
+                TokenKind::Semicolon,
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_struct_and_field_access() {
+        let kinds: Vec<TokenKind> = tokenize("struct P { x: i32 } p.x 0..1")
+            .unwrap()
+            .into_iter()
+            .map(|tok| tok.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Struct,
+                TokenKind::Ident {
+                    name: "P".to_string()
+                },
+                TokenKind::LBrace,
+                TokenKind::Ident {
+                    name: "x".to_string()
+                },
+                TokenKind::Colon,
+                TokenKind::I32,
+                TokenKind::RBrace,
+                TokenKind::Ident {
+                    name: "p".to_string()
+                },
+                TokenKind::Dot,
+                TokenKind::Ident {
+                    name: "x".to_string()
+                },
+                TokenKind::Number { num: 0 },
+                TokenKind::DotDot,
+                TokenKind::Number { num: 1 },
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_loops_and_ranges() {
+        let kinds: Vec<TokenKind> =
+            tokenize("'outer: for i in 0..=9 { break 'outer; continue; loop {} }")
+                .unwrap()
+                .into_iter()
+                .map(|tok| tok.kind)
+                .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Label {
+                    name: "outer".to_string()
+                },
+                TokenKind::Colon,
+                TokenKind::For,
+                TokenKind::Ident {
+                    name: "i".to_string()
+                },
+                TokenKind::In,
+                TokenKind::Number { num: 0 },
+                TokenKind::DotDotEq,
+                TokenKind::Number { num: 9 },
+                TokenKind::LBrace,
+                TokenKind::Break,
+                TokenKind::Label {
+                    name: "outer".to_string()
+                },
+                TokenKind::Semicolon,
+                TokenKind::Continue,
+                TokenKind::Semicolon,
+                TokenKind::Loop,
+                TokenKind::LBrace,
+                TokenKind::RBrace,
+                TokenKind::RBrace,
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_enum_and_match() {
+        let kinds: Vec<TokenKind> = tokenize("enum E { A } match e { E::A => 1, _ => 0 } my_var")
+            .unwrap()
+            .into_iter()
+            .map(|tok| tok.kind)
+            .collect();
+        let ident = |name: &str| TokenKind::Ident {
+            name: name.to_string(),
+        };
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Enum,
+                ident("E"),
+                TokenKind::LBrace,
+                ident("A"),
+                TokenKind::RBrace,
+                TokenKind::Match,
+                ident("e"),
+                TokenKind::LBrace,
+                ident("E"),
+                TokenKind::ColonColon,
+                ident("A"),
+                TokenKind::FatArrow,
+                TokenKind::Number { num: 1 },
+                TokenKind::Comma,
+                ident("_"),
+                TokenKind::FatArrow,
+                TokenKind::Number { num: 0 },
+                TokenKind::RBrace,
+                ident("my_var"),
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_const_and_static() {
+        let kinds: Vec<TokenKind> = tokenize("const N: i32 = 8; static mut S: i32 = N;")
+            .unwrap()
+            .into_iter()
+            .map(|tok| tok.kind)
+            .collect();
+        let ident = |name: &str| TokenKind::Ident {
+            name: name.to_string(),
+        };
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Const,
+                ident("N"),
+                TokenKind::Colon,
+                TokenKind::I32,
+                TokenKind::Assign,
+                TokenKind::Number { num: 8 },
+                TokenKind::Semicolon,
+                TokenKind::Static,
+                TokenKind::Mut,
+                ident("S"),
+                TokenKind::Colon,
+                TokenKind::I32,
+                TokenKind::Assign,
+                ident("N"),
+                TokenKind::Semicolon,
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_bool() {
+        let kinds: Vec<TokenKind> = tokenize("let b: bool = true != false;")
+            .unwrap()
+            .into_iter()
+            .map(|tok| tok.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Let,
+                TokenKind::Ident {
+                    name: "b".to_string()
+                },
+                TokenKind::Colon,
+                TokenKind::Bool,
+                TokenKind::Assign,
+                TokenKind::True,
+                TokenKind::Ne,
+                TokenKind::False,
+                TokenKind::Semicolon,
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_integer_types_and_as() {
+        let kinds: Vec<TokenKind> = tokenize("i8 i16 i64 u8 u16 u32 u64 usize x as u8")
+            .unwrap()
+            .into_iter()
+            .map(|tok| tok.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::I8,
+                TokenKind::I16,
+                TokenKind::I64,
+                TokenKind::U8,
+                TokenKind::U16,
+                TokenKind::U32,
+                TokenKind::U64,
+                TokenKind::Usize,
+                TokenKind::Ident {
+                    name: "x".to_string()
+                },
+                TokenKind::As,
+                TokenKind::U8,
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "整数リテラルが大きすぎます")]
+    fn test_tokenize_number_too_large() {
+        tokenize("18446744073709551616").unwrap_err().unwrap();
+    }
+
+    #[test]
+    fn test_tokenize_spans() {
+        let spans: Vec<(usize, usize)> = tokenize("let abc = 42;")
+            .unwrap()
+            .into_iter()
+            .map(|tok| (tok.pos, tok.end))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![(0, 3), (4, 7), (8, 9), (10, 12), (12, 13), (13, 13)]
+        );
+    }
+
     #[test]
     fn test_tokenize_indexing_syntax() {
         let kinds: Vec<TokenKind> = tokenize("arr[123]")
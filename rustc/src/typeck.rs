@@ -0,0 +1,2148 @@
+use crate::check::{Span, render_at};
+use crate::node::{MatchArm, Node, OpKind, Pattern, Type, TypeDefs, field_layout};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Represents a type error found while checking the program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeError {
+    pub msg: String,
+    // source range of the offending expression
+    pub span: Span,
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Type error: {}", self.msg)
+    }
+}
+
+impl TypeError {
+    /// Render the error under the offending source line, underlining the span.
+    pub fn render(&self, exp: &str) -> String {
+        render_at(exp, self.span, &self.msg)
+    }
+}
+
+fn error<T>(span: Span, msg: String) -> Result<T, TypeError> {
+    Err(TypeError { msg, span })
+}
+
+// Declared parameter and return types of a function
+struct Signature {
+    params: Vec<Type>,
+    ret: Option<Type>,
+}
+
+// source spelling of a binary operator, for messages
+fn op_str(op: OpKind) -> &'static str {
+    match op {
+        OpKind::Add => "+",
+        OpKind::Sub => "-",
+        OpKind::Mul => "*",
+        OpKind::Div => "/",
+        OpKind::Rem => "%",
+        OpKind::BitAnd => "&",
+        OpKind::BitOr => "|",
+        OpKind::BitXor => "^",
+        OpKind::Shl => "<<",
+        OpKind::Shr => ">>",
+        OpKind::And => "&&",
+        OpKind::Or => "||",
+        OpKind::Eq => "==",
+        OpKind::Ne => "!=",
+        OpKind::Lt => "<",
+        OpKind::Gt => ">",
+        OpKind::Le => "<=",
+        OpKind::Ge => ">=",
+    }
+}
+
+// result type of `l op r` for the expression at `span`
+fn binary_type(op: OpKind, l: Type, r: Type, span: Span) -> Result<Type, TypeError> {
+    match op {
+        OpKind::Add | OpKind::Sub | OpKind::Mul | OpKind::Div | OpKind::Rem => {
+            if l != r || !l.is_integer() {
+                return error(
+                    span,
+                    format!("cannot apply `{}` to `{}` and `{}`", op_str(op), l, r),
+                );
+            }
+            Ok(l)
+        }
+        // the shift amount may be of any integer type
+        OpKind::Shl | OpKind::Shr => {
+            if !l.is_integer() || !r.is_integer() {
+                return error(
+                    span,
+                    format!("cannot apply `{}` to `{}` and `{}`", op_str(op), l, r),
+                );
+            }
+            Ok(l)
+        }
+        // bitwise operators also work on two booleans, without short-circuiting
+        OpKind::BitAnd | OpKind::BitOr | OpKind::BitXor => {
+            if l != r || !(l.is_integer() || l == Type::Bool) {
+                return error(
+                    span,
+                    format!("cannot apply `{}` to `{}` and `{}`", op_str(op), l, r),
+                );
+            }
+            Ok(l)
+        }
+        OpKind::And | OpKind::Or => {
+            if l != Type::Bool || r != Type::Bool {
+                return error(
+                    span,
+                    format!("cannot apply `{}` to `{}` and `{}`", op_str(op), l, r),
+                );
+            }
+            Ok(Type::Bool)
+        }
+        OpKind::Eq | OpKind::Ne => {
+            if l != r {
+                return error(
+                    span,
+                    format!("cannot compare `{}` with `{}` using `{}`", l, r, op_str(op)),
+                );
+            }
+            Ok(Type::Bool)
+        }
+        OpKind::Lt | OpKind::Gt | OpKind::Le | OpKind::Ge => {
+            if l != r || !l.is_integer() {
+                return error(
+                    span,
+                    format!("cannot compare `{}` with `{}` using `{}`", l, r, op_str(op)),
+                );
+            }
+            Ok(Type::Bool)
+        }
+    }
+}
+
+// fail unless `found`, the type of the expression at `span`, is the `expected`
+// type; code that never produces a value fits anywhere, and `&mut T` may be
+// used as `&T`
+fn expect(expected: &Type, found: &Type, context: &str, span: Span) -> Result<(), TypeError> {
+    let coerces = matches!((expected, found), (Type::Ref(e), Type::RefMut(f)) if e == f);
+    if expected == found || *found == Type::Never || coerces {
+        Ok(())
+    } else {
+        error(
+            span,
+            format!(
+                "mismatched types: expected `{}`, found `{}` in {}",
+                expected, found, context
+            ),
+        )
+    }
+}
+
+// whether `node` is an integer literal, or arithmetic on such literals (which
+// includes a negative literal), whose type comes from where it is used
+fn is_int_literal(node: &Node) -> bool {
+    match node {
+        Node::Num { .. } => true,
+        Node::Not { expr, .. } | Node::Neg { expr, .. } => is_int_literal(expr),
+        Node::BinaryOp { op, lhs, rhs, .. } => {
+            matches!(
+                op,
+                OpKind::Add
+                    | OpKind::Sub
+                    | OpKind::Mul
+                    | OpKind::Div
+                    | OpKind::Rem
+                    | OpKind::BitAnd
+                    | OpKind::BitOr
+                    | OpKind::BitXor
+                    | OpKind::Shl
+                    | OpKind::Shr
+            ) && is_int_literal(lhs)
+                && is_int_literal(rhs)
+        }
+        _ => false,
+    }
+}
+
+// give the integer literal `node` the integer type `ty`, failing if a
+// literal does not fit in it
+fn retype_literal(node: &mut Node, ty: &Type) -> Result<(), TypeError> {
+    let (min, max) = ty.int_range().expect("an integer type");
+    match node {
+        Node::Num { value, span } => {
+            if *value as i128 > max {
+                return error(*span, format!("literal out of range for `{}`", ty));
+            }
+            Ok(())
+        }
+        Node::Not {
+            expr, ty: operand, ..
+        } => {
+            *operand = ty.clone();
+            retype_literal(expr, ty)
+        }
+        Node::Neg {
+            expr,
+            ty: operand,
+            span,
+        } => {
+            if min == 0 {
+                return error(
+                    *span,
+                    format!("cannot apply unary operator `-` to type `{}`", ty),
+                );
+            }
+            *operand = ty.clone();
+            // a negative literal may reach one past the largest positive value
+            if let Node::Num { value, .. } = expr.as_ref()
+                && *value as i128 == -min
+            {
+                return Ok(());
+            }
+            retype_literal(expr, ty)
+        }
+        Node::BinaryOp {
+            lhs,
+            rhs,
+            ty: operands,
+            ..
+        } => {
+            *operands = ty.clone();
+            retype_literal(lhs, ty)?;
+            retype_literal(rhs, ty)
+        }
+        _ => unreachable!("not an integer literal: {:?}", node),
+    }
+}
+
+// type of `node`, found to be `found`, where a value of type `expected` is
+// wanted: an integer literal takes any integer type
+fn infer_literal(node: &mut Node, found: Type, expected: &Type) -> Result<Type, TypeError> {
+    if found == Type::I32 && expected.is_integer() && is_int_literal(node) {
+        retype_literal(node, expected)?;
+        return Ok(expected.clone());
+    }
+    Ok(found)
+}
+
+// type of `node`, found to be `found`, where nothing decides its type: an
+// integer literal falls back to `i32` and has to fit in it
+fn fallback_literal(node: &mut Node, found: Type) -> Result<Type, TypeError> {
+    if found == Type::I32 && is_int_literal(node) {
+        retype_literal(node, &found)?;
+    }
+    Ok(found)
+}
+
+// whether `node` denotes a memory location: a variable, a `static`, a
+// dereferenced pointer, a struct field, an element of an array in memory or
+// the temporary slots of a struct literal
+fn is_place(node: &Node) -> bool {
+    match node {
+        Node::Var { .. }
+        | Node::Global { .. }
+        | Node::Deref { .. }
+        | Node::Field { .. }
+        | Node::StructLit { .. } => true,
+        Node::Index { expr, .. } => is_place(expr),
+        _ => false,
+    }
+}
+
+// Structs, and enums whose variants have fields, fill several slots and are
+// only handled through references. Returns `struct` or `enum` for such a type.
+fn aggregate_kind(ty: &Type, types: &TypeDefs) -> Option<&'static str> {
+    match ty {
+        Type::Struct(_) => Some("struct"),
+        Type::Enum(_) if ty.slots(types) > Some(1) => Some("enum"),
+        _ => None,
+    }
+}
+
+// replace the pointer `expr` by `*expr`
+fn insert_deref(expr: &mut Node) {
+    let span = expr.span();
+    let pointer = std::mem::replace(expr, Node::Num { value: 0, span });
+    *expr = Node::Deref {
+        span,
+        expr: Box::new(pointer),
+        // set when the dereferenced place is checked,
+        ty: Type::I64,
+    };
+}
+
+// The values of `ty` that no pattern of `arms` matches, as rustc spells them
+fn uncovered(ty: &Type, arms: &[MatchArm], types: &TypeDefs) -> Vec<String> {
+    let pats = || arms.iter().flat_map(|arm| &arm.pats);
+    if pats().any(|pat| matches!(pat, Pattern::Wild | Pattern::Bind { .. })) {
+        return Vec::new();
+    }
+    match ty {
+        Type::Enum(name) => types.enums[name]
+            .iter()
+            .filter(|(variant, _)| {
+                !pats()
+                    .any(|pat| matches!(pat, Pattern::Variant { variant: v, .. } if v == variant))
+            })
+            .map(|(variant, fields)| match fields.len() {
+                0 => format!("`{}::{}`", name, variant),
+                n => format!("`{}::{}({})`", name, variant, vec!["_"; n].join(", ")),
+            })
+            .collect(),
+        Type::Bool => [false, true]
+            .into_iter()
+            .filter(|b| !pats().any(|pat| *pat == Pattern::Bool { value: *b }))
+            .map(|b| format!("`{}`", b))
+            .collect(),
+        _ if ty.is_integer() => {
+            let (min, max) = ty.int_range().unwrap();
+            let mut ranges: Vec<(i128, i128)> = pats()
+                .filter_map(|pat| match pat {
+                    Pattern::Range { lo, hi } => Some((*lo as i128, *hi as i128)),
+                    _ => None,
+                })
+                .collect();
+            ranges.sort();
+            // the bounds are spelled as literals, so that the note can be
+            // pasted back as a pattern
+            let gap = |lo: i128, hi: i128| match lo == hi {
+                true => format!("`{}`", lo),
+                false => format!("`{}..={}`", lo, hi),
+            };
+            // sweep the ranges in order, collecting the holes between them
+            let mut missing = Vec::new();
+            let mut next = min;
+            for (lo, hi) in ranges {
+                if lo > next && next <= max {
+                    missing.push(gap(next, (lo - 1).min(max)));
+                }
+                next = next.max(hi + 1);
+            }
+            if next <= max {
+                missing.push(gap(next, max));
+            }
+            missing
+        }
+        _ => vec!["`_`".to_string()],
+    }
+}
+
+// list patterns like rustc: "`A`", "`A` and `B`", "`A`, `B`, `C` and 2 more"
+fn pattern_list(pats: &[String]) -> String {
+    match pats {
+        [one] => one.clone(),
+        [first @ .., last] if pats.len() <= 3 => format!("{} and {}", first.join(", "), last),
+        _ => format!("{} and {} more", pats[..3].join(", "), pats.len() - 3),
+    }
+}
+
+// A loop enclosing the code being checked
+struct LoopScope {
+    label: Option<String>,
+    // `loop`, `while` or `for`; only `loop` can break with a value
+    keyword: &'static str,
+    // type of the values passed to `break` so far
+    break_ty: Option<Type>,
+}
+
+// Checks the body of one function
+struct Checker<'a> {
+    functions: &'a HashMap<String, Signature>,
+    types: &'a TypeDefs,
+    // declared type of every `static` item
+    statics: &'a HashMap<String, Type>,
+    // type of every variable of the current function, keyed by slot offset
+    vars: HashMap<u64, Type>,
+    // declared return type of the current function, `()` if none
+    ret: Type,
+    // enclosing loops, innermost last
+    loops: Vec<LoopScope>,
+    // errors of the statements checking carried on past
+    errors: Vec<TypeError>,
+}
+
+impl Checker<'_> {
+    // type of a variable; names implicitly declared in legacy mode may be read
+    // before any assignment and then hold an integer
+    fn var_type(&self, offset: u64) -> Type {
+        self.vars.get(&offset).cloned().unwrap_or(Type::I32)
+    }
+
+    // check a loop body; returns the type given to `break`, if any
+    fn check_loop(
+        &mut self,
+        keyword: &'static str,
+        label: &Option<String>,
+        body: &mut Node,
+    ) -> Result<Option<Type>, TypeError> {
+        self.loops.push(LoopScope {
+            label: label.clone(),
+            keyword,
+            break_ty: None,
+        });
+        let result = self.check_expr(body);
+        let scope = self.loops.pop().unwrap();
+        result?;
+        Ok(scope.break_ty)
+    }
+
+    // the loop a `break` or `continue` at `span` refers to
+    fn target_loop(
+        &mut self,
+        label: &Option<String>,
+        keyword: &str,
+        span: Span,
+    ) -> Result<&mut LoopScope, TypeError> {
+        let found = match label {
+            Some(name) => self
+                .loops
+                .iter()
+                .rposition(|scope| scope.label.as_ref() == Some(name)),
+            None => self.loops.len().checked_sub(1),
+        };
+        match (found, label) {
+            (Some(i), _) => Ok(&mut self.loops[i]),
+            (None, Some(name)) => error(span, format!("use of undeclared label `'{}`", name)),
+            (None, None) => error(span, format!("`{}` outside of a loop", keyword)),
+        }
+    }
+
+    // type of a place that can be assigned to
+    fn check_place(&mut self, node: &mut Node) -> Result<Type, TypeError> {
+        if !is_place(node) || matches!(node, Node::StructLit { .. }) {
+            return error(
+                node.span(),
+                "invalid left-hand side of assignment".to_string(),
+            );
+        }
+        self.check_place_ty(node, Some("assign"))
+    }
+
+    // type of a place, which may be a whole struct. `action` is given when the
+    // place is written to or borrowed mutably: then reaching it through a
+    // pointer needs a `&mut` one. Whether a variable was declared `mut` is
+    // checked by the parser.
+    fn check_place_ty(&mut self, node: &mut Node, action: Option<&str>) -> Result<Type, TypeError> {
+        let span = node.span();
+        match node {
+            Node::Var { offset, .. } => Ok(self.var_type(*offset)),
+            Node::Global { name, ty, .. } => {
+                *ty = self.statics[name.as_str()].clone();
+                Ok(ty.clone())
+            }
+            Node::Deref { expr, ty, .. } => match (self.check_expr(expr)?, action) {
+                (Type::RefMut(inner), _) | (Type::Ref(inner), None) => {
+                    *ty = (*inner).clone();
+                    Ok(*inner)
+                }
+                (Type::Ref(_), Some(action)) => {
+                    error(span, format!("cannot {} through a `&` reference", action))
+                }
+                (other, _) => error(span, format!("type `{}` cannot be dereferenced", other)),
+            },
+            Node::Field { .. } => self.check_field(node, action),
+            Node::StructLit { .. } => self.check_struct_lit(node),
+            Node::Index {
+                expr: base,
+                index: idx,
+                ty,
+                ..
+            } => {
+                // any integer type indexes, not only `usize` as in Rust (see the README)
+                let idx_ty = self.check_expr(idx)?;
+                if !idx_ty.is_integer() {
+                    expect(&Type::I32, &idx_ty, "array index", idx.span())?;
+                }
+                // unknown in legacy mode
+                if let Node::Var { offset, .. } = base.as_ref()
+                    && !self.vars.contains_key(offset)
+                {
+                    return Ok(Type::I32);
+                }
+                if !is_place(base) {
+                    return error(span, "cannot index into a temporary value".to_string());
+                }
+                let mut base_ty = self.check_place_ty(base, action)?;
+                // a reference to an array is dereferenced first, so `r[i]`
+                // stands for `(*r)[i]`
+                if let Type::Ref(inner) | Type::RefMut(inner) = &base_ty
+                    && matches!(inner.as_ref(), Type::Array(..))
+                {
+                    if let (Type::Ref(_), Some(action)) = (&base_ty, action) {
+                        return error(span, format!("cannot {} through a `&` reference", action));
+                    }
+                    insert_deref(base);
+                    base_ty = inner.as_ref().clone();
+                }
+                match base_ty {
+                    Type::Array(elem, _) => {
+                        *ty = (*elem).clone();
+                        Ok(*elem)
+                    }
+                    other => error(
+                        span,
+                        format!("cannot index into a value of type `{}`", other),
+                    ),
+                }
+            }
+            _ => self.check_expr(node),
+        }
+    }
+
+    // type of the field selected by `node`, whose byte offset is recorded in
+    // it; a reference to a struct is dereferenced first, so `r.x` stands for
+    // `(*r).x`
+    fn check_field(&mut self, node: &mut Node, action: Option<&str>) -> Result<Type, TypeError> {
+        let span = node.span();
+        let Node::Field {
+            expr,
+            name,
+            offset,
+            ty: field_ty,
+            ..
+        } = node
+        else {
+            unreachable!("not a field: {:?}", node);
+        };
+        let mut base = self.check_place_ty(expr, action)?;
+        if let Type::Ref(inner) | Type::RefMut(inner) = &base
+            && matches!(inner.as_ref(), Type::Struct(_))
+        {
+            if let (Type::Ref(_), Some(action)) = (&base, action) {
+                return error(span, format!("cannot {} through a `&` reference", action));
+            }
+            insert_deref(expr);
+            base = inner.as_ref().clone();
+        }
+        let field = match &base {
+            Type::Struct(struct_name) => field_layout(self.types, struct_name, name),
+            _ => None,
+        };
+        let Some((field_offset, ty)) = field else {
+            return error(span, format!("no field `{}` on type `{}`", name, base));
+        };
+        *offset = field_offset;
+        *field_ty = ty.clone();
+        Ok(ty.clone())
+    }
+
+    // type of a place whose value is read; structs and enums with fields are
+    // only used through references
+    fn check_value(&mut self, node: &mut Node) -> Result<Type, TypeError> {
+        let ty = self.check_place_ty(node, None)?;
+        match aggregate_kind(&ty, self.types) {
+            Some(kind) => error(
+                node.span(),
+                format!(
+                    "{} `{}` cannot be used as a value; borrow it with `&` instead",
+                    kind, ty
+                ),
+            ),
+            None => Ok(ty),
+        }
+    }
+
+    // type of a `match`: every pattern must fit the matched value and together
+    // they must cover all of its values; the arms unify like `if` branches
+    fn check_match(&mut self, node: &mut Node) -> Result<Type, TypeError> {
+        let span = node.span();
+        let Node::Match {
+            expr, arms, by_ref, ..
+        } = node
+        else {
+            unreachable!("not a match: {:?}", node);
+        };
+        // an enum with fields is matched in place, so its fields can be bound
+        let mut ty = if is_place(expr) {
+            self.check_place_ty(expr, None)?
+        } else {
+            self.check_expr(expr)?
+        };
+        // a reference to an enum is matched through, so `match r` stands for
+        // `match *r`, and the names the patterns bind borrow from it
+        let mut reference = None;
+        if let Type::Ref(inner) | Type::RefMut(inner) = ty.clone()
+            && matches!(*inner, Type::Enum(_))
+        {
+            insert_deref(expr);
+            reference = Some(ty);
+            ty = *inner;
+            *by_ref = true;
+        }
+        let mut result: Option<Type> = None;
+        for arm in arms.iter_mut() {
+            for pat in &arm.pats {
+                self.check_pattern(pat, &ty, reference.as_ref(), arm.span)?;
+            }
+            let found = self.check_expr(&mut arm.body)?;
+            // an arm that returns or jumps takes the other arms' type
+            result = match (result, found) {
+                (None, ty) | (Some(Type::Never), ty) | (Some(ty), Type::Never) => Some(ty),
+                (Some(prev), found) if prev == found => Some(prev),
+                (Some(prev), found) => {
+                    return error(
+                        arm.body.span(),
+                        format!(
+                            "`match` arms have incompatible types `{}` and `{}`",
+                            prev, found
+                        ),
+                    );
+                }
+            };
+        }
+        let missing = uncovered(&ty, arms, self.types);
+        if !missing.is_empty() {
+            return error(
+                span,
+                format!(
+                    "non-exhaustive patterns: {} not covered",
+                    pattern_list(&missing)
+                ),
+            );
+        }
+        // a match without arms, on an enum without variants, never finishes
+        Ok(result.unwrap_or(Type::Never))
+    }
+
+    // check that `pat`, of the arm at `span`, can match a value of type `ty`,
+    // and give the names it binds their types. When the value is matched
+    // through `reference`, the names are bound to references of that kind.
+    fn check_pattern(
+        &mut self,
+        pat: &Pattern,
+        ty: &Type,
+        reference: Option<&Type>,
+        span: Span,
+    ) -> Result<(), TypeError> {
+        match pat {
+            Pattern::Wild => Ok(()),
+            Pattern::Bind { offset } => {
+                if let Some(reference) = reference {
+                    return self.bind_pattern(*offset, reference.clone(), span);
+                }
+                if let Some(kind) = aggregate_kind(ty, self.types) {
+                    return error(
+                        span,
+                        format!(
+                            "{} `{}` cannot be used as a value; borrow it with `&` instead",
+                            kind, ty
+                        ),
+                    );
+                }
+                self.bind_pattern(*offset, ty.clone(), span)
+            }
+            Pattern::Range { lo, hi } => {
+                let Some((min, max)) = ty.int_range() else {
+                    return expect(ty, &Type::I32, "pattern", span);
+                };
+                if (*lo as i128) < min || (*hi as i128) > max {
+                    return error(span, format!("literal out of range for `{}`", ty));
+                }
+                Ok(())
+            }
+            Pattern::Bool { .. } => expect(ty, &Type::Bool, "pattern", span),
+            Pattern::Variant {
+                name,
+                variant,
+                fields,
+                ..
+            } => {
+                expect(ty, &Type::Enum(name.clone()), "pattern", span)?;
+                let (_, field_types) = self.types.variant(name, variant).unwrap_or((0, &[]));
+                for (field, field_ty) in fields.iter().zip(field_types.to_vec()) {
+                    if let Some(offset) = field {
+                        let field_ty = match reference {
+                            Some(Type::RefMut(_)) => Type::RefMut(Box::new(field_ty)),
+                            Some(_) => Type::Ref(Box::new(field_ty)),
+                            None => field_ty,
+                        };
+                        self.bind_pattern(*offset, field_ty, span)?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // give a name bound by a pattern its type; alternatives of one pattern
+    // that bind the same name must agree on it
+    fn bind_pattern(&mut self, offset: u64, ty: Type, span: Span) -> Result<(), TypeError> {
+        match self.vars.get(&offset) {
+            Some(prev) => expect(prev, &ty, "pattern binding", span),
+            None => {
+                self.vars.insert(offset, ty);
+                Ok(())
+            }
+        }
+    }
+
+    // check the initializer of a `let` or of a struct field, which stores a
+    // value into the slots starting at the returned offset; also returns the
+    // type of the value and where it is. Integer literals take the `expected`
+    // type, if there is one.
+    fn check_init(
+        &mut self,
+        init: &mut Node,
+        expected: Option<&Type>,
+    ) -> Result<(u64, Type, Span), TypeError> {
+        match init {
+            Node::Assign { lhs, rhs, .. } => {
+                let Node::Var { offset, .. } = lhs.as_ref() else {
+                    unreachable!("`let` binds a variable");
+                };
+                let mut found = self.check_expr(rhs)?;
+                found = match expected {
+                    Some(expected) => infer_literal(rhs, found, expected)?,
+                    None => fallback_literal(rhs, found)?,
+                };
+                Ok((*offset, found, rhs.span()))
+            }
+            aggregate => {
+                match (&aggregate, expected) {
+                    (
+                        Node::ArrayAssign { .. } | Node::ArrayRepeat { .. },
+                        Some(Type::Array(elem, _)),
+                    ) => self.check_array(aggregate, Some(elem))?,
+                    (Node::StructLit { .. }, _) => self.check_struct_lit(aggregate)?,
+                    _ => self.check_expr(aggregate)?,
+                };
+                let (Node::ArrayAssign { offset, .. }
+                | Node::ArrayRepeat { offset, .. }
+                | Node::StructLit { offset, .. }
+                | Node::VariantLit { offset, .. }) = aggregate
+                else {
+                    unreachable!("`let` initializes a variable, an array, a struct or an enum");
+                };
+                Ok((*offset, self.var_type(*offset), aggregate.span()))
+            }
+        }
+    }
+
+    // check the fields of a struct literal and record the struct's type at
+    // its offset
+    fn check_struct_lit(&mut self, node: &mut Node) -> Result<Type, TypeError> {
+        let Node::StructLit {
+            name,
+            offset,
+            fields,
+            ..
+        } = node
+        else {
+            unreachable!("not a struct literal: {:?}", node);
+        };
+        for (field, init) in fields.iter_mut() {
+            // unknown fields were reported by the parser
+            let layout = field_layout(self.types, name, field);
+            let (_, found, value_span) = self.check_init(init, layout.map(|f| f.1))?;
+            if let Some((_, expected)) = layout {
+                expect(
+                    expected,
+                    &found,
+                    &format!("field `{}` of `{}`", field, name),
+                    value_span,
+                )?;
+            }
+        }
+        // the first field shares the struct's offset, so this goes last
+        let ty = Type::Struct(name.clone());
+        self.vars.insert(*offset, ty.clone());
+        Ok(ty)
+    }
+
+    // check an array literal in a `let`, whose integer literal elements take
+    // the `elem` type if one is known, and record the array's type
+    fn check_array(&mut self, node: &mut Node, elem: Option<&Type>) -> Result<Type, TypeError> {
+        match node {
+            Node::ArrayRepeat {
+                offset, value, len, ..
+            } => {
+                let mut found = self.check_expr(value)?;
+                found = match elem {
+                    Some(elem) => infer_literal(value, found, elem)?,
+                    None => fallback_literal(value, found)?,
+                };
+                self.vars
+                    .insert(*offset, Type::Array(Box::new(found), *len));
+            }
+            Node::ArrayAssign {
+                offset, elements, ..
+            } => {
+                let mut elem_ty = elem.cloned();
+                for (i, elem) in elements.iter_mut().enumerate() {
+                    let found = self.check_expr(elem)?;
+                    match &elem_ty {
+                        Some(first) => {
+                            let found = infer_literal(elem, found, first)?;
+                            expect(
+                                first,
+                                &found,
+                                &format!("array element {}", i + 1),
+                                elem.span(),
+                            )?
+                        }
+                        None => elem_ty = Some(found),
+                    }
+                }
+                // the elements take the type of the first one
+                if elem.is_none()
+                    && let Some(first) = &elem_ty
+                {
+                    for element in elements.iter_mut() {
+                        fallback_literal(element, first.clone())?;
+                    }
+                }
+                let ty = Type::Array(
+                    Box::new(elem_ty.unwrap_or(Type::I32)),
+                    elements.len() as u64,
+                );
+                self.vars.insert(*offset, ty);
+            }
+            _ => unreachable!("not an array literal: {:?}", node),
+        }
+        Ok(Type::Unit)
+    }
+
+    // check a statement, recording an error rather than returning it. A
+    // statement with an error is taken to never finish, like `return`, so
+    // that it does not cause errors of its own; a variable it declares keeps
+    // any type it was annotated with.
+    fn check_stmt(&mut self, node: &mut Node) -> Type {
+        match self.check_expr(node) {
+            Ok(ty) => ty,
+            Err(e) => {
+                self.errors.push(e);
+                if let Node::Semi { expr, .. } = node
+                    && let Node::Let {
+                        ty: Some(ty), init, ..
+                    } = expr.as_ref()
+                    && let Node::Assign { lhs, .. } = init.as_ref()
+                    && let Node::Var { offset, .. } = lhs.as_ref()
+                {
+                    self.vars.insert(*offset, ty.clone());
+                }
+                Type::Never
+            }
+        }
+    }
+
+    fn check_expr(&mut self, node: &mut Node) -> Result<Type, TypeError> {
+        let span = node.span();
+        match node {
+            Node::Seq { first, second, .. } => {
+                // the statements before the last and any ending in `;` carry
+                // on past an error
+                let first = self.check_stmt(first);
+                let second = match second.as_mut() {
+                    stmt @ Node::Semi { .. } => self.check_stmt(stmt),
+                    value => self.check_expr(value)?,
+                };
+                // a block that has already returned or jumped has no value of its own
+                if first == Type::Never && second == Type::Unit {
+                    Ok(Type::Never)
+                } else {
+                    Ok(second)
+                }
+            }
+            Node::Semi { expr, .. } => {
+                let found = self.check_expr(expr)?;
+                match fallback_literal(expr, found)? {
+                    Type::Never => Ok(Type::Never),
+                    _ => Ok(Type::Unit),
+                }
+            }
+            Node::Function { name, .. } => {
+                error(span, format!("nested function `{}` is not supported", name))
+            }
+            Node::Struct { name, .. } => {
+                error(span, format!("nested struct `{}` is not supported", name))
+            }
+            Node::Enum { name, .. } => {
+                error(span, format!("nested enum `{}` is not supported", name))
+            }
+            Node::Const { name, .. } => {
+                error(span, format!("nested constant `{}` is not supported", name))
+            }
+            Node::Static { name, .. } => {
+                error(span, format!("nested static `{}` is not supported", name))
+            }
+            Node::Num { .. } => Ok(Type::I32),
+            Node::Unit { .. } => Ok(Type::Unit),
+            Node::Bool { .. } => Ok(Type::Bool),
+            Node::Variant { name, .. } => Ok(Type::Enum(name.clone())),
+            Node::StringLiteral { .. } => Ok(Type::Ref(Box::new(Type::Str))),
+            Node::Var { .. }
+            | Node::Global { .. }
+            | Node::Deref { .. }
+            | Node::Field { .. }
+            | Node::Index { .. } => self.check_value(node),
+            Node::Call { name, args, .. } => {
+                let Some(sig) = self.functions.get(name) else {
+                    // functions defined outside the program (such as `debug1` in the
+                    // function-call test) are linked in later and cannot be checked
+                    for arg in args {
+                        self.check_expr(arg)?;
+                    }
+                    return Ok(Type::I32);
+                };
+                if sig.params.len() != args.len() {
+                    return error(
+                        span,
+                        format!(
+                            "function `{}` takes {} argument(s) but {} were supplied",
+                            name,
+                            sig.params.len(),
+                            args.len()
+                        ),
+                    );
+                }
+                for (i, (param, arg)) in sig.params.iter().zip(args).enumerate() {
+                    let found = self.check_expr(arg)?;
+                    let found = infer_literal(arg, found, param)?;
+                    expect(
+                        param,
+                        &found,
+                        &format!("argument {} of call to `{}`", i + 1, name),
+                        arg.span(),
+                    )?;
+                }
+                // functions without a declared return type yield `()`
+                Ok(sig.ret.clone().unwrap_or(Type::Unit))
+            }
+            Node::Syscall { name, args, .. } => {
+                if args.len() != 1 {
+                    return error(
+                        span,
+                        format!(
+                            "system call `{}` takes 1 argument but {} were supplied",
+                            name,
+                            args.len()
+                        ),
+                    );
+                }
+                let found = self.check_expr(&mut args[0])?;
+                expect(
+                    &Type::Ref(Box::new(Type::Str)),
+                    &found,
+                    &format!("argument 1 of call to `{}`", name),
+                    args[0].span(),
+                )?;
+                // the stub writes up to the null terminator, so nothing after
+                // a `\0` would be written
+                if let Node::StringLiteral { value, .. } = &args[0]
+                    && value.contains('\0')
+                {
+                    return error(
+                        args[0].span(),
+                        format!("string literal passed to `{}` cannot contain `\\0`", name),
+                    );
+                }
+                Ok(Type::I32)
+            }
+            Node::Assign { lhs, rhs, .. } => {
+                // a struct literal is copied into the place slot by slot
+                let found = match rhs.as_ref() {
+                    Node::StructLit { .. } => self.check_place_ty(rhs, None)?,
+                    _ => self.check_expr(rhs)?,
+                };
+                // the first assignment to a variable declares its type, unless
+                // the value needs more than the variable's single slot
+                if let Node::Var { offset, .. } = lhs.as_ref()
+                    && !self.vars.contains_key(offset)
+                    && aggregate_kind(&found, self.types).is_none()
+                {
+                    self.vars.insert(*offset, found.clone());
+                    return Ok(found);
+                }
+                let declared = self.check_place(lhs)?;
+                let found = infer_literal(rhs, found, &declared)?;
+                expect(&declared, &found, "assignment", rhs.span())?;
+                Ok(found)
+            }
+            Node::CompoundAssign {
+                op, lhs, rhs, ty, ..
+            } => {
+                let mut r = self.check_expr(rhs)?;
+                let l = self.check_place(lhs)?;
+                if !matches!(op, OpKind::Shl | OpKind::Shr) {
+                    r = infer_literal(rhs, r, &l)?;
+                }
+                *ty = l.clone();
+                binary_type(*op, l, r, span)
+            }
+            Node::BinaryOp {
+                op, lhs, rhs, ty, ..
+            } => {
+                let mut l = self.check_expr(lhs)?;
+                let mut r = self.check_expr(rhs)?;
+                // an integer literal takes the type of the other operand,
+                // except for a shift amount; arithmetic on two literals is
+                // typed by where it is used instead
+                let literals = is_int_literal(lhs) && is_int_literal(rhs);
+                if !matches!(op, OpKind::Shl | OpKind::Shr) && !literals {
+                    l = infer_literal(lhs, l, &r)?;
+                    r = infer_literal(rhs, r, &l)?;
+                }
+                // nothing decides the type of literals that are compared
+                if literals
+                    && matches!(
+                        op,
+                        OpKind::Eq | OpKind::Ne | OpKind::Lt | OpKind::Gt | OpKind::Le | OpKind::Ge
+                    )
+                {
+                    l = fallback_literal(lhs, l)?;
+                    r = fallback_literal(rhs, r)?;
+                }
+                // record which instructions the backends have to generate
+                *ty = l.clone();
+                binary_type(*op, l, r, span)
+            }
+            Node::Cast { expr, ty, .. } => {
+                let from = self.check_expr(expr)?;
+                let castable =
+                    from.is_integer() || matches!(from, Type::Bool | Type::Enum(_) | Type::Never);
+                if !castable {
+                    return error(span, format!("non-primitive cast: `{}` as `{}`", from, ty));
+                }
+                if !ty.is_integer() {
+                    return error(span, format!("cannot cast `{}` as `{}`", from, ty));
+                }
+                Ok(ty.clone())
+            }
+            Node::Not {
+                expr,
+                bitwise,
+                ty: operand,
+                ..
+            } => {
+                let ty = self.check_expr(expr)?;
+                *operand = ty.clone();
+                // record which `!` the backends have to generate
+                match &ty {
+                    Type::Bool => *bitwise = false,
+                    ty if ty.is_integer() => *bitwise = true,
+                    other => {
+                        return error(
+                            span,
+                            format!("cannot apply unary operator `!` to type `{}`", other),
+                        );
+                    }
+                }
+                Ok(ty)
+            }
+            Node::Neg {
+                expr, ty: operand, ..
+            } => {
+                let ty = self.check_expr(expr)?;
+                if !ty.int_bits().is_some_and(|(_, signed)| signed) {
+                    return error(
+                        span,
+                        format!("cannot apply unary operator `-` to type `{}`", ty),
+                    );
+                }
+                *operand = ty.clone();
+                Ok(ty)
+            }
+            Node::Return { expr, .. } => {
+                let ret = self.ret.clone();
+                match expr {
+                    Some(expr) => {
+                        let found = self.check_expr(expr)?;
+                        let found = infer_literal(expr, found, &ret)?;
+                        expect(&ret, &found, "return value", expr.span())?;
+                    }
+                    None => expect(&ret, &Type::Unit, "return value", span)?,
+                }
+                Ok(Type::Never)
+            }
+            Node::If {
+                cond,
+                then_stmt,
+                else_stmt,
+                ..
+            } => {
+                let c = self.check_expr(cond)?;
+                expect(&Type::Bool, &c, "`if` condition", cond.span())?;
+                let then_ty = self.check_expr(then_stmt)?;
+                match else_stmt {
+                    Some(es) => {
+                        let else_ty = self.check_expr(es)?;
+                        // a branch that returns or jumps takes the other one's type
+                        match (then_ty, else_ty) {
+                            (Type::Never, ty) | (ty, Type::Never) => Ok(ty),
+                            (t, e) if t == e => Ok(t),
+                            (t, e) => error(
+                                span,
+                                format!(
+                                    "`if` and `else` have incompatible types `{}` and `{}`",
+                                    t, e
+                                ),
+                            ),
+                        }
+                    }
+                    // without `else` the `if` is `()`, so its block must be too
+                    None => {
+                        expect(
+                            &Type::Unit,
+                            &then_ty,
+                            "`if` without `else`",
+                            last_stmt(then_stmt).span(),
+                        )?;
+                        Ok(Type::Unit)
+                    }
+                }
+            }
+            Node::While {
+                cond, body, label, ..
+            } => {
+                let c = self.check_expr(cond)?;
+                expect(&Type::Bool, &c, "`while` condition", cond.span())?;
+                self.check_loop("while", label, body)?;
+                Ok(Type::Unit)
+            }
+            Node::For {
+                init,
+                cond,
+                update,
+                body,
+                label,
+                ..
+            } => {
+                self.check_expr(init)?;
+                let c = self.check_expr(cond)?;
+                expect(&Type::Bool, &c, "`for` condition", cond.span())?;
+                self.check_expr(update)?;
+                self.check_loop("for", label, body)?;
+                Ok(Type::Unit)
+            }
+            Node::ForRange {
+                offset,
+                start,
+                end,
+                body,
+                label,
+                ..
+            } => {
+                // a literal bound takes the type of the other one
+                let s = self.check_expr(start)?;
+                let e = self.check_expr(end)?;
+                let s = infer_literal(start, s, &e)?;
+                let e = infer_literal(end, e, &s)?;
+                if !s.is_integer() {
+                    expect(&Type::I32, &s, "range start", start.span())?;
+                }
+                expect(&s, &e, "range end", end.span())?;
+                self.vars.insert(*offset, s);
+                self.check_loop("for", label, body)?;
+                Ok(Type::Unit)
+            }
+            Node::Loop { body, label, .. } => {
+                // the loop evaluates to the value its `break`s carry, and
+                // without any `break` it never finishes
+                let ty = self.check_loop("loop", label, body)?;
+                Ok(ty.unwrap_or(Type::Never))
+            }
+            Node::Break { label, value, .. } => {
+                let found = match value {
+                    Some(v) => self.check_expr(v)?,
+                    None => Type::Unit,
+                };
+                let value_span = value.as_deref().map_or(span, Node::span);
+                let scope = self.target_loop(label, "break", span)?;
+                if value.is_some() && scope.keyword != "loop" {
+                    return error(
+                        span,
+                        format!("`break` with value from a `{}` loop", scope.keyword),
+                    );
+                }
+                match &scope.break_ty {
+                    Some(prev) => expect(prev, &found, "`break` value", value_span)?,
+                    None => scope.break_ty = Some(found),
+                }
+                Ok(Type::Never)
+            }
+            Node::Continue { label, .. } => {
+                self.target_loop(label, "continue", span)?;
+                Ok(Type::Never)
+            }
+            Node::Addr { expr, mutable, .. } => {
+                // only places in memory have an address
+                if !is_place(expr) {
+                    return error(
+                        span,
+                        "cannot take the address of this expression".to_string(),
+                    );
+                }
+                if *mutable {
+                    let ty = self.check_place_ty(expr, Some("borrow as mutable"))?;
+                    return Ok(Type::RefMut(Box::new(ty)));
+                }
+                Ok(Type::Ref(Box::new(self.check_place_ty(expr, None)?)))
+            }
+            Node::Let { ty, init, .. } => {
+                // the initializer declares the variable with the type of its value
+                let (offset, found, value_span) = self.check_init(init, ty.as_ref())?;
+                match ty {
+                    Some(declared) => {
+                        expect(declared, &found, "`let` initializer", value_span)?;
+                        self.vars.insert(offset, declared.clone());
+                    }
+                    None => {
+                        self.vars.insert(offset, found);
+                    }
+                }
+                Ok(Type::Unit)
+            }
+            Node::ArrayAssign { .. } | Node::ArrayRepeat { .. } => self.check_array(node, None),
+            // a struct literal outside of a `let` is a temporary value
+            Node::StructLit { .. } => self.check_value(node),
+            Node::VariantLit {
+                name,
+                variant,
+                offset,
+                fields,
+                ..
+            } => {
+                let (_, expected) = self.types.variant(name, variant).unwrap_or((0, &[]));
+                for (i, (init, expected)) in fields.iter_mut().zip(expected).enumerate() {
+                    let (_, found, value_span) = self.check_init(init, Some(expected))?;
+                    expect(
+                        expected,
+                        &found,
+                        &format!("field {} of `{}::{}`", i, name, variant),
+                        value_span,
+                    )?;
+                }
+                self.vars.insert(*offset, Type::Enum(name.clone()));
+                Ok(Type::Unit)
+            }
+            Node::Match { .. } => self.check_match(node),
+        }
+    }
+}
+
+// the statement whose value a block yields
+fn last_stmt(node: &mut Node) -> &mut Node {
+    match node {
+        Node::Seq { second, .. } => last_stmt(second),
+        _ => node,
+    }
+}
+
+// collect the functions and the `const` and `static` items of the program in
+// definition order, the struct and enum declarations into `types` and the
+// type of every `static` into `statics`
+fn collect_items<'a>(
+    node: &'a mut Node,
+    out: &mut Vec<&'a mut Node>,
+    types: &mut TypeDefs,
+    statics: &mut HashMap<String, Type>,
+) {
+    match node {
+        Node::Seq { first, second, .. } => {
+            collect_items(first, out, types, statics);
+            collect_items(second, out, types, statics);
+        }
+        Node::Function { .. } | Node::Const { .. } => out.push(node),
+        Node::Static { name, ty, .. } => {
+            statics.insert(name.clone(), ty.clone());
+            out.push(node);
+        }
+        Node::Struct { name, fields, .. } => {
+            types.structs.insert(name.clone(), fields.clone());
+        }
+        Node::Enum { name, variants, .. } => {
+            types.enums.insert(name.clone(), variants.clone());
+        }
+        _ => {}
+    }
+}
+
+// structs and enums with fields are passed to and returned from functions by
+// reference only
+fn check_signature(
+    params: &[Type],
+    ret: &Option<Type>,
+    types: &TypeDefs,
+    span: Span,
+) -> Result<(), TypeError> {
+    if let Some((kind, ty)) = params
+        .iter()
+        .find_map(|ty| Some((aggregate_kind(ty, types)?, ty)))
+    {
+        return error(
+            span,
+            format!(
+                "{} `{}` cannot be passed by value; take `&{}` instead",
+                kind, ty, ty
+            ),
+        );
+    }
+    if let Some(ty) = ret
+        && let Some(kind) = aggregate_kind(ty, types)
+    {
+        return error(
+            span,
+            format!("{} `{}` cannot be returned by value", kind, ty),
+        );
+    }
+    Ok(())
+}
+
+/// Check the types of the whole program: operands of binary operators, call
+/// arguments, `return` values against the declared return type, or `()`
+/// without one, and `if`, `while` and `for` conditions, struct fields,
+/// `match` patterns, which must cover every value, and the initializers of
+/// `const` and `static` items. Checking stops at the first error in an item
+/// but carries on with the next one, so every failing item is reported.
+pub fn check(program: &mut Node) -> Result<(), Vec<TypeError>> {
+    let mut items = Vec::new();
+    let mut types = TypeDefs::default();
+    let mut statics = HashMap::new();
+    collect_items(program, &mut items, &mut types, &mut statics);
+    let mut functions = HashMap::new();
+    let mut errors = Vec::new();
+    for func in &items {
+        if let Node::Function {
+            name,
+            arg_types,
+            ret,
+            ..
+        } = func
+        {
+            let sig = Signature {
+                params: arg_types.clone(),
+                ret: ret.clone(),
+            };
+            if functions.insert(name.clone(), sig).is_some() {
+                errors.push(TypeError {
+                    msg: format!("the name `{}` is defined multiple times", name),
+                    span: func.span(),
+                });
+            }
+        }
+    }
+    for item in items {
+        let span = item.span();
+        let mut checker = Checker {
+            functions: &functions,
+            types: &types,
+            statics: &statics,
+            vars: HashMap::new(),
+            ret: Type::Unit,
+            loops: Vec::new(),
+            errors: Vec::new(),
+        };
+        let (result, what) = match item {
+            Node::Function {
+                name,
+                args,
+                arg_types,
+                ret,
+                body,
+                locals,
+                ..
+            } => {
+                checker.ret = ret.clone().unwrap_or(Type::Unit);
+                for (arg, ty) in args.iter().zip(arg_types.iter()) {
+                    if let Node::Var { offset, .. } = arg {
+                        checker.vars.insert(*offset, ty.clone());
+                    }
+                }
+                // the value of the body is the return value, as if returned
+                let result = check_signature(arg_types, ret, &types, span)
+                    .and_then(|_| checker.check_expr(body))
+                    .and_then(|found| {
+                        let tail = last_stmt(body);
+                        let found = infer_literal(tail, found, &checker.ret)?;
+                        expect(&checker.ret, &found, "function body", tail.span())
+                    });
+                // the backends read and write each variable at its type's width
+                *locals = std::mem::take(&mut checker.vars);
+                (result, format!("function `{}`", name))
+            }
+            Node::Const { name, ty, init, .. } => {
+                let init_span = init.span();
+                let result = checker
+                    .check_expr(init)
+                    .and_then(|found| infer_literal(init, found, ty))
+                    .and_then(|found| expect(ty, &found, "`const` initializer", init_span));
+                (result, format!("constant `{}`", name))
+            }
+            Node::Static { name, ty, init, .. } => {
+                let result =
+                    checker
+                        .check_init(init, Some(ty))
+                        .and_then(|(_, found, init_span)| {
+                            expect(ty, &found, "`static` initializer", init_span)
+                        });
+                (result, format!("static `{}`", name))
+            }
+            _ => unreachable!("only items are collected"),
+        };
+        for e in checker.errors.into_iter().chain(result.err()) {
+            errors.push(TypeError {
+                msg: format!("{} (in {})", e.msg, what),
+                span: e.span,
+            });
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::program;
+    use crate::symbol::SymbolTable;
+    use crate::token::tokenize;
+
+    // check a program, returning the first error
+    fn check_src(src: &str) -> Result<(), TypeError> {
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let mut node = program(&mut iter, &mut vars).unwrap();
+        check(&mut node).map_err(|mut errors| errors.remove(0))
+    }
+
+    #[test]
+    fn test_check_ok() {
+        let src = "fn add(a: i32, b: i32) -> i32 { return a + b; }
+                   fn main() { let x = add(1, 2); let p = &x; if (*p == 3) { write(\"ok\"); } }";
+        check_src(src).unwrap();
+    }
+
+    #[test]
+    fn test_check_arrays() {
+        let src = "fn main() -> i32 { let names = [\"a\", \"b\"]; let nums = [1, 2];
+                   let i = 0; write(names[i]); return nums[1] + 1; }";
+        check_src(src).unwrap();
+    }
+
+    #[test]
+    fn test_error_string_in_arithmetic() {
+        let err = check_src("fn main() { let s = \"a\"; return s + 1; }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "cannot apply `+` to `&str` and `i32` (in function `main`)"
+        );
+    }
+
+    #[test]
+    fn test_error_call_argument_type() {
+        let err =
+            check_src("fn f(n: i32) -> i32 { return n; } fn main() { f(\"x\"); }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "mismatched types: expected `i32`, found `&str` in argument 1 of call to `f` (in function `main`)"
+        );
+    }
+
+    #[test]
+    fn test_error_call_arity() {
+        let err = check_src("fn f(n: i32) -> i32 { return n; } fn main() { f(); }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "function `f` takes 1 argument(s) but 0 were supplied (in function `main`)"
+        );
+    }
+
+    #[test]
+    fn test_error_return_type() {
+        let err = check_src("fn f() -> i32 { return 1 == 1; } fn main() { f(); }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "mismatched types: expected `i32`, found `bool` in return value (in function `f`)"
+        );
+    }
+
+    #[test]
+    fn test_error_condition_not_bool() {
+        let err = check_src("fn main() { let mut a = 1; while (a) { a = 0; } }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "mismatched types: expected `bool`, found `i32` in `while` condition (in function `main`)"
+        );
+    }
+
+    #[test]
+    fn test_error_assignment_changes_type() {
+        let err = check_src("fn main() { let mut a = 1; a = \"s\"; }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "mismatched types: expected `i32`, found `&str` in assignment (in function `main`)"
+        );
+    }
+
+    #[test]
+    fn test_error_deref_non_reference() {
+        let err = check_src("fn main() { let a = 1; return *a; }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "type `i32` cannot be dereferenced (in function `main`)"
+        );
+    }
+
+    #[test]
+    fn test_error_points_at_expression() {
+        let src = "fn main() { let mut a = 1; a = \"s\"; }";
+        let err = check_src(src).unwrap_err();
+        assert_eq!(&src[err.span.start..err.span.end], "\"s\"");
+    }
+
+    #[test]
+    fn test_error_invalid_assignment_target() {
+        let src = "fn main() { let a = 1; a + 1 = 2; }";
+        let err = check_src(src).unwrap_err();
+        assert_eq!(
+            err.msg,
+            "invalid left-hand side of assignment (in function `main`)"
+        );
+        assert_eq!(&src[err.span.start..err.span.end], "a + 1");
+    }
+
+    #[test]
+    fn test_assign_to_element_and_deref() {
+        check_src(
+            "fn main() { let mut arr = [1, 2]; let mut a = 0; let p = &mut a; arr[1] = 5; *p = arr[0]; *p += 2; arr[0] %= 2; let q = &arr[1]; }",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_error_element_assignment_type() {
+        let src = "fn main() { let mut arr = [1, 2]; arr[0] = \"s\"; }";
+        let err = check_src(src).unwrap_err();
+        assert_eq!(
+            err.msg,
+            "mismatched types: expected `i32`, found `&str` in assignment (in function `main`)"
+        );
+    }
+
+    #[test]
+    fn test_error_compound_assignment_operands() {
+        let src = "fn main() { let mut a = 1; a += 1 == 1; }";
+        let err = check_src(src).unwrap_err();
+        assert_eq!(
+            err.msg,
+            "cannot apply `+` to `i32` and `bool` (in function `main`)"
+        );
+        assert_eq!(&src[err.span.start..err.span.end], "a += 1 == 1");
+    }
+
+    #[test]
+    fn test_error_assign_through_shared_reference() {
+        let src = "fn main() { let mut a = 1; let p = &a; *p = 2; }";
+        let err = check_src(src).unwrap_err();
+        assert_eq!(
+            err.msg,
+            "cannot assign through a `&` reference (in function `main`)"
+        );
+        assert_eq!(&src[err.span.start..err.span.end], "*p");
+    }
+
+    #[test]
+    fn test_error_borrow_mut_through_shared_reference() {
+        let err = check_src("fn f(p: &i32) { let q = &mut *p; } fn main() { f(&1); }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "cannot borrow as mutable through a `&` reference (in function `f`)"
+        );
+    }
+
+    #[test]
+    fn test_mut_reference_coerces_to_shared() {
+        check_src(
+            "fn get(p: &i32) -> i32 { *p } fn set(p: &mut i32) { *p = get(p) + 1; } fn main() { let mut x = 1; set(&mut x); get(&mut x); }",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_error_shared_reference_for_mut_parameter() {
+        let err = check_src("fn set(p: &mut i32) { *p = 1; } fn main() { let x = 1; set(&x); }")
+            .unwrap_err();
+        assert_eq!(
+            err.msg,
+            "mismatched types: expected `&mut i32`, found `&i32` in argument 1 of call to `set` (in function `main`)"
+        );
+    }
+
+    #[test]
+    fn test_let_type_annotations() {
+        check_src(
+            "fn main() { let x: i32 = 5; let b: bool = x > 1; let a: [i32; 4] = [x; 4]; let p: &i32 = &a[0]; }",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_error_let_annotation_mismatch() {
+        let src = "fn main() { let b: bool = 1; }";
+        let err = check_src(src).unwrap_err();
+        assert_eq!(
+            err.msg,
+            "mismatched types: expected `bool`, found `i32` in `let` initializer (in function `main`)"
+        );
+        assert_eq!(&src[err.span.start..err.span.end], "1");
+    }
+
+    #[test]
+    fn test_error_array_length_mismatch() {
+        let err = check_src("fn main() { let a: [i32; 3] = [0; 4]; }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "mismatched types: expected `[i32; 3]`, found `[i32; 4]` in `let` initializer (in function `main`)"
+        );
+    }
+
+    const POS: &str = "struct Pos { x: i32, y: i32 } struct Piece { pos: Pos, moves: [i32; 2] }";
+
+    #[test]
+    fn test_structs_through_references() {
+        let src = format!(
+            "{} fn step(p: &mut Piece) {{ p.pos.x += p.moves[1]; }}
+             fn main() -> i32 {{ let mut a: Piece = Piece {{ pos: Pos {{ x: 1, y: 2 }}, moves: [0; 2] }};
+             step(&mut a); let r = &a.pos; r.y }}",
+            POS
+        );
+        check_src(&src).unwrap();
+    }
+
+    #[test]
+    fn test_field_offsets_are_recorded() {
+        let src = format!(
+            "{} fn main() -> i32 {{ let a = Piece {{ pos: Pos {{ x: 1, y: 2 }}, moves: [3, 4] }}; a.pos.y + a.moves[1] }}",
+            POS
+        );
+        let mut iter = tokenize(&src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let mut node = program(&mut iter, &mut vars).unwrap();
+        check(&mut node).unwrap();
+        let Node::Seq { second, .. } = &mut node else {
+            panic!("expected the items of the program");
+        };
+        let Node::Function { body, .. } = second.as_mut() else {
+            panic!("expected main");
+        };
+        let Node::BinaryOp { lhs, .. } = last_stmt(body) else {
+            panic!("expected the sum");
+        };
+        assert!(matches!(lhs.as_ref(), Node::Field { offset: 8, .. }));
+    }
+
+    #[test]
+    fn test_error_struct_field_type() {
+        let src = format!("{} fn main() {{ let p = Pos {{ x: \"a\", y: 1 }}; }}", POS);
+        let err = check_src(&src).unwrap_err();
+        assert_eq!(
+            err.msg,
+            "mismatched types: expected `i32`, found `&str` in field `x` of `Pos` (in function `main`)"
+        );
+    }
+
+    #[test]
+    fn test_error_no_such_field() {
+        let src = format!("{} fn main() {{ let p = Pos {{ x: 1, y: 1 }}; p.z }}", POS);
+        let err = check_src(&src).unwrap_err();
+        assert_eq!(err.msg, "no field `z` on type `Pos` (in function `main`)");
+    }
+
+    #[test]
+    fn test_error_struct_used_as_value() {
+        let src = format!(
+            "{} fn main() {{ let p = Pos {{ x: 1, y: 1 }}; let q = p; }}",
+            POS
+        );
+        let err = check_src(&src).unwrap_err();
+        assert_eq!(
+            err.msg,
+            "struct `Pos` cannot be used as a value; borrow it with `&` instead (in function `main`)"
+        );
+    }
+
+    #[test]
+    fn test_error_struct_passed_by_value() {
+        let src = format!("{} fn f(p: Pos) -> i32 {{ 1 }}", POS);
+        let err = check_src(&src).unwrap_err();
+        assert_eq!(
+            err.msg,
+            "struct `Pos` cannot be passed by value; take `&Pos` instead (in function `f`)"
+        );
+    }
+
+    #[test]
+    fn test_error_assign_field_through_shared_reference() {
+        let src = format!("{} fn f(p: &Pos) {{ p.x = 1; }}", POS);
+        let err = check_src(&src).unwrap_err();
+        assert_eq!(
+            err.msg,
+            "cannot assign through a `&` reference (in function `f`)"
+        );
+    }
+
+    #[test]
+    fn test_arrays_through_references() {
+        check_src(
+            "fn set(a: &mut [i32; 3]) { a[0] = 4; a[1] += a[2]; }
+             fn main() -> i32 { let mut arr = [1, 2, 3]; set(&mut arr); let q = &arr; q[1] }",
+        )
+        .unwrap();
+        let err = check_src("fn f(a: &[i32; 3]) { a[0] = 1; }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "cannot assign through a `&` reference (in function `f`)"
+        );
+    }
+
+    #[test]
+    fn test_struct_literal_outside_let() {
+        let src = format!(
+            "{} fn f(p: &Pos) -> i32 {{ p.x }}
+             fn main() -> i32 {{ let mut a = Pos {{ x: 1, y: 2 }}; a = Pos {{ x: 3, y: 4 }};
+             f(&Pos {{ x: 5, y: 6 }}) + a.x }}",
+            POS
+        );
+        check_src(&src).unwrap();
+        let src = format!(
+            "{} fn main() {{ let mut a = 1; a = Pos {{ x: 3, y: 4 }}; }}",
+            POS
+        );
+        let err = check_src(&src).unwrap_err();
+        assert_eq!(
+            err.msg,
+            "mismatched types: expected `i32`, found `Pos` in assignment (in function `main`)"
+        );
+    }
+
+    #[test]
+    fn test_error_reference_arithmetic() {
+        let err = check_src("fn main() { let a = [1, 2, 3]; let v = &a - 2 * 8; }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "cannot apply `-` to `&[i32; 3]` and `i32` (in function `main`)"
+        );
+    }
+
+    #[test]
+    fn test_error_address_of_temporary() {
+        let err = check_src("fn main() { let p = &1; }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "cannot take the address of this expression (in function `main`)"
+        );
+    }
+
+    #[test]
+    fn test_loop_type_is_break_value() {
+        check_src("fn main() -> i32 { let x = loop { break 1; }; return x + 1; }").unwrap();
+    }
+
+    #[test]
+    fn test_error_break_outside_loop() {
+        let err = check_src("fn main() { break; }").unwrap_err();
+        assert_eq!(err.msg, "`break` outside of a loop (in function `main`)");
+    }
+
+    #[test]
+    fn test_error_undeclared_label() {
+        let err = check_src("fn main() { 'a: loop { continue 'b; } }").unwrap_err();
+        assert_eq!(err.msg, "use of undeclared label `'b` (in function `main`)");
+    }
+
+    #[test]
+    fn test_error_break_value_from_while() {
+        let err = check_src("fn main() { while 1 == 1 { break 3; } }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "`break` with value from a `while` loop (in function `main`)"
+        );
+    }
+
+    #[test]
+    fn test_error_break_values_disagree() {
+        let err =
+            check_src("fn main() { loop { if 1 == 1 { break 1; } break \"s\"; } }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "mismatched types: expected `i32`, found `&str` in `break` value (in function `main`)"
+        );
+    }
+
+    #[test]
+    fn test_block_and_if_expressions() {
+        check_src("fn f(c: bool) -> i32 { let x = if c { 1 } else { 2 }; { let y = x; y * 2 } } fn main() { f(1 == 1); }").unwrap();
+    }
+
+    #[test]
+    fn test_return_as_last_statement() {
+        check_src("fn f() -> i32 { return 1; } fn main() { f(); }").unwrap();
+    }
+
+    #[test]
+    fn test_error_missing_tail_expression() {
+        let src = "fn f() -> i32 { 1; } fn main() { f(); }";
+        let err = check_src(src).unwrap_err();
+        assert_eq!(
+            err.msg,
+            "mismatched types: expected `i32`, found `()` in function body (in function `f`)"
+        );
+        assert_eq!(&src[err.span.start..err.span.end], "1;");
+    }
+
+    #[test]
+    fn test_error_value_from_unit_function() {
+        let err = check_src("fn g() { return 5; } fn main() { g(); }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "mismatched types: expected `()`, found `i32` in return value (in function `g`)"
+        );
+        let err = check_src("fn main() { 5 }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "mismatched types: expected `()`, found `i32` in function body (in function `main`)"
+        );
+        let err = check_src("fn f() -> i32 { return; } fn main() { f(); }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "mismatched types: expected `i32`, found `()` in return value (in function `f`)"
+        );
+        // a unit function may leave early and have an empty body
+        check_src("fn f(a: i32) { if a == 0 { return; } write(\"a\"); } fn g() {} fn main() { f(1); g() }")
+            .unwrap();
+    }
+
+    #[test]
+    fn test_error_if_without_else_has_value() {
+        let src = "fn main() { let c = true; if c { 1 } }";
+        let err = check_src(src).unwrap_err();
+        assert_eq!(
+            err.msg,
+            "mismatched types: expected `()`, found `i32` in `if` without `else` (in function `main`)"
+        );
+        assert_eq!(&src[err.span.start..err.span.end], "1");
+        check_src("fn main() -> i32 { let c = true; if c { return 1 } if c {} 0 }").unwrap();
+    }
+
+    #[test]
+    fn test_error_if_else_incompatible() {
+        let err = check_src("fn main() { let x = if 1 == 1 { 1 } else { \"s\" }; }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "`if` and `else` have incompatible types `i32` and `&str` (in function `main`)"
+        );
+    }
+
+    #[test]
+    fn test_logical_and_bitwise_operators() {
+        check_src(
+            "fn main() -> i32 { let a = 6; if a % 4 == 2 && !(a > 9) || a & 1 == 0 { return a ^ 3 << 1; } return !a; }",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_not_is_bitwise_on_integers() {
+        let src = "fn main() { let a = 1; !a; !(a == 1); }";
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let mut node = program(&mut iter, &mut vars).unwrap();
+        check(&mut node).unwrap();
+        let mut flags = Vec::new();
+        fn collect(node: &Node, flags: &mut Vec<bool>) {
+            match node {
+                Node::Seq { first, second, .. } => {
+                    collect(first, flags);
+                    collect(second, flags);
+                }
+                Node::Function { body, .. } | Node::Semi { expr: body, .. } => collect(body, flags),
+                Node::Not { bitwise, .. } => flags.push(*bitwise),
+                _ => {}
+            }
+        }
+        collect(&node, &mut flags);
+        assert_eq!(flags, vec![true, false]);
+    }
+
+    #[test]
+    fn test_error_logical_operator_on_integers() {
+        let err = check_src("fn main() { let mut a = 1; if a && a == 1 { a = 2; } }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "cannot apply `&&` to `i32` and `bool` (in function `main`)"
+        );
+    }
+
+    #[test]
+    fn test_error_not_on_string() {
+        let err = check_src("fn main() { let s = \"a\"; !s; }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "cannot apply unary operator `!` to type `&str` (in function `main`)"
+        );
+    }
+
+    #[test]
+    fn test_error_nul_in_written_literal() {
+        let err = check_src("const S: &str = \"a\\0b\"; fn main() { write(S); }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "string literal passed to `write` cannot contain `\\0` (in function `main`)"
+        );
+        check_src("fn main() { let s = \"a\\0b\"; write(\"a\\x01b\"); }").unwrap();
+    }
+
+    const DIR: &str = "enum Dir { North, East, South, West } enum Shape { Dot, Rect(i32, i32) }";
+
+    #[test]
+    fn test_match_on_enums_and_integers() {
+        let src = format!(
+            "{} fn area(s: &Shape) -> i32 {{ match s {{ Shape::Rect(w, h) => *w * *h, Shape::Dot => 1 }} }}
+             fn turn(d: Dir) -> i32 {{ match d {{ Dir::North | Dir::South => 0, _ => 1 }} }}
+             fn main() -> i32 {{ let r = Shape::Rect(2, 3); let n = area(&r) + turn(Dir::East);
+             match n {{ -5..=0 => 0, 1..=9 => n, _ => {{ return 9; }} }} }}",
+            DIR
+        );
+        check_src(&src).unwrap();
+    }
+
+    #[test]
+    fn test_match_through_reference_binds_references() {
+        let src = format!(
+            "{} fn side(s: &Shape) -> i32 {{ match s {{ Shape::Rect(w, _) => *w, other => 0 }} }}
+             fn grow(s: &mut Shape) {{ match s {{ Shape::Rect(w, h) => {{ *w += 1; *h += 1; }} _ => {{}} }} }}",
+            DIR
+        );
+        check_src(&src).unwrap();
+        let src = format!(
+            "{} fn f(s: &Shape) -> i32 {{ match s {{ Shape::Rect(w, h) => w, Shape::Dot => 0 }} }}",
+            DIR
+        );
+        let err = check_src(&src).unwrap_err();
+        assert_eq!(
+            err.msg,
+            "`match` arms have incompatible types `&i32` and `i32` (in function `f`)"
+        );
+        let src = format!(
+            "{} fn f(s: &Shape) {{ match s {{ Shape::Rect(w, h) => {{ *w = 1; }} Shape::Dot => {{}} }} }}",
+            DIR
+        );
+        let err = check_src(&src).unwrap_err();
+        assert_eq!(
+            err.msg,
+            "cannot assign through a `&` reference (in function `f`)"
+        );
+    }
+
+    #[test]
+    fn test_error_non_exhaustive_match() {
+        let src = format!(
+            "{} fn f(d: Dir) -> i32 {{ match d {{ Dir::North => 0, Dir::West => 3 }} }}",
+            DIR
+        );
+        let err = check_src(&src).unwrap_err();
+        assert_eq!(
+            err.msg,
+            "non-exhaustive patterns: `Dir::East` and `Dir::South` not covered (in function `f`)"
+        );
+        let src = format!(
+            "{} fn f(s: &Shape) -> i32 {{ match s {{ Shape::Dot => 0 }} }}",
+            DIR
+        );
+        let err = check_src(&src).unwrap_err();
+        assert_eq!(
+            err.msg,
+            "non-exhaustive patterns: `Shape::Rect(_, _)` not covered (in function `f`)"
+        );
+        let err = check_src("fn f(n: i32) -> i32 { match n { 0..=5 => 1, 7 => 2 } }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "non-exhaustive patterns: `-2147483648..=-1`, `6` and `8..=2147483647` not covered (in function `f`)"
+        );
+        // the missing patterns can be pasted back as they are printed
+        check_src(
+            "fn f(n: i32) -> i32 { match n { -2147483648..=-1 => 0, 0..=5 => 1, 7 => 2, 6 => 3, 8..=2147483647 => 4 } }
+             fn g(n: i64) -> i32 { match n { -9223372036854775808..=0 => 0, 1..=9223372036854775807 => 1 } }",
+        )
+        .unwrap();
+        let err = check_src("fn f(b: bool) -> i32 { match b { true => 1 } }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "non-exhaustive patterns: `false` not covered (in function `f`)"
+        );
+    }
+
+    #[test]
+    fn test_check_bool() {
+        let src = "fn even(n: i32) -> bool { n % 2 == 0 }
+                   fn main() { let t: bool = true; let f = !t ^ even(3);
+                   if t && !f || false { match f { true => 1, false => 0 }; } }";
+        check_src(src).unwrap();
+        let err = check_src("fn main() { let n = true + 1; }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "cannot apply `+` to `bool` and `i32` (in function `main`)"
+        );
+        let err = check_src("fn f(n: i32) -> i32 { match n { true => 1, _ => 0 } }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "mismatched types: expected `i32`, found `bool` in pattern (in function `f`)"
+        );
+    }
+
+    #[test]
+    fn test_error_match_pattern_type() {
+        let src = format!(
+            "{} fn f(d: Dir) -> i32 {{ match d {{ 0 => 0, _ => 1 }} }}",
+            DIR
+        );
+        let err = check_src(&src).unwrap_err();
+        assert_eq!(
+            err.msg,
+            "mismatched types: expected `Dir`, found `i32` in pattern (in function `f`)"
+        );
+    }
+
+    #[test]
+    fn test_error_enum_by_value() {
+        let src = format!("{} fn f(s: Shape) {{}}", DIR);
+        let err = check_src(&src).unwrap_err();
+        assert_eq!(
+            err.msg,
+            "enum `Shape` cannot be passed by value; take `&Shape` instead (in function `f`)"
+        );
+    }
+
+    #[test]
+    fn test_error_static_initializer_type() {
+        let err = check_src("static NAMES: [&str; 2] = [\"a\", 2]; fn main() {}").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "mismatched types: expected `&str`, found `i32` in array element 2 (in static `NAMES`)"
+        );
+        let err = check_src("const N: i32 = \"a\"; fn main() {}").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "mismatched types: expected `i32`, found `&str` in `const` initializer (in constant `N`)"
+        );
+    }
+
+    #[test]
+    fn test_integer_types_and_casts() {
+        // literals take the integer type their context expects
+        let src = "fn low(b: u8) -> u8 { b & 15 }
+                   fn main() -> i32 { let a: u8 = 200; let b: i64 = -1; let s = [1, 2, 3];
+                   let c: u16 = 2; let mut x = low(a) as i32 + b as i32 + s[c as usize];
+                   for i in 0..c { x += i as i32; } match a { 0..=127 => x, 128..=255 => -x } }";
+        check_src(src).unwrap();
+        let err = check_src("fn main() { let a: u8 = 1; let b: i32 = 2; a + b; }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "cannot apply `+` to `u8` and `i32` (in function `main`)"
+        );
+        let err = check_src("fn main() { let a: u8 = 256; }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "literal out of range for `u8` (in function `main`)"
+        );
+        let err = check_src("fn f(a: i64) -> i32 { a }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "mismatched types: expected `i32`, found `i64` in function body (in function `f`)"
+        );
+        let err = check_src("fn f(a: u8) -> i32 { match a { 0..=127 => 1 } }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "non-exhaustive patterns: `128..=255` not covered (in function `f`)"
+        );
+    }
+
+    #[test]
+    fn test_error_in_every_statement() {
+        let src = "fn main() -> i32 { let a = 1 + true; let b = \"x\" * 2; let c: bool = a;
+                   let d: bool = 2; if d { a } else { 1 } }";
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let mut node = program(&mut iter, &mut vars).unwrap();
+        let errors = check(&mut node).unwrap_err();
+        let msgs: Vec<&str> = errors.iter().map(|e| e.msg.as_str()).collect();
+        // `a` is taken to be an `i32`, and `d` keeps its annotated type
+        assert_eq!(
+            msgs,
+            vec![
+                "cannot apply `+` to `i32` and `bool` (in function `main`)",
+                "cannot apply `*` to `&str` and `i32` (in function `main`)",
+                "mismatched types: expected `bool`, found `i32` in `let` initializer (in function `main`)",
+                "mismatched types: expected `bool`, found `i32` in `let` initializer (in function `main`)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_error_literal_fallback_out_of_range() {
+        // a literal nothing gives a type to is an `i32`
+        let err = check_src("fn main() -> i32 { let x = 3000000000; if x > 0 { 1 } else { 2 } }")
+            .unwrap_err();
+        assert_eq!(
+            err.msg,
+            "literal out of range for `i32` (in function `main`)"
+        );
+        let err = check_src("fn main() { 3000000000 < 1; }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "literal out of range for `i32` (in function `main`)"
+        );
+        check_src("fn main() { let a: i64 = 3000000000; let b = [a, 3000000000]; }").unwrap();
+    }
+
+    #[test]
+    fn test_error_negate_unsigned() {
+        let err = check_src("fn f(a: u8) -> u8 { -a }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "cannot apply unary operator `-` to type `u8` (in function `f`)"
+        );
+        let err = check_src("fn main() { let a: u32 = -1; }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "cannot apply unary operator `-` to type `u32` (in function `main`)"
+        );
+        check_src("fn main() { let a: i8 = -128; let b = -a; }").unwrap();
+    }
+
+    #[test]
+    fn test_error_cast() {
+        let err = check_src("fn main() { let s = \"a\"; s as i32; }").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "non-primitive cast: `&str` as `i32` (in function `main`)"
+        );
+        let err = check_src("fn main() { let a = 1; a as bool; }").unwrap_err();
+        assert_eq!(err.msg, "cannot cast `i32` as `bool` (in function `main`)");
+    }
+
+    #[test]
+    fn test_external_function_is_unchecked() {
+        check_src("fn main() { let res = 1; debug1(res); }").unwrap();
+    }
+
+    #[test]
+    fn test_errors_in_every_function_are_reported() {
+        let src = "fn f() -> i32 { return \"a\"; } fn main() { let a = 1; while (a) {} }";
+        let mut iter = tokenize(src).unwrap().into_iter().peekable();
+        let mut vars = SymbolTable::new();
+        let mut node = program(&mut iter, &mut vars).unwrap();
+        let errors = check(&mut node).unwrap_err();
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn test_reference_parameter() {
+        let src =
+            "fn get(p: &i32) -> i32 { return *p; } fn main() -> i32 { let a = 3; return get(&a); }";
+        check_src(src).unwrap();
+    }
+}
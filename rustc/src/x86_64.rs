@@ -0,0 +1,594 @@
+use crate::codegen::{
+    LABEL_COUNTER, emit_statics, emit_string, jump_table, panic_label, panic_strings, static_label,
+};
+use crate::ir::{BinOp, BlockId, CmpOp, Function, Inst, IntTy, Module, Panic, Terminator, VReg};
+use std::sync::atomic::Ordering;
+
+// System V integer argument registers, in order
+const ARG_REGS: [&str; 6] = ["rdi", "rsi", "rdx", "rcx", "r8", "r9"];
+
+// label of a basic block, unique across the module
+fn block_label(func: &Function, block: BlockId) -> String {
+    format!(".L{}_{}", func.name, block)
+}
+
+// label of a string literal
+fn string_label(id: usize) -> String {
+    format!(".L.str.{}", id)
+}
+
+// Emits one IR function; every virtual register gets its own frame slot
+// below the local variable region.
+struct FunctionEmitter<'a> {
+    func: &'a Function,
+    frame_size: u64,
+}
+
+impl<'a> FunctionEmitter<'a> {
+    fn new(func: &'a Function) -> Self {
+        let frame_size = (func.locals_size + func.vreg_types.len() as u64 * 8).div_ceil(16) * 16;
+        Self { func, frame_size }
+    }
+
+    // memory operand of a virtual register's slot
+    fn slot(&self, v: VReg) -> String {
+        format!("[rbp - {}]", self.func.locals_size + (v.0 as u64 + 1) * 8)
+    }
+
+    // load a virtual register into a machine register
+    fn load(&self, reg: &str, v: VReg) {
+        println!("    mov {}, {}", reg, self.slot(v));
+    }
+
+    // store a machine register into a virtual register's slot
+    fn store(&self, reg: &str, v: VReg) {
+        println!("    mov {}, {}", self.slot(v), reg);
+    }
+
+    fn prologue(&self) {
+        let name = &self.func.name;
+        println!(".globl {}", name);
+        println!(".type {}, @function", name);
+        println!("{}:", name);
+        // save old frame pointer and set up new
+        println!("    push rbp");
+        println!("    mov rbp, rsp");
+        // reserve space for local variables and virtual registers
+        println!("    sub rsp, {}", self.frame_size);
+        // move incoming arguments into their virtual registers
+        for (i, param) in self.func.params.iter().enumerate() {
+            if let Some(reg) = ARG_REGS.get(i) {
+                self.store(reg, *param);
+            } else {
+                // stack arguments sit above the saved rbp and return address
+                println!("    mov rax, [rbp + {}]", 16 + (i - ARG_REGS.len()) * 8);
+                self.store("rax", *param);
+            }
+        }
+    }
+
+    fn epilogue(&self) {
+        // deallocate the frame, restore frame pointer and return
+        println!("    mov rsp, rbp");
+        println!("    pop rbp");
+        println!("    ret");
+    }
+
+    fn emit_call(&self, dst: VReg, func: &str, args: &[VReg]) {
+        // arguments past the sixth are passed on the stack, keeping rsp 16-byte aligned
+        let stack_args = args.len().saturating_sub(ARG_REGS.len()) as u64;
+        let stack_bytes = (stack_args * 8).div_ceil(16) * 16;
+        if stack_bytes > 0 {
+            println!("    sub rsp, {}", stack_bytes);
+            for (i, arg) in args.iter().enumerate().skip(ARG_REGS.len()) {
+                self.load("rax", *arg);
+                println!("    mov [rsp + {}], rax", (i - ARG_REGS.len()) * 8);
+            }
+        }
+        for (reg, arg) in ARG_REGS.iter().zip(args) {
+            self.load(reg, *arg);
+        }
+        println!("    call {}", func);
+        if stack_bytes > 0 {
+            println!("    add rsp, {}", stack_bytes);
+        }
+        self.store("rax", dst);
+    }
+
+    fn emit_syscall(&self, dst: VReg, name: &str, args: &[VReg]) {
+        match name {
+            "write" => {
+                // For write syscall:
+                // rdi = file descriptor (1 for stdout)
+                // rsi = buffer address
+                // rdx = buffer length
+                self.load("rsi", args[0]); // string address
+                println!("    mov rdi, 1"); // stdout file descriptor
+                // Calculate string length
+                println!("    xor edx, edx"); // Initialize length counter
+                let id = LABEL_COUNTER.fetch_add(1, Ordering::SeqCst);
+                let loop_label = format!(".Lstrlen_loop{}", id);
+                let end_label = format!(".Lstrlen_end{}", id);
+                println!("{}:", loop_label);
+                println!("    cmp byte ptr [rsi + rdx], 0"); // Stop at the null terminator
+                println!("    je {}", end_label);
+                println!("    inc rdx"); // Increment length counter
+                println!("    jmp {}", loop_label);
+                println!("{}:", end_label);
+                println!("    mov rax, 1"); // __NR_write
+                println!("    syscall");
+            }
+            _ => panic!("unsupported system call: {}", name),
+        }
+        self.store("rax", dst);
+    }
+
+    fn emit_inst(&self, inst: &Inst) {
+        match inst {
+            Inst::Const { dst, value } => {
+                println!("    mov rax, {}", value);
+                self.store("rax", *dst);
+            }
+            Inst::Str { dst, id } => {
+                println!("    lea rax, [rip + {}]", string_label(*id));
+                self.store("rax", *dst);
+            }
+            Inst::FrameAddr { dst, offset } => {
+                println!("    lea rax, [rbp - {}]", offset);
+                self.store("rax", *dst);
+            }
+            Inst::StaticAddr { dst, name } => {
+                println!("    lea rax, [rip + {}]", static_label(name));
+                self.store("rax", *dst);
+            }
+            Inst::Load { dst, addr, ty } => {
+                self.load("rax", *addr);
+                // writing eax clears the upper half of rax
+                match ty {
+                    IntTy::I8 => println!("    movsx rax, byte ptr [rax]"),
+                    IntTy::U8 => println!("    movzx eax, byte ptr [rax]"),
+                    IntTy::I16 => println!("    movsx rax, word ptr [rax]"),
+                    IntTy::U16 => println!("    movzx eax, word ptr [rax]"),
+                    IntTy::I32 => println!("    movsxd rax, dword ptr [rax]"),
+                    IntTy::U32 => println!("    mov eax, dword ptr [rax]"),
+                    IntTy::I64 | IntTy::U64 => println!("    mov rax, [rax]"),
+                }
+                self.store("rax", *dst);
+            }
+            Inst::Store { addr, src, ty } => {
+                self.load("rax", *addr);
+                self.load("rdi", *src);
+                match ty {
+                    IntTy::I8 | IntTy::U8 => println!("    mov [rax], dil"),
+                    IntTy::I16 | IntTy::U16 => println!("    mov [rax], di"),
+                    IntTy::I32 | IntTy::U32 => println!("    mov [rax], edi"),
+                    IntTy::I64 | IntTy::U64 => println!("    mov [rax], rdi"),
+                }
+            }
+            Inst::Ext { dst, src, ty } => {
+                self.load("rax", *src);
+                match ty {
+                    IntTy::I8 => println!("    movsx rax, al"),
+                    IntTy::U8 => println!("    movzx eax, al"),
+                    IntTy::I16 => println!("    movsx rax, ax"),
+                    IntTy::U16 => println!("    movzx eax, ax"),
+                    IntTy::I32 => println!("    movsxd rax, eax"),
+                    IntTy::U32 => println!("    mov eax, eax"),
+                    IntTy::I64 | IntTy::U64 => {}
+                }
+                self.store("rax", *dst);
+            }
+            Inst::Bin { op, dst, lhs, rhs } => {
+                self.load("rax", *lhs);
+                self.load("rdi", *rhs);
+                match op {
+                    BinOp::Add => println!("    add rax, rdi"),
+                    BinOp::Sub => println!("    sub rax, rdi"),
+                    BinOp::Mul => println!("    imul rax, rdi"),
+                    BinOp::Div => {
+                        // sign-extend rax into rdx:rax before dividing
+                        println!("    cqo");
+                        println!("    idiv rdi");
+                    }
+                    BinOp::Rem => {
+                        // the remainder is left in rdx
+                        println!("    cqo");
+                        println!("    idiv rdi");
+                        println!("    mov rax, rdx");
+                    }
+                    // unsigned division divides rdx:rax with rdx cleared
+                    BinOp::UDiv => {
+                        println!("    xor edx, edx");
+                        println!("    div rdi");
+                    }
+                    BinOp::URem => {
+                        println!("    xor edx, edx");
+                        println!("    div rdi");
+                        println!("    mov rax, rdx");
+                    }
+                    BinOp::And => println!("    and rax, rdi"),
+                    BinOp::Or => println!("    or rax, rdi"),
+                    BinOp::Xor => println!("    xor rax, rdi"),
+                    // variable shift counts must be in cl
+                    BinOp::Shl => {
+                        println!("    mov rcx, rdi");
+                        println!("    shl rax, cl");
+                    }
+                    BinOp::Shr => {
+                        println!("    mov rcx, rdi");
+                        println!("    sar rax, cl");
+                    }
+                    BinOp::LShr => {
+                        println!("    mov rcx, rdi");
+                        println!("    shr rax, cl");
+                    }
+                }
+                self.store("rax", *dst);
+            }
+            Inst::CheckedBin {
+                op,
+                dst,
+                lhs,
+                rhs,
+                ty,
+                loc,
+            } => {
+                self.load("rax", *lhs);
+                self.load("rdi", *rhs);
+                let id = LABEL_COUNTER.fetch_add(1, Ordering::SeqCst);
+                let ok = format!(".Loverflow_ok{}", id);
+                match (op, ty) {
+                    // `mul` sets CF when the high half of the product in rdx
+                    // is not zero
+                    (BinOp::Mul, IntTy::U64) => {
+                        println!("    mul rdi");
+                        println!("    jnc {}", ok);
+                    }
+                    // OF flags signed overflow, CF an unsigned carry or borrow
+                    (_, IntTy::I64 | IntTy::U64) => {
+                        let mnemonic = match op {
+                            BinOp::Add => "add",
+                            BinOp::Sub => "sub",
+                            _ => "imul",
+                        };
+                        println!("    {} rax, rdi", mnemonic);
+                        let cond = if *ty == IntTy::I64 { "no" } else { "nc" };
+                        println!("    j{} {}", cond, ok);
+                    }
+                    // narrower operands cannot overflow 64 bits, so the
+                    // result only has to survive extension from its width
+                    _ => {
+                        let mnemonic = match op {
+                            BinOp::Add => "add",
+                            BinOp::Sub => "sub",
+                            _ => "imul",
+                        };
+                        println!("    {} rax, rdi", mnemonic);
+                        match ty {
+                            IntTy::I8 => println!("    movsx rcx, al"),
+                            IntTy::U8 => println!("    movzx ecx, al"),
+                            IntTy::I16 => println!("    movsx rcx, ax"),
+                            IntTy::U16 => println!("    movzx ecx, ax"),
+                            IntTy::I32 => println!("    movsxd rcx, eax"),
+                            _ => println!("    mov ecx, eax"),
+                        }
+                        println!("    cmp rax, rcx");
+                        println!("    je {}", ok);
+                    }
+                }
+                println!("    lea r12, [rip + {}]", string_label(*loc));
+                println!("    jmp {}", panic_label(Panic::Overflow(*op)));
+                println!("{}:", ok);
+                self.store("rax", *dst);
+            }
+            Inst::CheckIndex { index, len, loc } => {
+                self.load("rax", *index);
+                println!("    mov rcx, {}", len);
+                println!("    cmp rax, rcx");
+                let id = LABEL_COUNTER.fetch_add(1, Ordering::SeqCst);
+                let ok = format!(".Lindex_ok{}", id);
+                println!("    jb {}", ok);
+                // the panic does not return, so any register may carry its operands
+                println!("    mov r14, rax");
+                println!("    mov r13, rcx");
+                println!("    lea r12, [rip + {}]", string_label(*loc));
+                println!("    jmp {}", panic_label(Panic::Bounds));
+                println!("{}:", ok);
+            }
+            Inst::CheckDivisor { op, divisor, loc } => {
+                self.load("rax", *divisor);
+                println!("    test rax, rax");
+                let id = LABEL_COUNTER.fetch_add(1, Ordering::SeqCst);
+                let ok = format!(".Ldivisor_ok{}", id);
+                println!("    jnz {}", ok);
+                println!("    lea r12, [rip + {}]", string_label(*loc));
+                println!("    jmp {}", panic_label(Panic::ZeroDivisor(*op)));
+                println!("{}:", ok);
+            }
+            Inst::CheckDivOverflow {
+                op,
+                dividend,
+                divisor,
+                ty,
+                loc,
+            } => {
+                self.load("rax", *dividend);
+                self.load("rdi", *divisor);
+                let id = LABEL_COUNTER.fetch_add(1, Ordering::SeqCst);
+                let ok = format!(".Ldivide_ok{}", id);
+                println!("    cmp rdi, -1");
+                println!("    jne {}", ok);
+                println!("    mov rcx, {}", ty.min());
+                println!("    cmp rax, rcx");
+                println!("    jne {}", ok);
+                println!("    lea r12, [rip + {}]", string_label(*loc));
+                println!("    jmp {}", panic_label(Panic::Overflow(*op)));
+                println!("{}:", ok);
+            }
+            Inst::CheckShift {
+                op,
+                amount,
+                bits,
+                loc,
+            } => {
+                self.load("rax", *amount);
+                let id = LABEL_COUNTER.fetch_add(1, Ordering::SeqCst);
+                let ok = format!(".Lshift_ok{}", id);
+                println!("    cmp rax, {}", bits);
+                println!("    jb {}", ok);
+                println!("    lea r12, [rip + {}]", string_label(*loc));
+                println!("    jmp {}", panic_label(Panic::Overflow(*op)));
+                println!("{}:", ok);
+            }
+            Inst::CheckNeg { src, ty, loc } => {
+                self.load("rax", *src);
+                let id = LABEL_COUNTER.fetch_add(1, Ordering::SeqCst);
+                let ok = format!(".Lneg_ok{}", id);
+                println!("    mov rcx, {}", ty.min());
+                println!("    cmp rax, rcx");
+                println!("    jne {}", ok);
+                println!("    lea r12, [rip + {}]", string_label(*loc));
+                println!("    jmp {}", panic_label(Panic::NegOverflow));
+                println!("{}:", ok);
+            }
+            Inst::Cmp { op, dst, lhs, rhs } => {
+                self.load("rax", *lhs);
+                self.load("rdi", *rhs);
+                println!("    cmp rax, rdi");
+                println!("    set{} al", cond(*op));
+                println!("    movzx eax, al");
+                self.store("rax", *dst);
+            }
+            Inst::Copy { dst, src } => {
+                self.load("rax", *src);
+                self.store("rax", *dst);
+            }
+            Inst::Call { dst, func, args } => self.emit_call(*dst, func, args),
+            Inst::Syscall { dst, name, args } => self.emit_syscall(*dst, name, args),
+        }
+    }
+
+    fn emit_terminator(&self, term: &Terminator, next: Option<BlockId>) {
+        match term {
+            Terminator::Jump(target) => {
+                // fall through to the next block when possible
+                if Some(*target) != next {
+                    println!("    jmp {}", block_label(self.func, *target));
+                }
+            }
+            Terminator::Branch {
+                cond,
+                then_bb,
+                else_bb,
+            } => {
+                self.load("rax", *cond);
+                println!("    test rax, rax");
+                println!("    je {}", block_label(self.func, *else_bb));
+                if Some(*then_bb) != next {
+                    println!("    jmp {}", block_label(self.func, *then_bb));
+                }
+            }
+            Terminator::CmpBranch {
+                op,
+                lhs,
+                rhs,
+                then_bb,
+                else_bb,
+            } => {
+                self.load("rax", *lhs);
+                self.load("rdi", *rhs);
+                println!("    cmp rax, rdi");
+                // branch away on the negated condition to fall into `then_bb`
+                if Some(*then_bb) == next {
+                    let else_label = block_label(self.func, *else_bb);
+                    println!("    j{} {}", cond(op.negate()), else_label);
+                    return;
+                }
+                println!("    j{} {}", cond(*op), block_label(self.func, *then_bb));
+                if Some(*else_bb) != next {
+                    println!("    jmp {}", block_label(self.func, *else_bb));
+                }
+            }
+            Terminator::Switch {
+                value,
+                cases,
+                default,
+            } => {
+                self.load("rax", *value);
+                let default_label = block_label(self.func, *default);
+                let Some((lo, table)) = jump_table(cases, *default) else {
+                    // few or sparse cases: compare against each in turn
+                    for (v, target) in cases {
+                        println!("    mov rdi, {}", v);
+                        println!("    cmp rax, rdi");
+                        println!("    je {}", block_label(self.func, *target));
+                    }
+                    if Some(*default) != next {
+                        println!("    jmp {}", default_label);
+                    }
+                    return;
+                };
+                // index the table by the value's distance from its lowest
+                // case; below it the unsigned comparison sees a huge index
+                let id = LABEL_COUNTER.fetch_add(1, Ordering::SeqCst);
+                let table_label = format!(".Lswitch{}", id);
+                println!("    mov rdi, {}", lo);
+                println!("    sub rax, rdi");
+                println!("    mov rdi, {}", table.len());
+                println!("    cmp rax, rdi");
+                println!("    jae {}", default_label);
+                // entries hold each target's offset from the table
+                println!("    lea rdi, [rip + {}]", table_label);
+                println!("    movsxd rax, dword ptr [rdi + rax*4]");
+                println!("    add rax, rdi");
+                println!("    jmp rax");
+                println!("{}:", table_label);
+                for target in table {
+                    println!(
+                        "    .long {} - {}",
+                        block_label(self.func, target),
+                        table_label
+                    );
+                }
+            }
+            Terminator::Return(v) => {
+                self.load("rax", *v);
+                self.epilogue();
+            }
+        }
+    }
+
+    fn emit(&self) {
+        self.prologue();
+        for (i, block) in self.func.blocks.iter().enumerate() {
+            println!("{}:", block_label(self.func, BlockId(i)));
+            for inst in &block.insts {
+                self.emit_inst(inst);
+            }
+            let next = (i + 1 < self.func.blocks.len()).then_some(BlockId(i + 1));
+            self.emit_terminator(&block.term, next);
+        }
+    }
+}
+
+// condition code of `setcc` and `jcc` for a comparison
+fn cond(op: CmpOp) -> &'static str {
+    match op {
+        CmpOp::Eq => "e",
+        CmpOp::Ne => "ne",
+        CmpOp::Lt => "l",
+        CmpOp::Gt => "g",
+        CmpOp::Le => "le",
+        CmpOp::Ge => "ge",
+        CmpOp::ULt => "b",
+        CmpOp::UGt => "a",
+        CmpOp::ULe => "be",
+        CmpOp::UGe => "ae",
+    }
+}
+
+// the routines failed checks jump to: like a Rust panic, each writes where it
+// happened and its message to stderr and exits with status 101. Checks pass
+// their source location in r12, and a failed index check also passes the
+// length of the array in r13 and the index in r14.
+fn emit_panics(panics: &[Panic]) {
+    if panics.is_empty() {
+        return;
+    }
+    for panic in panics {
+        let label = panic_label(*panic);
+        println!("{}:", label);
+        println!("    call .Lpanic.header");
+        match panic {
+            Panic::Overflow(_) | Panic::ZeroDivisor(_) | Panic::NegOverflow => {}
+            Panic::Bounds => {
+                println!("    lea rsi, [rip + .Lpanic.bounds.len]");
+                println!("    call .Lpanic.write_str");
+                println!("    mov rax, r13");
+                println!("    call .Lpanic.write_num");
+                println!("    lea rsi, [rip + .Lpanic.bounds.index]");
+                println!("    call .Lpanic.write_str");
+                println!("    mov rax, r14");
+                println!("    call .Lpanic.write_num");
+                println!("    lea rsi, [rip + .Lpanic.bounds.end]");
+                println!("    jmp .Lpanic");
+                continue;
+            }
+        }
+        println!("    lea rsi, [rip + {}.msg]", label);
+        println!("    jmp .Lpanic");
+    }
+    // write the rest of the message, at rsi, and exit
+    println!(".Lpanic:");
+    println!("    call .Lpanic.write_str");
+    println!("    mov edi, 101");
+    println!("    mov eax, 60"); // __NR_exit
+    println!("    syscall");
+    // write the `thread 'main' panicked at <location>:` line
+    println!(".Lpanic.header:");
+    println!("    lea rsi, [rip + .Lpanic.thread]");
+    println!("    call .Lpanic.write_str");
+    println!("    mov rsi, r12");
+    println!("    call .Lpanic.write_str");
+    println!("    lea rsi, [rip + .Lpanic.colon]");
+    println!("    call .Lpanic.write_str");
+    println!("    ret");
+    // write the null-terminated string at rsi to stderr
+    println!(".Lpanic.write_str:");
+    println!("    xor edx, edx");
+    println!(".Lpanic.strlen:");
+    println!("    cmp byte ptr [rsi + rdx], 0");
+    println!("    je .Lpanic.write");
+    println!("    inc rdx");
+    println!("    jmp .Lpanic.strlen");
+    println!(".Lpanic.write:");
+    println!("    mov edi, 2"); // stderr
+    println!("    mov eax, 1"); // __NR_write
+    println!("    syscall");
+    println!("    ret");
+    // write rax as an unsigned decimal, whose digits are stored from the end
+    // of a buffer on the stack
+    println!(".Lpanic.write_num:");
+    println!("    sub rsp, 32");
+    println!("    lea rsi, [rsp + 32]");
+    println!("    mov ecx, 10");
+    println!(".Lpanic.digit:");
+    println!("    xor edx, edx");
+    println!("    div rcx");
+    println!("    add dl, 48"); // '0'
+    println!("    dec rsi");
+    println!("    mov byte ptr [rsi], dl");
+    println!("    test rax, rax");
+    println!("    jnz .Lpanic.digit");
+    println!("    lea rdx, [rsp + 32]");
+    println!("    sub rdx, rsi");
+    println!("    mov edi, 2"); // stderr
+    println!("    mov eax, 1"); // __NR_write
+    println!("    syscall");
+    println!("    add rsp, 32");
+    println!("    ret");
+    println!(".section .rodata");
+    for (label, s) in panic_strings(panics) {
+        emit_string(&label, &s);
+    }
+}
+
+/// Generate x86-64 (Intel syntax, System V ABI) assembly for the IR module.
+pub(crate) fn generate(module: &Module) {
+    println!(".intel_syntax noprefix");
+    println!(".text");
+    for func in &module.functions {
+        FunctionEmitter::new(func).emit();
+    }
+    emit_panics(&module.panics());
+    // Emit the string literals in the read-only data section
+    if !module.strings.is_empty() {
+        println!(".section .rodata");
+        for (id, s) in module.strings.iter().enumerate() {
+            emit_string(&string_label(id), s);
+        }
+    }
+    emit_statics(module, ".data", ".bss", string_label);
+    // mark the stack as non-executable for the GNU linker
+    println!(".section .note.GNU-stack,\"\",@progbits");
+}
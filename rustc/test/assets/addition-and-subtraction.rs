@@ -4,8 +4,7 @@
 // Expected return value: 48
 //
 // This file is not compatible with Rust because:
-// 1. Arithmetic expressions cannot be used as standalone expressions
-// 2. The return type of main() is not specified (should be () or Result<(), Box<dyn Error>>)
-fn main() {
-    1 + 50 - 3;
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+fn main() -> i32 {
+    1 + 50 - 3
 }
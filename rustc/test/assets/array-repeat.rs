@@ -0,0 +1,27 @@
+// Test: Type annotations and repeat array literals
+// This test verifies that the compiler can handle:
+// - `let` bindings with a declared type such as `let n: i32 = 4;`
+// - array types such as `[i32; 8]`
+// - repeat array literals such as `[1; 8]`, whose value is evaluated once
+// Expected return value: 23
+//
+// This file is not compatible with Rust because:
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+// 2. Arrays are indexed with integers other than `usize`, which Rust requires
+fn main() -> i32 {
+    let n: i32 = 4;
+    let mut squares: [i32; 8] = [1; 8];
+    for i in 0..n {
+        squares[i] = i * i;
+    }
+    // 0 + 1 + 4 + 9 + 1 + 1 + 1 + 1 = 18
+    let mut sum: i32 = 0;
+    for i in 0..8 {
+        sum += squares[i];
+    }
+    let flags: [bool; 3] = [n > 3; 3];
+    if flags[2] {
+        sum += 5;
+    }
+    sum
+}
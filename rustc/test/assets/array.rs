@@ -5,10 +5,9 @@
 // Expected return value: 3
 //
 // This file is not compatible with Rust because:
-// 1. The return type of main() is not specified (should be () or Result<(), Box<dyn Error>>)
-// 2. The return value of main() is not allowed in Rust (main should return unit type)
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
 
-fn main() {
+fn main() -> i32 {
     let arr = [1, 2, 3, 4, 5];
     return arr[2];
 }
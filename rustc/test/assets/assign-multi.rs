@@ -3,10 +3,10 @@
 // Expected return value: 3
 //
 // This file is not compatible with Rust because:
-// 1. Variables must be declared with 'let' keyword
-// 2. The return type of main() is not specified (should be () or Result<(), Box<dyn Error>>)
-// 3. The variables 'foo' and 'bar' are not declared before use
-// 4. Chained assignments are not allowed in Rust
-fn main() {
-    foo = bar = 2 + 1;
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+// 2. Chained assignments are not allowed in Rust
+fn main() -> i32 {
+    let mut foo = 0;
+    let mut bar = 0;
+    foo = bar = 2 + 1
 }
@@ -3,9 +3,8 @@
 // Expected return value: 3
 //
 // This file is not compatible with Rust because:
-// 1. Variables must be declared with 'let' keyword
-// 2. The return type of main() is not specified (should be () or Result<(), Box<dyn Error>>)
-// 3. The variable 'foo' is not declared before use
-fn main() {
-    foo = 3;
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+fn main() -> i32 {
+    let mut foo = 0;
+    foo = 3
 }
@@ -6,11 +6,10 @@
 // Expected return value: 6
 //
 // This file is not compatible with Rust because:
-// 1. Variables must be declared with 'let' keyword
-// 2. The return type of main() is not specified (should be () or Result<(), Box<dyn Error>>)
-// 3. The return value of main() is not allowed in Rust (main should return unit type)
-// 4. The variables 'a' and 'b' are not declared before use
-fn main() {
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+fn main() -> i32 {
+    let mut a = 0;
+    let mut b = 0;
     a = 1;
     b = 4;
     return a + b + 1;
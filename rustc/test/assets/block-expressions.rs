@@ -0,0 +1,29 @@
+// Test: Block and if expressions with tail values
+// This test verifies that the compiler can handle:
+// - `if` / `else` used as an expression, such as `let x = if c { 1 } else { 2 };`
+// - a block `{ stmts; expr }` whose value is its tail expression
+// - a trailing `;` turning an expression into a statement that yields ()
+// - a function body that returns its tail expression without `return`
+// Expected return value: 36
+//
+// This file is not compatible with Rust because:
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+fn clamp(n: i32, hi: i32) -> i32 {
+    if n > hi { hi } else { n }
+}
+
+fn main() -> i32 {
+    let a = if 2 < 1 { 100 } else { 7 };
+    // 7 * 3 = 21, clamped to 20
+    let b = {
+        let t = a * 3;
+        clamp(t, 20)
+    };
+    // the statement `b + 100;` is discarded
+    let c = {
+        b + 100;
+        b - 4
+    };
+    // 20 + 16 = 36
+    b + c
+}
@@ -0,0 +1,65 @@
+// Test: Booleans
+// This test verifies that the compiler can handle:
+// - the `bool` type with `true` and `false` literals
+// - `bool` parameters, return values, statics and constants folded with `!`
+// - short-circuit `&&` and `||` in conditions, skipping calls with side effects
+// - `!`, `^`, `&` and `|` on booleans
+// - `match` on a `bool` with `true` and `false` patterns
+// Expected return value: 53
+//
+// This file is not compatible with Rust because:
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+// 2. Reading or writing a `static mut` requires an `unsafe` block in Rust
+const DEBUG: bool = !true;
+const BIG: bool = !(1 > 2) && 3 == 3;
+static mut CALLS: i32 = 0;
+
+fn touch(v: bool) -> bool {
+    CALLS += 1;
+    v
+}
+
+fn is_even(n: i32) -> bool {
+    n % 2 == 0
+}
+
+fn score(b: bool) -> i32 {
+    match b {
+        true => 10,
+        false => 1,
+    }
+}
+
+fn main() -> i32 {
+    let mut total = 0;
+    let flag: bool = true;
+    // neither call runs: the left operands decide
+    if false && touch(true) {
+        total += 100;
+    }
+    if true || touch(true) {
+        total += 1;
+    }
+    if !(flag && touch(false)) {
+        total += 2;
+    }
+    let mut i = 0;
+    while i < 10 && !(i == 7) {
+        if is_even(i) || i == 5 {
+            total += 1;
+        }
+        i += 1;
+    }
+    if DEBUG {
+        total += 1000;
+    }
+    if BIG {
+        total += 20;
+    }
+    let x = flag ^ true;
+    let y = (flag & !x) | false;
+    if y == true && x != true {
+        total += 4;
+    }
+    total + score(is_even(4)) + score(x) + CALLS * 10
+}
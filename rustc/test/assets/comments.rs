@@ -6,10 +6,9 @@
 // Expected return value: 10
 //
 // This file is not compatible with Rust because:
-// 1. The return type of main() is not specified (should be () or Result<(), Box<dyn Error>>)
-// 2. The return value of main() is not allowed in Rust (main should return unit type)
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
 // これは、comment outのテストです。
-fn main() {
+fn main() -> i32 {
     // 関数内のコメントアウト
     /*
     これは、
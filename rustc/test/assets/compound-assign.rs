@@ -0,0 +1,39 @@
+// Test: Compound assignment and assignment through places
+// This test verifies that the compiler can handle:
+// - assignment to array elements such as `board[i] = v`
+// - assignment through a dereferenced pointer such as `*p = v`
+// - the compound operators `+=`, `-=`, `*=`, `/=`, `%=`, `&=`, `|=`, `^=`, `<<=` and `>>=`
+// Expected return value: 47
+//
+// This file is not compatible with Rust because:
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+// 2. Arrays are indexed with integers other than `usize`, which Rust requires
+fn main() -> i32 {
+    // flip every other cell of the board in place
+    let mut board = [0, 0, 0, 0, 0, 0];
+    for i in 0..6 {
+        if i % 2 == 0 {
+            board[i] = 1;
+        }
+        board[i] += i;
+    }
+    // board is now [1, 1, 3, 3, 5, 5]
+    let mut total = 0;
+    let p = &mut total;
+    for i in 0..6 {
+        *p += board[i];
+    }
+    // 18 * 3 = 54, / 2 = 27, % 20 = 7
+    total *= 3;
+    total /= 2;
+    total %= 20;
+    // 7 - 1 = 6, 6 << 3 = 48, 48 >> 1 = 24
+    total -= 1;
+    total <<= 3;
+    total >>= 1;
+    // 24 | 7 = 31, 31 & 29 = 29, 29 ^ 50 = 47
+    let mut bits = 24 | 7;
+    bits &= 29;
+    bits ^= 50;
+    return bits + total - 24;
+}
@@ -0,0 +1,23 @@
+// Test: Division by zero
+// This test verifies that the compiler can handle:
+// - `/` and `%` by a variable divisor checked against zero
+// - a zero divisor stopping the program like a panic instead of yielding 0
+// Expected return value: 101
+//
+// This file is not compatible with Rust because:
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+fn average(total: i32, count: i32) -> i32 {
+    total / count
+}
+
+fn main() -> i32 {
+    let mut total = 0;
+    for i in 1..5 {
+        total += i * 10;
+    }
+    if average(total, 4) != 25 || total % 7 != 2 {
+        return 1;
+    }
+    // no values to average
+    average(total, 0)
+}
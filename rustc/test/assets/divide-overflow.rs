@@ -0,0 +1,26 @@
+// Test: Division overflow
+// This test verifies that the compiler can handle:
+// - `/` and `%` by a variable divisor of -1 for any dividend but the lowest
+// - the lowest value of a signed type divided by -1 stopping the program like
+//   a panic instead of trapping or yielding a wrong value
+// Expected return value: 101
+//
+// This file is not compatible with Rust because:
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+fn divide(a: i64, b: i64) -> i64 {
+    a / b
+}
+
+fn main() -> i32 {
+    let min: i32 = -2147483647 - 1;
+    let minus_one: i32 = -1;
+    if (min + 1) / minus_one != 2147483647 || min % 7 != -2 || 9 % minus_one != 0 {
+        return 1;
+    }
+    let low: i64 = -9223372036854775807 - 1;
+    if divide(low + 1, -1) != 9223372036854775807 {
+        return 2;
+    }
+    // the quotient of the lowest `i64` by -1 does not fit
+    divide(low, -1) as i32
+}
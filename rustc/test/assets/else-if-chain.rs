@@ -0,0 +1,29 @@
+// Test: Rust-style conditions and else-if chains
+// This test verifies that the compiler can handle:
+// - `if` and `while` conditions without parentheses
+// - `else if` chains that pick the first matching branch
+// Expected return value: 14
+//
+// This file is not compatible with Rust because:
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+fn classify(n: i32) -> i32 {
+    if n < 0 {
+        return 1;
+    } else if n == 0 {
+        return 2;
+    } else if n < 10 {
+        return 3;
+    } else {
+        return 4;
+    }
+}
+
+fn main() -> i32 {
+    let mut sum = 0;
+    let mut i = 0;
+    while i < 3 {
+        sum = sum + classify(i * 5);
+        i = i + 1;
+    }
+    return sum + classify(0 - 7) + classify(100);
+}
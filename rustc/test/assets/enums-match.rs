@@ -0,0 +1,82 @@
+// Test: Enums and match
+// This test verifies that the compiler can handle:
+// - C-like `enum` declarations and variants with fields
+// - `match` on enums, by value and through `&` references
+// - integer, range and `|` patterns with `_` and binding fallbacks
+// - arrays of enum values
+// - dense matches lowered to a jump table
+// Expected return value: 77
+//
+// This file is not compatible with Rust because:
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+// 2. Enums without `#[derive(Clone, Copy)]` cannot be copied out of an array in Rust
+// 3. Arrays are indexed with integers other than `usize`, which Rust requires
+enum Dir {
+    North,
+    East,
+    South,
+    West,
+}
+
+enum Shape {
+    Dot,
+    Rect(i32, i32),
+    Square(i32),
+}
+
+fn area(shape: &Shape) -> i32 {
+    match shape {
+        Shape::Dot => 1,
+        Shape::Rect(w, h) => *w * *h,
+        Shape::Square(side) => *side * *side,
+    }
+}
+
+fn turn(dir: Dir) -> Dir {
+    match dir {
+        Dir::North => Dir::East,
+        Dir::East => Dir::South,
+        Dir::South => Dir::West,
+        Dir::West => Dir::North,
+    }
+}
+
+fn score(n: i32) -> i32 {
+    match n {
+        0 => 0,
+        1 | 2 | 3 => 1,
+        4..=9 => 2,
+        10..100 => 3,
+        _ => 4,
+    }
+}
+
+fn main() -> i32 {
+    let mut dirs = [Dir::North; 4];
+    for i in 1..4 {
+        dirs[i] = turn(dirs[i - 1]);
+    }
+    // North, East, South, West = 0 + 1 + 2 + 3
+    let mut sum = 0;
+    for i in 0..4 {
+        sum += match dirs[i] {
+            Dir::North => 0,
+            Dir::East => 1,
+            Dir::South => 2,
+            Dir::West => 3,
+        };
+    }
+    let rect = Shape::Rect(3, 4);
+    let square = Shape::Square(5);
+    let dot = Shape::Dot;
+    // 6 + 12 + 25 + 1 + 0 + 1 + 2 + 3 + 4 + a dense match on 23
+    sum + area(&rect) + area(&square) + area(&dot) + score(0) + score(2) + score(7) + score(42)
+        + score(100)
+        + match area(&square) - 2 {
+            20 => 0,
+            21 => 1,
+            22 => 2,
+            23 => 23,
+            n => n,
+        }
+}
@@ -7,18 +7,14 @@
 // Expected: Calculates fibonacci(10)
 //
 // This file is not compatible with Rust because:
-// 1. The return type of functions is not specified
-// 2. The return type of main() is not specified (should be () or Result<(), Box<dyn Error>>)
-// 3. The return value of main() is not allowed in Rust (main should return unit type)
-// 4. Parentheses around if condition are not needed in Rust
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
 fn fib(n: i32) -> i32 {
-    // Note: Rust warns about parentheses around the `if` condition, but our compiler ignores these warnings.
-    if (n <= 1) {
+    if n <= 1 {
         return n;
     }
     return fib(n - 1) + fib(n - 2);
 }
 
-fn main() {
-    fib(10);
+fn main() -> i32 {
+    fib(10)
 }
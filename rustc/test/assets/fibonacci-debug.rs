@@ -8,7 +8,7 @@
 // 2. The `test` function is not marked with #[test] attribute
 // 3. The main() function is missing
 fn fib(n: i32) -> i32 {
-    if (n <= 1) {
+    if n <= 1 {
         return n;
     }
     return fib(n - 1) + fib(n - 2);
@@ -7,13 +7,11 @@
 // Expected return value: 10
 //
 // This file is not compatible with Rust because:
-// 1. Variables must be declared with 'let' keyword
-// 2. The return type of main() is not specified (should be () or Result<(), Box<dyn Error>>)
-// 3. The return value of main() is not allowed in Rust (main should return unit type)
-// 4. The variables 'a' and 'i' are not declared before use
-// 5. For loop syntax is different in Rust (uses range or iterator)
-fn main(){
-    a=0;
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+// 2. For loop syntax is different in Rust (uses range or iterator)
+fn main() -> i32 {
+    let mut a=0;
+    let mut i=0;
     for ( i=0; i<10; i=i+1 ) {
         a=a+1;
     }
@@ -7,15 +7,12 @@
 // Expected return value: 5
 //
 // This file is not compatible with Rust because:
-// 1. Variables must be declared with 'let' keyword
-// 2. The return type of functions is not specified
-// 3. The return type of main() is not specified (should be () or Result<(), Box<dyn Error>>)
-// 4. The return value of main() is not allowed in Rust (main should return unit type)
-fn foo() {
-    a = 3;
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+fn foo() -> i32 {
+    let a = 3;
     return a;
 }
-fn main() {
-    b = foo();
+fn main() -> i32 {
+    let b = foo();
     return b + 2;
 }
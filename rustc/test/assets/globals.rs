@@ -0,0 +1,51 @@
+// Test: Constants and statics
+// This test verifies that the compiler can handle:
+// - top-level `const` items folded at compile time, including array lengths
+//   and constants that use one declared after them
+// - immutable `static` tables shared by every call
+// - `static mut` counters and arrays updated from several functions
+// - `&mut` borrows of `static mut` elements inside `unsafe` blocks
+// Expected return value: 48
+//
+// This file is not compatible with Rust because:
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+// 2. Arrays are indexed with integers other than `usize`, which Rust requires
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+const CELLS: i32 = SIZE * SIZE;
+const SIZE: i32 = 4;
+const GREETING: &str = "globals\n";
+
+static SQUARES: [i32; SIZE] = [0, 1, 4, 9];
+static ORIGIN: Point = Point { x: 2, y: 3 };
+static mut CALLS: i32 = 0;
+static mut GRID: [i32; CELLS] = [0; CELLS];
+
+fn square(n: i32) -> i32 {
+    unsafe {
+        CALLS += 1;
+    }
+    SQUARES[n]
+}
+
+fn mark(row: i32, col: i32, value: i32) {
+    unsafe {
+        let cell = &mut GRID[row * SIZE + col];
+        *cell = value;
+    }
+}
+
+fn main() -> i32 {
+    write(GREETING);
+    mark(1, 2, square(3));
+    mark(3, 3, square(2));
+    let mut total = 0;
+    for i in 0..CELLS {
+        total += unsafe { GRID[i] };
+    }
+    // 13 from the grid, 20 from the two calls, 6 from the origin, 9 from row 1
+    unsafe { total + CALLS * 10 + ORIGIN.x * ORIGIN.y + GRID[SIZE + 2] }
+}
@@ -4,10 +4,9 @@
 // Expected return value: 2
 //
 // This file is not compatible with Rust because:
-// 1. The return type of main() is not specified (should be () or Result<(), Box<dyn Error>>)
-// 2. The return value of main() is not allowed in Rust (main should return unit type)
-fn main() {
-    if (1 == 2) {
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+fn main() -> i32 {
+    if 1 == 2 {
         return 3;
     } else {
         return 2;
@@ -4,15 +4,14 @@
 // Expected return value: 2
 //
 // This file is not compatible with Rust because:
-// 1. The return type of main() is not specified (should be () or Result<(), Box<dyn Error>>)
-// 2. The return value of main() is not allowed in Rust (main should return unit type)
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
 
-fn main() {
-    let res = 0;
-    if (1 == 2) {
+fn main() -> i32 {
+    let mut res = 0;
+    if 1 == 2 {
         res = res + 1;
     }
-    if (1 == 1) {
+    if 1 == 1 {
         res = res + 2;
     }
     return res;
@@ -0,0 +1,33 @@
+// Test: Array index out of bounds
+// This test verifies that the compiler can handle:
+// - indexing checked against the length of the array, also through a reference and in a struct field
+// - an index past the end stopping the program like a panic instead of reading another variable
+// Expected return value: 101
+//
+// This file is not compatible with Rust because:
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+// 2. Arrays are indexed with integers other than `usize`, which Rust requires
+struct Grid {
+    cells: [i32; 4],
+}
+
+fn last(a: &[i32; 8], n: i32) -> i32 {
+    (*a)[n - 1]
+}
+
+fn main() -> i32 {
+    let arr = [1, 2, 3, 4, 5, 6, 7, 8];
+    let guard = 42;
+    let g = Grid {
+        cells: [9, 9, 9, 9],
+    };
+    let mut sum = 0;
+    for i in 0..4 {
+        sum += g.cells[i];
+    }
+    if last(&arr, 8) != 8 || sum != 36 || guard != 42 {
+        return 1;
+    }
+    // one past the end
+    last(&arr, 9)
+}
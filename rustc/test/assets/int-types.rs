@@ -0,0 +1,78 @@
+// Test: Sized integer types
+// This test verifies that the compiler can handle:
+// - `i8`, `i16`, `i32`, `i64`, `u8`, `u16`, `u32`, `u64` and `usize` variables, parameters and return values
+// - `as` casts that truncate, sign-extend and zero-extend
+// - unsigned division, remainder, shifts and comparisons
+// - `u8` statics, array elements and struct fields, written through references
+// - integer literals typed by the context they are used in
+// Expected return value: 32
+//
+// This file is not compatible with Rust because:
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+// 2. Reading or writing a `static mut` requires an `unsafe` block in Rust
+// 3. Arrays are indexed with integers other than `usize`, which Rust requires
+static mut BYTES: [u8; 4] = [200, 5, 128, 7];
+const LIMIT: u16 = 60000;
+
+struct Pixel {
+    r: u8,
+    g: u8,
+    level: i16,
+}
+
+fn brighten(p: &mut Pixel, by: u8) {
+    if p.r < 255 - by {
+        p.r += by;
+    } else {
+        p.r = 255;
+    }
+    p.level -= by as i16;
+}
+
+fn digits(mut n: u64) -> u32 {
+    let mut count: u32 = 0;
+    while n > 0 {
+        n /= 10;
+        count += 1;
+    }
+    count
+}
+
+fn main() -> i32 {
+    let mut score = 0;
+    // truncation, sign extension and zero extension
+    let big: i32 = 300;
+    if big as u8 == 44 {
+        score += 1;
+    }
+    let neg: i8 = -1;
+    if neg as u8 == 255 && neg as i64 == -1 {
+        score += 2;
+    }
+    let huge = neg as u64;
+    // only true as an unsigned comparison
+    if huge > 100 && huge >> 63 == 1 && huge / 2 == 9223372036854775807 {
+        score += 4;
+    }
+    if (huge % 10) as usize == 5 && (-7 as i16) as u16 == 65529 {
+        score += 8;
+    }
+    // byte arrays and narrow struct fields
+    BYTES[1] = BYTES[1] + 250;
+    let mut sum: u32 = 0;
+    for i in 0..4 {
+        sum += BYTES[i] as u32;
+    }
+    let mut p = Pixel {
+        r: 250,
+        g: BYTES[3],
+        level: 0,
+    };
+    brighten(&mut p, 3);
+    brighten(&mut p, 3);
+    if p.r == 255 && p.g == 7 && p.level == -6 {
+        score += 16;
+    }
+    let x: u16 = LIMIT + 5000;
+    score + (sum - 590) as i32 + digits(x as u64 * 1000) as i32 - 8 + (x / 32768) as i32
+}
@@ -0,0 +1,34 @@
+// Test: Remainder, bitwise and logical operators
+// This test verifies that the compiler can handle:
+// - `%` and the bitwise operators `&`, `|`, `^`, `<<` and `>>`
+// - `&&` and `||`, whose right operand only runs when needed
+// - unary `!` on conditions and on integers
+// - Rust precedence between all of them
+// Expected return value: 42
+//
+// This file is not compatible with Rust because:
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+fn main() -> i32 {
+    // 3 + ((12 & 10) ^ 9) % 2 = 3 + 1 = 4
+    let mut bits = 17 % 7 + (12 & 10 ^ 9) % 2;
+    // `<<` binds tighter than `|`: 4 | 8 = 12
+    bits = bits | 1 << 3;
+    // 12 >> 2 = 3
+    let small = bits >> 2;
+    // the division by zero on the right is never evaluated
+    let zero = 0;
+    let mut count = 0;
+    if small == 3 || 1 / zero == 0 {
+        count = count + 10;
+    }
+    if small > 5 && 1 / zero == 0 {
+        count = count + 100;
+    }
+    if !(small > 5) && small != 0 {
+        count = count + 20;
+    }
+    // !small is -4, so this adds 4 - 1 = 3
+    count = count - !small - 1;
+    // 12 + 33 - 3
+    return bits + count - small;
+}
@@ -0,0 +1,45 @@
+// Test: Range loops, infinite loops and loop control
+// This test verifies that the compiler can handle:
+// - `for x in a..b` and `for x in a..=b` range loops
+// - `loop` with `break value`
+// - `break` and `continue`, including labelled ones such as `'outer`
+// Expected return value: 78
+//
+// This file is not compatible with Rust because:
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+fn main() -> i32 {
+    // 0 + 1 + ... + 4 = 10
+    let mut sum = 0;
+    for i in 0..5 {
+        sum = sum + i;
+    }
+    // odd numbers up to 9: 1 + 3 + 5 + 7 + 9 = 25
+    for i in 1..=9 {
+        if i % 2 == 0 {
+            continue;
+        }
+        sum = sum + i;
+    }
+    // pairs (i, j) with j < i until i reaches 4: 0 + 1 + 2 + 3 = 6 pairs
+    let mut pairs = 0;
+    'outer: for i in 0..10 {
+        for j in 0..10 {
+            if i == 4 {
+                break 'outer;
+            }
+            if j >= i {
+                continue 'outer;
+            }
+            pairs = pairs + 1;
+        }
+    }
+    // first power of two above 30
+    let mut n = 1;
+    let power = loop {
+        n = n * 2;
+        if n > 30 {
+            break n;
+        }
+    };
+    return sum + pairs + power + 5;
+}
@@ -0,0 +1,32 @@
+// Test: Mutable bindings and references
+// This test verifies that the compiler can handle:
+// - `let mut` bindings that are reassigned
+// - `&mut` references passed to functions that write through them
+// - `mut` function parameters and `for mut` loop variables
+// - `&mut T` arguments where `&T` is expected
+// Expected return value: 27
+//
+// This file is not compatible with Rust because:
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+fn bump(p: &mut i32, mut by: i32) {
+    by *= 2;
+    *p += by;
+}
+
+fn get(p: &i32) -> i32 {
+    *p
+}
+
+fn main() -> i32 {
+    let mut total = 1;
+    // 1 + 2 + 4 + 6 = 13
+    for mut i in 0..3 {
+        i += 1;
+        bump(&mut total, i);
+    }
+    let mut cells = [0, 0];
+    let c = &mut cells[1];
+    *c = get(&mut total);
+    // 13 + 13 + 1 = 27
+    total + cells[1] + 1
+}
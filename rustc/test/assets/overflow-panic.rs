@@ -0,0 +1,26 @@
+// Test: Integer overflow panics
+// This test verifies that the compiler can handle:
+// - `+`, `-` and `*` that stay in range at their declared width
+// - a multiplication that overflows `u32` stopping the program like a panic
+// Expected return value: 101
+//
+// This file is not compatible with Rust because:
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+fn factorial(n: u32) -> u32 {
+    let mut acc: u32 = 1;
+    for i in 1..n + 1 {
+        acc *= i as u32;
+    }
+    acc
+}
+
+fn main() -> i32 {
+    let small: i8 = 127;
+    let diff: u8 = 255 - 200;
+    // 12! still fits in a u32
+    if factorial(12) != 479001600 || small - 1 != 126 || diff != 55 {
+        return 1;
+    }
+    // 13! does not
+    factorial(13) as i32
+}
@@ -6,13 +6,10 @@
 // Expected return value: 3
 //
 // This file is not compatible with Rust because:
-// 1. Variables must be declared with 'let' keyword
-// 2. The return type of main() is not specified (should be () or Result<(), Box<dyn Error>>)
-// 3. The return value of main() is not allowed in Rust (main should return unit type)
-// 4. The variables 'a' and 'b' are not declared before use
-// 5. References in Rust require explicit type annotations and lifetime specifications
-fn main() {
-    a = 3;
-    b = &a;
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+// 2. References in Rust require explicit type annotations and lifetime specifications
+fn main() -> i32 {
+    let a = 3;
+    let b = &a;
     return *b;
 }
@@ -0,0 +1,21 @@
+// Test: Scoped variables
+// This test verifies that the compiler can handle:
+// - Separate locals for each function
+// - Block scopes that shadow outer variables
+// - Variables going out of scope at the closing brace
+// - Re-declaring a name with 'let' after its scope ended
+fn helper(x: i32) -> i32 {
+    let y = x * 2;
+    return y;
+}
+
+fn main() -> i32 {
+    let x = 1;
+    {
+        let x = 10;
+        let y = x + 1;
+    }
+    let y = helper(3);
+    let x = x + y;
+    return x;
+}
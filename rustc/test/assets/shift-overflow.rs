@@ -0,0 +1,22 @@
+// Test: Shift overflow panics
+// This test verifies that the compiler can handle:
+// - `<<` and `>>` by less than the width of the shifted type
+// - negating any value but the lowest of its type
+// - a shift by the width of the type stopping the program like a panic
+// Expected return value: 101
+//
+// This file is not compatible with Rust because:
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+fn shift(value: i32, amount: i32) -> i32 {
+    value << amount
+}
+
+fn main() -> i32 {
+    let low: i8 = -127;
+    let byte: u8 = 1;
+    if shift(1, 31) != -2147483647 - 1 || -low != 127 || byte << 7 >> 6 != 2 {
+        return 1;
+    }
+    // 32 is the width of `i32`
+    shift(1, 32)
+}
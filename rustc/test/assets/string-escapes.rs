@@ -0,0 +1,14 @@
+// Test: String literal escapes
+// This test verifies that the compiler can handle:
+// - Quote, backslash, tab and newline escapes
+// - \x and \u{...} escapes, including characters outside ASCII
+// - Line continuations that skip the leading whitespace of the next line
+// Expected output: "say \"hi\"\tC:\\tmp\nA\u{e9}\u{1F600} joined\n"
+//
+// This file is not compatible with Rust because:
+// 1. The `write` function is not defined or imported
+fn main() {
+    let s = "say \"hi\"\tC:\\tmp\n\x41\u{e9}\u{1F600} \
+             joined\n";
+    write(s);
+}
@@ -3,9 +3,9 @@
 // Expected return value: 0
 //
 // This file is not compatible with Rust because:
-// 1. String literals cannot be used as standalone expressions
-// 2. The return type of main() is not specified (should be () or Result<(), Box<dyn Error>>)
-fn main() {
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+// 2. String literals cannot be used as standalone expressions
+fn main() -> i32 {
     "Hello, world!";
     return 0;
 }
@@ -0,0 +1,70 @@
+// Test: Structs
+// This test verifies that the compiler can handle:
+// - `struct` declarations with scalar, array and nested struct fields
+// - struct literals, with fields given in any order, also assigned to
+//   places and borrowed as temporaries
+// - reading and writing fields with `.`, including `p.pos.x` and `board.cells[i]`
+// - passing structs to functions by `&` and `&mut` reference
+// Expected return value: 42
+//
+// This file is not compatible with Rust because:
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+// 2. Arrays are indexed with integers other than `usize`, which Rust requires
+struct Pos {
+    x: i32,
+    y: i32,
+}
+
+struct Piece {
+    pos: Pos,
+    color: i32,
+}
+
+struct Board {
+    cells: [i32; 9],
+    moves: i32,
+}
+
+fn place(board: &mut Board, piece: &Piece) {
+    board.cells[piece.pos.y * 3 + piece.pos.x] = piece.color;
+    board.moves += 1;
+}
+
+fn count(board: &Board, color: i32) -> i32 {
+    let mut n = 0;
+    for i in 0..9 {
+        if board.cells[i] == color {
+            n += 1;
+        }
+    }
+    n
+}
+
+fn main() -> i32 {
+    let mut board = Board { cells: [0; 9], moves: 0 };
+    let mut piece = Piece {
+        color: 1,
+        pos: Pos { x: 0, y: 0 },
+    };
+    // fill the diagonal
+    for i in 0..3 {
+        piece = Piece {
+            color: 1,
+            pos: Pos { x: i, y: i },
+        };
+        place(&mut board, &piece);
+    }
+    let pos = &mut piece.pos;
+    *pos = Pos { x: 2, y: 0 };
+    piece.color = 2;
+    place(&mut board, &piece);
+    place(
+        &mut board,
+        &Piece {
+            color: 3,
+            pos: Pos { x: 0, y: 2 },
+        },
+    );
+    // 3 * 10 + 1 + 5 moves + cell 2 holds 2 + 4
+    count(&board, 1) * 10 + count(&board, 2) + board.moves + board.cells[2] + 4
+}
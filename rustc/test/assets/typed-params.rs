@@ -0,0 +1,27 @@
+// Test: Typed function parameters
+// This test verifies that the compiler can handle:
+// - Reference parameters (&i32, &str)
+// - Comparisons producing booleans used as conditions
+// - Return values checked against the declared return type
+// Expected return value: 7
+//
+// This file is not compatible with Rust because:
+// 1. The return value of main() is not allowed in Rust (main should return unit type)
+fn get(p: &i32) -> i32 {
+    return *p;
+}
+
+fn greet(s: &str) -> i32 {
+    write(s);
+    return 0;
+}
+
+fn main() -> i32 {
+    let a = 7;
+    let msg = "typed\n";
+    greet(msg);
+    if get(&a) == 7 {
+        return get(&a);
+    }
+    return 0;
+}
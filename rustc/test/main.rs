@@ -38,10 +38,27 @@ fn main() {
         (2, "./test/assets/if-multi.rs", None),
         (3, "./test/assets/if-else-true.rs", None),
         (2, "./test/assets/if-else-false.rs", None),
+        (14, "./test/assets/else-if-chain.rs", None),
         (10, "./test/assets/while-loop.rs", None),
         (10, "./test/assets/for-loop.rs", None),
         (4, "./test/assets/for-loop-nested.rs", None),
         (60, "./test/assets/for-loop-multi-nested.rs", None),
+        (78, "./test/assets/loops.rs", None),
+        (42, "./test/assets/logical-and-bitwise.rs", None),
+        (47, "./test/assets/compound-assign.rs", None),
+        (36, "./test/assets/block-expressions.rs", None),
+        (27, "./test/assets/mut-references.rs", None),
+        (23, "./test/assets/array-repeat.rs", None),
+        (42, "./test/assets/structs.rs", None),
+        (77, "./test/assets/enums-match.rs", None),
+        (48, "./test/assets/globals.rs", Some("globals\n")),
+        (53, "./test/assets/bool.rs", None),
+        (32, "./test/assets/int-types.rs", None),
+        (101, "./test/assets/overflow-panic.rs", None),
+        (101, "./test/assets/index-out-of-bounds.rs", None),
+        (101, "./test/assets/divide-by-zero.rs", None),
+        (101, "./test/assets/divide-overflow.rs", None),
+        (101, "./test/assets/shift-overflow.rs", None),
         (5, "./test/assets/func-call.rs", None),
         (55, "./test/assets/fibonacci-allow-warnings.rs", None),
         (3, "./test/assets/reference-and-dereference.rs", None),
@@ -53,8 +70,15 @@ fn main() {
             "./test/assets/systemcall-write.rs",
             Some("Hello, \nworld!\n"),
         ),
+        (
+            0,
+            "./test/assets/string-escapes.rs",
+            Some("say \"hi\"\tC:\\tmp\nA\u{e9}\u{1F600} joined\n"),
+        ),
         (3, "./test/assets/array.rs", None),
         (15, "./test/assets/array-sum.rs", None),
+        (7, "./test/assets/scope-shadowing.rs", None),
+        (7, "./test/assets/typed-params.rs", Some("typed\n")),
     ];
 
     fs::create_dir_all("bin").unwrap_or_else(|e| {
@@ -62,8 +86,26 @@ fn main() {
         exit(1);
     });
 
-    let args: Vec<String> = env::args().collect();
-    let parallel: usize = args.get(1).and_then(|s| s.parse().ok()).unwrap_or(10);
+    // parse `[--target <target> | --interp] [parallel_degree]`
+    let mut target = String::from("aarch64-macos");
+    let mut interp = false;
+    let mut parallel: usize = 10;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--target" {
+            target = args.next().unwrap_or_else(|| {
+                eprintln!("--target requires a value");
+                exit(1);
+            });
+        } else if let Some(value) = arg.strip_prefix("--target=") {
+            target = value.to_string();
+        } else if arg == "--interp" {
+            interp = true;
+        } else if let Ok(n) = arg.parse() {
+            parallel = n;
+        }
+    }
+    let target = Arc::new(target);
 
     // Build the rustc compiler once to avoid sequential cargo runs
     let build_status = Command::new("cargo")
@@ -104,6 +146,7 @@ fn main() {
     for _ in 0..parallel {
         let rx = Arc::clone(&rx);
         let rustc_bin = Arc::clone(&rustc_bin);
+        let target = Arc::clone(&target);
         let tx_res = tx_res.clone();
         let handle = thread::spawn(move || {
             loop {
@@ -121,65 +164,84 @@ fn main() {
                     .unwrap()
                     .to_str()
                     .unwrap();
-                let asm_path = format!("bin/arm64-{}.s", test_name);
-                let bin_path = format!("bin/arm64-{}", test_name);
+                let asm_path = format!("bin/{}-{}.s", target, test_name);
+                let bin_path = format!("bin/{}-{}", target, test_name);
 
-                // Generate assembly
-                let asm_file = File::create(&asm_path).unwrap_or_else(|e| {
-                    eprintln!("failed to create asm file {}: {}", asm_path, e);
-                    exit(1);
-                });
-                let gen_status = Command::new(&*rustc_bin)
-                    .arg(input)
-                    .stdout(asm_file)
-                    .status()
-                    .unwrap_or_else(|e| {
-                        eprintln!("failed to run rustc binary {:?}: {}", rustc_bin, e);
+                let output = if interp {
+                    // Run the program with the interpreter, no assembler needed
+                    Command::new(&*rustc_bin)
+                        .arg("--interp")
+                        .arg(input)
+                        .output()
+                        .unwrap_or_else(|e| {
+                            eprintln!("failed to run rustc binary {:?}: {}", rustc_bin, e);
+                            exit(1);
+                        })
+                } else {
+                    // Generate assembly
+                    let asm_file = File::create(&asm_path).unwrap_or_else(|e| {
+                        eprintln!("failed to create asm file {}: {}", asm_path, e);
                         exit(1);
                     });
-                if !gen_status.success() {
-                    // report generation failure and continue
-                    let duration = start.elapsed();
-                    tx_res
-                        .send((input.to_string(), expected, 0, duration, false, None))
-                        .unwrap();
-                    // indicate failure
-                    print!("F");
-                    io::stdout().flush().unwrap();
-                    continue;
-                }
+                    let gen_status = Command::new(&*rustc_bin)
+                        .arg("--target")
+                        .arg(&*target)
+                        .arg(input)
+                        .stdout(asm_file)
+                        .status()
+                        .unwrap_or_else(|e| {
+                            eprintln!("failed to run rustc binary {:?}: {}", rustc_bin, e);
+                            exit(1);
+                        });
+                    if !gen_status.success() {
+                        // report generation failure and continue
+                        let duration = start.elapsed();
+                        tx_res
+                            .send((input.to_string(), expected, 0, duration, false, None))
+                            .unwrap();
+                        // indicate failure
+                        print!("F");
+                        io::stdout().flush().unwrap();
+                        continue;
+                    }
 
-                // Assemble
-                let assemble_status = Command::new("clang")
-                    .arg("-arch")
-                    .arg("arm64")
-                    .arg("-x")
-                    .arg("assembler")
-                    .arg(&asm_path)
-                    .arg("-o")
-                    .arg(&bin_path)
-                    .status()
-                    .unwrap_or_else(|e| {
-                        eprintln!("failed to run clang: {}", e);
-                        exit(1);
-                    });
-                if !assemble_status.success() {
-                    // report assembly failure and continue
-                    let duration = start.elapsed();
-                    tx_res
-                        .send((input.to_string(), expected, 0, duration, false, None))
-                        .unwrap();
-                    // indicate failure
-                    print!("F");
-                    io::stdout().flush().unwrap();
-                    continue;
-                }
+                    // Assemble (Mach-O output needs clang's -arch, ELF targets use the host cc)
+                    let mut assembler = if target.as_str() == "aarch64-macos" {
+                        let mut cmd = Command::new("clang");
+                        cmd.arg("-arch").arg("arm64");
+                        cmd
+                    } else {
+                        Command::new("cc")
+                    };
+                    let assemble_status = assembler
+                        .arg("-x")
+                        .arg("assembler")
+                        .arg(&asm_path)
+                        .arg("-o")
+                        .arg(&bin_path)
+                        .status()
+                        .unwrap_or_else(|e| {
+                            eprintln!("failed to run assembler: {}", e);
+                            exit(1);
+                        });
+                    if !assemble_status.success() {
+                        // report assembly failure and continue
+                        let duration = start.elapsed();
+                        tx_res
+                            .send((input.to_string(), expected, 0, duration, false, None))
+                            .unwrap();
+                        // indicate failure
+                        print!("F");
+                        io::stdout().flush().unwrap();
+                        continue;
+                    }
 
-                // Run binary
-                let output = Command::new(&bin_path).output().unwrap_or_else(|e| {
-                    eprintln!("failed to run binary: {}", e);
-                    exit(1);
-                });
+                    // Run binary
+                    Command::new(&bin_path).output().unwrap_or_else(|e| {
+                        eprintln!("failed to run binary: {}", e);
+                        exit(1);
+                    })
+                };
 
                 let (success, failure_info) = if let Some(expected_output) = expected_output {
                     // Check stdout output
@@ -249,8 +311,8 @@ fn main() {
     results.sort_by(|a, b| a.0.cmp(&b.0));
     // Print organized summary
     println!(
-        "{:<30} {:<6} {:<8} {:<8} {}",
-        "Test", "Result", "Expected", "Got", "Time"
+        "{:<30} {:<6} {:<8} {:<8} Time",
+        "Test", "Result", "Expected", "Got"
     );
     for (name, expected, actual, duration, success, _) in &results {
         println!(